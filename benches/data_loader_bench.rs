@@ -82,6 +82,7 @@ pub fn benchmark_data_loader(c: &mut Criterion) {
                     extensions: Arc::new(vec![]),
                     cmd_worker: None,
                     worker: None,
+                    wasm: None,
                 };
                 let loader = HttpDataLoader::new(rt, None, false);
                 let loader = loader.to_data_loader(Batch::default().delay(1));
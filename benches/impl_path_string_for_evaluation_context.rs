@@ -248,6 +248,7 @@ fn request_context() -> RequestContext {
         extensions: Arc::new(vec![]),
         cmd_worker: None,
         worker: None,
+        wasm: None,
     };
     RequestContext::new(runtime)
         .server(server)
@@ -2,11 +2,13 @@ use std::borrow::Cow;
 
 use criterion::{black_box, Criterion};
 use derive_setters::Setters;
+use hyper::header::HeaderName;
 use hyper::HeaderMap;
 use serde_json::json;
 use tailcall::core::endpoint::Endpoint;
 use tailcall::core::has_headers::HasHeaders;
 use tailcall::core::http::RequestTemplate;
+use tailcall::core::mustache::Mustache;
 use tailcall::core::path::PathString;
 
 #[derive(Setters)]
@@ -41,6 +43,23 @@ pub fn benchmark_to_request(c: &mut Criterion) {
     ))
     .unwrap();
 
+    let tmpl_full = RequestTemplate::try_from(Endpoint::new(
+        "http://localhost:3000/{{args.b}}?a={{args.a}}&b={{args.b}}&c={{args.c}}".to_string(),
+    ))
+    .unwrap()
+    .method(reqwest::Method::POST)
+    .headers(vec![
+        (
+            HeaderName::from_static("x-user-id"),
+            Mustache::parse("{{args.b}}").unwrap(),
+        ),
+        (
+            HeaderName::from_static("x-tenant"),
+            Mustache::parse("tailcall").unwrap(),
+        ),
+    ])
+    .body_path(Some(Mustache::parse("{{args}}").unwrap()));
+
     let ctx = Context::default().value(json!({
       "args": {
         "b": "foo"
@@ -58,4 +77,10 @@ pub fn benchmark_to_request(c: &mut Criterion) {
             black_box(tmpl_mustache.to_request(&ctx).unwrap());
         })
     });
+
+    c.bench_function("with_headers_query_and_body", |b| {
+        b.iter(|| {
+            black_box(tmpl_full.to_request(&ctx).unwrap());
+        })
+    });
 }
@@ -26,6 +26,17 @@ pub enum Command {
         /// separated by spaces if more than one
         #[arg(required = true)]
         file_paths: Vec<String>,
+
+        /// Reloads the server when one of the configuration files changes
+        #[arg(short, long)]
+        watch: bool,
+
+        /// Loads the resolved configuration from this snapshot when it's
+        /// valid, otherwise writes it there. The snapshot is written again
+        /// when the configuration files change, delete it to pick up changes
+        /// of the files linked in the configuration.
+        #[arg(long)]
+        snapshot: Option<String>,
     },
 
     /// Validate a composition spec
@@ -46,6 +57,10 @@ pub enum Command {
         /// Prints the input config in the provided format.
         #[clap(short, long)]
         format: Option<config::Source>,
+
+        /// Exits with a non-zero code on warnings as well as on errors
+        #[arg(long)]
+        deny_warnings: bool,
     },
 
     /// Initialize a new project
@@ -4,6 +4,8 @@ use colored::Colorize;
 use derive_setters::Setters;
 use thiserror::Error;
 
+use crate::core::check::Diagnostic;
+use crate::core::config::SyntaxError;
 use crate::core::valid::ValidationError;
 
 #[derive(Debug, Error, Setters, PartialEq, Clone)]
@@ -15,6 +17,10 @@ pub struct CLIError {
     #[setters(strip_option)]
     description: Option<String>,
     trace: Vec<String>,
+    /// The part of the config a syntax error is located at, the validation
+    /// errors have a trace instead
+    #[setters(strip_option)]
+    snippet: Option<String>,
 
     #[setters(skip)]
     caused_by: Vec<CLIError>,
@@ -28,6 +34,7 @@ impl CLIError {
             message: message.to_string(),
             description: Default::default(),
             trace: Default::default(),
+            snippet: Default::default(),
             caused_by: Default::default(),
         }
     }
@@ -113,6 +120,11 @@ impl Display for CLIError {
             f.write_str(&self.colored(&buf, colored::Color::Cyan))?;
         }
 
+        if let Some(snippet) = &self.snippet {
+            f.write_str("\n")?;
+            f.write_str(&self.colored(snippet, colored::Color::Blue))?;
+        }
+
         if !self.caused_by.is_empty() {
             f.write_str("\n")?;
             f.write_str(self.dimmed("Caused by:").as_str())?;
@@ -159,25 +171,25 @@ impl From<rustls::Error> for CLIError {
 impl From<anyhow::Error> for CLIError {
     fn from(error: anyhow::Error) -> Self {
         // Convert other errors to CLIError
-        let cli_error = match error.downcast::<CLIError>() {
-            Ok(cli_error) => cli_error,
+        let error = match error.downcast::<CLIError>() {
+            Ok(cli_error) => return cli_error,
+            Err(error) => error,
+        };
+        let error = match error.downcast::<SyntaxError>() {
+            Ok(syntax_error) => return CLIError::from(syntax_error),
+            Err(error) => error,
+        };
+        match error.downcast::<ValidationError<String>>() {
+            Ok(validation_error) => CLIError::from(validation_error),
             Err(error) => {
-                // Convert other errors to CLIError
-                let cli_error = match error.downcast::<ValidationError<String>>() {
-                    Ok(validation_error) => CLIError::from(validation_error),
-                    Err(error) => {
-                        let sources = error
-                            .source()
-                            .map(|error| vec![CLIError::new(error.to_string().as_str())])
-                            .unwrap_or_default();
-
-                        CLIError::new(&error.to_string()).caused_by(sources)
-                    }
-                };
-                cli_error
+                let sources = error
+                    .source()
+                    .map(|error| vec![CLIError::new(error.to_string().as_str())])
+                    .unwrap_or_default();
+
+                CLIError::new(&error.to_string()).caused_by(sources)
             }
-        };
-        cli_error
+        }
     }
 }
 
@@ -223,6 +235,25 @@ impl From<ValidationError<String>> for CLIError {
     }
 }
 
+impl From<SyntaxError> for CLIError {
+    fn from(error: SyntaxError) -> Self {
+        CLIError::new("Invalid Configuration")
+            .caused_by(vec![
+                CLIError::new(error.message.as_str()).snippet(error.snippet())
+            ])
+    }
+}
+
+impl From<&Diagnostic> for CLIError {
+    fn from(diagnostic: &Diagnostic) -> Self {
+        let error = CLIError::new(diagnostic.message.as_str()).trace(diagnostic.trace.clone());
+        match &diagnostic.description {
+            Some(description) => error.description(description.clone()),
+            None => error,
+        }
+    }
+}
+
 impl From<Box<dyn std::error::Error>> for CLIError {
     fn from(value: Box<dyn std::error::Error>) -> Self {
         CLIError::new(value.to_string().as_str())
@@ -322,6 +353,23 @@ mod tests {
         assert_eq!(error.to_string(), expected);
     }
 
+    #[test]
+    fn test_syntax_error() {
+        let error = SyntaxError::new("expected name", "schema {\n}", 2, 1).path("config.graphql");
+        let error = CLIError::from(error);
+
+        let expected = r"|Invalid Configuration
+                     |Caused by:
+                     |  • expected name
+                     |     --> config.graphql:2:1
+                     |      |
+                     |    2 | }
+                     |      | ^"
+            .strip_margin();
+
+        assert_eq!(error.to_string(), expected);
+    }
+
     #[test]
     fn test_title_trace_multiple_caused_by() {
         let error = CLIError::new("Configuration Error").caused_by(vec![
@@ -7,6 +7,8 @@ pub mod metrics;
 pub mod server;
 mod tc;
 pub mod telemetry;
+#[cfg(feature = "wasm-plugins")]
+pub mod wasm;
 
 pub mod runtime;
 pub(crate) mod update_checker;
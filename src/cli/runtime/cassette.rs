@@ -0,0 +1,311 @@
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use base64::prelude::BASE64_STANDARD;
+use base64::Engine;
+use hyper::body::Bytes;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::core::EnvIO;
+
+/// Path of the cassette the upstream calls are recorded to or replayed from.
+const CASSETTE: &str = "TAILCALL_CASSETTE";
+
+/// `record` to call the upstreams and write the cassette, the calls are
+/// replayed from the cassette otherwise.
+const CASSETTE_MODE: &str = "TAILCALL_CASSETTE_MODE";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    Record,
+    Replay,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+enum Body {
+    Text(String),
+    Base64(String),
+}
+
+impl From<&[u8]> for Body {
+    fn from(bytes: &[u8]) -> Self {
+        match std::str::from_utf8(bytes) {
+            Ok(text) => Body::Text(text.to_string()),
+            Err(_) => Body::Base64(BASE64_STANDARD.encode(bytes)),
+        }
+    }
+}
+
+impl Body {
+    fn to_bytes(&self) -> Result<Bytes> {
+        match self {
+            Body::Text(text) => Ok(Bytes::from(text.clone())),
+            Body::Base64(encoded) => Ok(Bytes::from(BASE64_STANDARD.decode(encoded)?)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct RecordedRequest {
+    method: String,
+    url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    body: Option<Body>,
+}
+
+impl From<&reqwest::Request> for RecordedRequest {
+    fn from(request: &reqwest::Request) -> Self {
+        Self {
+            method: request.method().to_string(),
+            url: request.url().to_string(),
+            body: request
+                .body()
+                .and_then(|body| body.as_bytes())
+                .map(Body::from),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct RecordedResponse {
+    status: u16,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    headers: BTreeMap<String, String>,
+    body: Body,
+}
+
+impl RecordedResponse {
+    /// Reads the whole response, it's rebuilt from the recorded one
+    async fn from_reqwest(response: reqwest::Response) -> Result<Self> {
+        let status = response.status().as_u16();
+        let headers = response
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+            .collect();
+        let body = Body::from(response.bytes().await?.as_ref());
+
+        Ok(Self { status, headers, body })
+    }
+
+    fn to_reqwest(&self) -> Result<reqwest::Response> {
+        let mut response = hyper::Response::builder().status(self.status);
+        for (name, value) in self.headers.iter() {
+            response = response.header(name.as_str(), value.as_str());
+        }
+
+        Ok(reqwest::Response::from(
+            response.body(self.body.to_bytes()?)?,
+        ))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct Interaction {
+    request: RecordedRequest,
+    response: RecordedResponse,
+}
+
+/// Records the calls made to the upstreams along with their responses to a
+/// file, so that they can be replayed later without a network.
+pub struct Cassette {
+    mode: Mode,
+    path: PathBuf,
+    interactions: Mutex<Vec<Interaction>>,
+}
+
+impl Cassette {
+    pub fn new(mode: Mode, path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let interactions = match mode {
+            Mode::Record => vec![],
+            Mode::Replay => serde_json::from_str(&std::fs::read_to_string(&path)?)?,
+        };
+
+        Ok(Self { mode, path, interactions: Mutex::new(interactions) })
+    }
+
+    /// Reads the cassette configured by `TAILCALL_CASSETTE` and
+    /// `TAILCALL_CASSETTE_MODE`, if any.
+    pub fn from_env(env: &dyn EnvIO) -> Result<Option<Self>> {
+        let Some(path) = env.get(CASSETTE) else {
+            return Ok(None);
+        };
+        let mode = match env.get(CASSETTE_MODE).as_deref() {
+            Some("record") => Mode::Record,
+            Some("replay") | None => Mode::Replay,
+            Some(mode) => return Err(anyhow!("Unknown cassette mode: {}", mode)),
+        };
+
+        Ok(Some(Self::new(mode, path.as_ref())?))
+    }
+
+    /// Sends the request and records its response, or replays the response
+    /// recorded for it. Every response is recorded, whatever its status.
+    pub async fn execute<F, Fut>(
+        &self,
+        request: reqwest::Request,
+        send: F,
+    ) -> Result<reqwest::Response>
+    where
+        F: FnOnce(reqwest::Request) -> Fut,
+        Fut: Future<Output = Result<reqwest::Response>>,
+    {
+        let recorded = RecordedRequest::from(&request);
+        match self.mode {
+            Mode::Record => {
+                let response = RecordedResponse::from_reqwest(send(request).await?).await?;
+                self.record(recorded, response.clone()).await?;
+                response.to_reqwest()
+            }
+            Mode::Replay => self.replay(&recorded).await,
+        }
+    }
+
+    async fn record(&self, request: RecordedRequest, response: RecordedResponse) -> Result<()> {
+        let mut interactions = self.interactions.lock().await;
+        interactions.push(Interaction { request, response });
+        tokio::fs::write(&self.path, serde_json::to_string_pretty(&*interactions)?).await?;
+
+        Ok(())
+    }
+
+    async fn replay(&self, request: &RecordedRequest) -> Result<reqwest::Response> {
+        let interactions = self.interactions.lock().await;
+        let interaction = interactions
+            .iter()
+            .find(|interaction| &interaction.request == request)
+            .ok_or(anyhow!(
+                "No response recorded in {} for {} {}",
+                self.path.display(),
+                request.method,
+                request.url
+            ))?;
+
+        interaction.response.to_reqwest()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    /// Responds with the url of the request and the given status.
+    async fn echo(
+        request: reqwest::Request,
+        status: u16,
+        calls: &AtomicUsize,
+    ) -> Result<reqwest::Response> {
+        calls.fetch_add(1, Ordering::SeqCst);
+        let response = hyper::Response::builder()
+            .status(status)
+            .header("content-type", "text/plain")
+            .body(request.url().to_string())?;
+
+        Ok(reqwest::Response::from(response))
+    }
+
+    /// Fails every call, as if there was no network.
+    async fn offline(_: reqwest::Request) -> Result<reqwest::Response> {
+        Err(anyhow!("Network is disabled"))
+    }
+
+    struct TestEnv(HashMap<String, String>);
+
+    impl EnvIO for TestEnv {
+        fn get(&self, key: &str) -> Option<Cow<'_, str>> {
+            self.0.get(key).map(Cow::from)
+        }
+    }
+
+    fn request(url: &str) -> reqwest::Request {
+        reqwest::Request::new(reqwest::Method::GET, url.parse().unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_record_and_replay() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cassette.json");
+
+        let calls = AtomicUsize::new(0);
+        let recorder = Cassette::new(Mode::Record, &path).unwrap();
+        let recorded = recorder
+            .execute(request("http://a.com/users/1"), |request| {
+                echo(request, 200, &calls)
+            })
+            .await
+            .unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        let status = recorded.status();
+        let headers = recorded.headers().clone();
+
+        let player = Cassette::new(Mode::Replay, &path).unwrap();
+        let replayed = player
+            .execute(request("http://a.com/users/1"), offline)
+            .await
+            .unwrap();
+
+        assert_eq!(replayed.status(), status);
+        assert_eq!(replayed.headers(), &headers);
+        assert_eq!(replayed.text().await.unwrap(), "http://a.com/users/1");
+
+        let error = player
+            .execute(request("http://a.com/users/2"), offline)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().starts_with("No response recorded in"));
+    }
+
+    #[tokio::test]
+    async fn test_record_and_replay_error_status() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cassette.json");
+
+        let calls = AtomicUsize::new(0);
+        let recorder = Cassette::new(Mode::Record, &path).unwrap();
+        let recorded = recorder
+            .execute(request("http://a.com/users/3"), |request| {
+                echo(request, 404, &calls)
+            })
+            .await
+            .unwrap();
+        assert_eq!(recorded.status().as_u16(), 404);
+
+        let player = Cassette::new(Mode::Replay, &path).unwrap();
+        let replayed = player
+            .execute(request("http://a.com/users/3"), offline)
+            .await
+            .unwrap();
+        assert_eq!(replayed.status().as_u16(), 404);
+        assert!(replayed.error_for_status().is_err());
+    }
+
+    #[test]
+    fn test_from_env() {
+        let env = TestEnv(HashMap::new());
+        assert!(Cassette::from_env(&env).unwrap().is_none());
+
+        let env = TestEnv(HashMap::from([
+            (CASSETTE.to_string(), "cassette.json".to_string()),
+            (CASSETTE_MODE.to_string(), "record".to_string()),
+        ]));
+        let cassette = Cassette::from_env(&env).unwrap().unwrap();
+        assert_eq!(cassette.mode, Mode::Record);
+
+        let env = TestEnv(HashMap::from([
+            (CASSETTE.to_string(), "cassette.json".to_string()),
+            (CASSETTE_MODE.to_string(), "rewind".to_string()),
+        ]));
+        assert!(Cassette::from_env(&env).is_err());
+    }
+}
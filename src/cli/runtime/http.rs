@@ -1,6 +1,8 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
 use std::time::Duration;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use http_cache_reqwest::{Cache, CacheMode, HttpCache, HttpCacheOptions};
 use hyper::body::Bytes;
 use once_cell::sync::Lazy;
@@ -16,9 +18,11 @@ use reqwest_middleware::{ClientBuilder, ClientWithMiddleware};
 use tailcall_http_cache::HttpCacheManager;
 use tracing_opentelemetry::OpenTelemetrySpanExt;
 
+use super::cassette::Cassette;
 use super::HttpIO;
 use crate::core::blueprint::telemetry::Telemetry;
-use crate::core::blueprint::Upstream;
+use crate::core::blueprint::{TargetTls, Upstream};
+use crate::core::config::TlsVersion;
 use crate::core::http::Response;
 
 static HTTP_CLIENT_REQUEST_COUNT: Lazy<Counter<u64>> = Lazy::new(|| {
@@ -70,79 +74,115 @@ fn get_response_status(response: &reqwest_middleware::Result<reqwest::Response>)
 #[derive(Clone)]
 pub struct NativeHttp {
     client: ClientWithMiddleware,
+    /// Clients of the targets with their own TLS material, by origin.
+    targets: BTreeMap<String, ClientWithMiddleware>,
     http2_only: bool,
     enable_telemetry: bool,
+    min_tls: TlsVersion,
+    cassette: Option<Arc<Cassette>>,
 }
 
 impl Default for NativeHttp {
     fn default() -> Self {
         Self {
             client: ClientBuilder::new(Client::new()).build(),
+            targets: BTreeMap::new(),
             http2_only: false,
             enable_telemetry: false,
+            min_tls: TlsVersion::default(),
+            cassette: None,
         }
     }
 }
 
 impl NativeHttp {
-    pub fn init(upstream: &Upstream, telemetry: &Telemetry) -> Self {
-        let mut builder = Client::builder()
-            .tcp_keepalive(Some(Duration::from_secs(upstream.tcp_keep_alive)))
-            .timeout(Duration::from_secs(upstream.timeout))
-            .connect_timeout(Duration::from_secs(upstream.connect_timeout))
-            .http2_keep_alive_interval(Some(Duration::from_secs(upstream.keep_alive_interval)))
-            .http2_keep_alive_timeout(Duration::from_secs(upstream.keep_alive_timeout))
-            .http2_keep_alive_while_idle(upstream.keep_alive_while_idle)
-            .pool_idle_timeout(Some(Duration::from_secs(upstream.pool_idle_timeout)))
-            .pool_max_idle_per_host(upstream.pool_max_idle_per_host)
-            .user_agent(upstream.user_agent.clone());
-
-        // Add Http2 Prior Knowledge
-        if upstream.http2_only {
-            builder = builder.http2_prior_knowledge();
-        }
+    pub fn init(upstream: &Upstream, telemetry: &Telemetry) -> Result<Self> {
+        let targets = upstream
+            .targets
+            .iter()
+            .map(|(origin, tls)| Ok((origin.clone(), init_client(upstream, tls)?)))
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            client: init_client(upstream, &TargetTls::default())?,
+            targets,
+            http2_only: upstream.http2_only,
+            enable_telemetry: telemetry.export.is_some(),
+            min_tls: upstream.min_tls,
+            cassette: None,
+        })
+    }
+}
 
-        // Add Http Proxy
-        if let Some(ref proxy) = upstream.proxy {
-            builder = builder.proxy(
-                reqwest::Proxy::http(proxy.url.clone())
-                    .expect("Failed to set proxy in http client"),
-            );
-        }
+fn init_client(upstream: &Upstream, tls: &TargetTls) -> Result<ClientWithMiddleware> {
+    let mut builder = Client::builder()
+        .tcp_keepalive(upstream.get_tcp_keep_alive())
+        .timeout(Duration::from_secs(upstream.timeout))
+        .connect_timeout(Duration::from_secs(upstream.connect_timeout))
+        .http2_keep_alive_interval(upstream.get_http2_keep_alive_interval())
+        .http2_keep_alive_timeout(Duration::from_secs(upstream.keep_alive_timeout))
+        .http2_keep_alive_while_idle(upstream.keep_alive_while_idle)
+        .pool_idle_timeout(Some(Duration::from_secs(upstream.pool_idle_timeout)))
+        .pool_max_idle_per_host(upstream.get_pool_max_idle_per_host())
+        .min_tls_version(min_tls_version(upstream.min_tls))
+        .user_agent(upstream.user_agent.clone());
+
+    if let Some(ca_cert) = &tls.ca_cert {
+        let ca_cert = reqwest::Certificate::from_pem(ca_cert.as_bytes())
+            .context("Failed to read the CA certificate")?;
+        builder = builder.add_root_certificate(ca_cert);
+    }
 
-        let mut client = ClientBuilder::new(builder.build().expect("Failed to build client"));
+    if let Some(identity) = &tls.identity {
+        let identity = reqwest::Identity::from_pem(identity.as_bytes())
+            .context("Failed to read the client certificate")?;
+        builder = builder.identity(identity);
+    }
 
-        if upstream.http_cache > 0 {
-            client = client.with(Cache(HttpCache {
-                mode: CacheMode::Default,
-                manager: HttpCacheManager::new(upstream.http_cache),
-                options: HttpCacheOptions::default(),
-            }))
-        }
-        Self {
-            client: client.build(),
-            http2_only: upstream.http2_only,
-            enable_telemetry: telemetry.export.is_some(),
-        }
+    // Add Http2 Prior Knowledge
+    if upstream.http2_only {
+        builder = builder.http2_prior_knowledge();
     }
+
+    // Add Http Proxy
+    if let Some(ref proxy) = upstream.proxy {
+        builder = builder.proxy(
+            reqwest::Proxy::http(proxy.url.clone())
+                .context("Failed to set proxy in http client")?,
+        );
+    }
+
+    let mut client = ClientBuilder::new(builder.build().context("Failed to build client")?);
+
+    if upstream.http_cache > 0 {
+        client = client.with(Cache(HttpCache {
+            mode: CacheMode::Default,
+            manager: HttpCacheManager::new(upstream.http_cache),
+            options: HttpCacheOptions::default(),
+        }))
+    }
+
+    Ok(client.build())
 }
 
-#[async_trait::async_trait]
-impl HttpIO for NativeHttp {
-    #[allow(clippy::blocks_in_conditions)]
-    // because of the issue with tracing and clippy - https://github.com/rust-lang/rust-clippy/issues/12281
-    #[tracing::instrument(
-        skip_all,
-        err,
-        fields(
-            otel.name = "upstream_request",
-            otel.kind = ?SpanKind::Client,
-            url.full = %request.url(),
-            http.request.method = %request.method(),
-            network.protocol.version = ?request.version()
-        )
-    )]
-    async fn execute(&self, mut request: reqwest::Request) -> Result<Response<Bytes>> {
+impl NativeHttp {
+    /// Records the calls to or replays them from the cassette
+    pub(super) fn with_cassette(mut self, cassette: Option<Arc<Cassette>>) -> Self {
+        self.cassette = cassette;
+        self
+    }
+
+    fn client(&self, url: &reqwest::Url) -> &ClientWithMiddleware {
+        if self.targets.is_empty() {
+            return &self.client;
+        }
+
+        self.targets
+            .get(&url.origin().ascii_serialization())
+            .unwrap_or(&self.client)
+    }
+
+    async fn send(&self, mut request: reqwest::Request) -> Result<reqwest::Response> {
         if self.http2_only {
             *request.version_mut() = reqwest::Version::HTTP_2;
         }
@@ -165,7 +205,8 @@ impl HttpIO for NativeHttp {
             request.version()
         );
         tracing::debug!("request: {:?}", request);
-        let response = self.client.execute(request).await;
+        let host = request.url().host_str().unwrap_or_default().to_string();
+        let response = self.client(request.url()).execute(request).await;
         tracing::debug!("response: {:?}", response);
 
         req_counter.update(&response);
@@ -175,21 +216,100 @@ impl HttpIO for NativeHttp {
             tracing::Span::current().set_attribute(status_code.key, status_code.value);
         }
 
-        Ok(Response::from_reqwest(
-            response?
-                .error_for_status()
-                .map_err(|err| err.without_url())?,
+        response.map_err(|err| {
+            if is_tls_version_error(&err) {
+                anyhow::anyhow!(
+                    "Upstream {} doesn't support {} or above, required by minTls",
+                    host,
+                    self.min_tls
+                )
+            } else {
+                err.into()
+            }
+        })
+    }
+}
+
+fn min_tls_version(version: TlsVersion) -> reqwest::tls::Version {
+    match version {
+        TlsVersion::TLSv1_2 => reqwest::tls::Version::TLS_1_2,
+        TlsVersion::TLSv1_3 => reqwest::tls::Version::TLS_1_3,
+    }
+}
+
+/// Upstreams that don't support the minimum TLS version fail the handshake,
+/// either by sending a `ProtocolVersion` alert or by negotiating a version
+/// that isn't supported.
+fn is_tls_version_error(err: &(dyn std::error::Error + 'static)) -> bool {
+    use reqwest_rustls::{AlertDescription, Error, PeerIncompatible};
+
+    let mut source = Some(err);
+    while let Some(err) = source {
+        // The TLS errors are wrapped in IO errors, whose `source` skips them
+        let tls_error = err
+            .downcast_ref::<std::io::Error>()
+            .and_then(|err| err.get_ref())
+            .and_then(|err| err.downcast_ref::<Error>())
+            .or_else(|| err.downcast_ref::<Error>());
+
+        if matches!(
+            tls_error,
+            Some(
+                Error::AlertReceived(AlertDescription::ProtocolVersion)
+                    | Error::PeerIncompatible(
+                        PeerIncompatible::ServerDoesNotSupportTls12Or13
+                            | PeerIncompatible::ServerTlsVersionIsDisabledByOurConfig
+                    )
+            )
+        ) {
+            return true;
+        }
+        source = err.source();
+    }
+    false
+}
+
+#[async_trait::async_trait]
+impl HttpIO for NativeHttp {
+    #[allow(clippy::blocks_in_conditions)]
+    // because of the issue with tracing and clippy - https://github.com/rust-lang/rust-clippy/issues/12281
+    #[tracing::instrument(
+        skip_all,
+        err,
+        fields(
+            otel.name = "upstream_request",
+            otel.kind = ?SpanKind::Client,
+            url.full = %request.url(),
+            http.request.method = %request.method(),
+            network.protocol.version = ?request.version()
         )
-        .await?)
+    )]
+    async fn execute(&self, request: reqwest::Request) -> Result<Response<Bytes>> {
+        let response = match &self.cassette {
+            Some(cassette) => {
+                cassette
+                    .execute(request, |request| self.send(request))
+                    .await?
+            }
+            None => self.send(request).await?,
+        };
+
+        Response::from_reqwest_for_status(response).await
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::convert::Infallible;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use hyper::service::{make_service_fn, service_fn};
     use reqwest::Method;
     use tokio;
 
     use super::*;
+    use crate::core::config::GrpcPool;
     use crate::core::http::Response;
 
     fn start_mock_server() -> httpmock::MockServer {
@@ -211,7 +331,7 @@ mod tests {
             then.status(200).body("Hello");
         });
 
-        let native_http = NativeHttp::init(&Default::default(), &Default::default());
+        let native_http = NativeHttp::init(&Default::default(), &Default::default()).unwrap();
         let port = server.port();
         // Build a GET request to the mock server
         let request_url = format!("http://localhost:{}/test", port);
@@ -253,7 +373,7 @@ mod tests {
         });
 
         let upstream = Upstream { http_cache: 2, ..Default::default() };
-        let native_http = NativeHttp::init(&upstream, &Default::default());
+        let native_http = NativeHttp::init(&upstream, &Default::default()).unwrap();
         let port = server.port();
 
         let url1 = format!("http://localhost:{}/test-1", port);
@@ -281,4 +401,143 @@ mod tests {
         let resp = make_request(&url1, &native_http).await;
         assert_eq!(resp.headers.get("x-cache-lookup").unwrap(), "MISS");
     }
+
+    #[tokio::test]
+    async fn test_grpc_channel_reused() {
+        let connections = Arc::new(AtomicUsize::new(0));
+        let counter = connections.clone();
+        let make_service = make_service_fn(move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            async {
+                Ok::<_, Infallible>(service_fn(|_| async {
+                    Ok::<_, Infallible>(hyper::Response::new(hyper::Body::from("Hello")))
+                }))
+            }
+        });
+        let server = hyper::Server::bind(&([127, 0, 0, 1], 0).into())
+            .http2_only(true)
+            .serve(make_service);
+        let request_url = format!("http://127.0.0.1:{}/test", server.local_addr().port());
+        tokio::spawn(server);
+
+        let grpc_pool = GrpcPool { max_idle_per_host: Some(1), idle_timeout: Some(60) };
+        let upstream = Upstream { grpc_pool: Some(grpc_pool), ..Default::default() }.grpc();
+        let native_http = NativeHttp::init(&upstream, &Default::default()).unwrap();
+
+        let response = make_request(&request_url, &native_http).await;
+        assert_eq!(response.body, Bytes::from("Hello"));
+
+        let responses = futures_util::future::join_all(
+            (0..4).map(|_| make_request(&request_url, &native_http)),
+        )
+        .await;
+        assert!(responses
+            .iter()
+            .all(|response| response.status == reqwest::StatusCode::OK));
+
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_grpc_targets() {
+        let cert = include_str!("../../../tests/server/config/example.crt").to_string();
+        let key = include_str!("../../../tests/server/config/example-pkcs8.key");
+        let tls = TargetTls {
+            ca_cert: Some(cert.clone()),
+            identity: Some(format!("{}\n{}", cert, key)),
+        };
+        let upstream = Upstream {
+            grpc_targets: [("https://a.com".to_string(), tls)].into(),
+            ..Default::default()
+        };
+        assert!(upstream.targets.is_empty());
+
+        let upstream = upstream.grpc();
+        assert!(upstream.http2_only);
+
+        let native_http = NativeHttp::init(&upstream, &Default::default()).unwrap();
+        let url = "https://a.com/news.NewsService/GetAllNews".parse().unwrap();
+        assert!(std::ptr::eq(
+            native_http.client(&url),
+            &native_http.targets["https://a.com"]
+        ));
+        let url = "https://b.com/news.NewsService/GetAllNews".parse().unwrap();
+        assert!(std::ptr::eq(native_http.client(&url), &native_http.client));
+    }
+
+    #[test]
+    fn test_invalid_client_cert() {
+        let tls = TargetTls { identity: Some("foo".to_string()), ..Default::default() };
+        let upstream = Upstream {
+            targets: [("https://a.com".to_string(), tls)].into(),
+            ..Default::default()
+        };
+
+        let error = NativeHttp::init(&upstream, &Default::default())
+            .err()
+            .unwrap();
+        assert_eq!(error.to_string(), "Failed to read the client certificate");
+    }
+
+    #[test]
+    fn test_min_tls_version() {
+        let native_http = NativeHttp::init(&Default::default(), &Default::default()).unwrap();
+        assert_eq!(native_http.min_tls, TlsVersion::TLSv1_2);
+        assert_eq!(
+            min_tls_version(native_http.min_tls),
+            reqwest::tls::Version::TLS_1_2
+        );
+
+        let upstream = Upstream { min_tls: TlsVersion::TLSv1_3, ..Default::default() };
+        let native_http = NativeHttp::init(&upstream, &Default::default()).unwrap();
+        assert_eq!(native_http.min_tls, TlsVersion::TLSv1_3);
+        assert_eq!(
+            min_tls_version(native_http.min_tls),
+            reqwest::tls::Version::TLS_1_3
+        );
+    }
+
+    #[test]
+    fn test_is_tls_version_error() {
+        let err = std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            reqwest_rustls::Error::AlertReceived(reqwest_rustls::AlertDescription::ProtocolVersion),
+        );
+        assert!(is_tls_version_error(&err));
+
+        let err = std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "received fatal alert: ProtocolVersion",
+        );
+        assert!(!is_tls_version_error(&err));
+    }
+
+    #[tokio::test]
+    async fn test_min_tls_handshake() {
+        // Rejects every handshake with a `ProtocolVersion` alert, like the
+        // servers that don't support the TLS versions offered by the client
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut client_hello = [0; 1024];
+                let _ = stream.read(&mut client_hello).await;
+                // A fatal alert record: level 2, description 70
+                let _ = stream.write_all(&[21, 3, 3, 0, 2, 2, 70]).await;
+            }
+        });
+
+        let upstream = Upstream { min_tls: TlsVersion::TLSv1_3, ..Default::default() };
+        let native_http = NativeHttp::init(&upstream, &Default::default()).unwrap();
+        let request_url = format!("https://localhost:{}/test", port);
+        let request = reqwest::Request::new(Method::GET, request_url.parse().unwrap());
+
+        let error = native_http.execute(request).await.unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Upstream localhost doesn't support TLS 1.3 or above, required by minTls"
+        );
+    }
 }
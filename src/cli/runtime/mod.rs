@@ -1,3 +1,4 @@
+mod cassette;
 mod env;
 mod file;
 mod http;
@@ -5,13 +6,15 @@ mod http;
 use std::hash::Hash;
 use std::sync::Arc;
 
+use cassette::Cassette;
 pub use http::NativeHttp;
+use once_cell::sync::OnceCell;
 
 use crate::core::blueprint::Blueprint;
 use crate::core::cache::InMemoryCache;
 use crate::core::runtime::TargetRuntime;
 use crate::core::worker::{Command, Event};
-use crate::core::{blueprint, EnvIO, FileIO, HttpIO, WorkerIO};
+use crate::core::{blueprint, EnvIO, FileIO, HttpIO, WasmIO, WorkerIO};
 
 // Provides access to env in native rust environment
 fn init_env() -> Arc<dyn EnvIO> {
@@ -47,38 +50,62 @@ fn init_resolver_worker_io(
     }
 }
 
+fn init_wasm_io() -> Option<Arc<dyn WasmIO>> {
+    #[cfg(feature = "wasm-plugins")]
+    return Some(Arc::new(super::wasm::NativeWasm::default()));
+    #[cfg(not(feature = "wasm-plugins"))]
+    None
+}
+
 // Provides access to http in native rust environment
-fn init_http(blueprint: &Blueprint) -> Arc<dyn HttpIO> {
-    Arc::new(http::NativeHttp::init(
-        &blueprint.upstream,
-        &blueprint.telemetry,
-    ))
+fn init_http(
+    blueprint: &Blueprint,
+    cassette: Option<Arc<Cassette>>,
+) -> anyhow::Result<Arc<dyn HttpIO>> {
+    let http = http::NativeHttp::init(&blueprint.upstream, &blueprint.telemetry)?;
+    Ok(Arc::new(http.with_cassette(cassette)))
 }
 
 // Provides access to http in native rust environment
-fn init_http2_only(blueprint: &Blueprint) -> Arc<dyn HttpIO> {
-    Arc::new(http::NativeHttp::init(
-        &blueprint.upstream.clone().http2_only(true),
-        &blueprint.telemetry,
-    ))
+fn init_http2_only(
+    blueprint: &Blueprint,
+    cassette: Option<Arc<Cassette>>,
+) -> anyhow::Result<Arc<dyn HttpIO>> {
+    let http = http::NativeHttp::init(&blueprint.upstream.grpc(), &blueprint.telemetry)?;
+    Ok(Arc::new(http.with_cassette(cassette)))
 }
 
 fn init_in_memory_cache<K: Hash + Eq, V: Clone>() -> InMemoryCache<K, V> {
     InMemoryCache::new()
 }
 
-pub fn init(blueprint: &Blueprint) -> TargetRuntime {
+// The cassette is shared by every runtime, so that the calls recorded by one
+// aren't overwritten by the others
+static CASSETTE: OnceCell<Option<Arc<Cassette>>> = OnceCell::new();
+
+// Records the upstream calls to or replays them from the cassette set in the
+// env, if any
+fn init_cassette() -> anyhow::Result<Option<Arc<Cassette>>> {
+    CASSETTE
+        .get_or_try_init(|| Ok(Cassette::from_env(init_env().as_ref())?.map(Arc::new)))
+        .cloned()
+}
+
+pub fn init(blueprint: &Blueprint) -> anyhow::Result<TargetRuntime> {
     #[cfg(not(feature = "js"))]
     tracing::warn!("JS capabilities are disabled in this build");
 
-    TargetRuntime {
-        http: init_http(blueprint),
-        http2_only: init_http2_only(blueprint),
+    let cassette = init_cassette()?;
+
+    Ok(TargetRuntime {
+        http: init_http(blueprint, cassette.clone())?,
+        http2_only: init_http2_only(blueprint, cassette)?,
         env: init_env(),
         file: init_file(),
         cache: Arc::new(init_in_memory_cache()),
         extensions: Arc::new(vec![]),
         cmd_worker: init_http_worker_io(blueprint.server.script.clone()),
         worker: init_resolver_worker_io(blueprint.server.script.clone()),
-    }
+        wasm: init_wasm_io(),
+    })
 }
@@ -17,7 +17,7 @@ pub async fn start_http_1(
         let state = Arc::clone(&sc);
         async move {
             Ok::<_, anyhow::Error>(service_fn(move |req| {
-                handle_request::<GraphQLRequest>(req, state.app_ctx.clone())
+                handle_request::<GraphQLRequest>(req, state.app_ctx())
             }))
         }
     });
@@ -26,13 +26,13 @@ pub async fn start_http_1(
         let state = Arc::clone(&sc);
         async move {
             Ok::<_, anyhow::Error>(service_fn(move |req| {
-                handle_request::<GraphQLBatchRequest>(req, state.app_ctx.clone())
+                handle_request::<GraphQLBatchRequest>(req, state.app_ctx())
             }))
         }
     });
     let builder = hyper::Server::try_bind(&addr)
         .map_err(CLIError::from)?
-        .http1_pipeline_flush(sc.app_ctx.blueprint.server.pipeline_flush);
+        .http1_pipeline_flush(sc.blueprint.server.pipeline_flush);
     super::log_launch(sc.as_ref());
 
     if let Some(sender) = server_up_sender {
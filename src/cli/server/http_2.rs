@@ -29,7 +29,7 @@ pub async fn start_http_2(
         let state = Arc::clone(&sc);
         async move {
             Ok::<_, anyhow::Error>(service_fn(move |req| {
-                handle_request::<GraphQLRequest>(req, state.app_ctx.clone())
+                handle_request::<GraphQLRequest>(req, state.app_ctx())
             }))
         }
     });
@@ -38,7 +38,7 @@ pub async fn start_http_2(
         let state = Arc::clone(&sc);
         async move {
             Ok::<_, anyhow::Error>(service_fn(move |req| {
-                handle_request::<GraphQLBatchRequest>(req, state.app_ctx.clone())
+                handle_request::<GraphQLBatchRequest>(req, state.app_ctx())
             }))
         }
     });
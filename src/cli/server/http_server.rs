@@ -1,4 +1,3 @@
-use std::ops::Deref;
 use std::sync::Arc;
 
 use anyhow::Result;
@@ -7,6 +6,8 @@ use tokio::sync::oneshot::{self};
 use super::http_1::start_http_1;
 use super::http_2::start_http_2;
 use super::server_config::ServerConfig;
+use super::watcher::Watcher;
+use crate::cli::tc::log_warnings;
 use crate::cli::telemetry::init_opentelemetry;
 use crate::cli::CLIError;
 use crate::core::blueprint::{Blueprint, Http};
@@ -15,11 +16,18 @@ use crate::core::config::ConfigModule;
 pub struct Server {
     config_module: ConfigModule,
     server_up_sender: Option<oneshot::Sender<()>>,
+    watcher: Option<Watcher>,
 }
 
 impl Server {
     pub fn new(config_module: ConfigModule) -> Self {
-        Self { config_module, server_up_sender: None }
+        Self { config_module, server_up_sender: None, watcher: None }
+    }
+
+    /// Reloads the server with the watcher once it's up
+    pub fn watch(mut self, watcher: Watcher) -> Self {
+        self.watcher = Some(watcher);
+        self
     }
 
     pub fn server_up_receiver(&mut self) -> oneshot::Receiver<()> {
@@ -33,6 +41,12 @@ impl Server {
     /// Starts the server in the current Runtime
     pub async fn start(self) -> Result<()> {
         let blueprint = Blueprint::try_from(&self.config_module).map_err(CLIError::from)?;
+        self.start_blueprint(blueprint).await
+    }
+
+    async fn start_blueprint(self, blueprint: Blueprint) -> Result<()> {
+        log_warnings(&blueprint);
+
         let server_config = Arc::new(
             ServerConfig::new(
                 blueprint.clone(),
@@ -41,7 +55,14 @@ impl Server {
             .await?,
         );
 
-        init_opentelemetry(blueprint.telemetry.clone(), &server_config.app_ctx.runtime)?;
+        init_opentelemetry(
+            blueprint.telemetry.clone(),
+            &server_config.app_ctx().runtime,
+        )?;
+
+        if let Some(watcher) = self.watcher {
+            tokio::spawn(watcher.watch(server_config.clone()));
+        }
 
         match blueprint.server.http.clone() {
             Http::HTTP2 { cert, key } => {
@@ -51,16 +72,67 @@ impl Server {
         }
     }
 
-    /// Starts the server in its own multithreaded Runtime
+    /// Starts the server in its own multithreaded Runtime, using the number of
+    /// worker threads set in `@server(workers:)`
     pub async fn fork_start(self) -> Result<()> {
-        let runtime = tokio::runtime::Builder::new_multi_thread()
-            .worker_threads(self.config_module.deref().server.get_workers())
-            .enable_all()
-            .build()?;
+        let blueprint = Blueprint::try_from(&self.config_module).map_err(CLIError::from)?;
+        let runtime = runtime_builder(blueprint.server.worker).build()?;
 
-        let result = runtime.spawn(async { self.start().await }).await?;
+        let result = runtime
+            .spawn(async { self.start_blueprint(blueprint).await })
+            .await?;
         runtime.shutdown_background();
 
         result
     }
 }
+
+/// The builder of the runtime the server is forked into
+fn runtime_builder(workers: usize) -> tokio::runtime::Builder {
+    let mut builder = tokio::runtime::Builder::new_multi_thread();
+    builder.worker_threads(workers).enable_all();
+    builder
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Barrier};
+
+    use super::runtime_builder;
+
+    #[test]
+    fn test_runtime_worker_threads() {
+        let workers = 3;
+        let started = Arc::new(AtomicUsize::new(0));
+        let runtime = runtime_builder(workers)
+            .on_thread_start({
+                let started = started.clone();
+                move || {
+                    started.fetch_add(1, Ordering::SeqCst);
+                }
+            })
+            .build()
+            .unwrap();
+
+        // Every task blocks its thread until all of them run, which requires
+        // as many worker threads
+        let barrier = Arc::new(Barrier::new(workers));
+        runtime.block_on(async {
+            let tasks = (0..workers)
+                .map(|_| {
+                    let barrier = barrier.clone();
+                    tokio::spawn(async move {
+                        barrier.wait();
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            for task in tasks {
+                task.await.unwrap();
+            }
+        });
+
+        assert_eq!(started.load(Ordering::SeqCst), workers);
+    }
+}
@@ -3,13 +3,13 @@ pub mod http_2;
 pub mod http_server;
 pub mod playground;
 pub mod server_config;
+pub mod watcher;
 
 pub use http_server::Server;
+pub use watcher::Watcher;
 
 use self::server_config::ServerConfig;
 
-const GRAPHQL_SLUG: &str = "/graphql";
-
 fn log_launch(sc: &ServerConfig) {
     let addr = sc.addr().to_string();
     tracing::info!(
@@ -18,7 +18,14 @@ fn log_launch(sc: &ServerConfig) {
         sc.http_version()
     );
 
-    let graphiql_url = sc.graphiql_url() + GRAPHQL_SLUG;
+    if !sc.blueprint.server.enable_introspection {
+        tracing::warn!(
+            "Introspection is disabled, the playground won't be able to load the schema"
+        );
+        return;
+    }
+
+    let graphiql_url = sc.graphiql_url() + &sc.blueprint.server.graphql_path;
     let url = playground::build_url(&graphiql_url);
     tracing::info!("🌍 Playground: {}", url);
 }
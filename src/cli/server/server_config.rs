@@ -1,18 +1,24 @@
-use std::net::{IpAddr, Ipv4Addr, SocketAddr};
-use std::sync::Arc;
+use std::collections::BTreeSet;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, ToSocketAddrs};
+use std::sync::{Arc, RwLock};
 
 use async_graphql_extension_apollo_tracing::ApolloTracing;
 
 use crate::cli::runtime::init;
 use crate::core::blueprint::telemetry::TelemetryExporter;
-use crate::core::blueprint::{Blueprint, Http};
+use crate::core::blueprint::{Blueprint, Definition, Hostname, Http};
 use crate::core::http::AppContext;
+use crate::core::ir::{IO, IR};
 use crate::core::rest::{EndpointSet, Unchecked};
+use crate::core::runtime::TargetRuntime;
 use crate::core::schema_extension::SchemaExtension;
 
 pub struct ServerConfig {
+    /// The blueprint the server was started with. The settings of the listener
+    /// like the port are not reloaded.
     pub blueprint: Blueprint,
-    pub app_ctx: Arc<AppContext>,
+    addr: SocketAddr,
+    app_ctx: RwLock<Arc<AppContext>>,
 }
 
 impl ServerConfig {
@@ -20,7 +26,35 @@ impl ServerConfig {
         blueprint: Blueprint,
         endpoints: EndpointSet<Unchecked>,
     ) -> anyhow::Result<Self> {
-        let mut rt = init(&blueprint);
+        let addr = resolve_addr(&blueprint).await?;
+        let app_ctx = Self::init_app_ctx(&blueprint, endpoints).await?;
+
+        Ok(Self { addr, app_ctx: RwLock::new(app_ctx), blueprint })
+    }
+
+    /// The context the requests are currently handled with
+    pub fn app_ctx(&self) -> Arc<AppContext> {
+        self.app_ctx.read().unwrap().clone()
+    }
+
+    /// Builds the context of the new blueprint and swaps it in. The requests
+    /// in flight keep using the previous context.
+    pub async fn reload(
+        &self,
+        blueprint: Blueprint,
+        endpoints: EndpointSet<Unchecked>,
+    ) -> anyhow::Result<()> {
+        let app_ctx = Self::init_app_ctx(&blueprint, endpoints).await?;
+        *self.app_ctx.write().unwrap() = app_ctx;
+
+        Ok(())
+    }
+
+    async fn init_app_ctx(
+        blueprint: &Blueprint,
+        endpoints: EndpointSet<Unchecked>,
+    ) -> anyhow::Result<Arc<AppContext>> {
+        let mut rt = init(blueprint)?;
 
         let mut extensions = vec![];
 
@@ -36,14 +70,17 @@ impl ServerConfig {
         }
         rt.add_extensions(extensions);
 
-        let endpoints = endpoints.into_checked(&blueprint, rt.clone()).await?;
-        let app_context = Arc::new(AppContext::new(blueprint.clone(), rt, endpoints));
+        let endpoints = endpoints.into_checked(blueprint, rt.clone()).await?;
+        load_wasm_modules(blueprint, &rt).await?;
 
-        Ok(Self { app_ctx: app_context, blueprint })
+        let app_ctx = Arc::new(AppContext::new(blueprint.clone(), rt, endpoints));
+        spawn_health_check(&app_ctx);
+
+        Ok(app_ctx)
     }
 
     pub fn addr(&self) -> SocketAddr {
-        (self.blueprint.server.hostname, self.blueprint.server.port).into()
+        self.addr
     }
 
     pub fn http_version(&self) -> String {
@@ -67,3 +104,72 @@ impl ServerConfig {
         format!("{}://{}", protocol, addr)
     }
 }
+
+/// Compiles the modules of the `@wasm` resolvers, so that a module that can't
+/// be loaded fails the startup instead of the requests using it.
+async fn load_wasm_modules(blueprint: &Blueprint, rt: &TargetRuntime) -> anyhow::Result<()> {
+    let Some(wasm) = rt.wasm.as_ref() else {
+        return Ok(());
+    };
+
+    let mut modules = BTreeSet::new();
+    for def in blueprint.definitions.iter() {
+        if let Definition::Object(def) = def {
+            for resolver in def.fields.iter().filter_map(|field| field.resolver.clone()) {
+                resolver.modify(|ir| {
+                    if let IR::IO(IO::Wasm { module, .. }) = ir {
+                        modules.insert(module.clone());
+                    }
+                    None
+                });
+            }
+        }
+    }
+
+    for module in modules {
+        wasm.load(&module).await?;
+    }
+
+    Ok(())
+}
+
+/// Probes the upstreams of the context in the background. Every generation
+/// of the context gets its own task, which stops once the context is dropped
+/// after a reload.
+fn spawn_health_check(app_ctx: &Arc<AppContext>) {
+    let Some(health_checker) = app_ctx.health_checker.as_ref() else {
+        return;
+    };
+    let mut interval = tokio::time::interval(health_checker.interval());
+    let app_ctx = Arc::downgrade(app_ctx);
+
+    tokio::spawn(async move {
+        loop {
+            interval.tick().await;
+            let Some(app_ctx) = app_ctx.upgrade() else {
+                break;
+            };
+            if let Some(health_checker) = app_ctx.health_checker.as_ref() {
+                health_checker.check(&app_ctx.runtime).await;
+            }
+        }
+    });
+}
+
+/// Resolves the address the server binds to. DNS names are looked up here
+/// rather than while building the blueprint, which can't do any I/O.
+async fn resolve_addr(blueprint: &Blueprint) -> anyhow::Result<SocketAddr> {
+    let port = blueprint.server.port;
+    let name = match &blueprint.server.hostname {
+        Hostname::Ip(ip) => return Ok(SocketAddr::new(*ip, port)),
+        Hostname::Dns(name) => name.clone(),
+    };
+
+    tokio::task::spawn_blocking(move || {
+        (name.as_str(), port)
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("Could not resolve hostname '{}'", name))
+    })
+    .await?
+}
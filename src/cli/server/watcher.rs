@@ -0,0 +1,77 @@
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use super::server_config::ServerConfig;
+use crate::cli::CLIError;
+use crate::core::blueprint::Blueprint;
+use crate::core::config::reader::ConfigReader;
+use crate::core::runtime::TargetRuntime;
+
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Reloads the server when one of the config files changes. The files are
+/// polled for their modification time, links to remote configs are never
+/// reloaded.
+pub struct Watcher {
+    runtime: TargetRuntime,
+    file_paths: Vec<String>,
+    interval: Duration,
+}
+
+impl Watcher {
+    pub fn new(runtime: TargetRuntime, file_paths: Vec<String>) -> Self {
+        Self { runtime, file_paths, interval: DEFAULT_INTERVAL }
+    }
+
+    /// Sets how often the files are checked for changes
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    fn modified(&self) -> Vec<Option<SystemTime>> {
+        self.file_paths
+            .iter()
+            .map(|path| {
+                std::fs::metadata(path)
+                    .and_then(|meta| meta.modified())
+                    .ok()
+            })
+            .collect()
+    }
+
+    /// Watches the files until the server stops. A config that fails to build
+    /// is logged and the last valid one keeps being served.
+    pub async fn watch(self, sc: Arc<ServerConfig>) {
+        let mut last_modified = self.modified();
+        let mut interval = tokio::time::interval(self.interval);
+
+        loop {
+            interval.tick().await;
+
+            let modified = self.modified();
+            if modified == last_modified {
+                continue;
+            }
+            last_modified = modified;
+
+            match self.reload(&sc).await {
+                Ok(_) => tracing::info!("🔄 Config reloaded"),
+                Err(error) => tracing::error!(
+                    "Failed to reload the config, serving the last valid one: {}",
+                    error
+                ),
+            }
+        }
+    }
+
+    async fn reload(&self, sc: &ServerConfig) -> anyhow::Result<()> {
+        // A new reader is used because the reader caches the files it reads
+        let reader = ConfigReader::init(self.runtime.clone());
+        let config_module = reader.read_all(&self.file_paths).await?;
+        let blueprint = Blueprint::try_from(&config_module).map_err(CLIError::from)?;
+
+        sc.reload(blueprint, config_module.extensions.endpoint_set)
+            .await
+    }
+}
@@ -1,4 +1,5 @@
 use std::fs;
+use std::io::Write;
 use std::path::Path;
 
 use anyhow::Result;
@@ -7,19 +8,23 @@ use convert_case::{Case, Casing};
 use dotenvy::dotenv;
 use inquire::Confirm;
 use lazy_static::lazy_static;
+use sha2::{Digest, Sha256};
 use stripmargin::StripMargin;
 
 use super::command::{Cli, Command};
 use super::update_checker;
 use crate::cli::fmt::Fmt;
-use crate::cli::server::Server;
+use crate::cli::server::{Server, Watcher};
 use crate::cli::{self, CLIError};
 use crate::core::blueprint::Blueprint;
+use crate::core::check::{self, CheckReport};
 use crate::core::config::reader::ConfigReader;
+use crate::core::config::{ConfigModule, TlsVersion};
 use crate::core::generator::Generator;
 use crate::core::http::API_URL_PREFIX;
 use crate::core::print_schema;
 use crate::core::rest::{EndpointSet, Unchecked};
+use crate::core::runtime::TargetRuntime;
 const FILE_NAME: &str = ".tailcallrc.graphql";
 const YML_FILE_NAME: &str = ".graphqlrc.yml";
 const JSON_FILE_NAME: &str = ".tailcallrc.schema.json";
@@ -33,8 +38,7 @@ pub async fn run() -> Result<()> {
     }
     let cli = Cli::parse();
     update_checker::check_for_update().await;
-    let runtime = cli::runtime::init(&Blueprint::default());
-    let config_reader = ConfigReader::init(runtime.clone());
+    let runtime = cli::runtime::init(&Blueprint::default())?;
 
     // Initialize ping event every 60 seconds
     let _ = TRACKER
@@ -46,39 +50,71 @@ pub async fn run() -> Result<()> {
         .dispatch(cli.command.to_string().to_case(Case::Snake).as_str())
         .await;
     match cli.command {
-        Command::Start { file_paths } => {
-            let config_module = config_reader.read_all(&file_paths).await?;
+        Command::Start { file_paths, watch, snapshot } => {
+            let runtime = init_config_runtime(&runtime, &file_paths).await?;
+            let config_reader = ConfigReader::init(runtime.clone());
+            let config_module = match snapshot {
+                Some(snapshot) => {
+                    read_with_snapshot(&config_reader, &file_paths, &snapshot).await?
+                }
+                None => config_reader.read_all(&file_paths).await?,
+            };
             log_endpoint_set(&config_module.extensions.endpoint_set);
             Fmt::log_n_plus_one(false, &config_module.config);
-            let server = Server::new(config_module);
+            let watch = watch || config_module.config.server.enable_watch();
+            let mut server = Server::new(config_module);
+            if watch {
+                server = server.watch(Watcher::new(runtime.clone(), file_paths));
+            }
             server.fork_start().await?;
             Ok(())
         }
-        Command::Check { file_paths, n_plus_one_queries, schema, format } => {
+        Command::Check {
+            file_paths,
+            n_plus_one_queries,
+            schema,
+            format,
+            deny_warnings,
+        } => {
+            let runtime = init_config_runtime(&runtime, &file_paths).await?;
+            let config_reader = ConfigReader::init(runtime.clone());
             let config_module = (config_reader.read_all(&file_paths)).await?;
             log_endpoint_set(&config_module.extensions.endpoint_set);
             if let Some(format) = format {
                 Fmt::display(format.encode(&config_module)?);
             }
-            let blueprint = Blueprint::try_from(&config_module).map_err(CLIError::from);
-
-            match blueprint {
-                Ok(blueprint) => {
-                    tracing::info!("Config {} ... ok", file_paths.join(", "));
-                    Fmt::log_n_plus_one(n_plus_one_queries, &config_module.config);
-                    // Check the endpoints' schema
-                    let _ = config_module
-                        .extensions
-                        .endpoint_set
-                        .into_checked(&blueprint, runtime)
-                        .await?;
-                    if schema {
-                        display_schema(&blueprint);
-                    }
-                    Ok(())
-                }
-                Err(e) => Err(e.into()),
+
+            let (blueprint, report) = check::check_blueprint(&config_module);
+            if report.exit_code(deny_warnings) != exitcode::OK {
+                return Err(report_error(&report, deny_warnings).into());
+            }
+            // The N + 1 queries are logged below along with their count
+            for warning in report
+                .warnings
+                .iter()
+                .filter(|warning| warning.message != check::N_PLUS_ONE)
+            {
+                tracing::warn!("{}", CLIError::from(warning).color(true));
+            }
+
+            // The blueprint is always built when the report has no errors
+            let Some(blueprint) = blueprint else {
+                return Err(report_error(&report, deny_warnings).into());
+            };
+
+            tracing::info!("Config {} ... ok", file_paths.join(", "));
+            log_warnings(&blueprint);
+            Fmt::log_n_plus_one(n_plus_one_queries, &config_module.config);
+            // Check the endpoints' schema
+            let _ = config_module
+                .extensions
+                .endpoint_set
+                .into_checked(&blueprint, runtime)
+                .await?;
+            if schema {
+                display_schema(&blueprint);
             }
+            Ok(())
         }
         Command::Init { folder_path } => init(&folder_path).await,
         Command::Gen { paths, input, output, query } => {
@@ -94,6 +130,92 @@ pub async fn run() -> Result<()> {
     }
 }
 
+/// Builds the runtime that fetches the links and the introspections of the
+/// config files with their `@upstream(minTls:)`. The files themselves are read
+/// before their upstream is known.
+async fn init_config_runtime(
+    runtime: &TargetRuntime,
+    file_paths: &[String],
+) -> Result<TargetRuntime> {
+    let upstream = ConfigReader::init(runtime.clone())
+        .read_upstream(file_paths)
+        .await?;
+
+    let min_tls = upstream.get_min_tls();
+    if min_tls == TlsVersion::default() {
+        return Ok(runtime.clone());
+    }
+
+    let mut blueprint = Blueprint::default();
+    blueprint.upstream.min_tls = min_tls;
+    cli::runtime::init(&blueprint)
+}
+
+/// Loads the config module from the snapshot, the config files are read and
+/// the snapshot is written again when it's missing, invalid or was written
+/// from other config files
+async fn read_with_snapshot(
+    config_reader: &ConfigReader,
+    file_paths: &[String],
+    snapshot: &str,
+) -> Result<ConfigModule> {
+    let source = snapshot_source(file_paths)?;
+
+    if Path::new(snapshot).exists() {
+        match fs::read(snapshot)
+            .map_err(anyhow::Error::from)
+            .and_then(|bytes| ConfigModule::from_bytes(&bytes, &source))
+        {
+            Ok(config_module) => {
+                tracing::info!("Config loaded from the snapshot {}", snapshot);
+                return Ok(config_module);
+            }
+            Err(e) => tracing::warn!("Ignoring the snapshot {}: {}", snapshot, e),
+        }
+    }
+
+    let config_module = config_reader.read_all(file_paths).await?;
+    write_private(snapshot, &config_module.to_bytes(&source)?)?;
+    tracing::info!("Config snapshot written to {}", snapshot);
+
+    Ok(config_module)
+}
+
+/// Hashes the paths of the config files along with their contents, so that a
+/// snapshot is only loaded for the files it was written from. Remote files
+/// and the files linked in the config are only identified by their path.
+fn snapshot_source(file_paths: &[String]) -> Result<String> {
+    let mut hasher = Sha256::new();
+    for path in file_paths {
+        hasher.update(path.as_bytes());
+        if !path.starts_with("http://") && !path.starts_with("https://") {
+            hasher.update(fs::read(path)?);
+        }
+    }
+
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Writes the file so that only its owner can read it, the snapshot contains
+/// the secrets linked in the config
+fn write_private(path: &str, content: &[u8]) -> Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+
+        options.mode(0o600);
+        // The mode is only applied to new files
+        if Path::new(path).exists() {
+            fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+        }
+    }
+
+    options.open(path)?.write_all(content)?;
+    Ok(())
+}
+
 pub async fn init(folder_path: &str) -> Result<()> {
     let folder_exists = fs::metadata(folder_path).is_ok();
 
@@ -202,6 +324,27 @@ fn log_endpoint_set(endpoint_set: &EndpointSet<Unchecked>) {
     }
 }
 
+/// Lists the diagnostics that failed the check, ie. the errors and, when
+/// `deny_warnings` is set, the warnings.
+fn report_error(report: &CheckReport, deny_warnings: bool) -> CLIError {
+    let warnings = report.warnings.iter().filter(|_| deny_warnings);
+
+    CLIError::new("Invalid Configuration").caused_by(
+        report
+            .errors
+            .iter()
+            .chain(warnings)
+            .map(CLIError::from)
+            .collect(),
+    )
+}
+
+pub(crate) fn log_warnings(blueprint: &Blueprint) {
+    for warning in blueprint.warnings.iter() {
+        tracing::warn!("{}", warning);
+    }
+}
+
 pub fn display_schema(blueprint: &Blueprint) {
     Fmt::display(Fmt::heading("GraphQL Schema:\n"));
     let sdl = blueprint.to_schema();
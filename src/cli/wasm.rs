@@ -0,0 +1,330 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context};
+use async_graphql_value::ConstValue;
+use tokio::sync::OnceCell;
+use wasmtime::{
+    Engine, Instance, Memory, Module, Store, StoreLimits, StoreLimitsBuilder, TypedFunc,
+};
+
+use crate::core::WasmIO;
+
+/// Interval at which the engine checks the deadline of the running calls.
+const TICK: Duration = Duration::from_millis(10);
+
+/// Runs the modules of `@wasm` with wasmtime. Every call gets its own
+/// instance, without any import, a capped memory and a deadline.
+pub struct NativeWasm {
+    engine: Engine,
+    // Every module is compiled once, the calls waiting for its compilation
+    // share the result
+    modules: Mutex<HashMap<String, Arc<OnceCell<Module>>>>,
+    max_memory: usize,
+    timeout: Duration,
+    ticker: OnceLock<Arc<AtomicBool>>,
+}
+
+impl Default for NativeWasm {
+    fn default() -> Self {
+        Self::new(64 * 1024 * 1024, Duration::from_secs(1))
+    }
+}
+
+impl NativeWasm {
+    pub fn new(max_memory: usize, timeout: Duration) -> Self {
+        let mut config = wasmtime::Config::new();
+        config.epoch_interruption(true);
+        // NOTE: Using expect because the config is static and always valid
+        let engine = Engine::new(&config).expect("WASM engine not initialized");
+
+        Self {
+            engine,
+            modules: Mutex::new(HashMap::new()),
+            max_memory,
+            timeout,
+            ticker: OnceLock::new(),
+        }
+    }
+
+    /// Starts the thread moving the engine forward, it's only started with
+    /// the first call so that configs without `@wasm` don't pay for it.
+    fn start_ticker(&self) {
+        self.ticker.get_or_init(|| {
+            let stopped = Arc::new(AtomicBool::new(false));
+            let engine = self.engine.clone();
+            let ticker_stopped = stopped.clone();
+            thread::spawn(move || {
+                while !ticker_stopped.load(Ordering::Relaxed) {
+                    thread::sleep(TICK);
+                    engine.increment_epoch();
+                }
+            });
+            stopped
+        });
+    }
+
+    /// Returns the compiled module, compiling it on a blocking thread the
+    /// first time it's used.
+    async fn module(&self, path: &str) -> anyhow::Result<Module> {
+        let cell = self
+            .modules
+            .lock()
+            .unwrap()
+            .entry(path.to_string())
+            .or_default()
+            .clone();
+
+        let module = cell
+            .get_or_try_init(|| {
+                let engine = self.engine.clone();
+                let path = path.to_string();
+                async move {
+                    tokio::task::spawn_blocking(move || {
+                        Module::from_file(&engine, &path)
+                            .with_context(|| format!("Failed to load the WASM module {}", path))
+                    })
+                    .await?
+                }
+            })
+            .await?;
+
+        Ok(module.clone())
+    }
+
+    fn deadline(&self) -> u64 {
+        (self.timeout.as_millis() / TICK.as_millis()).max(1) as u64
+    }
+}
+
+impl Drop for NativeWasm {
+    fn drop(&mut self) {
+        if let Some(stopped) = self.ticker.get() {
+            stopped.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+struct State {
+    limits: StoreLimits,
+}
+
+/// Copies the bytes into the memory of the instance, in a buffer allocated by
+/// the module itself.
+fn write(
+    store: &mut Store<State>,
+    memory: &Memory,
+    alloc: &TypedFunc<i32, i32>,
+    bytes: &[u8],
+) -> anyhow::Result<(i32, i32)> {
+    let len = i32::try_from(bytes.len())?;
+    let ptr = alloc.call(&mut *store, len)?;
+    memory.write(&mut *store, ptr as u32 as usize, bytes)?;
+    Ok((ptr, len))
+}
+
+fn call(
+    mut store: Store<State>,
+    module: &Module,
+    func: &str,
+    value: &[u8],
+    args: &[u8],
+) -> anyhow::Result<ConstValue> {
+    store.limiter(|state| &mut state.limits);
+
+    // No imports are provided, so the module has no access to the host
+    let instance = Instance::new(&mut store, module, &[])?;
+    let memory = instance
+        .get_memory(&mut store, "memory")
+        .ok_or_else(|| anyhow!("The WASM module doesn't export its memory"))?;
+    let alloc = instance.get_typed_func::<i32, i32>(&mut store, "alloc")?;
+    let func = instance.get_typed_func::<(i32, i32, i32, i32), i64>(&mut store, func)?;
+
+    let (value_ptr, value_len) = write(&mut store, &memory, &alloc, value)?;
+    let (args_ptr, args_len) = write(&mut store, &memory, &alloc, args)?;
+    let output = func.call(&mut store, (value_ptr, value_len, args_ptr, args_len))?;
+
+    let ptr = (output >> 32) as u32 as usize;
+    let len = output as u32 as usize;
+    let bytes = memory
+        .data(&store)
+        .get(ptr..ptr + len)
+        .ok_or_else(|| anyhow!("The WASM module returned an out of bounds output"))?;
+
+    Ok(serde_json::from_slice(bytes)?)
+}
+
+#[async_trait::async_trait]
+impl WasmIO for NativeWasm {
+    async fn load(&self, module: &str) -> anyhow::Result<()> {
+        self.module(module).await.map(|_| ())
+    }
+
+    async fn call(
+        &self,
+        module: &str,
+        func: &str,
+        value: ConstValue,
+        args: ConstValue,
+    ) -> anyhow::Result<ConstValue> {
+        self.start_ticker();
+
+        let module = self.module(module).await?;
+        let func = func.to_string();
+        let value = serde_json::to_vec(&value)?;
+        let args = serde_json::to_vec(&args)?;
+
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(self.max_memory)
+            .instances(1)
+            .build();
+        let mut store = Store::new(&self.engine, State { limits });
+        store.set_epoch_deadline(self.deadline());
+
+        tokio::task::spawn_blocking(move || call(store, &module, &func, &value, &args)).await?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::*;
+
+    /// Uppercases the string values of the fields of the object it receives
+    const UPPERCASE: &str = r#"
+    (module
+      (memory (export "memory") 1)
+      (global $next (mut i32) (i32.const 1024))
+      (func (export "alloc") (param $len i32) (result i32)
+        (local $ptr i32)
+        (local.set $ptr (global.get $next))
+        (global.set $next (i32.add (global.get $next) (local.get $len)))
+        (local.get $ptr))
+      (func (export "uppercase")
+        (param $ptr i32) (param $len i32) (param $args_ptr i32) (param $args_len i32)
+        (result i64)
+        (local $i i32) (local $byte i32) (local $in_string i32) (local $in_value i32)
+        (block $done
+          (loop $next
+            (br_if $done (i32.ge_u (local.get $i) (local.get $len)))
+            (local.set $byte (i32.load8_u (i32.add (local.get $ptr) (local.get $i))))
+            (if (i32.eq (local.get $byte) (i32.const 34))
+              (then (local.set $in_string (i32.eqz (local.get $in_string))))
+              (else
+                (if (local.get $in_string)
+                  (then
+                    (if (i32.and
+                          (local.get $in_value)
+                          (i32.and
+                            (i32.ge_u (local.get $byte) (i32.const 97))
+                            (i32.le_u (local.get $byte) (i32.const 122))))
+                      (then
+                        (i32.store8
+                          (i32.add (local.get $ptr) (local.get $i))
+                          (i32.sub (local.get $byte) (i32.const 32))))))
+                  (else
+                    (if (i32.eq (local.get $byte) (i32.const 58))
+                      (then (local.set $in_value (i32.const 1))))
+                    (if (i32.or
+                          (i32.eq (local.get $byte) (i32.const 44))
+                          (i32.eq (local.get $byte) (i32.const 123)))
+                      (then (local.set $in_value (i32.const 0))))))))
+            (local.set $i (i32.add (local.get $i) (i32.const 1)))
+            (br $next)))
+        (i64.or
+          (i64.shl (i64.extend_i32_u (local.get $ptr)) (i64.const 32))
+          (i64.extend_i32_u (local.get $len)))))
+    "#;
+
+    fn module(source: &str) -> tempfile::NamedTempFile {
+        let mut file = tempfile::Builder::new().suffix(".wat").tempfile().unwrap();
+        file.write_all(source.as_bytes()).unwrap();
+        file
+    }
+
+    async fn call(wasm: &NativeWasm, source: &str, func: &str) -> anyhow::Result<ConstValue> {
+        let file = module(source);
+        let value = ConstValue::from_json(json!({"id": 1, "name": "leanne"})).unwrap();
+        wasm.call(
+            file.path().to_str().unwrap(),
+            func,
+            value,
+            ConstValue::from_json(json!({})).unwrap(),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_call() {
+        let actual = call(&NativeWasm::default(), UPPERCASE, "uppercase")
+            .await
+            .unwrap();
+        let expected = ConstValue::from_json(json!({"id": 1, "name": "LEANNE"})).unwrap();
+        assert_eq!(actual, expected);
+    }
+
+    #[tokio::test]
+    async fn test_load() {
+        let wasm = NativeWasm::default();
+        let file = module(UPPERCASE);
+        assert!(wasm.load(file.path().to_str().unwrap()).await.is_ok());
+
+        let file = module("(module");
+        assert!(wasm.load(file.path().to_str().unwrap()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_missing_func() {
+        let actual = call(&NativeWasm::default(), UPPERCASE, "lowercase").await;
+        assert!(actual.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_timeout() {
+        let source = r#"
+        (module
+          (memory (export "memory") 1)
+          (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+          (func (export "spin") (param i32 i32 i32 i32) (result i64)
+            (loop $forever (br $forever))
+            (i64.const 0)))
+        "#;
+        let wasm = NativeWasm::new(1024 * 1024, Duration::from_millis(50));
+        let actual = call(&wasm, source, "spin").await;
+        assert!(actual.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_memory_limit() {
+        // 32 pages of 64KiB are above the limit of 1MiB
+        let source = r#"
+        (module
+          (memory (export "memory") 32)
+          (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+          (func (export "noop") (param i32 i32 i32 i32) (result i64) (i64.const 0)))
+        "#;
+        let wasm = NativeWasm::new(1024 * 1024, Duration::from_secs(1));
+        let actual = call(&wasm, source, "noop").await;
+        assert!(actual.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_imports_are_not_provided() {
+        let source = r#"
+        (module
+          (import "wasi_snapshot_preview1" "proc_exit" (func (param i32)))
+          (memory (export "memory") 1)
+          (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+          (func (export "noop") (param i32 i32 i32 i32) (result i64) (i64.const 0)))
+        "#;
+        let actual = call(&NativeWasm::default(), source, "noop").await;
+        assert!(actual.is_err());
+    }
+}
@@ -1,19 +1,28 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::sync::Arc;
+use std::time::Duration;
 
 use async_graphql::dynamic::{self, DynamicRequest};
 use async_graphql::Response;
+use async_graphql_value::ConstValue;
+use hyper::body::Bytes;
 
 use crate::core::auth::context::GlobalAuthContext;
 use crate::core::blueprint::Type::ListType;
 use crate::core::blueprint::{Blueprint, Definition, SchemaModifiers};
 use crate::core::data_loader::DataLoader;
 use crate::core::graphql::GraphqlDataLoader;
-use crate::core::grpc;
 use crate::core::grpc::data_loader::GrpcDataLoader;
-use crate::core::http::{DataLoaderRequest, HttpDataLoader};
-use crate::core::ir::{DataLoaderId, IO, IR};
+use crate::core::http::{
+    playground_source, CircuitBreaker, ConcurrencyLimit, DataLoaderRequest, DedupeWindow,
+    HealthChecker, HttpDataLoader, RequestLimit,
+};
+use crate::core::ir::{DataLoaderId, EvaluationError, IoId, NativeResolvers, IO, IR};
+use crate::core::path::PathString;
 use crate::core::rest::{Checked, EndpointSet};
 use crate::core::runtime::TargetRuntime;
+use crate::core::{grpc, EnvIO};
 
 pub struct AppContext {
     pub schema: dynamic::Schema,
@@ -24,22 +33,94 @@ pub struct AppContext {
     pub grpc_data_loaders: Arc<Vec<DataLoader<grpc::DataLoaderRequest, GrpcDataLoader>>>,
     pub endpoints: EndpointSet<Checked>,
     pub auth_ctx: Arc<GlobalAuthContext>,
+    pub circuit_breaker: Option<Arc<CircuitBreaker>>,
+    /// Probes the hosts of the `baseURLs` pools, it's run in the background by
+    /// the server
+    pub health_checker: Option<Arc<HealthChecker>>,
+    /// Shares the upstream reads across requests within `dedupeWindow`
+    pub dedupe_window: Option<Arc<DedupeWindow<IoId, ConstValue, EvaluationError>>>,
+    /// The page of the playground, it's only built once when enabled
+    pub playground: Option<Bytes>,
+    /// Caps the number of GraphQL requests executed at the same time
+    pub request_limit: Option<Arc<RequestLimit>>,
+}
+
+/// Looks for `@modify(wrapInList:)` through the IRs wrapping the resolver
+fn is_wrapped_in_list(resolver: &IR) -> bool {
+    match resolver {
+        IR::WrapInList(_) => true,
+        IR::Cache(cache) => is_wrapped_in_list(&cache.expr),
+        IR::Path(expr, _)
+        | IR::Protect(expr)
+        | IR::OnError { expr, .. }
+        | IR::WithDefault { expr, .. }
+        | IR::Patch { expr, .. }
+        | IR::Flatten { expr, .. }
+        | IR::Memoize { expr, .. } => is_wrapped_in_list(expr),
+        _ => false,
+    }
 }
 
 impl AppContext {
     pub fn new(
-        mut blueprint: Blueprint,
+        blueprint: Blueprint,
         runtime: TargetRuntime,
         endpoints: EndpointSet<Checked>,
     ) -> Self {
+        Self::with_resolvers(blueprint, runtime, endpoints, NativeResolvers::default())
+    }
+
+    /// Creates the context with native resolvers for some of the fields, they
+    /// replace the resolvers of the directives of those fields.
+    pub fn with_resolvers(
+        mut blueprint: Blueprint,
+        mut runtime: TargetRuntime,
+        endpoints: EndpointSet<Checked>,
+        mut resolvers: NativeResolvers,
+    ) -> Self {
+        for def in blueprint.definitions.iter_mut() {
+            if let Definition::Object(def) = def {
+                for field in &mut def.fields {
+                    let key = (def.name.clone(), field.name.clone());
+                    if let Some(resolver) = resolvers.remove(&key) {
+                        field.resolver = Some(IR::IO(IO::Native(resolver)));
+                    }
+                }
+            }
+        }
+        for (type_name, field_name) in resolvers.keys() {
+            tracing::warn!(
+                "Native resolver of {}.{} is not used, the field is not defined",
+                type_name,
+                field_name
+            );
+        }
+
+        // The limit is shared by the clients so that they can't exceed it
+        // together, it applies to the data loaders as well
+        if let Some(max_concurrency) = blueprint.upstream.max_concurrency {
+            let limit = Arc::new(ConcurrencyLimit::new(max_concurrency));
+            runtime.http = limit.limit(runtime.http);
+            runtime.http2_only = limit.limit(runtime.http2_only);
+        }
+
         let mut http_data_loaders = vec![];
         let mut gql_data_loaders = vec![];
         let mut grpc_data_loaders = vec![];
+        let mut url_pools = vec![];
+        let static_ctx = StaticContext { env: runtime.env.as_ref(), vars: &blueprint.server.vars };
 
+        // Every IO gets its own data loader and a `DataLoaderId` pointing to it.
+        // Whether the requests are batched was decided while compiling the
+        // blueprint: by the group by of http and grpc and the batch flag of
+        // graphql, which already include the fields batched by default.
         for def in blueprint.definitions.iter_mut() {
             if let Definition::Object(def) = def {
                 for field in &mut def.fields {
-                    let of_type = field.of_type.clone();
+                    // The upstream of a field wrapped in a list resolves a
+                    // single item, grouped by the data loader like any other
+                    let is_list = matches!(field.of_type, ListType { .. })
+                        && !field.resolver.as_ref().is_some_and(is_wrapped_in_list);
                     let upstream_batch = &blueprint.upstream.batch;
                     field.map_expr(|expr| {
                         expr.modify(|expr| match expr {
@@ -48,12 +129,16 @@ impl AppContext {
                                     let data_loader = HttpDataLoader::new(
                                         runtime.clone(),
                                         group_by.clone(),
-                                        matches!(of_type, ListType { .. }),
+                                        is_list,
                                     )
+                                    .expect_status(req_template.endpoint.expect_status.clone())
                                     .to_data_loader(upstream_batch.clone().unwrap_or_default());
+                                    url_pools.extend(req_template.url_pool.clone());
 
                                     let result = Some(IR::IO(IO::Http {
-                                        req_template: req_template.clone(),
+                                        req_template: req_template
+                                            .clone()
+                                            .partially_render(&static_ctx),
                                         group_by: group_by.clone(),
                                         dl_id: Some(DataLoaderId::new(http_data_loaders.len())),
                                         http_filter: http_filter.clone(),
@@ -64,9 +149,12 @@ impl AppContext {
                                     result
                                 }
 
-                                IO::GraphQL { req_template, field_name, batch, .. } => {
+                                IO::GraphQL {
+                                    req_template, field_name, batch, timeout, ..
+                                } => {
                                     let graphql_data_loader =
                                         GraphqlDataLoader::new(runtime.clone(), *batch)
+                                            .entities(req_template.entity.is_some())
                                             .into_data_loader(
                                                 upstream_batch.clone().unwrap_or_default(),
                                             );
@@ -75,6 +163,7 @@ impl AppContext {
                                         req_template: req_template.clone(),
                                         field_name: field_name.clone(),
                                         batch: *batch,
+                                        timeout: *timeout,
                                         dl_id: Some(DataLoaderId::new(gql_data_loaders.len())),
                                     }));
 
@@ -106,7 +195,11 @@ impl AppContext {
                                 IO::Js { name: method } => {
                                     Some(IR::IO(IO::Js { name: method.clone() }))
                                 }
+                                IO::Wasm { .. } | IO::Native(_) => Some(IR::IO(io.clone())),
                             },
+                            IR::Dynamic(value) => {
+                                Some(IR::Dynamic(value.partially_render(&static_ctx)))
+                            }
                             _ => None,
                         })
                     });
@@ -118,6 +211,32 @@ impl AppContext {
             .to_schema_with(SchemaModifiers::default().extensions(runtime.extensions.clone()));
         let auth = blueprint.server.auth.clone();
         let auth_ctx = GlobalAuthContext::new(auth);
+        let circuit_breaker = blueprint
+            .upstream
+            .circuit_breaker
+            .as_ref()
+            .map(|circuit_breaker| Arc::new(CircuitBreaker::from(circuit_breaker)));
+        let health_checker = blueprint
+            .upstream
+            .health_check
+            .as_ref()
+            .filter(|_| !url_pools.is_empty())
+            .map(|health_check| Arc::new(HealthChecker::new(health_check, url_pools)));
+        let dedupe_window = blueprint
+            .upstream
+            .dedupe_window
+            .map(|window| Arc::new(DedupeWindow::new(Duration::from_millis(window))));
+
+        let playground =
+            playground_source(blueprint.server.playground, &blueprint.server.graphql_path)
+                .map(Bytes::from);
+        let request_limit = blueprint.server.request_limits.as_ref().map(|limits| {
+            Arc::new(RequestLimit::new(
+                limits.max_concurrent_requests,
+                limits.queue_size,
+                limits.retry_after,
+            ))
+        });
 
         AppContext {
             schema,
@@ -128,6 +247,11 @@ impl AppContext {
             grpc_data_loaders: Arc::new(grpc_data_loaders),
             endpoints,
             auth_ctx: Arc::new(auth_ctx),
+            circuit_breaker,
+            health_checker,
+            dedupe_window,
+            playground,
+            request_limit,
         }
     }
 
@@ -135,3 +259,96 @@ impl AppContext {
         self.schema.execute(request).await
     }
 }
+
+/// Resolves the values that stay the same for every request, so they can be
+/// rendered once at startup.
+struct StaticContext<'a> {
+    env: &'a dyn EnvIO,
+    vars: &'a BTreeMap<String, String>,
+}
+
+impl PathString for StaticContext<'_> {
+    fn path_string<T: AsRef<str>>(&self, path: &[T]) -> Option<Cow<'_, str>> {
+        match path {
+            [head, key] => match head.as_ref() {
+                "env" => self.env.get(key.as_ref()),
+                "vars" => self.vars.get(key.as_ref()).map(|value| value.into()),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use hyper::{Body, Request};
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::*;
+    use crate::core::async_graphql_hyper::GraphQLRequest;
+    use crate::core::config::{Config, ConfigModule};
+    use crate::core::http::handle_request;
+    use crate::core::ir::NativeResolver;
+    use crate::core::valid::Validator;
+
+    #[tokio::test]
+    async fn test_native_resolver() {
+        let config = Config::from_sdl(
+            r#"
+            schema {
+              query: Query
+            }
+
+            type Query {
+              greet(name: String!): String @expr(body: "Hello World!")
+              version: String @expr(body: "1.0")
+            }
+            "#,
+        )
+        .to_result()
+        .unwrap();
+        let blueprint = Blueprint::try_from(&ConfigModule::from(config)).unwrap();
+        let runtime = crate::core::runtime::test::init(None);
+
+        let greet = NativeResolver::new(|ctx| async move {
+            match ctx.args {
+                ConstValue::Object(args) => match args.get("name") {
+                    Some(ConstValue::String(name)) => {
+                        Ok(ConstValue::String(format!("Hello {}!", name)))
+                    }
+                    _ => Ok(ConstValue::Null),
+                },
+                _ => Ok(ConstValue::Null),
+            }
+        });
+        let resolvers =
+            NativeResolvers::from([(("Query".to_string(), "greet".to_string()), greet)]);
+        let app_ctx = Arc::new(AppContext::with_resolvers(
+            blueprint,
+            runtime,
+            EndpointSet::default(),
+            resolvers,
+        ));
+
+        let query = json!({"query": "{ greet(name: \"Tailcall\") version }"});
+        let req = Request::builder()
+            .method("POST")
+            .uri("http://localhost:8000/graphql")
+            .body(Body::from(query.to_string()))
+            .unwrap();
+        let res = handle_request::<GraphQLRequest>(req, app_ctx)
+            .await
+            .unwrap();
+
+        let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body,
+            json!({"data": {"greet": "Hello Tailcall!", "version": "1.0"}})
+        );
+    }
+}
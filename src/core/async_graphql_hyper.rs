@@ -8,6 +8,8 @@ use hyper::{Body, Response, StatusCode};
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 
+use crate::core::config::ErrorFormat;
+
 #[async_trait::async_trait]
 pub trait GraphQLRequestLike {
     fn data<D: Any + Clone + Send + Sync>(self, data: D) -> Self;
@@ -16,6 +18,15 @@ pub trait GraphQLRequestLike {
         E: Executor;
 
     fn parse_query(&mut self) -> Option<&ExecutableDocument>;
+
+    /// Returns the raw queries of all the operations in the request
+    fn queries(&self) -> Vec<&str>;
+
+    /// Creates the request from an already parsed batch, eg. the one of a
+    /// multipart request
+    fn from_batch(batch: async_graphql::BatchRequest) -> Result<Self>
+    where
+        Self: Sized;
 }
 
 #[derive(Debug, Deserialize)]
@@ -41,6 +52,17 @@ impl GraphQLRequestLike for GraphQLBatchRequest {
     fn parse_query(&mut self) -> Option<&ExecutableDocument> {
         None
     }
+
+    fn queries(&self) -> Vec<&str> {
+        self.0
+            .iter()
+            .map(|request| request.query.as_str())
+            .collect()
+    }
+
+    fn from_batch(batch: async_graphql::BatchRequest) -> Result<Self> {
+        Ok(Self(batch))
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -66,6 +88,14 @@ impl GraphQLRequestLike for GraphQLRequest {
     fn parse_query(&mut self) -> Option<&ExecutableDocument> {
         self.0.parsed_query().ok()
     }
+
+    fn queries(&self) -> Vec<&str> {
+        vec![self.0.query.as_str()]
+    }
+
+    fn from_batch(batch: async_graphql::BatchRequest) -> Result<Self> {
+        Ok(Self(batch.into_single()?))
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -120,6 +150,9 @@ impl GraphQLQuery {
 static APPLICATION_JSON: Lazy<HeaderValue> =
     Lazy::new(|| HeaderValue::from_static("application/json"));
 
+static APPLICATION_PROBLEM_JSON: Lazy<HeaderValue> =
+    Lazy::new(|| HeaderValue::from_static("application/problem+json"));
+
 impl GraphQLResponse {
     fn build_response(&self, status: StatusCode, body: Body) -> Result<Response<Body>> {
         let mut response = Response::builder()
@@ -143,6 +176,32 @@ impl GraphQLResponse {
         Ok(Body::from(serde_json::to_string(&self.0)?))
     }
 
+    /// Serializes the errors of the response as an [RFC 7807](https://datatracker.ietf.org/doc/html/rfc7807)
+    /// problem, whose `detail` lists the messages of the errors.
+    fn problem_json_response(&self, status: StatusCode) -> Result<Response<Body>> {
+        let errors: Vec<_> = match &self.0 {
+            BatchResponse::Single(res) => res.errors.iter().collect(),
+            BatchResponse::Batch(list) => list.iter().flat_map(|res| res.errors.iter()).collect(),
+        };
+        let detail = errors
+            .iter()
+            .map(|error| error.message.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let problem = serde_json::json!({
+            "type": "about:blank",
+            "title": status.canonical_reason().unwrap_or_default(),
+            "status": status.as_u16(),
+            "detail": detail,
+        });
+
+        let mut response = self.build_response(status, Body::from(problem.to_string()))?;
+        response
+            .headers_mut()
+            .insert(CONTENT_TYPE, APPLICATION_PROBLEM_JSON.clone());
+        Ok(response)
+    }
+
     pub fn into_response(self) -> Result<Response<hyper::Body>> {
         self.build_response(StatusCode::OK, self.default_body()?)
     }
@@ -157,9 +216,17 @@ impl GraphQLResponse {
     /// Transforms a plain `GraphQLResponse` into a `Response<Body>`.
     /// Differs as `to_response` by flattening the response's data
     /// `{"data": {"user": {"name": "John"}}}` becomes `{"name": "John"}`.
-    pub fn into_rest_response(self) -> Result<Response<hyper::Body>> {
+    /// Errors are serialized as per the `error_format`.
+    pub fn into_rest_response(self, error_format: ErrorFormat) -> Result<Response<hyper::Body>> {
         if !self.0.is_ok() {
-            return self.build_response(StatusCode::INTERNAL_SERVER_ERROR, self.default_body()?);
+            return match error_format {
+                ErrorFormat::GraphQL => {
+                    self.build_response(StatusCode::INTERNAL_SERVER_ERROR, self.default_body()?)
+                }
+                ErrorFormat::ProblemJson => {
+                    self.problem_json_response(StatusCode::INTERNAL_SERVER_ERROR)
+                }
+            };
         }
 
         match self.0 {
@@ -213,6 +280,21 @@ impl GraphQLResponse {
         };
         self
     }
+
+    /// Adds an entry to the `extensions` of every response in the batch.
+    pub fn set_extension(mut self, name: &str, value: Value) -> GraphQLResponse {
+        match self.0 {
+            BatchResponse::Single(ref mut res) => {
+                res.extensions.insert(name.to_string(), value);
+            }
+            BatchResponse::Batch(ref mut list) => {
+                for res in list {
+                    res.extensions.insert(name.to_string(), value.clone());
+                }
+            }
+        };
+        self
+    }
 }
 
 #[cfg(test)]
@@ -232,7 +314,7 @@ mod tests {
         let data = IndexMap::from([(Name::new("user"), Value::Object(user))]);
 
         let response = GraphQLResponse(BatchResponse::Single(Response::new(Value::Object(data))));
-        let rest_response = response.into_rest_response().unwrap();
+        let rest_response = response.into_rest_response(ErrorFormat::GraphQL).unwrap();
 
         assert_eq!(rest_response.status(), StatusCode::OK);
         assert_eq!(rest_response.headers()["content-type"], "application/json");
@@ -259,7 +341,7 @@ mod tests {
             .collect();
 
         let response = GraphQLResponse(BatchResponse::Batch(list));
-        let rest_response = response.into_rest_response().unwrap();
+        let rest_response = response.into_rest_response(ErrorFormat::GraphQL).unwrap();
 
         assert_eq!(rest_response.status(), StatusCode::OK);
         assert_eq!(rest_response.headers()["content-type"], "application/json");
@@ -288,7 +370,7 @@ mod tests {
             .map(|error| ServerError::new(error.to_string(), None))
             .collect();
         let response = GraphQLResponse(BatchResponse::Single(response));
-        let rest_response = response.into_rest_response().unwrap();
+        let rest_response = response.into_rest_response(ErrorFormat::GraphQL).unwrap();
 
         assert_eq!(rest_response.status(), StatusCode::INTERNAL_SERVER_ERROR);
         assert_eq!(rest_response.headers()["content-type"], "application/json");
@@ -310,4 +392,35 @@ mod tests {
             .to_vec()
         );
     }
+
+    #[tokio::test]
+    async fn test_to_rest_response_with_problem_json() {
+        let mut response: Response = Default::default();
+        response.errors = vec![
+            ServerError::new("Some error", None),
+            ServerError::new("Another error", None),
+        ];
+        let response = GraphQLResponse(BatchResponse::Single(response));
+        let rest_response = response
+            .into_rest_response(ErrorFormat::ProblemJson)
+            .unwrap();
+
+        assert_eq!(rest_response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(
+            rest_response.headers()["content-type"],
+            "application/problem+json"
+        );
+        let body = hyper::body::to_bytes(rest_response.into_body())
+            .await
+            .unwrap();
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap(),
+            json!({
+                "type": "about:blank",
+                "title": "Internal Server Error",
+                "status": 500,
+                "detail": "Some error, Another error",
+            })
+        );
+    }
 }
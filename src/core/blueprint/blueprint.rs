@@ -1,4 +1,5 @@
 use std::collections::{BTreeSet, HashMap};
+use std::fmt::Display;
 use std::sync::Arc;
 
 use async_graphql::dynamic::{Schema, SchemaBuilder};
@@ -9,10 +10,12 @@ use derive_setters::Setters;
 use serde_json::Value;
 
 use super::telemetry::Telemetry;
-use super::GlobalTimeout;
+use super::{GlobalTimeout, NoIntrospection};
 use crate::core::blueprint::{Server, Upstream};
 use crate::core::ir::IR;
+use crate::core::scalar;
 use crate::core::schema_extension::SchemaExtension;
+use crate::core::valid::Cause;
 
 /// Blueprint is an intermediary representation that allows us to generate
 /// graphQL APIs. It can only be generated from a valid Config.
@@ -25,6 +28,9 @@ pub struct Blueprint {
     pub server: Server,
     pub upstream: Upstream,
     pub telemetry: Telemetry,
+    pub version: Option<String>,
+    /// Non fatal issues found while building the blueprint
+    pub warnings: Vec<Cause<String>>,
 }
 
 #[derive(Clone, Debug)]
@@ -57,6 +63,34 @@ impl Type {
     pub fn is_list(&self) -> bool {
         matches!(self, Type::ListType { .. })
     }
+    /// Checks a resolved value against the type. Only scalars can be checked
+    /// by value, the other types accept anything that isn't null.
+    pub fn is_valid_value(&self, value: &ConstValue) -> bool {
+        match (self, value) {
+            (_, ConstValue::Null) => self.is_nullable(),
+            (Type::ListType { of_type, .. }, ConstValue::List(items)) => {
+                items.iter().all(|item| of_type.is_valid_value(item))
+            }
+            (Type::ListType { .. }, _) => false,
+            (Type::NamedType { name, .. }, value) => match name.as_str() {
+                "String" | "ID" => matches!(value, ConstValue::String(_)),
+                "Int" => matches!(value, ConstValue::Number(n) if n.is_i64() || n.is_u64()),
+                "Float" => matches!(value, ConstValue::Number(_)),
+                "Boolean" => matches!(value, ConstValue::Boolean(_)),
+                name => scalar::get_scalar(name)(value),
+            },
+        }
+    }
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (inner, non_null) = match self {
+            Type::NamedType { name, non_null } => (name.to_string(), non_null),
+            Type::ListType { of_type, non_null } => (format!("[{}]", of_type), non_null),
+        };
+        write!(f, "{}{}", inner, if *non_null { "!" } else { "" })
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -101,6 +135,8 @@ pub struct InputObjectTypeDefinition {
     pub name: String,
     pub fields: Vec<InputFieldDefinition>,
     pub description: Option<String>,
+    /// Exactly one of the fields has to be set, with `@oneOf`
+    pub one_of: bool,
 }
 
 #[derive(Clone, Debug)]
@@ -253,6 +289,10 @@ impl Blueprint {
             schema = schema.disable_introspection();
         }
 
+        if !server.get_enable_introspection() {
+            schema = schema.extension(NoIntrospection);
+        }
+
         for extension in schema_modifiers.extensions.iter().cloned() {
             schema = schema.extension(extension);
         }
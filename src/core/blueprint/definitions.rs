@@ -1,4 +1,6 @@
-use async_graphql_value::ConstValue;
+use std::collections::{BTreeMap, BTreeSet};
+
+use async_graphql_value::{ConstValue, Name};
 use regex::Regex;
 
 use crate::core::blueprint::Type::ListType;
@@ -30,21 +32,32 @@ pub fn to_union_type_definition((name, u): (&String, &Union)) -> Definition {
 
 pub fn to_input_object_type_definition(
     definition: ObjectTypeDefinition,
+    type_: &config::Type,
 ) -> Valid<Definition, String> {
-    Valid::succeed(Definition::InputObject(InputObjectTypeDefinition {
-        name: definition.name,
-        fields: definition
-            .fields
-            .iter()
-            .map(|field| InputFieldDefinition {
-                name: field.name.clone(),
-                description: field.description.clone(),
-                default_value: None,
-                of_type: field.of_type.clone(),
-            })
-            .collect(),
-        description: definition.description,
-    }))
+    let one_of = type_.one_of.is_some();
+
+    Valid::from_iter(definition.fields.iter(), |field| {
+        Valid::<(), String>::fail("Fields of a @oneOf input must be nullable".to_string())
+            .when(|| one_of && !field.of_type.is_nullable())
+            .trace(&field.name)
+    })
+    .map(|_| {
+        Definition::InputObject(InputObjectTypeDefinition {
+            name: definition.name,
+            fields: definition
+                .fields
+                .iter()
+                .map(|field| InputFieldDefinition {
+                    name: field.name.clone(),
+                    description: field.description.clone(),
+                    default_value: None,
+                    of_type: field.of_type.clone(),
+                })
+                .collect(),
+            description: definition.description,
+            one_of,
+        })
+    })
 }
 
 pub fn to_interface_type_definition(definition: ObjectTypeDefinition) -> Valid<Definition, String> {
@@ -341,12 +354,33 @@ pub fn update_cache_resolvers<'a>(
 ) -> TryFold<'a, (&'a ConfigModule, &'a Field, &'a config::Type, &'a str), FieldDefinition, String>
 {
     TryFold::<(&ConfigModule, &Field, &config::Type, &str), FieldDefinition, String>::new(
-        move |(_config, field, typ, _name), mut b_field| {
-            if let Some(config::Cache { max_age }) = field.cache.as_ref().or(typ.cache.as_ref()) {
-                b_field.map_expr(|expression| Cache::wrap(*max_age, expression))
-            }
-
-            Valid::succeed(b_field)
+        move |(config_module, field, typ, _name), mut b_field| {
+            let Some(config::Cache { max_age, vary_by, stale_while_revalidate }) =
+                field.cache.as_ref().or(typ.cache.as_ref())
+            else {
+                return Valid::succeed(b_field);
+            };
+
+            // Only the allowed headers are forwarded to the request context
+            let allowed_headers = config_module.upstream.get_allowed_headers();
+            Valid::from_iter(vary_by.iter(), |name| {
+                Valid::<(), String>::fail(format!(
+                    "header '{name}' is not in @upstream(allowedHeaders:)"
+                ))
+                .when(|| {
+                    !allowed_headers
+                        .iter()
+                        .any(|allowed| allowed.eq_ignore_ascii_case(name))
+                })
+            })
+            .trace("varyBy")
+            .trace(config::Cache::trace_name().as_str())
+            .map(|_| {
+                b_field.map_expr(|expression| {
+                    Cache::wrap(*max_age, vary_by, *stale_while_revalidate, expression)
+                });
+                b_field
+            })
         },
     )
 }
@@ -474,10 +508,101 @@ fn to_fields(
     });
     fields.zip(added_fields).map(|(mut fields, added_fields)| {
         fields.extend(added_fields);
-        fields
+        update_siblings(fields, object_name, type_of)
     })
 }
 
+/// Names of the sibling fields read with `{{.value.<name>}}` by the `@expr`
+/// and `@default` of the field
+fn to_sibling_names(field: &Field) -> BTreeSet<String> {
+    let values = field
+        .const_field
+        .iter()
+        .filter_map(|expr| expr.body.as_ref())
+        .chain(field.default_value.iter().map(|default| &default.value));
+
+    values
+        .filter_map(|value| DynamicValue::try_from(value).ok())
+        .flat_map(|value| {
+            value
+                .expression_segments()
+                .into_iter()
+                .filter(|parts| parts.len() > 1 && parts[0] == "value")
+                .map(|parts| parts[1].clone())
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Siblings that have their own resolver aren't part of the parent value, so
+/// they're resolved before the `@expr` and `@default` that read them. Their
+/// resolvers are used as they are, so they can't read computed siblings in
+/// turn. A sibling is resolved once per parent value, with the default values
+/// of its args when it's read by another field.
+fn update_siblings(
+    fields: Vec<FieldDefinition>,
+    object_name: &str,
+    type_of: &config::Type,
+) -> Vec<FieldDefinition> {
+    let read_siblings = fields
+        .iter()
+        .filter_map(|field| type_of.fields.get(&field.name))
+        .flat_map(to_sibling_names)
+        .collect::<BTreeSet<_>>();
+
+    let fields = fields
+        .into_iter()
+        .map(|mut field| {
+            if read_siblings.contains(&field.name) {
+                let name = format!("{}.{}", object_name, field.name);
+                field.resolver = field
+                    .resolver
+                    .map(|resolver| IR::Memoize { name, expr: Box::new(resolver) });
+            }
+            field
+        })
+        .collect::<Vec<_>>();
+
+    let resolvers = fields
+        .iter()
+        .filter_map(|field| {
+            let resolver = field.resolver.clone()?;
+            let args = field
+                .args
+                .iter()
+                .filter_map(|arg| {
+                    let value = ConstValue::from_json(arg.default_value.clone()?).ok()?;
+                    Some((Name::new(&arg.name), value))
+                })
+                .collect();
+            let args = IR::Dynamic(DynamicValue::Value(ConstValue::Object(args)));
+
+            Some((field.name.clone(), resolver.with_args(args)))
+        })
+        .collect::<BTreeMap<_, _>>();
+
+    fields
+        .into_iter()
+        .map(|mut field| {
+            let Some(config_field) = type_of.fields.get(&field.name) else {
+                return field;
+            };
+            let siblings = to_sibling_names(config_field)
+                .into_iter()
+                .filter(|name| *name != field.name)
+                .filter_map(|name| Some((name.clone(), resolvers.get(&name)?.clone())))
+                .collect::<Vec<_>>();
+
+            if !siblings.is_empty() {
+                field.resolver = field.resolver.map(|resolver| {
+                    IR::Context(Context::PushSiblings { siblings, and_then: Box::new(resolver) })
+                });
+            }
+            field
+        })
+        .collect()
+}
+
 #[allow(clippy::too_many_arguments)]
 pub fn to_field_definition(
     field: &Field,
@@ -488,6 +613,8 @@ pub fn to_field_definition(
     name: &String,
 ) -> Valid<FieldDefinition, String> {
     let directives = field.resolvable_directives();
+    let field = to_paginated_field(config_module, field);
+    let field = field.as_ref();
 
     if directives.len() > 1 {
         return Valid::fail(format!(
@@ -501,11 +628,16 @@ pub fn to_field_definition(
         .and(update_grpc(operation_type).trace(config::Grpc::trace_name().as_str()))
         .and(update_const_field().trace(config::Expr::trace_name().as_str()))
         .and(update_js_field().trace(config::JS::trace_name().as_str()))
+        .and(update_wasm_field().trace(config::Wasm::trace_name().as_str()))
         .and(update_graphql(operation_type).trace(config::GraphQL::trace_name().as_str()))
         .and(update_modify().trace(config::Modify::trace_name().as_str()))
         .and(update_call(operation_type, object_name).trace(config::Call::trace_name().as_str()))
         .and(fix_dangling_resolvers())
         .and(update_cache_resolvers())
+        .and(update_default(object_name).trace(config::default::Default::trace_name().as_str()))
+        .and(update_patch().trace(config::Patch::trace_name().as_str()))
+        .and(update_flatten().trace(config::Modify::trace_name().as_str()))
+        .and(update_wrap_in_list().trace(config::Modify::trace_name().as_str()))
         .and(update_protected(object_name).trace(Protected::trace_name().as_str()))
         .try_fold(
             &(config_module, field, type_of, name),
@@ -520,7 +652,9 @@ pub fn to_definitions<'a>() -> TryFold<'a, ConfigModule, Vec<Definition>, String
 
         Valid::from_iter(config_module.types.iter(), |(name, type_)| {
             let dbl_usage = input_types.contains(name) && output_types.contains(name);
-            if type_.scalar() {
+            if type_.extend {
+                Valid::fail("Type is extended but never defined".to_string()).trace(name)
+            } else if type_.scalar() {
                 to_scalar_type_definition(name).trace(name)
             } else if dbl_usage {
                 Valid::fail("type is used in input and output".to_string()).trace(name)
@@ -530,7 +664,8 @@ pub fn to_definitions<'a>() -> TryFold<'a, ConfigModule, Vec<Definition>, String
                     .and_then(|definition| match definition.clone() {
                         Definition::Object(object_type_definition) => {
                             if config_module.input_types.contains(name) {
-                                to_input_object_type_definition(object_type_definition).trace(name)
+                                to_input_object_type_definition(object_type_definition, type_)
+                                    .trace(name)
                             } else if config_module.interface_types.contains(name) {
                                 to_interface_type_definition(object_type_definition).trace(name)
                             } else {
@@ -3,6 +3,8 @@ use indexmap::IndexMap;
 use serde_json::Value;
 
 use crate::core::mustache::Mustache;
+use crate::core::path::PathString;
+use crate::core::serde_value_ext::ValueExt;
 
 #[derive(Debug, Clone)]
 pub enum DynamicValue {
@@ -47,6 +49,43 @@ impl DynamicValue {
             _ => true,
         }
     }
+
+    /// Returns the expressions of all the mustache templates of the value
+    pub fn expression_segments(&self) -> Vec<&Vec<String>> {
+        match self {
+            DynamicValue::Value(_) => vec![],
+            DynamicValue::Mustache(m) => m.expression_segments(),
+            DynamicValue::Object(obj) => {
+                obj.values().flat_map(|v| v.expression_segments()).collect()
+            }
+            DynamicValue::Array(arr) => arr.iter().flat_map(|v| v.expression_segments()).collect(),
+        }
+    }
+
+    /// Renders the expressions that can be resolved by `ctx` and keeps the
+    /// rest of them for later. Mustache values that don't have any expression
+    /// left are turned into plain values.
+    pub fn partially_render(&self, ctx: &impl PathString) -> DynamicValue {
+        match self {
+            DynamicValue::Value(value) => DynamicValue::Value(value.clone()),
+            DynamicValue::Mustache(m) => {
+                let m = DynamicValue::Mustache(m.partially_render(ctx));
+                if m.is_const() {
+                    DynamicValue::Value(m.render_value(&Value::Null))
+                } else {
+                    m
+                }
+            }
+            DynamicValue::Object(obj) => DynamicValue::Object(
+                obj.iter()
+                    .map(|(k, v)| (k.clone(), v.partially_render(ctx)))
+                    .collect(),
+            ),
+            DynamicValue::Array(arr) => {
+                DynamicValue::Array(arr.iter().map(|v| v.partially_render(ctx)).collect())
+            }
+        }
+    }
 }
 
 impl TryFrom<&Value> for DynamicValue {
@@ -6,7 +6,8 @@ use self::telemetry::to_opentelemetry;
 use super::{Server, TypeLike};
 use crate::core::blueprint::compress::compress;
 use crate::core::blueprint::*;
-use crate::core::config::{Arg, Batch, Config, ConfigModule, Field};
+use crate::core::config::{Arg, Batch, Config, ConfigModule, Field, Version};
+use crate::core::directive::DirectiveCodec;
 use crate::core::ir::{IO, IR};
 use crate::core::json::JsonSchema;
 use crate::core::try_fold::TryFold;
@@ -40,12 +41,29 @@ pub fn config_blueprint<'a>() -> TryFold<'a, ConfigModule, Blueprint, String> {
         |blueprint| blueprint.telemetry,
     );
 
+    let version = TryFoldConfig::<Blueprint>::new(|config_module, blueprint| {
+        let Some(version) = config_module.version.as_ref() else {
+            return Valid::succeed(blueprint);
+        };
+
+        Valid::<(), String>::fail("Version tag can not be empty".to_string())
+            .when(|| version.tag.trim().is_empty())
+            .trace(Version::trace_name().as_str())
+            .map_to(blueprint.version(Some(version.tag.clone())))
+    });
+
+    let warnings = TryFoldConfig::<Blueprint>::new(|config_module, blueprint| {
+        Valid::succeed(blueprint.warnings(to_warnings(config_module)))
+    });
+
     server
         .and(schema)
         .and(definitions)
         .and(upstream)
         .and(links)
         .and(opentelemetry)
+        .and(version)
+        .and(warnings)
         .update(apply_batching)
         .update(compress)
 }
@@ -56,7 +74,7 @@ pub fn apply_batching(mut blueprint: Blueprint) -> Blueprint {
     for def in blueprint.definitions.iter() {
         if let Definition::Object(object_type_definition) = def {
             for field in object_type_definition.fields.iter() {
-                if let Some(IR::IO(IO::Http { group_by: Some(_), .. })) = field.resolver.clone() {
+                if field.resolver.as_ref().is_some_and(is_batched) {
                     blueprint.upstream.batch = blueprint.upstream.batch.or(Some(Batch::default()));
                     return blueprint;
                 }
@@ -66,7 +84,33 @@ pub fn apply_batching(mut blueprint: Blueprint) -> Blueprint {
     blueprint
 }
 
+/// Looks for a batched request through the IRs wrapping it, eg. the fallback
+/// of `@http(onError:)` or `@default`.
+fn is_batched(resolver: &IR) -> bool {
+    match resolver {
+        IR::IO(IO::Http { group_by, .. }) => group_by.is_some(),
+        IR::Cache(cache) => is_batched(&cache.expr),
+        IR::Path(expr, _)
+        | IR::Protect(expr)
+        | IR::WrapInList(expr)
+        | IR::OnError { expr, .. }
+        | IR::WithDefault { expr, .. }
+        | IR::Patch { expr, .. }
+        | IR::Flatten { expr, .. }
+        | IR::Memoize { expr, .. } => is_batched(expr),
+        _ => false,
+    }
+}
+
 pub fn to_json_schema_for_field(field: &Field, config: &Config) -> JsonSchema {
+    // The value is only wrapped into a list once it's resolved
+    if field.is_wrapped_in_list() {
+        return to_json_schema(&field.clone().list(false).required(false), config);
+    }
+    // The nested object is only hoisted once it's resolved
+    if field.flattened().is_some() {
+        return JsonSchema::Any;
+    }
     to_json_schema(field, config)
 }
 pub fn to_json_schema_for_args(args: &BTreeMap<String, Arg>, config: &Config) -> JsonSchema {
@@ -88,7 +132,12 @@ where
     let schema = if let Some(type_) = type_ {
         let mut schema_fields = HashMap::new();
         for (name, field) in type_.fields.iter() {
-            if field.script.is_none() && field.http.is_none() {
+            if field.script.is_none()
+                && field.wasm.is_none()
+                && field.http.is_none()
+                && field.default_value.is_none()
+                && field.patch.is_none()
+            {
                 schema_fields.insert(name.clone(), to_json_schema_for_field(field, config));
             }
         }
@@ -135,3 +184,55 @@ impl TryFrom<&ConfigModule> for Blueprint {
             .to_result()
     }
 }
+
+impl Blueprint {
+    /// Compiles a snapshot written by [ConfigModule::to_bytes]. Only the
+    /// reading of the config files and of their links is skipped, the config
+    /// is compiled again.
+    pub fn from_bytes(bytes: &[u8], source: &str) -> anyhow::Result<Blueprint> {
+        let config_module = ConfigModule::from_bytes(bytes, source)?;
+        Ok(Blueprint::try_from(&config_module)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::apply_batching;
+    use crate::core::blueprint::Blueprint;
+    use crate::core::config::{Config, ConfigModule};
+    use crate::core::valid::Validator;
+
+    #[test]
+    fn test_batching_through_default() {
+        let config = Config::from_sdl(
+            r#"
+            schema @upstream(baseURL: "http://jsonplaceholder.typicode.com", batch: {delay: 1}) {
+              query: Query
+            }
+
+            type Query {
+              posts: [Post] @http(path: "/posts")
+            }
+
+            type Post {
+              id: Int
+              userId: Int!
+              user: User
+                @http(path: "/users", query: [{key: "id", value: "{{.value.userId}}"}], batchKey: ["id"])
+                @default(value: {id: 0})
+            }
+
+            type User {
+              id: Int
+            }
+            "#,
+        )
+        .to_result()
+        .unwrap();
+
+        let mut blueprint = Blueprint::try_from(&ConfigModule::from(config)).unwrap();
+        blueprint.upstream.batch = None;
+
+        assert!(apply_batching(blueprint).upstream.batch.is_some());
+    }
+}
@@ -2,12 +2,12 @@ use std::borrow::Cow;
 use std::sync::Arc;
 
 use async_graphql::dynamic::{self, FieldFuture, FieldValue, SchemaBuilder};
-use async_graphql::ErrorExtensions;
 use async_graphql_value::ConstValue;
 use futures_util::TryFutureExt;
 use tracing::Instrument;
 
 use crate::core::blueprint::{Blueprint, Definition, Type};
+use crate::core::config::ResponseValidation;
 use crate::core::http::RequestContext;
 use crate::core::ir::{Eval, EvaluationContext, ResolverContext};
 use crate::core::scalar::CUSTOM_SCALARS;
@@ -34,6 +34,29 @@ fn to_type_ref(type_of: &Type) -> dynamic::TypeRef {
     }
 }
 
+/// Checks a resolved value against the type of its field, violations fail the
+/// field in `STRICT` mode and are only logged in `WARN` mode.
+fn validate_value(
+    mode: ResponseValidation,
+    field_path: &str,
+    of_type: &Type,
+    value: &ConstValue,
+) -> async_graphql::Result<()> {
+    if !mode.is_enabled() || of_type.is_valid_value(value) {
+        return Ok(());
+    }
+
+    let message = format!(
+        "{} resolved to {} which is not a valid {}",
+        field_path, value, of_type
+    );
+    if mode == ResponseValidation::Strict {
+        return Err(async_graphql::Error::new(message));
+    }
+    tracing::warn!("{}", message);
+    Ok(())
+}
+
 fn to_type(def: &Definition) -> dynamic::Type {
     match def {
         Definition::Object(def) => {
@@ -42,20 +65,32 @@ fn to_type(def: &Definition) -> dynamic::Type {
                 let field = field.clone();
                 let type_ref = to_type_ref(&field.of_type);
                 let field_name = &field.name.clone();
+                let field_path = format!("{}.{}", def.name, field.name);
                 let mut dyn_schema_field = dynamic::Field::new(
                     field_name,
                     type_ref.clone(),
                     move |ctx| {
                         let req_ctx = ctx.ctx.data::<Arc<RequestContext>>().unwrap();
                         let field_name = &field.name;
+                        let response_validation = req_ctx.server.response_validation;
 
                         match &field.resolver {
                             None => {
                                 let ctx: ResolverContext = ctx.into();
                                 let ctx = EvaluationContext::new(req_ctx, &ctx);
-                                FieldFuture::from_value(
-                                    ctx.path_value(&[field_name]).map(|a| a.into_owned()),
-                                )
+                                let value = ctx.path_value(&[field_name]).map(|a| a.into_owned());
+                                let result = validate_value(
+                                    response_validation,
+                                    &field_path,
+                                    &field.of_type,
+                                    value.as_ref().unwrap_or(&ConstValue::Null),
+                                );
+                                match result {
+                                    Ok(()) => FieldFuture::from_value(value),
+                                    Err(err) => FieldFuture::new(async move {
+                                        Err::<Option<ConstValue>, _>(err)
+                                    }),
+                                }
                             }
                             Some(expr) => {
                                 let span = tracing::info_span!(
@@ -63,13 +98,35 @@ fn to_type(def: &Definition) -> dynamic::Type {
                                     otel.name = ctx.path_node.map(|p| p.to_string()).unwrap_or(field_name.clone()), graphql.returnType = %type_ref
                                 );
                                 let expr = expr.to_owned();
+                                let is_nullable = field.of_type.is_nullable();
+                                let validation = response_validation
+                                    .is_enabled()
+                                    .then(|| (field_path.clone(), field.of_type.clone()));
                                 FieldFuture::new(
                                     async move {
                                         let ctx: ResolverContext = ctx.into();
                                         let ctx = EvaluationContext::new(req_ctx, &ctx);
 
-                                        let const_value =
-                                            expr.eval(ctx).await.map_err(|err| err.extend())?;
+                                        let const_value = match expr.eval(ctx).await {
+                                            Ok(value) => value,
+                                            // Missing entities resolve to null on nullable fields
+                                            Err(err) if err.is_not_found() && is_nullable => {
+                                                ConstValue::Null
+                                            }
+                                            Err(err) => {
+                                                return Err(err.extend_with_statuses(
+                                                    &req_ctx.server.grpc_http_status,
+                                                ))
+                                            }
+                                        };
+                                        if let Some((field_path, of_type)) = &validation {
+                                            validate_value(
+                                                response_validation,
+                                                field_path,
+                                                of_type,
+                                                &const_value,
+                                            )?;
+                                        }
                                         let p = match const_value {
                                             ConstValue::List(a) => Some(FieldValue::list(a)),
                                             ConstValue::Null => FieldValue::NONE,
@@ -128,6 +185,9 @@ fn to_type(def: &Definition) -> dynamic::Type {
             if let Some(description) = &def.description {
                 input_object = input_object.description(description);
             }
+            if def.one_of {
+                input_object = input_object.oneof();
+            }
 
             dynamic::Type::InputObject(input_object)
         }
@@ -159,6 +219,9 @@ fn to_type(def: &Definition) -> dynamic::Type {
     }
 }
 
+/// Fields of the same selection set are resolved concurrently by the executor
+/// of async-graphql, so independent upstream calls of sibling fields overlap.
+/// Only the root fields of a mutation are resolved one after the other.
 impl From<&Blueprint> for SchemaBuilder {
     fn from(blueprint: &Blueprint) -> Self {
         let query = blueprint.query();
@@ -0,0 +1,62 @@
+use std::sync::Arc;
+
+use async_graphql::extensions::{Extension, ExtensionContext, ExtensionFactory, NextParseQuery};
+use async_graphql::parser::types::{ExecutableDocument, Selection, SelectionSet};
+use async_graphql::{Pos, ServerError, ServerResult, Variables};
+
+/// Rejects the queries that select `__schema` or `__type` when introspection
+/// is disabled, independently of the validation mode of the schema.
+pub struct NoIntrospection;
+
+impl ExtensionFactory for NoIntrospection {
+    fn create(&self) -> Arc<dyn Extension> {
+        Arc::new(NoIntrospectionExtension)
+    }
+}
+
+struct NoIntrospectionExtension;
+
+#[async_trait::async_trait]
+impl Extension for NoIntrospectionExtension {
+    async fn parse_query(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        query: &str,
+        variables: &Variables,
+        next: NextParseQuery<'_>,
+    ) -> ServerResult<ExecutableDocument> {
+        let document = next.run(ctx, query, variables).await?;
+
+        let pos = document
+            .operations
+            .iter()
+            .find_map(|(_, operation)| find_introspection(&operation.node.selection_set.node))
+            .or_else(|| {
+                document
+                    .fragments
+                    .values()
+                    .find_map(|fragment| find_introspection(&fragment.node.selection_set.node))
+            });
+
+        match pos {
+            Some(pos) => Err(ServerError::new("Introspection is disabled", Some(pos))),
+            None => Ok(document),
+        }
+    }
+}
+
+fn find_introspection(selection_set: &SelectionSet) -> Option<Pos> {
+    selection_set
+        .items
+        .iter()
+        .find_map(|selection| match &selection.node {
+            Selection::Field(field) => match field.node.name.node.as_str() {
+                "__schema" | "__type" => Some(field.pos),
+                _ => find_introspection(&field.node.selection_set.node),
+            },
+            Selection::InlineFragment(fragment) => {
+                find_introspection(&fragment.node.selection_set.node)
+            }
+            Selection::FragmentSpread(_) => None,
+        })
+}
@@ -6,6 +6,7 @@ mod definitions;
 mod dynamic_value;
 mod from_config;
 mod into_schema;
+mod introspection;
 mod links;
 mod mustache;
 mod operators;
@@ -14,6 +15,7 @@ mod server;
 pub mod telemetry;
 mod timeout;
 mod upstream;
+mod warnings;
 
 pub use auth::*;
 pub use blueprint::*;
@@ -21,12 +23,14 @@ pub use cors::*;
 pub use definitions::*;
 pub use dynamic_value::*;
 pub use from_config::*;
+pub use introspection::NoIntrospection;
 pub use links::*;
 pub use operators::*;
 pub use schema::*;
 pub use server::*;
 pub use timeout::GlobalTimeout;
 pub use upstream::*;
+pub use warnings::*;
 
 use crate::core::config::{Arg, ConfigModule, Field};
 use crate::core::try_fold::TryFold;
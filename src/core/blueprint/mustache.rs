@@ -15,6 +15,21 @@ impl<'a> MustachePartsValidator<'a> {
         Self { type_of, config, field }
     }
 
+    /// Checks if the argument is injected into the resolver with
+    /// `@modify(args:)`, in which case it isn't an argument of the field.
+    fn is_injected_arg(&self, name: &str) -> bool {
+        self.type_of
+            .fields
+            .get(&self.field.name)
+            .and_then(|field| field.modify.as_ref())
+            .is_some_and(|modify| modify.args.contains_key(name))
+    }
+
+    /// Checks if the key is built by the `context` of `@server`
+    fn is_context_key(&self, key: &str) -> bool {
+        self.config.server.context.iter().any(|kv| kv.key == key)
+    }
+
     fn validate_type(&self, parts: &[String], is_query: bool) -> Result<(), String> {
         let mut len = parts.len();
         let mut type_of = self.type_of;
@@ -46,12 +61,52 @@ impl<'a> MustachePartsValidator<'a> {
         Ok(())
     }
 
+    /// Validates templates that refer to a whole source, eg: `{{.args}}`
+    fn validate_root(&self, parts: &[String]) -> Valid<(), String> {
+        match parts.first().map(String::as_str) {
+            Some("value" | "args" | "vars" | "context" | "selection") => Valid::succeed(()),
+            Some("headers" | "env") | None => Valid::fail("too few parts in template".to_string()),
+            Some(head) => Valid::fail(format!("unknown template directive '{head}'")),
+        }
+    }
+
+    /// Only validates that the source and the first segment of the template
+    /// exist, used for templates that can render any type eg: the body.
+    fn validate_reference(&self, parts: &[String]) -> Valid<(), String> {
+        if parts.len() < 2 {
+            return self.validate_root(parts);
+        }
+
+        let head = parts[0].as_str();
+        let tail = parts[1].as_str();
+
+        match head {
+            "value" if !self.type_of.fields.contains_key(tail) => {
+                Valid::fail(format!("no value '{tail}' found"))
+            }
+            "args"
+                if !self.field.args.iter().any(|arg| arg.name == tail)
+                    && !self.is_injected_arg(tail) =>
+            {
+                Valid::fail(format!("no argument '{tail}' found"))
+            }
+            "vars" if !self.config.server.vars.iter().any(|vars| vars.key == tail) => {
+                Valid::fail(format!("var '{tail}' is not set in the server config"))
+            }
+            "context" if !self.is_context_key(tail) => {
+                Valid::fail(format!("context '{tail}' is not set in the server config"))
+            }
+            "value" | "args" | "vars" | "context" | "headers" | "env" => Valid::succeed(()),
+            _ => Valid::fail(format!("unknown template directive '{head}'")),
+        }
+    }
+
     fn validate(&self, parts: &[String], is_query: bool) -> Valid<(), String> {
         let config = self.config;
         let args = &self.field.args;
 
         if parts.len() < 2 {
-            return Valid::fail("too few parts in template".to_string());
+            return self.validate_root(parts);
         }
 
         let head = parts[0].as_str();
@@ -79,7 +134,7 @@ impl<'a> MustachePartsValidator<'a> {
                     if !is_query && arg.default_value.is_none() && arg.of_type.is_nullable() {
                         return Valid::fail(format!("argument '{tail}' is a nullable type"));
                     }
-                } else {
+                } else if !self.is_injected_arg(tail) {
                     return Valid::fail(format!("no argument '{tail}' found"));
                 }
             }
@@ -88,6 +143,13 @@ impl<'a> MustachePartsValidator<'a> {
                     return Valid::fail(format!("var '{tail}' is not set in the server config"));
                 }
             }
+            "context" => {
+                if !self.is_context_key(tail) {
+                    return Valid::fail(format!(
+                        "context '{tail}' is not set in the server config"
+                    ));
+                }
+            }
             "headers" | "env" => {
                 // "headers" and "env" refers to values known at runtime, which
                 // we can't validate here
@@ -111,60 +173,70 @@ impl FieldDefinition {
         // So we must duplicate some of that logic here :(
         let parts_validator = MustachePartsValidator::new(type_of, config, self);
 
-        match &self.resolver {
-            Some(IR::IO(IO::Http { req_template, .. })) => {
-                Valid::from_iter(req_template.root_url.expression_segments(), |parts| {
-                    parts_validator.validate(parts, false).trace("path")
+        let resolver = match &self.resolver {
+            Some(IR::Path(expr, _)) => Some(expr.as_ref()),
+            resolver => resolver.as_ref(),
+        };
+
+        match resolver {
+            Some(IR::IO(IO::Http { req_template, .. })) => Valid::from_iter(
+                req_template.root_url.expression_segments_in_sections(),
+                |(parts, in_section)| parts_validator.validate(parts, in_section).trace("path"),
+            )
+            .and(Valid::from_iter(req_template.query.clone(), |query| {
+                let (_, mustache) = query;
+
+                Valid::from_iter(mustache.expression_segments(), |parts| {
+                    parts_validator.validate(parts, true).trace("query")
                 })
-                .and(Valid::from_iter(req_template.query.clone(), |query| {
-                    let (_, mustache) = query;
-
+            }))
+            .and(Valid::from_iter(
+                req_template.headers.clone(),
+                |(_, mustache)| {
                     Valid::from_iter(mustache.expression_segments(), |parts| {
-                        parts_validator.validate(parts, true).trace("query")
+                        parts_validator.validate_reference(parts).trace("headers")
                     })
-                }))
-                .unit()
-            }
+                },
+            ))
+            .and(Valid::from_iter(req_template.body_path.clone(), |body| {
+                Valid::from_iter(body.expression_segments(), |parts| {
+                    parts_validator.validate_reference(parts).trace("body")
+                })
+            }))
+            .unit(),
             Some(IR::IO(IO::GraphQL { req_template, .. })) => {
                 Valid::from_iter(req_template.headers.clone(), |(_, mustache)| {
                     Valid::from_iter(mustache.expression_segments(), |parts| {
-                        parts_validator.validate(parts, true).trace("headers")
+                        parts_validator.validate_reference(parts).trace("headers")
                     })
                 })
-                .and_then(|_| {
-                    if let Some(args) = &req_template.operation_arguments {
-                        Valid::from_iter(args, |(_, mustache)| {
-                            Valid::from_iter(mustache.expression_segments(), |parts| {
-                                parts_validator.validate(parts, true).trace("args")
-                            })
+                .and(Valid::from_iter(
+                    req_template.operation_arguments.iter().flatten(),
+                    |(_, mustache)| {
+                        Valid::from_iter(mustache.expression_segments(), |parts| {
+                            parts_validator.validate(parts, true).trace("args")
                         })
-                    } else {
-                        Valid::succeed(Default::default())
-                    }
-                })
+                    },
+                ))
                 .unit()
             }
             Some(IR::IO(IO::Grpc { req_template, .. })) => {
                 Valid::from_iter(req_template.url.expression_segments(), |parts| {
                     parts_validator.validate(parts, false).trace("path")
                 })
-                .and(
-                    Valid::from_iter(req_template.headers.clone(), |(_, mustache)| {
+                .and(Valid::from_iter(
+                    req_template.headers.clone(),
+                    |(_, mustache)| {
                         Valid::from_iter(mustache.expression_segments(), |parts| {
-                            parts_validator.validate(parts, true).trace("headers")
+                            parts_validator.validate_reference(parts).trace("headers")
                         })
+                    },
+                ))
+                .and(Valid::from_iter(req_template.body.iter(), |body| {
+                    Valid::from_iter(body.expression_segments(), |parts| {
+                        parts_validator.validate_reference(parts).trace("body")
                     })
-                    .unit(),
-                )
-                .and_then(|_| {
-                    if let Some(body) = &req_template.body {
-                        Valid::from_iter(body.expression_segments(), |parts| {
-                            parts_validator.validate(parts, true).trace("body")
-                        })
-                    } else {
-                        Valid::succeed(Default::default())
-                    }
-                })
+                }))
                 .unit()
             }
             _ => Valid::succeed(()),
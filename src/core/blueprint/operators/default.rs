@@ -0,0 +1,41 @@
+use crate::core::blueprint::*;
+use crate::core::config;
+use crate::core::config::Field;
+use crate::core::ir::{Context, IR};
+use crate::core::try_fold::TryFold;
+use crate::core::valid::{Valid, Validator};
+
+/// Wraps the resolver of the field so that it resolves to the default value
+/// instead of null. Fields without a resolver read their value from the parent.
+pub fn update_default<'a>(
+    type_name: &'a str,
+) -> TryFold<'a, (&'a ConfigModule, &'a Field, &'a config::Type, &'a str), FieldDefinition, String>
+{
+    TryFold::<(&ConfigModule, &Field, &config::Type, &str), FieldDefinition, String>::new(
+        |(config_module, field, _, _), b_field| {
+            let Some(default) = &field.default_value else {
+                return Valid::succeed(b_field);
+            };
+
+            if config_module.input_types.contains(type_name) {
+                return Valid::fail("Input types can not have a default resolver".to_owned());
+            }
+
+            compile_expr(CompileExpr {
+                config_module,
+                field,
+                value: &default.value,
+                validate: true,
+            })
+            .trace("value")
+            .map(|default| {
+                let expr = b_field
+                    .resolver
+                    .clone()
+                    .unwrap_or(IR::Context(Context::Path(vec![b_field.name.clone()])));
+                let resolver = IR::WithDefault { expr: Box::new(expr), default: Box::new(default) };
+                b_field.resolver(Some(resolver))
+            })
+        },
+    )
+}
@@ -40,7 +40,8 @@ pub fn compile_expr(inputs: CompileExpr) -> Valid<IR, String> {
     )
     .and_then(|value| {
         if !value.is_const() {
-            // TODO: Add validation for const with Mustache here
+            // Values depending only on env and vars are already rendered by the
+            // config reader, the others are rendered for every request
             Valid::succeed(Dynamic(value.to_owned()))
         } else {
             let data = &value;
@@ -59,6 +60,65 @@ pub fn compile_expr(inputs: CompileExpr) -> Valid<IR, String> {
     })
 }
 
+/// The range is expanded when the blueprint is built, so it's kept short
+const MAX_RANGE_LENGTH: usize = 10_000;
+
+/// Generates the list of the `range`, which can only be used instead of the
+/// `body` on list fields. An explicit `body: null` is only allowed on nullable
+/// fields, whatever their type, and one of them is always required.
+fn to_body(field: &config::Field, expr: &config::Expr) -> Valid<serde_json::Value, String> {
+    let Some(range) = &expr.range else {
+        let Some(body) = &expr.body else {
+            return Valid::fail("either body or range is required".to_string());
+        };
+
+        return Valid::<(), String>::fail("body can only be null on a nullable field".to_string())
+            .when(|| field.required && body.is_null())
+            .trace("body")
+            .map(|_| body.clone());
+    };
+
+    Valid::<(), String>::fail("body and range can not be used together".to_string())
+        .when(|| expr.body.is_some())
+        .and(
+            Valid::<(), String>::fail("range can only be used on a list field".to_string())
+                .when(|| !field.list),
+        )
+        .and(
+            Valid::<(), String>::fail("step must be greater than 0".to_string())
+                .when(|| range.step == Some(0))
+                .trace("step"),
+        )
+        .and_then(|_| {
+            Valid::from(
+                usize::try_from(range.step.unwrap_or(1))
+                    .map_err(|_| ValidationError::new("step is too large".to_string())),
+            )
+            .trace("step")
+        })
+        .and_then(|step| {
+            let length = if range.to < range.from {
+                0
+            } else {
+                (range.to as i128 - range.from as i128) / step as i128 + 1
+            };
+
+            Valid::<(), String>::fail(format!(
+                "range can't have more than {MAX_RANGE_LENGTH} items"
+            ))
+            .when(|| length > MAX_RANGE_LENGTH as i128)
+            .map(|_| {
+                serde_json::Value::Array(
+                    (range.from..=range.to)
+                        .step_by(step)
+                        .map(serde_json::Value::from)
+                        .collect(),
+                )
+            })
+        })
+        .trace("range")
+}
+
 pub fn update_const_field<'a>(
 ) -> TryFold<'a, (&'a ConfigModule, &'a Field, &'a config::Type, &'a str), FieldDefinition, String>
 {
@@ -68,13 +128,11 @@ pub fn update_const_field<'a>(
                 return Valid::succeed(b_field);
             };
 
-            compile_expr(CompileExpr {
-                config_module,
-                field,
-                value: &const_field.body,
-                validate: true,
-            })
-            .map(|resolver| b_field.resolver(Some(resolver)))
+            to_body(field, const_field)
+                .and_then(|body| {
+                    compile_expr(CompileExpr { config_module, field, value: &body, validate: true })
+                })
+                .map(|resolver| b_field.resolver(Some(resolver)))
         },
     )
 }
@@ -1,14 +1,95 @@
-use crate::core::blueprint::FieldDefinition;
-use crate::core::config::{self, ConfigModule, Field, GraphQLOperationType};
-use crate::core::graphql::RequestTemplate;
+use std::collections::{HashSet, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::core::blueprint::{to_type, FieldDefinition};
+use crate::core::config::{self, ConfigModule, Field, GraphQLOperationType, KeyValue};
+use crate::core::graphql::{Entity, PersistedQueries, RequestTemplate};
 use crate::core::helpers;
 use crate::core::ir::{IO, IR};
+use crate::core::mustache::{Mustache, Segment};
 use crate::core::try_fold::TryFold;
 use crate::core::valid::{Valid, ValidationError, Validator};
 
+/// Returns the names of the fragments spread in a selection ie. `...Name`.
+/// Inline fragments ie. `... on Type` are skipped.
+fn fragment_spreads(selection: &str) -> Vec<&str> {
+    selection
+        .split("...")
+        .skip(1)
+        .filter_map(|rest| {
+            let rest = rest.trim_start();
+            let end = rest
+                .find(|c: char| c != '_' && !c.is_ascii_alphanumeric())
+                .unwrap_or(rest.len());
+            let name = &rest[..end];
+            (!name.is_empty() && name != "on").then_some(name)
+        })
+        .collect()
+}
+
+/// Collects the definitions of the fragment and of every fragment it spreads,
+/// each one only once.
+fn compile_fragments(config: &config::Config, fragment: &str) -> Valid<Vec<String>, String> {
+    let mut names = VecDeque::from([fragment]);
+    let mut visited = HashSet::new();
+    let mut definitions = vec![];
+
+    while let Some(name) = names.pop_front() {
+        if !visited.insert(name) {
+            continue;
+        }
+
+        let Some(fragment) = config.fragments.iter().find(|f| f.name == name) else {
+            return Valid::fail(format!("Fragment {} is not defined", name)).trace("fragment");
+        };
+
+        let definition = format!(
+            "fragment {} on {} {{ {} }}",
+            fragment.name,
+            fragment.on,
+            fragment.selection.trim()
+        );
+        definitions.push(definition.escape_default().to_string());
+        names.extend(fragment_spreads(&fragment.selection));
+    }
+
+    Valid::succeed(definitions)
+}
+
+/// Builds the federation entity the field is requested on, whose key has to be
+/// a field of the parent type.
+fn compile_entity(
+    operation_type: &GraphQLOperationType,
+    parent: &config::Type,
+    graphql: &config::GraphQL,
+) -> Valid<Option<Entity>, String> {
+    let Some(entity) = &graphql.entity else {
+        return Valid::<(), String>::fail("Key can only be used with an entity".to_string())
+            .when(|| graphql.key.is_some())
+            .trace("key")
+            .map_to(None);
+    };
+    let key = graphql.key.clone().unwrap_or_else(|| "id".to_string());
+
+    Valid::<(), String>::fail("Entities can only be requested in a query".to_string())
+        .when(|| matches!(operation_type, GraphQLOperationType::Mutation))
+        .and(
+            Valid::<(), String>::fail(format!("Field {} is not defined in the parent", key))
+                .when(|| !parent.fields.contains_key(&key))
+                .trace("key"),
+        )
+        .and(Valid::from(
+            Entity::new(entity.clone(), key).map_err(|e| ValidationError::new(e.to_string())),
+        ))
+        .map(Some)
+        .trace("entity")
+}
+
 pub fn compile_graphql(
     config: &config::Config,
     operation_type: &config::GraphQLOperationType,
+    parent: &config::Type,
     graphql: &config::GraphQL,
 ) -> Valid<IR, String> {
     let args = graphql.args.as_ref();
@@ -20,7 +101,12 @@ pub fn compile_graphql(
         "No base URL defined".to_string(),
     )
     .zip(helpers::headers::to_mustache_headers(&graphql.headers))
-    .and_then(|(base_url, headers)| {
+    .zip(match &graphql.fragment {
+        Some(fragment) => compile_fragments(config, fragment),
+        None => Valid::succeed(vec![]),
+    })
+    .zip(compile_entity(operation_type, parent, graphql))
+    .and_then(|(((base_url, headers), fragments), entity)| {
         Valid::from(
             RequestTemplate::new(
                 base_url.to_owned(),
@@ -31,14 +117,124 @@ pub fn compile_graphql(
             )
             .map_err(|e| ValidationError::new(e.to_string())),
         )
+        .map(|req_template| {
+            req_template
+                .selection_set(
+                    graphql
+                        .fragment
+                        .as_ref()
+                        .map(|fragment| format!("{{ ...{} }}", fragment)),
+                )
+                .fragments(fragments)
+                .entity(entity)
+                .allowed_headers(graphql.allowed_headers.clone())
+                .persisted(
+                    graphql
+                        .persist
+                        .unwrap_or_default()
+                        .then(|| Arc::new(PersistedQueries::default())),
+                )
+        })
     })
     .map(|req_template| {
         let field_name = graphql.name.clone();
-        let batch = graphql.batch;
-        IR::IO(IO::GraphQL { req_template, field_name, batch, dl_id: None })
+        let batch = graphql
+            .batch
+            .unwrap_or(config.upstream.get_batch_by_default());
+        let timeout = graphql.timeout.map(Duration::from_millis);
+        IR::IO(IO::GraphQL { req_template, field_name, batch, timeout, dl_id: None })
     })
 }
 
+/// Adds the arguments injected with `@modify(args:)` to the arguments of the
+/// upstream field, unless they're already mapped in `args`. The arguments
+/// renamed with `@modify(renameArgs:)` are sent under their new name.
+fn with_injected_args(field: &Field, graphql: &config::GraphQL) -> config::GraphQL {
+    let Some(modify) = field
+        .modify
+        .as_ref()
+        .filter(|modify| !modify.args.is_empty() || !modify.rename_args.is_empty())
+    else {
+        return graphql.clone();
+    };
+
+    let mut graphql = graphql.clone();
+    let args = graphql.args.get_or_insert_with(Vec::new);
+    for (name, key) in modify.rename_args.iter() {
+        match args.iter_mut().find(|arg| &arg.key == name) {
+            Some(arg) => arg.key = key.clone(),
+            None if !args.iter().any(|arg| &arg.key == key) => {
+                let value = format!("{{{{.args.{}}}}}", name);
+                args.push(KeyValue { key: key.clone(), value });
+            }
+            None => {}
+        }
+    }
+    for key in modify.args.keys() {
+        if !args.iter().any(|arg| &arg.key == key) {
+            let value = format!("{{{{.args.{}}}}}", key);
+            args.push(KeyValue { key: key.clone(), value });
+        }
+    }
+    graphql
+}
+
+/// Returns the type of the value passed to an upstream argument, when it's
+/// one of the arguments of the field eg. `{{.args.id}}`.
+fn arg_type(field: &Field, value: &str) -> Option<String> {
+    let mustache = Mustache::parse(value).ok()?;
+    match mustache.get_segments().as_slice() {
+        [Segment::Expression(parts)] => match parts.as_slice() {
+            [head, name] if head == "args" => field
+                .args
+                .get(name)
+                .map(|arg| to_type(arg, None).to_string()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Checks the field against the introspection of its upstream, when the
+/// upstream is linked with `@link(type: GraphQL)` and the field doesn't opt
+/// out with `introspect: false`.
+fn validate_introspection(
+    config_module: &ConfigModule,
+    operation_type: &GraphQLOperationType,
+    field: &Field,
+    graphql: &config::GraphQL,
+) -> Valid<(), String> {
+    let base_url = graphql
+        .base_url
+        .as_ref()
+        .or(config_module.upstream.base_url.as_ref());
+
+    let introspection = base_url.and_then(|base_url| {
+        config_module
+            .extensions
+            .graphql_introspections
+            .iter()
+            .find(|introspection| &introspection.url == base_url)
+    });
+
+    match introspection {
+        // Fields of entities aren't root fields of the upstream
+        Some(_) if graphql.entity.is_some() => Valid::succeed(()),
+        Some(_) if graphql.introspect == Some(false) => Valid::succeed(()),
+        Some(introspection) => {
+            let args = graphql
+                .args
+                .iter()
+                .flatten()
+                .map(|arg| (arg.key.clone(), arg_type(field, &arg.value)))
+                .collect::<Vec<_>>();
+
+            introspection.validate_field(operation_type, &graphql.name, &args)
+        }
+        None => Valid::succeed(()),
+    }
+}
+
 pub fn update_graphql<'a>(
     operation_type: &'a GraphQLOperationType,
 ) -> TryFold<'a, (&'a ConfigModule, &'a Field, &'a config::Type, &'a str), FieldDefinition, String>
@@ -48,8 +244,10 @@ pub fn update_graphql<'a>(
             let Some(graphql) = &field.graphql else {
                 return Valid::succeed(b_field);
             };
+            let graphql = &with_injected_args(field, graphql);
 
-            compile_graphql(config, operation_type, graphql)
+            validate_introspection(config, operation_type, field, graphql)
+                .and(compile_graphql(config, operation_type, type_of, graphql))
                 .map(|resolver| b_field.resolver(Some(resolver)))
                 .and_then(|b_field| b_field.validate_field(type_of, config).map_to(b_field))
         },
@@ -1,7 +1,8 @@
 use std::fmt::Display;
 
+use async_graphql_value::ConstValue;
 use prost_reflect::prost_types::FileDescriptorSet;
-use prost_reflect::FieldDescriptor;
+use prost_reflect::{FieldDescriptor, Kind, MessageDescriptor};
 
 use crate::core::blueprint::{FieldDefinition, TypeLike};
 use crate::core::config::group_by::GroupBy;
@@ -21,7 +22,11 @@ fn to_url(grpc: &Grpc, method: &GrpcMethod, config: &Config) -> Valid<Mustache,
         "No base URL defined".to_string(),
     )
     .and_then(|base_url| {
-        let mut base_url = base_url.trim_end_matches('/').to_owned();
+        let mut base_url = match grpc.tls {
+            Some(tls) => with_scheme(base_url, tls),
+            None => base_url.to_owned(),
+        };
+        base_url.truncate(base_url.trim_end_matches('/').len());
         base_url.push('/');
         base_url.push_str(format!("{}.{}", method.package, method.service).as_str());
         base_url.push('/');
@@ -31,6 +36,77 @@ fn to_url(grpc: &Grpc, method: &GrpcMethod, config: &Config) -> Valid<Mustache,
     })
 }
 
+/// Replaces the scheme of the base URL with `https` for TLS targets and with
+/// `http` for plaintext (h2c) ones.
+fn with_scheme(base_url: &str, tls: bool) -> String {
+    let rest = base_url
+        .strip_prefix("https://")
+        .or_else(|| base_url.strip_prefix("http://"))
+        .unwrap_or(base_url);
+    let scheme = if tls { "https" } else { "http" };
+    format!("{}://{}", scheme, rest)
+}
+
+/// The CA certificate is only used by TLS targets and has to contain at least
+/// one PEM encoded certificate.
+fn validate_ca_cert(grpc: &Grpc) -> Valid<(), String> {
+    let Some(ca_cert) = &grpc.ca_cert else {
+        return Valid::succeed(());
+    };
+
+    if grpc.tls == Some(false) {
+        return Valid::fail("caCert can not be used with a plaintext target".to_string())
+            .trace("caCert");
+    }
+
+    match rustls_pemfile::certs(&mut ca_cert.as_bytes()) {
+        Ok(certs) if !certs.is_empty() => Valid::succeed(()),
+        _ => Valid::fail("caCert is not a PEM encoded certificate".to_string()).trace("caCert"),
+    }
+}
+
+/// The client certificate is presented to TLS targets for mTLS, so it has to
+/// come with its private key.
+fn validate_client_cert(grpc: &Grpc) -> Valid<(), String> {
+    let (cert, key) = match (&grpc.client_cert, &grpc.client_key) {
+        (None, None) => return Valid::succeed(()),
+        (Some(cert), Some(key)) => (cert, key),
+        (Some(_), None) => {
+            return Valid::fail("clientCert requires a clientKey".to_string()).trace("clientCert")
+        }
+        (None, Some(_)) => {
+            return Valid::fail("clientKey requires a clientCert".to_string()).trace("clientKey")
+        }
+    };
+
+    if grpc.tls == Some(false) {
+        return Valid::fail("clientCert can not be used with a plaintext target".to_string())
+            .trace("clientCert");
+    }
+
+    let is_cert = matches!(
+        rustls_pemfile::certs(&mut cert.as_bytes()),
+        Ok(certs) if !certs.is_empty()
+    );
+    let is_key = matches!(
+        rustls_pemfile::read_one(&mut key.as_bytes()),
+        Ok(Some(
+            rustls_pemfile::Item::RSAKey(_)
+                | rustls_pemfile::Item::PKCS8Key(_)
+                | rustls_pemfile::Item::ECKey(_)
+        ))
+    );
+
+    Valid::<(), String>::fail("clientCert is not a PEM encoded certificate".to_string())
+        .trace("clientCert")
+        .when(|| !is_cert)
+        .and(
+            Valid::<(), String>::fail("clientKey is not a PEM encoded private key".to_string())
+                .trace("clientKey")
+                .when(|| !is_key),
+        )
+}
+
 fn to_operation(
     method: &GrpcMethod,
     file_descriptor_set: FileDescriptorSet,
@@ -85,6 +161,114 @@ fn validate_schema(
         })
 }
 
+/// Validates the static parts of the request body against the input message,
+/// ie. every key has to be a field of the message and every literal value has
+/// to match the type of the field. Mustache templates are resolved at runtime,
+/// so they're not validated.
+fn validate_body(body: &str, operation: &ProtobufOperation) -> Valid<(), String> {
+    match serde_json::from_str::<serde_json::Value>(body) {
+        Ok(value @ serde_json::Value::Object(_)) => {
+            validate_message(&value, &operation.input_type).trace("body")
+        }
+        _ => Valid::succeed(()),
+    }
+}
+
+fn validate_message(value: &serde_json::Value, message: &MessageDescriptor) -> Valid<(), String> {
+    let serde_json::Value::Object(map) = value else {
+        return Valid::fail(format!("expected object of type {}", message.full_name()));
+    };
+
+    Valid::from_iter(map.iter(), |(key, value)| {
+        let field = message
+            .get_field_by_json_name(key)
+            .or_else(|| message.get_field_by_name(key));
+
+        match field {
+            Some(field) => validate_field(value, &field, field.is_list()),
+            None => Valid::fail(format!(
+                "Field {} is not defined in {}",
+                key,
+                message.full_name()
+            )),
+        }
+        .trace(key)
+    })
+    .unit()
+}
+
+fn validate_field(
+    value: &serde_json::Value,
+    field: &FieldDescriptor,
+    is_list: bool,
+) -> Valid<(), String> {
+    match (value, field.kind()) {
+        (serde_json::Value::String(template), _) if template.contains("{{") => Valid::succeed(()),
+        (serde_json::Value::Array(list), _) if is_list => {
+            Valid::from_iter(list.iter().enumerate(), |(i, item)| {
+                validate_field(item, field, false).trace(i.to_string().as_str())
+            })
+            .unit()
+        }
+        (value, Kind::Message(message)) if !is_list && !field.is_map() => {
+            validate_message(value, &message)
+        }
+        (value, _) => Valid::from(JsonSchema::try_from(field)).and_then(|schema| {
+            let schema = match schema {
+                JsonSchema::Arr(schema) if !is_list => *schema,
+                schema => schema,
+            };
+            let value = ConstValue::from_json(value.clone()).unwrap_or_default();
+            match schema.validate(&value).to_result() {
+                Ok(_) => Valid::succeed(()),
+                Err(err) => Valid::from_validation_err(err.transform(&(|a| a.to_owned()))),
+            }
+        }),
+    }
+}
+
+/// Walks the path on the output message and compares the field with the type
+/// of the value at the end of it. Every part of the path but the last one has
+/// to be a message.
+fn validate_path(
+    field_schema: FieldSchema,
+    operation: &ProtobufOperation,
+    path: &[String],
+) -> Valid<(), String> {
+    let mut message = operation.output_type.clone();
+
+    for (i, name) in path.iter().enumerate() {
+        let Some(field) = message.get_field_by_json_name(name) else {
+            return Valid::fail(format!(
+                "Field {} is not defined in {}",
+                name,
+                message.full_name()
+            ))
+            .trace("path");
+        };
+
+        if i + 1 == path.len() {
+            return Valid::from(JsonSchema::try_from(&field))
+                .and_then(|schema| field_schema.field.compare(&schema, name))
+                .trace("path");
+        }
+
+        match field.kind() {
+            Kind::Message(inner) if !field.is_list() && !field.is_map() => message = inner,
+            _ => {
+                return Valid::fail(format!(
+                    "Field {} of {} is not a message",
+                    name,
+                    message.full_name()
+                ))
+                .trace("path")
+            }
+        }
+    }
+
+    Valid::succeed(())
+}
+
 fn validate_group_by(
     field_schema: &FieldSchema,
     operation: &ProtobufOperation,
@@ -171,7 +355,13 @@ pub fn compile_grpc(inputs: CompileGrpc) -> Valid<IR, String> {
                 return Valid::fail("Protobuf files were not specified in the config".to_string());
             }
 
-            to_operation(&method, file_descriptor_set)
+            if !grpc.path.is_empty() && !grpc.group_by.is_empty() {
+                return Valid::fail("batchKey can not be used together with path".to_string());
+            }
+
+            validate_ca_cert(grpc)
+                .and(validate_client_cert(grpc))
+                .and(to_operation(&method, file_descriptor_set))
                 .fuse(to_url(grpc, &method, config_module))
                 .fuse(helpers::headers::to_mustache_headers(&grpc.headers))
                 .fuse(helpers::body::to_body(grpc.body.as_deref()))
@@ -180,10 +370,22 @@ pub fn compile_grpc(inputs: CompileGrpc) -> Valid<IR, String> {
         .and_then(|(operation, url, headers, body)| {
             let validation = if validate_with_schema {
                 let field_schema = json_schema_from_field(config_module, field);
-                if grpc.group_by.is_empty() {
-                    validate_schema(field_schema, &operation, field.name()).unit()
+                let body_validation = grpc
+                    .body
+                    .as_deref()
+                    .map_or(Valid::succeed(()), |body| validate_body(body, &operation));
+                if !grpc.path.is_empty() {
+                    validate_path(field_schema, &operation, &grpc.path)
+                        .zip(body_validation)
+                        .unit()
+                } else if grpc.group_by.is_empty() {
+                    validate_schema(field_schema, &operation, field.name())
+                        .zip(body_validation)
+                        .unit()
                 } else {
-                    validate_group_by(&field_schema, &operation, grpc.group_by.clone()).unit()
+                    validate_group_by(&field_schema, &operation, grpc.group_by.clone())
+                        .zip(body_validation)
+                        .unit()
                 }
             } else {
                 Valid::succeed(())
@@ -198,7 +400,7 @@ pub fn compile_grpc(inputs: CompileGrpc) -> Valid<IR, String> {
                 body,
                 operation_type: operation_type.clone(),
             };
-            if !grpc.group_by.is_empty() {
+            let io = if !grpc.group_by.is_empty() {
                 IR::IO(IO::Grpc {
                     req_template,
                     group_by: Some(GroupBy::new(grpc.group_by.clone())),
@@ -206,6 +408,12 @@ pub fn compile_grpc(inputs: CompileGrpc) -> Valid<IR, String> {
                 })
             } else {
                 IR::IO(IO::Grpc { req_template, group_by: None, dl_id: None })
+            };
+
+            if grpc.path.is_empty() {
+                io
+            } else {
+                IR::Path(Box::new(io), grpc.path.clone())
             }
         })
 }
@@ -241,8 +449,22 @@ pub fn update_grpc<'a>(
 mod tests {
     use std::convert::TryFrom;
 
-    use super::GrpcMethod;
-    use crate::core::valid::ValidationError;
+    use super::{to_url, validate_ca_cert, validate_client_cert, GrpcMethod};
+    use crate::core::config::{Config, Grpc};
+    use crate::core::valid::{ValidationError, Validator};
+
+    fn url(base_url: &str, tls: Option<bool>) -> String {
+        let grpc = Grpc {
+            base_url: Some(base_url.to_string()),
+            tls,
+            ..Default::default()
+        };
+        let method = GrpcMethod::try_from("news.NewsService.GetAllNews").unwrap();
+        to_url(&grpc, &method, &Config::default())
+            .to_result()
+            .unwrap()
+            .render(&serde_json::Value::Null)
+    }
 
     #[test]
     fn try_from_grpc_method() {
@@ -268,4 +490,97 @@ mod tests {
             ValidationError::new("Invalid method format: package_name.ServiceName. Expected format is <package>.<service>.<method>".to_string())
         );
     }
+
+    #[test]
+    fn test_url_tls() {
+        assert_eq!(
+            url("http://localhost:50051", Some(true)),
+            "https://localhost:50051/news.NewsService/GetAllNews"
+        );
+        assert_eq!(
+            url("https://localhost:50051/", Some(false)),
+            "http://localhost:50051/news.NewsService/GetAllNews"
+        );
+        assert_eq!(
+            url("https://localhost:50051", None),
+            "https://localhost:50051/news.NewsService/GetAllNews"
+        );
+    }
+
+    #[test]
+    fn test_ca_cert() {
+        let ca_cert = include_str!("../../../../tests/server/config/example.crt").to_string();
+
+        let grpc = Grpc {
+            ca_cert: Some(ca_cert.clone()),
+            tls: Some(true),
+            ..Default::default()
+        };
+        assert!(validate_ca_cert(&grpc).is_succeed());
+
+        let grpc = Grpc {
+            ca_cert: Some(ca_cert),
+            tls: Some(false),
+            ..Default::default()
+        };
+        assert_eq!(
+            validate_ca_cert(&grpc).to_result().unwrap_err(),
+            ValidationError::new("caCert can not be used with a plaintext target".to_string())
+                .trace("caCert")
+        );
+
+        let grpc = Grpc { ca_cert: Some("foo".to_string()), ..Default::default() };
+        assert_eq!(
+            validate_ca_cert(&grpc).to_result().unwrap_err(),
+            ValidationError::new("caCert is not a PEM encoded certificate".to_string())
+                .trace("caCert")
+        );
+    }
+
+    #[test]
+    fn test_client_cert() {
+        let cert = include_str!("../../../../tests/server/config/example.crt").to_string();
+        let key = include_str!("../../../../tests/server/config/example-pkcs8.key").to_string();
+
+        let grpc = Grpc {
+            client_cert: Some(cert.clone()),
+            client_key: Some(key.clone()),
+            ..Default::default()
+        };
+        assert!(validate_client_cert(&grpc).is_succeed());
+
+        let grpc = Grpc { client_cert: Some(cert.clone()), ..Default::default() };
+        assert_eq!(
+            validate_client_cert(&grpc).to_result().unwrap_err(),
+            ValidationError::new("clientCert requires a clientKey".to_string()).trace("clientCert")
+        );
+
+        let grpc = Grpc {
+            client_cert: Some(cert),
+            client_key: Some(key.clone()),
+            tls: Some(false),
+            ..Default::default()
+        };
+        assert_eq!(
+            validate_client_cert(&grpc).to_result().unwrap_err(),
+            ValidationError::new("clientCert can not be used with a plaintext target".to_string())
+                .trace("clientCert")
+        );
+
+        let grpc = Grpc {
+            client_cert: Some(key),
+            client_key: Some("foo".to_string()),
+            ..Default::default()
+        };
+        let errors = validate_client_cert(&grpc).to_result().unwrap_err();
+        assert_eq!(
+            errors,
+            ValidationError::new("clientCert is not a PEM encoded certificate".to_string())
+                .trace("clientCert")
+                .combine(
+                    ValidationError::new("clientKey is not a PEM encoded private key".to_string())
+                        .trace("clientKey")
+                )
+        );
+    }
 }
@@ -1,13 +1,213 @@
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Arc;
+
 use crate::core::blueprint::*;
 use crate::core::config::group_by::GroupBy;
 use crate::core::config::Field;
 use crate::core::endpoint::Endpoint;
-use crate::core::http::{HttpFilter, Method, RequestTemplate};
+use crate::core::http::{
+    HmacSigner, HttpFilter, Method, QuerySigner, RequestTemplate, SigV4, UrlPool,
+};
 use crate::core::ir::{IO, IR};
+use crate::core::json::JsonSchema;
+use crate::core::mustache::{Mustache, Segment};
 use crate::core::try_fold::TryFold;
 use crate::core::valid::{Valid, ValidationError, Validator};
 use crate::core::{config, helpers};
 
+/// The headers are mapped into the fields of the resolved object, so the field
+/// has to return a single object that has all the mapped fields. A header is
+/// either the name of a header, or a template that reads the headers of the
+/// response eg. `{{.response.headers.x-page}}`.
+fn compile_header_map(
+    config_module: &config::ConfigModule,
+    field: &config::Field,
+    http: &config::Http,
+) -> Valid<BTreeMap<String, Mustache>, String> {
+    if http.header_map.is_empty() {
+        return Valid::succeed(BTreeMap::new());
+    }
+
+    let type_of = config_module
+        .find_type(&field.type_of)
+        .filter(|_| !field.list);
+
+    Valid::from_option(
+        type_of,
+        "headerMap can only be used on fields returning an object".to_string(),
+    )
+    .and_then(|type_of| {
+        Valid::from_iter(http.header_map.iter(), |(name, header)| {
+            Valid::<(), String>::fail(format!(
+                "Field {} is not defined in {}",
+                name, field.type_of
+            ))
+            .when(|| !type_of.fields.contains_key(name))
+            .and(Valid::from(
+                Mustache::parse(header).map_err(|e| ValidationError::new(e.to_string())),
+            ))
+            .map(|template| {
+                if template.is_const() {
+                    let header = header.to_lowercase();
+                    let parts = vec!["response".to_string(), "headers".to_string(), header];
+                    Mustache::from(vec![Segment::Expression(parts)])
+                } else {
+                    template
+                }
+            })
+            .and_then(|template| {
+                Valid::from_iter(template.expression_segments(), |parts| {
+                    match parts.as_slice() {
+                        [response, headers, header]
+                            if response == "response" && headers == "headers" =>
+                        {
+                            Valid::<(), String>::fail(format!("Invalid header name: {}", header))
+                                .when(|| {
+                                    reqwest::header::HeaderName::from_bytes(header.as_bytes())
+                                        .is_err()
+                                })
+                        }
+                        _ => Valid::fail(format!(
+                            "Only the headers of the response can be read, found {{{{.{}}}}}",
+                            parts.join(".")
+                        )),
+                    }
+                })
+                .map_to((name.clone(), template))
+            })
+            .trace(name)
+        })
+    })
+    .map(|header_map| header_map.into_iter().collect())
+}
+
+/// The query params of the request, with the arguments of the field when
+/// they are forwarded. The params of `query` are sent first, in their order,
+/// and take precedence over the forwarded arguments: an argument isn't
+/// forwarded when its key is already in `query` or when `query` already sends
+/// it under another key, eg. `{key: "q", value: "{{.args.searchTerm}}"}`.
+fn to_query(field: &config::Field, http: &config::Http) -> Vec<(String, String)> {
+    let mut query: Vec<(String, String)> = http
+        .query
+        .iter()
+        .map(|key_value| (key_value.key.clone(), key_value.value.clone()))
+        .collect();
+
+    if http.forward_args {
+        let mapped: HashSet<String> = http
+            .query
+            .iter()
+            .filter_map(|key_value| Mustache::parse(&key_value.value).ok())
+            .flat_map(|mustache| {
+                mustache
+                    .expression_segments()
+                    .into_iter()
+                    .filter_map(|parts| match parts.as_slice() {
+                        [root, name, ..] if root == "args" => Some(name.clone()),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+
+        let renamed = field.modify.as_ref().map(|modify| &modify.rename_args);
+        let args = field.args.keys().map(|name| {
+            let key = renamed
+                .and_then(|renamed| renamed.get(name))
+                .unwrap_or(name);
+            (key, name)
+        });
+        let injected = field
+            .modify
+            .iter()
+            .flat_map(|modify| modify.args.keys())
+            .map(|name| (name, name));
+        for (key, name) in args.chain(injected) {
+            if !mapped.contains(name) && !query.iter().any(|(other, _)| other == key) {
+                query.push((key.clone(), format!("{{{{.args.{}}}}}", name)));
+            }
+        }
+    }
+
+    query
+}
+
+/// Adds the `limit` and `offset` arguments to the list fields that paginate
+/// their API, with `@server(defaultPageSize:)` or `@http(pagination:)`, and
+/// sends them as the pagination params of the API. The arguments that are
+/// already defined on the field are left as they are.
+pub fn to_paginated_field<'a>(config_module: &ConfigModule, field: &'a Field) -> Cow<'a, Field> {
+    let default_page_size = config_module.server.default_page_size;
+    let pagination = field.http.as_ref().filter(|_| field.list).and_then(|http| {
+        http.pagination
+            .clone()
+            .or(default_page_size.map(|_| config::Pagination::default()))
+    });
+    let Some(pagination) = pagination else {
+        return Cow::Borrowed(field);
+    };
+
+    let mut field = field.clone();
+    let params = [
+        (
+            "limit",
+            pagination.limit.unwrap_or("limit".to_string()),
+            pagination.page_size.or(default_page_size),
+        ),
+        (
+            "offset",
+            pagination.offset.unwrap_or("offset".to_string()),
+            None,
+        ),
+    ];
+    for (name, key, default_value) in params {
+        if field.args.contains_key(name) {
+            continue;
+        }
+        field.args.insert(
+            name.to_string(),
+            config::Arg {
+                type_of: "Int".to_string(),
+                default_value: default_value.map(serde_json::Value::from),
+                ..Default::default()
+            },
+        );
+        if let Some(http) = field.http.as_mut() {
+            if !http.query.iter().any(|key_value| key_value.key == key) {
+                let value = format!("{{{{.args.{}}}}}", name);
+                http.query.push(config::KeyValue { key, value });
+            }
+        }
+    }
+
+    Cow::Owned(field)
+}
+
+/// The signed query params are computed from the other query params, so their
+/// inputs have to be sent and their keys can't be sent already.
+fn validate_signed_query(
+    query: &[(String, String)],
+    signed_query: &[config::SignedQuery],
+) -> Valid<(), String> {
+    Valid::from_iter(signed_query.iter(), |signed| {
+        let is_sent = |name: &str| query.iter().any(|(key, _)| key == name);
+
+        Valid::<(), String>::fail(format!("Query param {} is already sent", signed.key))
+            .when(|| is_sent(&signed.key))
+            .trace("key")
+            .and(
+                Valid::from_iter(signed.inputs.iter(), |input| {
+                    Valid::<(), String>::fail(format!("Query param {} is not sent", input))
+                        .when(|| !is_sent(input))
+                })
+                .trace("inputs"),
+            )
+            .trace(&signed.key)
+    })
+    .unit()
+}
+
 pub fn compile_http(
     config_module: &config::ConfigModule,
     field: &config::Field,
@@ -15,6 +215,11 @@ pub fn compile_http(
 ) -> Valid<IR, String> {
     Valid::<(), String>::fail("GroupBy is only supported for GET requests".to_string())
         .when(|| !http.group_by.is_empty() && http.method != Method::GET)
+        .and(
+            Valid::<(), String>::fail("GroupBy can not be used with batch: false".to_string())
+                .when(|| !http.group_by.is_empty() && http.batch == Some(false))
+                .trace("batch"),
+        )
         .and(
             Valid::<(), String>::fail(
                 "GroupBy can only be applied if batching is enabled".to_string(),
@@ -25,24 +230,68 @@ pub fn compile_http(
                     && !http.group_by.is_empty()
             }),
         )
+        .and(
+            Valid::from_iter(http.expect_status.iter(), |status| {
+                Valid::<(), String>::fail(format!("Invalid status code: {}", status))
+                    .when(|| reqwest::StatusCode::from_u16(*status).is_err())
+            })
+            .trace("expectStatus"),
+        )
+        .and(
+            validate_signed_query(
+                &to_query(field, http),
+                http.auth
+                    .as_ref()
+                    .map(|auth| auth.signed_query.as_slice())
+                    .unwrap_or_default(),
+            )
+            .trace("signedQuery")
+            .trace("auth"),
+        )
+        .and(
+            Valid::<(), String>::fail("baseURL and baseURLs can not be used together".to_string())
+                .when(|| http.base_url.is_some() && !http.base_urls.is_empty())
+                .trace("baseURLs"),
+        )
+        .and(
+            Valid::<(), String>::fail(
+                "baseURLByProfile and baseURLs can not be used together".to_string(),
+            )
+            .when(|| !http.base_url_by_profile.is_empty() && !http.base_urls.is_empty())
+            .trace("baseURLByProfile"),
+        )
         .and(Valid::from_option(
-            http.base_url
-                .as_ref()
+            config_module
+                .server
+                .get_profile()
+                .and_then(|profile| http.base_url_by_profile.get(profile))
+                .or(http.base_url.as_ref())
+                .or(http.base_urls.first())
                 .or(config_module.upstream.base_url.as_ref()),
             "No base URL defined".to_string(),
         ))
         .zip(helpers::headers::to_mustache_headers(&http.headers))
-        .and_then(|(base_url, headers)| {
-            let mut base_url = base_url.trim_end_matches('/').to_owned();
-            base_url.push_str(http.path.clone().as_str());
+        .zip(compile_header_map(config_module, field, http).trace("headerMap"))
+        .and_then(|((base_url, headers), header_map)| {
+            // Exactly one slash separates the base URL from the path
+            let to_root_url = |base_url: &str| {
+                let mut base_url = base_url.trim_end_matches('/').to_owned();
+                if !http.path.is_empty() {
+                    base_url.push('/');
+                    base_url.push_str(http.path.trim_start_matches('/'));
+                }
+                base_url
+            };
+            let base_url = to_root_url(base_url);
 
-            let query = http
-                .query
-                .clone()
-                .iter()
-                .map(|key_value| (key_value.key.clone(), key_value.value.clone()))
-                .collect();
-            let output_schema = to_json_schema_for_field(field, config_module);
+            let query = to_query(field, http);
+            // The response is only shaped as the type of the field once it's
+            // patched
+            let output_schema = if field.patch.is_some() {
+                JsonSchema::Any
+            } else {
+                to_json_schema_for_field(field, config_module)
+            };
             let input_schema = to_json_schema_for_args(&field.args, config_module);
 
             RequestTemplate::try_from(
@@ -52,9 +301,54 @@ pub fn compile_http(
                     .output(output_schema)
                     .input(input_schema)
                     .body(http.body.clone())
-                    .encoding(http.encoding.clone()),
+                    .encoding(http.encoding.clone())
+                    .expect_status(http.expect_status.clone())
+                    .header_map(header_map),
             )
-            .map(|req_tmpl| req_tmpl.headers(headers))
+            .and_then(|req_tmpl| {
+                let sigv4 = http
+                    .auth
+                    .as_ref()
+                    .and_then(|auth| auth.sigv4.as_ref())
+                    .map(SigV4::try_from)
+                    .transpose()?;
+                let hmac = http
+                    .auth
+                    .as_ref()
+                    .and_then(|auth| auth.hmac.as_ref())
+                    .map(HmacSigner::try_from)
+                    .transpose()?;
+                let signed_query = http
+                    .auth
+                    .iter()
+                    .flat_map(|auth| auth.signed_query.iter())
+                    .map(QuerySigner::try_from)
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+
+                let url_pool = if http.base_urls.len() > 1 {
+                    let root_urls = http
+                        .base_urls
+                        .iter()
+                        .map(|base_url| Mustache::parse(&to_root_url(base_url)))
+                        .collect::<anyhow::Result<Vec<_>>>()?;
+                    Some(Arc::new(UrlPool::new(root_urls)))
+                } else {
+                    None
+                };
+
+                let allowed_hosts = config_module.upstream.get_allowed_hosts();
+
+                Ok(req_tmpl
+                    .headers(headers)
+                    .allowed_hosts(Arc::new(allowed_hosts.into_iter().collect()))
+                    .allowed_headers(http.allowed_headers.clone())
+                    .idempotent(http.idempotent)
+                    .query_encoding(http.query_encoding.clone())
+                    .sigv4(sigv4)
+                    .hmac(hmac)
+                    .signed_query(signed_query)
+                    .url_pool(url_pool))
+            })
             .map_err(|e| ValidationError::new(e.to_string()))
             .into()
         })
@@ -66,10 +360,13 @@ pub fn compile_http(
                 .or(config_module.upstream.on_request.clone())
                 .map(|on_request| HttpFilter { on_request });
 
-            if !http.group_by.is_empty() && http.method == Method::GET {
+            // Every http resolver gets its own data loader once the app context
+            // is created, but only the ones with a group by batch their requests
+            let group_by = http.get_group_by(config_module.upstream.get_batch_by_default());
+            if !group_by.is_empty() && http.method == Method::GET {
                 IR::IO(IO::Http {
                     req_template,
-                    group_by: Some(GroupBy::new(http.group_by.clone())),
+                    group_by: Some(GroupBy::new(group_by)),
                     dl_id: None,
                     http_filter,
                 })
@@ -79,6 +376,37 @@ pub fn compile_http(
         })
 }
 
+/// Wraps the resolver so that it resolves to the fallback when the request
+/// fails. It's done after the templates are validated, as they are only
+/// checked on the IO itself.
+fn compile_on_error(
+    config_module: &ConfigModule,
+    field: &Field,
+    http: &config::Http,
+    b_field: FieldDefinition,
+) -> Valid<FieldDefinition, String> {
+    let Some(on_error) = &http.on_error else {
+        return Valid::succeed(b_field);
+    };
+
+    compile_expr(CompileExpr {
+        config_module,
+        field,
+        value: &on_error.fallback,
+        validate: true,
+    })
+    .trace("fallback")
+    .trace("onError")
+    .map(|fallback| {
+        let resolver = b_field.resolver.clone().map(|expr| IR::OnError {
+            expr: Box::new(expr),
+            fallback: Box::new(fallback),
+            log: on_error.log,
+        });
+        b_field.resolver(resolver)
+    })
+}
+
 pub fn update_http<'a>(
 ) -> TryFold<'a, (&'a ConfigModule, &'a Field, &'a config::Type, &'a str), FieldDefinition, String>
 {
@@ -95,6 +423,7 @@ pub fn update_http<'a>(
                         .validate_field(type_of, config_module)
                         .map_to(b_field)
                 })
+                .and_then(|b_field| compile_on_error(config_module, field, http, b_field))
         },
     )
 }
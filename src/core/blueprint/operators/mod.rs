@@ -1,17 +1,23 @@
 mod call;
+mod default;
 mod expr;
 mod graphql;
 mod grpc;
 mod http;
 mod js;
 mod modify;
+mod patch;
 mod protected;
+mod wasm;
 
 pub use call::*;
+pub use default::*;
 pub use expr::*;
 pub use graphql::*;
 pub use grpc::*;
 pub use http::*;
 pub use js::*;
 pub use modify::*;
+pub use patch::*;
 pub use protected::*;
+pub use wasm::*;
@@ -1,17 +1,170 @@
+use std::collections::BTreeMap;
+
 use crate::core::blueprint::*;
 use crate::core::config;
 use crate::core::config::Field;
 use crate::core::ir::{Context, IR};
+use crate::core::mustache::Mustache;
 use crate::core::try_fold::TryFold;
-use crate::core::valid::Valid;
+use crate::core::valid::{Valid, ValidationError, Validator};
+
+/// Compiles `@modify(merge:)` into an object assembled from the fields of the
+/// parent value. Every key must be a field of the type of the merged field and
+/// every `.value` path must point to a field of the parent type.
+fn compile_merge(
+    config: &ConfigModule,
+    field: &Field,
+    parent: &config::Type,
+    merge: &BTreeMap<String, String>,
+) -> Valid<IR, String> {
+    let fields = config
+        .find_type(&field.type_of)
+        .map(|type_of| &type_of.fields);
+
+    Valid::<(), String>::fail("Merge can not be used on a field with a resolver".to_string())
+        .when(|| field.has_resolver())
+        .and(Valid::from_iter(merge.iter(), |(key, value)| {
+            Valid::<(), String>::fail(format!("Field {} is not defined in {}", key, field.type_of))
+                .when(|| fields.is_some_and(|fields| !fields.contains_key(key)))
+                .and(Valid::from(
+                    Mustache::parse(value).map_err(|e| ValidationError::new(e.to_string())),
+                ))
+                .and_then(|mustache| {
+                    Valid::from_iter(mustache.expression_segments(), |parts| {
+                        match parts.as_slice() {
+                            [root, name, ..]
+                                if root == "value" && !parent.fields.contains_key(name) =>
+                            {
+                                Valid::fail(format!("Field {} is not defined in the parent", name))
+                            }
+                            _ => Valid::succeed(()),
+                        }
+                    })
+                })
+                .trace(key)
+        }))
+        .and_then(|_| {
+            let value = serde_json::Value::Object(
+                merge
+                    .iter()
+                    .map(|(key, value)| (key.clone(), serde_json::Value::String(value.clone())))
+                    .collect(),
+            );
+            Valid::from(
+                DynamicValue::try_from(&value).map_err(|e| ValidationError::new(e.to_string())),
+            )
+        })
+        .map(IR::Dynamic)
+        .trace("merge")
+}
+
+/// Compiles `@modify(args:)` into the arguments the resolver of the field is
+/// evaluated with: the arguments of the client merged with the injected
+/// constants. The injected arguments can't shadow the arguments of the field.
+fn compile_args(field: &Field, args: &BTreeMap<String, serde_json::Value>) -> Valid<IR, String> {
+    Valid::<(), String>::fail("Args can only be injected into a field with a resolver".to_string())
+        .when(|| !field.has_resolver())
+        .and(
+            Valid::<(), String>::fail(
+                "Args can not be injected into a @call field, use the args of its steps instead"
+                    .to_string(),
+            )
+            .when(|| field.call.is_some()),
+        )
+        .and(Valid::from_iter(args.keys(), |key| {
+            Valid::<(), String>::fail(format!("Argument {} is already defined on the field", key))
+                .when(|| field.args.contains_key(key))
+        }))
+        .and_then(|_| {
+            let value = serde_json::Value::Object(
+                field
+                    .args
+                    .keys()
+                    .map(|key| {
+                        let template = format!("{{{{.args.{}}}}}", key);
+                        (key.clone(), serde_json::Value::String(template))
+                    })
+                    .chain(args.clone())
+                    .collect(),
+            );
+            Valid::from(
+                DynamicValue::try_from(&value).map_err(|e| ValidationError::new(e.to_string())),
+            )
+        })
+        .map(IR::Dynamic)
+        .trace("args")
+}
+
+/// Checks `@modify(renameArgs:)`, only the arguments of the field that are sent
+/// by name to the upstream can be renamed.
+fn validate_rename_args(
+    field: &Field,
+    rename_args: &BTreeMap<String, String>,
+) -> Valid<(), String> {
+    let forwarded = field.http.as_ref().is_some_and(|http| http.forward_args);
+
+    Valid::<(), String>::fail(
+        "Args can only be renamed with @http(forwardArgs: true) or @graphQL".to_string(),
+    )
+    .when(|| !forwarded && field.graphql.is_none())
+    .and(Valid::from_iter(rename_args.keys(), |key| {
+        Valid::<(), String>::fail(format!("Argument {} is not defined on the field", key))
+            .when(|| !field.args.contains_key(key))
+    }))
+    .unit()
+    .trace("renameArgs")
+}
 
 pub fn update_modify<'a>(
 ) -> TryFold<'a, (&'a ConfigModule, &'a Field, &'a config::Type, &'a str), FieldDefinition, String>
 {
     TryFold::<(&ConfigModule, &Field, &config::Type, &'a str), FieldDefinition, String>::new(
-        |(config, field, type_of, _), mut b_field| {
+        |(config, field, type_of, name), mut b_field| {
             if let Some(modify) = field.modify.as_ref() {
+                if !modify.args.is_empty() {
+                    match compile_args(field, &modify.args).to_result() {
+                        Ok(args) => b_field.map_expr(|expr| expr.with_args(args.clone())),
+                        Err(error) => return Valid::from_validation_err(error),
+                    }
+                }
+
+                if !modify.rename_args.is_empty() {
+                    if let Err(error) = validate_rename_args(field, &modify.rename_args).to_result()
+                    {
+                        return Valid::from_validation_err(error);
+                    }
+                }
+
+                if !modify.merge.is_empty() {
+                    match compile_merge(config, field, type_of, &modify.merge).to_result() {
+                        Ok(resolver) => b_field.resolver = Some(resolver),
+                        Err(error) => return Valid::from_validation_err(error),
+                    }
+                }
+
                 if let Some(new_name) = &modify.name {
+                    // Fields that are renamed as well free up their name, which
+                    // allows to swap the names of two fields
+                    let already_exists = type_of.fields.iter().any(|(other_name, other)| {
+                        other_name.as_str() != *name
+                            && other
+                                .modify
+                                .as_ref()
+                                .and_then(|modify| modify.name.as_ref())
+                                .unwrap_or(other_name)
+                                == new_name
+                    }) || type_of
+                        .added_fields
+                        .iter()
+                        .any(|added_field| added_field.name == *new_name);
+
+                    if already_exists {
+                        return Valid::fail(format!(
+                            "{} cannot rename to {}: {} already exists",
+                            name, new_name, new_name
+                        ));
+                    }
+
                     for name in type_of.implements.iter() {
                         let interface = config.find_type(name);
                         if let Some(interface) = interface {
@@ -34,3 +187,62 @@ pub fn update_modify<'a>(
         },
     )
 }
+
+/// Hoists the fields of the nested object into the value the field resolves
+/// to. Fields without a resolver read their value from the parent.
+pub fn update_flatten<'a>(
+) -> TryFold<'a, (&'a ConfigModule, &'a Field, &'a config::Type, &'a str), FieldDefinition, String>
+{
+    TryFold::<(&ConfigModule, &Field, &config::Type, &str), FieldDefinition, String>::new(
+        |(config, field, _, _), b_field| {
+            let Some(key) = field.flattened() else {
+                return Valid::succeed(b_field);
+            };
+
+            Valid::from_option(
+                config.find_type(&field.type_of),
+                "Flatten can only be used on a field of an object type".to_string(),
+            )
+            .and_then(|type_of| {
+                Valid::<(), String>::fail(format!(
+                    "Field {} is flattened, it can not be defined on {}",
+                    key, field.type_of
+                ))
+                .when(|| type_of.fields.contains_key(key))
+            })
+            .trace("flatten")
+            .map(|_| {
+                let expr = b_field
+                    .resolver
+                    .clone()
+                    .unwrap_or(IR::Context(Context::Path(vec![b_field.name.clone()])));
+                b_field.resolver(Some(IR::Flatten { expr: Box::new(expr), key: key.clone() }))
+            })
+        },
+    )
+}
+
+/// Wraps the resolver of the field so that the value it resolves to is exposed
+/// as a list. Fields without a resolver read their value from the parent.
+pub fn update_wrap_in_list<'a>(
+) -> TryFold<'a, (&'a ConfigModule, &'a Field, &'a config::Type, &'a str), FieldDefinition, String>
+{
+    TryFold::<(&ConfigModule, &Field, &config::Type, &str), FieldDefinition, String>::new(
+        |(_, field, _, _), b_field| {
+            if !field.is_wrapped_in_list() {
+                return Valid::succeed(b_field);
+            }
+
+            Valid::<(), String>::fail("wrapInList can only be used on a list field".to_string())
+                .when(|| !field.list)
+                .trace("wrapInList")
+                .map(|_| {
+                    let expr = b_field
+                        .resolver
+                        .clone()
+                        .unwrap_or(IR::Context(Context::Path(vec![b_field.name.clone()])));
+                    b_field.resolver(Some(IR::WrapInList(Box::new(expr))))
+                })
+        },
+    )
+}
@@ -0,0 +1,88 @@
+use async_graphql_value::ConstValue;
+
+use crate::core::blueprint::*;
+use crate::core::config;
+use crate::core::config::{Field, PatchOp};
+use crate::core::ir::{Context, IR};
+use crate::core::json::{JsonPatch, JsonPointer, Operation};
+use crate::core::try_fold::TryFold;
+use crate::core::valid::{Valid, ValidationError, Validator};
+
+fn to_pointer(pointer: Option<&String>, name: &str, op: PatchOp) -> Valid<JsonPointer, String> {
+    Valid::from_option(pointer, format!("{} is required by {}", name, op))
+        .and_then(|pointer| Valid::from(JsonPointer::parse(pointer).map_err(ValidationError::new)))
+        .trace(name)
+}
+
+fn to_value(operation: &config::PatchOperation) -> Valid<ConstValue, String> {
+    Valid::from_option(
+        operation.value.clone(),
+        format!("value is required by {}", operation.op),
+    )
+    .and_then(|value| {
+        Valid::from(ConstValue::from_json(value).map_err(|e| ValidationError::new(e.to_string())))
+    })
+    .trace("value")
+}
+
+fn to_operation(operation: &config::PatchOperation) -> Valid<Operation, String> {
+    let op = operation.op;
+    let path = to_pointer(Some(&operation.path), "path", op);
+    let from = || to_pointer(operation.from.as_ref(), "from", op);
+
+    match op {
+        PatchOp::Add => path
+            .zip(to_value(operation))
+            .map(|(path, value)| Operation::Add { path, value }),
+        PatchOp::Remove => path.and_then(|path| {
+            Valid::<(), String>::fail("The root can not be removed".to_string())
+                .when(|| path.is_root())
+                .map_to(Operation::Remove { path })
+        }),
+        PatchOp::Replace => path
+            .zip(to_value(operation))
+            .map(|(path, value)| Operation::Replace { path, value }),
+        PatchOp::Move => path.zip(from()).and_then(|(path, from)| {
+            Valid::<(), String>::fail(format!(
+                "{} can not be moved into one of its children",
+                from
+            ))
+            .when(|| path.is_child_of(&from))
+            .map_to(Operation::Move { from, path })
+        }),
+        PatchOp::Copy => path
+            .zip(from())
+            .map(|(path, from)| Operation::Copy { from, path }),
+        PatchOp::Test => path
+            .zip(to_value(operation))
+            .map(|(path, value)| Operation::Test { path, value }),
+    }
+}
+
+/// Wraps the resolver of the field so that the patch is applied to the value
+/// it resolves to. Fields without a resolver read their value from the parent.
+pub fn update_patch<'a>(
+) -> TryFold<'a, (&'a ConfigModule, &'a Field, &'a config::Type, &'a str), FieldDefinition, String>
+{
+    TryFold::<(&ConfigModule, &Field, &config::Type, &str), FieldDefinition, String>::new(
+        |(_, field, _, _), b_field| {
+            let Some(patch) = &field.patch else {
+                return Valid::succeed(b_field);
+            };
+
+            Valid::from_iter(patch.ops.iter().enumerate(), |(i, operation)| {
+                to_operation(operation).trace(&i.to_string())
+            })
+            .trace("ops")
+            .map(|operations| {
+                let expr = b_field
+                    .resolver
+                    .clone()
+                    .unwrap_or(IR::Context(Context::Path(vec![b_field.name.clone()])));
+                let resolver =
+                    IR::Patch { expr: Box::new(expr), patch: JsonPatch::from(operations) };
+                b_field.resolver(Some(resolver))
+            })
+        },
+    )
+}
@@ -0,0 +1,29 @@
+use crate::core::blueprint::FieldDefinition;
+use crate::core::config;
+use crate::core::config::{ConfigModule, Field};
+use crate::core::ir::{IO, IR};
+use crate::core::try_fold::TryFold;
+use crate::core::valid::{Valid, Validator};
+
+pub fn compile_wasm(wasm: &config::Wasm) -> Valid<IR, String> {
+    Valid::<(), String>::fail("module is required".to_string())
+        .when(|| wasm.module.is_empty())
+        .and(
+            Valid::<(), String>::fail("func is required".to_string()).when(|| wasm.func.is_empty()),
+        )
+        .map(|_| IR::IO(IO::Wasm { module: wasm.module.clone(), func: wasm.func.clone() }))
+}
+
+pub fn update_wasm_field<'a>(
+) -> TryFold<'a, (&'a ConfigModule, &'a Field, &'a config::Type, &'a str), FieldDefinition, String>
+{
+    TryFold::<(&ConfigModule, &Field, &config::Type, &str), FieldDefinition, String>::new(
+        |(_, field, _, _), b_field| {
+            let Some(wasm) = &field.wasm else {
+                return Valid::succeed(b_field);
+            };
+
+            compile_wasm(wasm).map(|resolver| b_field.resolver(Some(resolver)))
+        },
+    )
+}
@@ -1,5 +1,5 @@
 use std::collections::{BTreeMap, BTreeSet, HashSet};
-use std::net::{AddrParseError, IpAddr};
+use std::net::IpAddr;
 use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Duration;
@@ -11,33 +11,70 @@ use rustls_pki_types::{CertificateDer, PrivateKeyDer};
 
 use super::Auth;
 use crate::core::blueprint::Cors;
-use crate::core::config::{self, ConfigModule, HttpVersion};
+use crate::core::config::{
+    self, ConfigModule, ErrorFormat, HttpVersion, Playground, ResponseValidation, TelemetryExporter,
+};
+use crate::core::http::{is_operation_hash, SHOWCASE_PATH};
+use crate::core::ir::grpc_code_names;
+use crate::core::mustache::Mustache;
 use crate::core::valid::{Valid, ValidationError, Validator};
 
 #[derive(Clone, Debug, Setters)]
 pub struct Server {
+    pub allowed_operations: Option<BTreeSet<String>>,
     pub enable_apollo_tracing: bool,
+    /// Sends the stats of the request in the `tailcall` extension
+    pub include_stats: bool,
     pub enable_cache_control_header: bool,
     pub enable_set_cookie_header: bool,
     pub enable_introspection: bool,
     pub enable_query_validation: bool,
-    pub enable_response_validation: bool,
+    pub response_validation: ResponseValidation,
+    /// The format of the errors of the REST routes
+    pub error_format: ErrorFormat,
     pub enable_batch_requests: bool,
     pub enable_showcase: bool,
+    pub playground: Playground,
     pub global_response_timeout: i64,
+    pub graphql_path: String,
+    /// The HTTP status reported for the gRPC errors, by the name of their code
+    pub grpc_http_status: BTreeMap<String, u16>,
+    pub max_batch_size: usize,
+    pub max_upload_size: usize,
+    pub request_timeout: Option<Duration>,
+    pub request_limits: Option<RequestLimits>,
     pub worker: usize,
     pub port: u16,
-    pub hostname: IpAddr,
+    pub hostname: Hostname,
     pub vars: BTreeMap<String, String>,
+    /// Templates rendered once per request into the `context` of templates
+    pub context: BTreeMap<String, Mustache>,
     pub response_headers: HeaderMap,
     pub http: Http,
     pub pipeline_flush: bool,
     pub script: Option<Script>,
     pub cors: Option<Cors>,
     pub experimental_headers: HashSet<HeaderName>,
+    /// Headers of the upstream responses that never reach the client
+    pub strip_headers: HashSet<HeaderName>,
     pub auth: Option<Auth>,
 }
 
+/// The host the server binds to. DNS names are only resolved by the CLI when
+/// it binds, so that building the blueprint never does any I/O.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Hostname {
+    Ip(IpAddr),
+    Dns(String),
+}
+
+#[derive(Clone, Debug)]
+pub struct RequestLimits {
+    pub max_concurrent_requests: usize,
+    pub queue_size: usize,
+    pub retry_after: Duration,
+}
+
 /// Mimic of mini_v8::Script that's wasm compatible
 #[derive(Clone, Debug)]
 pub struct Script {
@@ -62,9 +99,6 @@ impl Default for Server {
 }
 
 impl Server {
-    pub fn get_enable_http_validation(&self) -> bool {
-        self.enable_response_validation
-    }
     pub fn get_enable_cache_control(&self) -> bool {
         self.enable_cache_control_header
     }
@@ -127,24 +161,71 @@ impl TryFrom<crate::core::config::ConfigModule> for Server {
                     .and_then(|headers| headers.get_cors()),
             ))
             .fuse(Auth::make(&config_module))
+            .fuse(
+                validate_workers((config_server).get_workers()).zip(validate_max_batch_size(
+                    (config_server).get_max_batch_size(),
+                )),
+            )
+            .fuse(validate_allowed_operations(
+                (config_server).get_allowed_operations(),
+            ))
+            .fuse(validate_graphql_path(
+                (config_server).get_graphql_path(),
+                &config_module,
+            ))
+            .fuse(
+                handle_strip_headers((config_server).get_strip_headers())
+                    .zip(to_context((config_server).get_context())),
+            )
+            .fuse(
+                validate_request_limits(config_server.request_limits.as_ref())
+                    .zip(validate_grpc_http_status(&config_server.grpc_http_status)),
+            )
             .map(
-                |(hostname, http, response_headers, script, experimental_headers, cors, auth)| {
+                |(
+                    hostname,
+                    http,
+                    response_headers,
+                    script,
+                    experimental_headers,
+                    cors,
+                    auth,
+                    (worker, max_batch_size),
+                    allowed_operations,
+                    graphql_path,
+                    (strip_headers, context),
+                    (request_limits, grpc_http_status),
+                )| {
                     Server {
+                        allowed_operations,
                         enable_apollo_tracing: (config_server).enable_apollo_tracing(),
+                        include_stats: (config_server).enable_include_stats(),
                         enable_cache_control_header: (config_server).enable_cache_control(),
                         enable_set_cookie_header: (config_server).enable_set_cookies(),
                         enable_introspection: (config_server).enable_introspection(),
                         enable_query_validation: (config_server).enable_query_validation(),
-                        enable_response_validation: (config_server).enable_http_validation(),
+                        response_validation: (config_server).get_response_validation(),
+                        error_format: (config_server).get_error_format(),
                         enable_batch_requests: (config_server).enable_batch_requests(),
                         enable_showcase: (config_server).enable_showcase(),
+                        playground: (config_server).get_playground(),
                         experimental_headers,
+                        strip_headers,
                         global_response_timeout: (config_server).get_global_response_timeout(),
+                        graphql_path,
+                        grpc_http_status,
+                        max_batch_size,
+                        max_upload_size: (config_server).get_max_upload_size(),
+                        request_timeout: (config_server)
+                            .get_request_timeout()
+                            .map(Duration::from_millis),
+                        request_limits,
                         http,
-                        worker: (config_server).get_workers(),
+                        worker,
                         port: (config_server).get_port(),
                         hostname,
                         vars: (config_server).get_vars(),
+                        context,
                         pipeline_flush: (config_server).get_pipeline_flush(),
                         response_headers,
                         script,
@@ -174,6 +255,24 @@ fn to_script(config_module: &crate::core::config::ConfigModule) -> Valid<Option<
     )
 }
 
+fn validate_grpc_http_status(
+    statuses: &BTreeMap<String, u16>,
+) -> Valid<BTreeMap<String, u16>, String> {
+    Valid::from_iter(statuses.iter(), |(code, status)| {
+        Valid::<(), String>::fail(format!("Unknown gRPC status code: {}", code))
+            .when(|| !grpc_code_names().any(|name| name == code))
+            .and(
+                Valid::<(), String>::fail(format!("Invalid status code: {}", status))
+                    .when(|| reqwest::StatusCode::from_u16(*status).is_err()),
+            )
+            .trace(code)
+    })
+    .map_to(statuses.clone())
+    .trace("grpcHttpStatus")
+    .trace("@server")
+    .trace("schema")
+}
+
 fn validate_cors(cors: Option<config::cors::Cors>) -> Valid<Option<Cors>, String> {
     Valid::from(cors.map(|cors| cors.try_into()).transpose())
         .trace("cors")
@@ -182,17 +281,122 @@ fn validate_cors(cors: Option<config::cors::Cors>) -> Valid<Option<Cors>, String
         .trace("schema")
 }
 
-fn validate_hostname(hostname: String) -> Valid<IpAddr, String> {
-    if hostname == "localhost" {
-        Valid::succeed(IpAddr::from([127, 0, 0, 1]))
-    } else {
-        Valid::from(hostname.parse().map_err(|e: AddrParseError| {
-            ValidationError::new(format!("Parsing failed because of {}", e))
+fn validate_workers(workers: usize) -> Valid<usize, String> {
+    Valid::<(), String>::fail("workers must be greater than 0".to_string())
+        .when(|| workers < 1)
+        .map_to(workers)
+        .trace("workers")
+        .trace("@server")
+        .trace("schema")
+}
+
+fn validate_max_batch_size(max_batch_size: usize) -> Valid<usize, String> {
+    Valid::<(), String>::fail("maxBatchSize must be greater than 0".to_string())
+        .when(|| max_batch_size < 1)
+        .map_to(max_batch_size)
+        .trace("maxBatchSize")
+        .trace("@server")
+        .trace("schema")
+}
+
+fn validate_request_limits(
+    limits: Option<&config::RequestLimits>,
+) -> Valid<Option<RequestLimits>, String> {
+    let Some(limits) = limits else {
+        return Valid::succeed(None);
+    };
+
+    Valid::<(), String>::fail("maxConcurrentRequests must be greater than 0".to_string())
+        .when(|| limits.max_concurrent_requests < 1)
+        .trace("maxConcurrentRequests")
+        .map_to(Some(RequestLimits {
+            max_concurrent_requests: limits.max_concurrent_requests,
+            queue_size: limits.queue_size.unwrap_or(0),
+            retry_after: Duration::from_secs(limits.retry_after.unwrap_or(1)),
         }))
-        .trace("hostname")
+        .trace("requestLimits")
+        .trace("@server")
+        .trace("schema")
+}
+
+/// The GraphQL endpoint is matched before any other route, so it can't use
+/// the path of another endpoint
+fn validate_graphql_path(
+    graphql_path: String,
+    config_module: &config::ConfigModule,
+) -> Valid<String, String> {
+    let prometheus_path = match config_module.telemetry.export.as_ref() {
+        Some(TelemetryExporter::Prometheus(prometheus)) => Some(prometheus.path.as_str()),
+        _ => None,
+    };
+
+    Valid::<(), String>::fail("graphqlPath must start with /".to_string())
+        .when(|| !graphql_path.starts_with('/'))
+        .and(
+            Valid::<(), String>::fail(format!(
+                "graphqlPath {} is already used by the showcase",
+                graphql_path
+            ))
+            .when(|| graphql_path == SHOWCASE_PATH),
+        )
+        .and(
+            Valid::<(), String>::fail(format!(
+                "graphqlPath {} is already used by the prometheus metrics",
+                graphql_path
+            ))
+            .when(|| prometheus_path == Some(graphql_path.as_str())),
+        )
+        .map_to(graphql_path.clone())
+        .trace("graphqlPath")
         .trace("@server")
         .trace("schema")
+}
+
+fn validate_allowed_operations(
+    allowed_operations: Option<BTreeSet<String>>,
+) -> Valid<Option<BTreeSet<String>>, String> {
+    Valid::from_iter(allowed_operations.iter().flatten(), |hash| {
+        Valid::<(), String>::fail(format!("Invalid operation hash: {}", hash))
+            .when(|| !is_operation_hash(hash))
+    })
+    .map_to(allowed_operations)
+    .trace("allowedOperations")
+    .trace("@server")
+    .trace("schema")
+}
+
+fn validate_hostname(hostname: String) -> Valid<Hostname, String> {
+    if hostname == "localhost" {
+        return Valid::succeed(Hostname::Ip(IpAddr::from([127, 0, 0, 1])));
     }
+
+    // IPv6 literals are commonly written in their bracketed URL form eg: `[::1]`
+    let host = hostname
+        .strip_prefix('[')
+        .and_then(|host| host.strip_suffix(']'))
+        .unwrap_or(hostname.as_str());
+
+    let hostname = match host.parse::<IpAddr>() {
+        Ok(ip) => Valid::succeed(Hostname::Ip(ip)),
+        Err(_) if is_dns_name(host) => Valid::succeed(Hostname::Dns(host.to_string())),
+        Err(e) => Valid::fail(format!("Parsing failed because of {}", e)),
+    };
+
+    hostname.trace("hostname").trace("@server").trace("schema")
+}
+
+/// Only fully qualified names are accepted, a single label is more likely a
+/// mistyped IP address than a host of the local network.
+fn is_dns_name(host: &str) -> bool {
+    host.contains('.')
+        && host.len() <= 253
+        && host.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
 }
 
 fn handle_response_headers(resp_headers: Vec<(String, String)>) -> Valid<HeaderMap, String> {
@@ -214,6 +418,31 @@ fn handle_response_headers(resp_headers: Vec<(String, String)>) -> Valid<HeaderM
     .trace("schema")
 }
 
+fn to_context(context: BTreeMap<String, String>) -> Valid<BTreeMap<String, Mustache>, String> {
+    Valid::from_iter(context, |(key, value)| {
+        Valid::from(Mustache::parse(&value).map_err(|e| ValidationError::new(e.to_string())))
+            .map(|mustache| (key.clone(), mustache))
+            .trace(&key)
+    })
+    .map(BTreeMap::from_iter)
+    .trace("context")
+    .trace("@server")
+    .trace("schema")
+}
+
+fn handle_strip_headers(headers: BTreeSet<String>) -> Valid<HashSet<HeaderName>, String> {
+    Valid::from_iter(headers.iter(), |h| {
+        Valid::from(
+            HeaderName::from_bytes(h.as_bytes())
+                .map_err(|e| ValidationError::new(format!("Parsing failed because of {}", e))),
+        )
+    })
+    .map(HashSet::from_iter)
+    .trace("stripHeaders")
+    .trace("@server")
+    .trace("schema")
+}
+
 fn handle_experimental_headers(headers: BTreeSet<String>) -> Valid<HashSet<HeaderName>, String> {
     Valid::from_iter(headers.iter(), |h| {
         if !h.to_lowercase().starts_with("x-") {
@@ -237,11 +466,102 @@ fn handle_experimental_headers(headers: BTreeSet<String>) -> Valid<HashSet<Heade
 
 #[cfg(test)]
 mod tests {
-    use crate::core::config::ConfigModule;
+    use std::net::{IpAddr, Ipv6Addr};
+
+    use super::{validate_hostname, Hostname};
+    use crate::core::config::{self, ConfigModule};
+    use crate::core::valid::Validator;
 
     #[test]
     fn test_try_from_default() {
         let actual = super::Server::try_from(ConfigModule::default());
         assert!(actual.is_ok())
     }
+
+    #[test]
+    fn test_try_from_workers() {
+        let config = config::Config::default()
+            .server(config::Server { workers: Some(2), ..Default::default() });
+        let actual = super::Server::try_from(ConfigModule::from(config)).unwrap();
+        assert_eq!(actual.worker, 2)
+    }
+
+    #[test]
+    fn test_try_from_zero_workers() {
+        let config = config::Config::default()
+            .server(config::Server { workers: Some(0), ..Default::default() });
+        let actual = super::Server::try_from(ConfigModule::from(config));
+        assert!(actual.is_err())
+    }
+
+    #[test]
+    fn test_try_from_zero_max_batch_size() {
+        let config = config::Config::default()
+            .server(config::Server { max_batch_size: Some(0), ..Default::default() });
+        let actual = super::Server::try_from(ConfigModule::from(config));
+        assert!(actual.is_err())
+    }
+
+    #[test]
+    fn test_try_from_zero_max_concurrent_requests() {
+        let config = config::Config::default().server(config::Server {
+            request_limits: Some(config::RequestLimits {
+                max_concurrent_requests: 0,
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+        let actual = super::Server::try_from(ConfigModule::from(config));
+        assert!(actual.is_err())
+    }
+
+    #[test]
+    fn test_try_from_invalid_allowed_operations() {
+        let config = config::Config::default().server(config::Server {
+            allowed_operations: Some(vec!["md5:abc".to_string()]),
+            ..Default::default()
+        });
+        let actual = super::Server::try_from(ConfigModule::from(config));
+        assert!(actual.is_err())
+    }
+
+    #[test]
+    fn test_hostname_ipv6() {
+        let actual = validate_hostname("::1".to_string()).to_result().unwrap();
+        assert_eq!(actual, Hostname::Ip(IpAddr::V6(Ipv6Addr::LOCALHOST)));
+    }
+
+    #[test]
+    fn test_hostname_bracketed_ipv6() {
+        let actual = validate_hostname("[::]".to_string()).to_result().unwrap();
+        assert_eq!(actual, Hostname::Ip(IpAddr::V6(Ipv6Addr::UNSPECIFIED)));
+    }
+
+    #[test]
+    fn test_hostname_dns_name_is_not_resolved() {
+        let actual = validate_hostname("api.example.invalid".to_string())
+            .to_result()
+            .unwrap();
+        assert_eq!(actual, Hostname::Dns("api.example.invalid".to_string()));
+    }
+
+    #[test]
+    fn test_hostname_single_label() {
+        let actual = validate_hostname("abc".to_string()).to_result();
+        assert!(actual.is_err())
+    }
+
+    #[test]
+    fn test_hostname_invalid() {
+        let actual = validate_hostname("not a hostname!".to_string()).to_result();
+        assert!(actual.is_err())
+    }
+
+    #[test]
+    fn test_try_from_invalid_hostname() {
+        let config = config::Config::default()
+            .server(config::Server { hostname: Some("[::1".to_string()), ..Default::default() });
+        let actual = super::Server::try_from(ConfigModule::from(config));
+        assert!(actual.is_err())
+    }
 }
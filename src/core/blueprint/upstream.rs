@@ -1,8 +1,11 @@
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::Duration;
 
 use derive_setters::Setters;
 
-use crate::core::config::{self, Batch, ConfigModule};
+use crate::core::config::{
+    self, Batch, CircuitBreaker, ConfigModule, GrpcPool, HealthCheck, TlsVersion,
+};
 use crate::core::valid::{Valid, ValidationError, Validator};
 
 #[derive(PartialEq, Eq, Clone, Debug, schemars::JsonSchema)]
@@ -10,10 +13,33 @@ pub struct Proxy {
     pub url: String,
 }
 
+/// The TLS material of a target that is sent its requests with a client of its
+/// own, so that it isn't used for the other targets.
+#[derive(PartialEq, Eq, Clone, Debug, Default, schemars::JsonSchema)]
+pub struct TargetTls {
+    /// PEM encoded CA certificate trusted on top of the built-in roots.
+    pub ca_cert: Option<String>,
+    /// PEM encoded certificate and private key presented to the target.
+    pub identity: Option<String>,
+}
+
+impl From<&config::Grpc> for TargetTls {
+    fn from(grpc: &config::Grpc) -> Self {
+        let identity = grpc
+            .client_cert
+            .as_ref()
+            .zip(grpc.client_key.as_ref())
+            .map(|(cert, key)| format!("{}\n{}", cert, key));
+
+        Self { ca_cert: grpc.ca_cert.clone(), identity }
+    }
+}
+
 #[derive(PartialEq, Eq, Clone, Debug, Setters, schemars::JsonSchema)]
 pub struct Upstream {
     pub pool_idle_timeout: u64,
     pub pool_max_idle_per_host: usize,
+    pub keep_alive: bool,
     pub keep_alive_interval: u64,
     pub keep_alive_timeout: u64,
     pub keep_alive_while_idle: bool,
@@ -23,12 +49,27 @@ pub struct Upstream {
     pub tcp_keep_alive: u64,
     pub user_agent: String,
     pub allowed_headers: BTreeSet<String>,
+    /// Headers listed by the `@http` and `@graphQL` operators with their own
+    /// `allowedHeaders`.
+    pub target_headers: BTreeSet<String>,
     pub base_url: Option<String>,
     pub http_cache: u64,
     pub batch: Option<Batch>,
+    pub circuit_breaker: Option<CircuitBreaker>,
+    pub health_check: Option<HealthCheck>,
+    pub grpc_pool: Option<GrpcPool>,
+    /// TLS material of the `@grpc` targets with a `caCert` or a client
+    /// certificate, by the origin of the target.
+    pub grpc_targets: BTreeMap<String, TargetTls>,
+    /// Targets sent their requests with a client of their own, by origin.
+    pub targets: BTreeMap<String, TargetTls>,
     pub http2_only: bool,
     pub dedupe: bool,
+    pub dedupe_window: Option<u64>,
+    pub max_concurrency: Option<usize>,
+    pub min_tls: TlsVersion,
     pub on_request: Option<String>,
+    pub accept_encoding: Option<String>,
 }
 
 impl Upstream {
@@ -41,6 +82,44 @@ impl Upstream {
             false
         }
     }
+
+    /// Idle connections are only kept in the pool when keep-alive is enabled
+    pub fn get_pool_max_idle_per_host(&self) -> usize {
+        if self.keep_alive {
+            self.pool_max_idle_per_host
+        } else {
+            0
+        }
+    }
+
+    /// The settings of the client of the `@grpc` upstreams, which only sends
+    /// HTTP2 requests, pools its channels as per `grpcPool` and connects to
+    /// the targets with their own TLS material.
+    pub fn grpc(&self) -> Upstream {
+        let mut upstream = self
+            .clone()
+            .http2_only(true)
+            .targets(self.grpc_targets.clone());
+        if let Some(grpc_pool) = &self.grpc_pool {
+            if let Some(max_idle_per_host) = grpc_pool.max_idle_per_host {
+                upstream.pool_max_idle_per_host = max_idle_per_host;
+            }
+            if let Some(idle_timeout) = grpc_pool.idle_timeout {
+                upstream.pool_idle_timeout = idle_timeout;
+            }
+        }
+        upstream
+    }
+
+    pub fn get_tcp_keep_alive(&self) -> Option<Duration> {
+        self.keep_alive
+            .then_some(Duration::from_secs(self.tcp_keep_alive))
+    }
+
+    pub fn get_http2_keep_alive_interval(&self) -> Option<Duration> {
+        self.keep_alive
+            .then_some(Duration::from_secs(self.keep_alive_interval))
+    }
 }
 
 impl Default for Upstream {
@@ -63,41 +142,140 @@ impl TryFrom<&ConfigModule> for Upstream {
             allowed_headers.insert(hyper::header::AUTHORIZATION.to_string());
         }
 
+        let target_headers = get_target_headers(config_module);
+
         get_batch(&config_upstream)
             .fuse(get_base_url(&config_upstream))
             .fuse(get_proxy(&config_upstream))
-            .map(|(batch, base_url, proxy)| Upstream {
-                pool_idle_timeout: (config_upstream).get_pool_idle_timeout(),
-                pool_max_idle_per_host: (config_upstream).get_pool_max_idle_per_host(),
-                keep_alive_interval: (config_upstream).get_keep_alive_interval(),
-                keep_alive_timeout: (config_upstream).get_keep_alive_timeout(),
-                keep_alive_while_idle: (config_upstream).get_keep_alive_while_idle(),
-                proxy,
-                connect_timeout: (config_upstream).get_connect_timeout(),
-                timeout: (config_upstream).get_timeout(),
-                tcp_keep_alive: (config_upstream).get_tcp_keep_alive(),
-                user_agent: (config_upstream).get_user_agent(),
-                allowed_headers,
-                base_url,
-                http_cache: (config_upstream).get_http_cache_size(),
-                batch,
-                http2_only: (config_upstream).get_http_2_only(),
-                dedupe: (config_upstream).get_dedupe(),
-                on_request: (config_upstream).get_on_request(),
-            })
+            .fuse(get_circuit_breaker(&config_upstream))
+            .fuse(get_health_check(&config_upstream))
+            .fuse(get_max_concurrency(&config_upstream))
+            .fuse(get_grpc_targets(config_module))
+            .map(
+                |(
+                    batch,
+                    base_url,
+                    proxy,
+                    circuit_breaker,
+                    health_check,
+                    max_concurrency,
+                    grpc_targets,
+                )| {
+                    Upstream {
+                        pool_idle_timeout: (config_upstream).get_pool_idle_timeout(),
+                        pool_max_idle_per_host: (config_upstream).get_pool_max_idle_per_host(),
+                        keep_alive: (config_upstream).get_keep_alive(),
+                        keep_alive_interval: (config_upstream).get_keep_alive_interval(),
+                        keep_alive_timeout: (config_upstream).get_keep_alive_timeout(),
+                        keep_alive_while_idle: (config_upstream).get_keep_alive_while_idle(),
+                        proxy,
+                        connect_timeout: (config_upstream).get_connect_timeout(),
+                        timeout: (config_upstream).get_timeout(),
+                        tcp_keep_alive: (config_upstream).get_tcp_keep_alive(),
+                        user_agent: (config_upstream).get_user_agent(),
+                        allowed_headers,
+                        target_headers,
+                        base_url,
+                        http_cache: (config_upstream).get_http_cache_size(),
+                        batch,
+                        circuit_breaker,
+                        health_check,
+                        grpc_pool: (config_upstream).grpc_pool.clone(),
+                        grpc_targets,
+                        targets: BTreeMap::new(),
+                        http2_only: (config_upstream).get_http_2_only(),
+                        dedupe: (config_upstream).get_dedupe(),
+                        dedupe_window: (config_upstream).dedupe_window,
+                        max_concurrency,
+                        min_tls: (config_upstream).get_min_tls(),
+                        on_request: (config_upstream).get_on_request(),
+                        accept_encoding: (config_upstream).get_accept_encoding(),
+                    }
+                },
+            )
             .to_result()
     }
 }
 
+fn get_target_headers(config_module: &ConfigModule) -> BTreeSet<String> {
+    config_module
+        .types
+        .values()
+        .flat_map(|type_| type_.fields.values())
+        .flat_map(|field| {
+            let http = field
+                .http
+                .as_ref()
+                .and_then(|http| http.allowed_headers.as_ref());
+            let graphql = field
+                .graphql
+                .as_ref()
+                .and_then(|graphql| graphql.allowed_headers.as_ref());
+            http.into_iter().chain(graphql).flatten().cloned()
+        })
+        .collect()
+}
+
+/// The TLS material of the `@grpc` targets, which is only sent over TLS, so
+/// the targets are keyed by the `https` origin of their base URL.
+fn get_grpc_targets(config_module: &ConfigModule) -> Valid<BTreeMap<String, TargetTls>, String> {
+    let base_url = config_module.upstream.base_url.as_deref();
+    let grpc_fields = config_module.types.iter().flat_map(|(type_name, type_)| {
+        type_.fields.iter().filter_map(move |(field_name, field)| {
+            let grpc = field.grpc.as_ref()?;
+            let tls = TargetTls::from(grpc);
+            (tls != TargetTls::default()).then_some((type_name, field_name, grpc, tls))
+        })
+    });
+
+    Valid::from_iter(grpc_fields, |(type_name, field_name, grpc, tls)| {
+        let origin = grpc.base_url.as_deref().or(base_url).and_then(tls_origin);
+        Valid::from_option(origin, "No valid base URL defined".to_string())
+            .map(|origin| (origin, tls))
+            .trace("@grpc")
+            .trace(field_name)
+            .trace(type_name)
+    })
+    .and_then(|targets| {
+        let mut tls_by_origin = BTreeMap::new();
+        for (origin, tls) in targets {
+            if tls_by_origin
+                .get(&origin)
+                .is_some_and(|other| other != &tls)
+            {
+                return Valid::fail(format!(
+                    "Target {} is configured with different TLS material",
+                    origin
+                ));
+            }
+            tls_by_origin.insert(origin, tls);
+        }
+        Valid::succeed(tls_by_origin)
+    })
+}
+
+fn tls_origin(base_url: &str) -> Option<String> {
+    let mut url = reqwest::Url::parse(base_url).ok()?;
+    url.set_scheme("https").ok()?;
+    Some(url.origin().ascii_serialization())
+}
+
 fn get_batch(upstream: &config::Upstream) -> Valid<Option<Batch>, String> {
     upstream.batch.as_ref().map_or_else(
         || Valid::succeed(None),
         |batch| {
-            Valid::succeed(Some(Batch {
+            Valid::<(), String>::fail(
+                "Batching by default requires a delay of at least 1ms".to_string(),
+            )
+            .when(|| batch.by_default && upstream.get_delay() < 1)
+            .trace("byDefault")
+            .map_to(Some(Batch {
+                by_default: batch.by_default,
                 max_size: Some((upstream).get_max_size()),
                 delay: (upstream).get_delay(),
                 headers: batch.headers.clone(),
             }))
+            .trace("batch")
         },
     )
 }
@@ -118,3 +296,157 @@ fn get_proxy(upstream: &config::Upstream) -> Valid<Option<Proxy>, String> {
         Valid::succeed(None)
     }
 }
+
+fn get_circuit_breaker(upstream: &config::Upstream) -> Valid<Option<CircuitBreaker>, String> {
+    match upstream.circuit_breaker {
+        Some(ref circuit_breaker) => {
+            Valid::<(), String>::fail("failureThreshold must be greater than 0".to_string())
+                .when(|| circuit_breaker.failure_threshold == 0)
+                .map_to(Some(circuit_breaker.clone()))
+                .trace("circuitBreaker")
+                .trace("@upstream")
+        }
+        None => Valid::succeed(None),
+    }
+}
+
+fn get_health_check(upstream: &config::Upstream) -> Valid<Option<HealthCheck>, String> {
+    match upstream.health_check {
+        Some(ref health_check) => {
+            Valid::<(), String>::fail("unhealthyThreshold must be greater than 0".to_string())
+                .when(|| health_check.get_unhealthy_threshold() == 0)
+                .and(
+                    Valid::<(), String>::fail("interval must be greater than 0".to_string())
+                        .when(|| health_check.get_interval() == 0),
+                )
+                .map_to(Some(health_check.clone()))
+                .trace("healthCheck")
+                .trace("@upstream")
+        }
+        None => Valid::succeed(None),
+    }
+}
+
+fn get_max_concurrency(upstream: &config::Upstream) -> Valid<Option<usize>, String> {
+    Valid::<(), String>::fail("maxConcurrency must be greater than 0".to_string())
+        .when(|| upstream.max_concurrency == Some(0))
+        .map_to(upstream.max_concurrency)
+        .trace("maxConcurrency")
+        .trace("@upstream")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{TargetTls, Upstream};
+    use crate::core::config::{self, ConfigModule};
+
+    fn upstream(upstream: config::Upstream) -> Upstream {
+        let config = config::Config::default().upstream(upstream);
+        Upstream::try_from(&ConfigModule::from(config)).unwrap()
+    }
+
+    #[test]
+    fn test_keep_alive_by_default() {
+        let upstream = upstream(config::Upstream {
+            pool_max_idle_per_host: Some(10),
+            tcp_keep_alive: Some(30),
+            keep_alive_interval: Some(15),
+            ..Default::default()
+        });
+
+        assert_eq!(upstream.get_pool_max_idle_per_host(), 10);
+        assert_eq!(upstream.get_tcp_keep_alive(), Some(Duration::from_secs(30)));
+        assert_eq!(
+            upstream.get_http2_keep_alive_interval(),
+            Some(Duration::from_secs(15))
+        );
+    }
+
+    #[test]
+    fn test_grpc_pool() {
+        let upstream = upstream(config::Upstream {
+            pool_max_idle_per_host: Some(10),
+            pool_idle_timeout: Some(30),
+            grpc_pool: Some(config::GrpcPool { max_idle_per_host: Some(2), ..Default::default() }),
+            ..Default::default()
+        });
+
+        let grpc = upstream.grpc();
+        assert!(grpc.http2_only);
+        assert_eq!(grpc.get_pool_max_idle_per_host(), 2);
+        assert_eq!(grpc.pool_idle_timeout, 30);
+        assert_eq!(upstream.get_pool_max_idle_per_host(), 10);
+    }
+
+    #[test]
+    fn test_keep_alive_disabled() {
+        let upstream = upstream(config::Upstream {
+            keep_alive: Some(false),
+            pool_max_idle_per_host: Some(10),
+            ..Default::default()
+        });
+
+        assert_eq!(upstream.get_pool_max_idle_per_host(), 0);
+        assert_eq!(upstream.get_tcp_keep_alive(), None);
+        assert_eq!(upstream.get_http2_keep_alive_interval(), None);
+    }
+
+    #[test]
+    fn test_max_concurrency_must_be_positive() {
+        let config = config::Config::default()
+            .upstream(config::Upstream { max_concurrency: Some(0), ..Default::default() });
+
+        assert!(Upstream::try_from(&ConfigModule::from(config)).is_err());
+    }
+
+    #[test]
+    fn test_grpc_targets() {
+        let field = |base_url: &str, ca_cert: &str| config::Field {
+            grpc: Some(config::Grpc {
+                base_url: Some(base_url.to_string()),
+                ca_cert: Some(ca_cert.to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let config = |fields: Vec<(&str, config::Field)>| {
+            let fields = fields
+                .into_iter()
+                .map(|(name, field)| (name.to_string(), field))
+                .collect();
+            let mut config = config::Config::default();
+            config.types.insert(
+                "Query".to_string(),
+                config::Type { fields, ..Default::default() },
+            );
+            ConfigModule::from(config)
+        };
+
+        let upstream = Upstream::try_from(&config(vec![
+            ("news", field("http://localhost:50051", "a")),
+            ("users", field("https://localhost:50051/", "a")),
+            ("posts", field("https://localhost", "b")),
+        ]))
+        .unwrap();
+        let ca_cert =
+            |ca_cert: &str| TargetTls { ca_cert: Some(ca_cert.to_string()), identity: None };
+        assert_eq!(
+            upstream.grpc_targets,
+            [
+                ("https://localhost".to_string(), ca_cert("b")),
+                ("https://localhost:50051".to_string(), ca_cert("a")),
+            ]
+            .into()
+        );
+        assert!(upstream.targets.is_empty());
+        assert_eq!(upstream.grpc().targets, upstream.grpc_targets);
+
+        let result = Upstream::try_from(&config(vec![
+            ("news", field("https://localhost:50051", "a")),
+            ("users", field("https://localhost:50051", "b")),
+        ]));
+        assert!(result.is_err());
+    }
+}
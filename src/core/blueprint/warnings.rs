@@ -0,0 +1,464 @@
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::core::config::{ConfigModule, Field, Http, Playground};
+use crate::core::mustache::{Mustache, Segment};
+use crate::core::valid::Cause;
+
+/// Returns the serialized directive of the field when its resolver is built
+/// from templates, along with the name of the directive.
+fn template_resolver(field: &Field) -> Option<(&'static str, Value)> {
+    fn to_value(value: &impl Serialize) -> Value {
+        serde_json::to_value(value).unwrap_or_default()
+    }
+
+    if let Some(http) = &field.http {
+        // Every argument is sent as a query param
+        if http.forward_args {
+            return None;
+        }
+        return Some(("@http", to_value(http)));
+    }
+
+    if let Some(grpc) = &field.grpc {
+        return Some(("@grpc", to_value(grpc)));
+    }
+
+    field
+        .graphql
+        .as_ref()
+        .map(|graphql| ("@graphQL", to_value(graphql)))
+}
+
+/// Parses every string of the serialized directive as a template
+fn templates(value: &Value, acc: &mut Vec<Mustache>) {
+    match value {
+        Value::String(text) => {
+            if let Ok(mustache) = Mustache::parse(text) {
+                acc.push(mustache);
+            }
+        }
+        Value::Array(items) => items.iter().for_each(|item| templates(item, acc)),
+        Value::Object(fields) => fields.values().for_each(|field| templates(field, acc)),
+        _ => {}
+    }
+}
+
+/// Finds the arguments that are never read by the resolver of their field.
+fn unused_args(config_module: &ConfigModule) -> Vec<Cause<String>> {
+    let mut warnings = vec![];
+
+    for (type_name, type_of) in config_module.types.iter() {
+        for (field_name, field) in type_of.fields.iter() {
+            let Some((directive, directive_value)) = template_resolver(field) else {
+                continue;
+            };
+
+            let mut mustaches = vec![];
+            templates(&directive_value, &mut mustaches);
+
+            // The name of the argument read by every `args` expression, if any
+            let used_args: Vec<Option<&String>> = mustaches
+                .iter()
+                .flat_map(|mustache| mustache.expression_segments())
+                .filter_map(|parts| match parts.split_first() {
+                    Some((head, tail)) if head == "args" => Some(tail.first()),
+                    _ => None,
+                })
+                .collect();
+
+            // `{{.args}}` renders all of them
+            if used_args.contains(&None) {
+                continue;
+            }
+
+            for arg_name in field.args.keys() {
+                if !used_args.contains(&Some(arg_name)) {
+                    let message = format!(
+                        "Argument {} is not used by the {} resolver",
+                        arg_name, directive
+                    );
+                    warnings.push(Cause::new(message).trace(vec![
+                        type_name.as_str(),
+                        field_name.as_str(),
+                        arg_name.as_str(),
+                    ]));
+                }
+            }
+        }
+    }
+
+    warnings
+}
+
+/// The part of a url a template expression is rendered into
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum UrlPosition {
+    Authority,
+    Path,
+    Query,
+}
+
+impl UrlPosition {
+    /// Finds the position of whatever comes after the given start of a url
+    fn after(prefix: &str) -> UrlPosition {
+        if prefix.contains('?') {
+            return UrlPosition::Query;
+        }
+
+        let rest = prefix.split_once("://").map_or(prefix, |(_, rest)| rest);
+        if rest.contains('/') {
+            UrlPosition::Path
+        } else {
+            UrlPosition::Authority
+        }
+    }
+}
+
+/// Returns the expressions of a url template along with the part of the url
+/// they're rendered into.
+fn url_expressions(url: &Mustache) -> Vec<(String, UrlPosition)> {
+    fn collect(segments: Vec<&Segment>, prefix: &mut String, acc: &mut Vec<(String, UrlPosition)>) {
+        for segment in segments {
+            match segment {
+                Segment::Literal(text) => prefix.push_str(text),
+                Segment::Expression(parts) | Segment::Transform(parts, _) => {
+                    acc.push((parts.join("."), UrlPosition::after(prefix)));
+                    // Whatever the expression renders to, it's part of the url
+                    prefix.push('_');
+                }
+                Segment::Section(parts, content) => {
+                    acc.push((parts.join("."), UrlPosition::after(prefix)));
+                    collect(content.get_segments(), prefix, acc);
+                }
+            }
+        }
+    }
+
+    let mut acc = vec![];
+    collect(url.get_segments(), &mut String::new(), &mut acc);
+    acc
+}
+
+/// The base urls an @http resolver can send its requests to
+fn base_urls<'a>(config_module: &'a ConfigModule, http: &'a Http) -> Vec<&'a String> {
+    let base_urls: Vec<_> = http
+        .base_url
+        .iter()
+        .chain(http.base_urls.iter())
+        .chain(http.base_url_by_profile.values())
+        .collect();
+
+    if base_urls.is_empty() {
+        config_module.upstream.base_url.iter().collect()
+    } else {
+        base_urls
+    }
+}
+
+/// Finds the base urls whose host is rendered from the request, which lets
+/// clients send requests to any host unless `@upstream(allowedHosts:)` is set.
+/// The env and the vars are set by the server so they're not considered.
+fn dynamic_hosts(config_module: &ConfigModule) -> Vec<Cause<String>> {
+    if !config_module.upstream.get_allowed_hosts().is_empty() {
+        return vec![];
+    }
+
+    let mut warnings = vec![];
+
+    for (type_name, type_of) in config_module.types.iter() {
+        for (field_name, field) in type_of.fields.iter() {
+            let Some(http) = &field.http else {
+                continue;
+            };
+
+            for base_url in base_urls(config_module, http) {
+                let Ok(url) = Mustache::parse(base_url) else {
+                    continue;
+                };
+
+                for (expression, position) in url_expressions(&url) {
+                    let is_dynamic =
+                        !expression.starts_with("env.") && !expression.starts_with("vars.");
+                    if is_dynamic && position == UrlPosition::Authority {
+                        let message = format!(
+                            "The host of the baseURL {} is rendered from {{{{.{}}}}}, set @upstream(allowedHosts:) to restrict it",
+                            base_url, expression
+                        );
+                        warnings.push(Cause::new(message).trace(vec![
+                            type_name.as_str(),
+                            field_name.as_str(),
+                            "@http",
+                        ]));
+                    }
+                }
+            }
+        }
+    }
+
+    warnings
+}
+
+/// Finds the `allowedOperations` that are ignored because a playground is
+/// served, which lets every client run any operation.
+fn ignored_allowed_operations(config_module: &ConfigModule) -> Vec<Cause<String>> {
+    let server = &config_module.server;
+    if server.allowed_operations.is_none() || server.get_playground() == Playground::None {
+        return vec![];
+    }
+
+    vec![Cause::new(
+        "allowedOperations isn't enforced while a playground is served, set playground: NONE to enforce it"
+            .to_string(),
+    )
+    .trace(vec!["schema", "@server", "allowedOperations"])]
+}
+
+/// Collects the issues of the config that don't prevent the blueprint from
+/// being built, but are most likely mistakes.
+pub fn to_warnings(config_module: &ConfigModule) -> Vec<Cause<String>> {
+    let mut warnings = unused_args(config_module);
+    warnings.extend(dynamic_hosts(config_module));
+    warnings.extend(ignored_allowed_operations(config_module));
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::{url_expressions, UrlPosition};
+    use crate::core::blueprint::Blueprint;
+    use crate::core::config::{Config, ConfigModule};
+    use crate::core::mustache::Mustache;
+    use crate::core::valid::Validator;
+
+    fn blueprint(sdl: &str) -> Blueprint {
+        let config = Config::from_sdl(sdl).to_result().unwrap();
+
+        Blueprint::try_from(&ConfigModule::from(config)).unwrap()
+    }
+
+    #[test]
+    fn test_unused_arg() {
+        let blueprint = blueprint(
+            r#"
+            schema @upstream(baseURL: "http://jsonplaceholder.typicode.com") {
+              query: Query
+            }
+
+            type Query {
+              user(id: Int!, name: String): String @http(path: "/users/{{.args.id}}")
+            }
+            "#,
+        );
+
+        let warnings = blueprint
+            .warnings
+            .iter()
+            .map(|warning| warning.to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            warnings,
+            vec!["[Query, user, name] Argument name is not used by the @http resolver"]
+        );
+    }
+
+    #[test]
+    fn test_unused_arg_with_prefix() {
+        let blueprint = blueprint(
+            r#"
+            schema @upstream(baseURL: "http://jsonplaceholder.typicode.com") {
+              query: Query
+            }
+
+            type Query {
+              user(id: Int!, i: Int): String @http(path: "/users/{{.args.id}}")
+            }
+            "#,
+        );
+
+        let warnings = blueprint
+            .warnings
+            .iter()
+            .map(|warning| warning.to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            warnings,
+            vec!["[Query, user, i] Argument i is not used by the @http resolver"]
+        );
+    }
+
+    #[test]
+    fn test_all_args_used() {
+        let blueprint = blueprint(
+            r#"
+            schema @upstream(baseURL: "http://jsonplaceholder.typicode.com") {
+              query: Query
+              mutation: Mutation
+            }
+
+            type Mutation {
+              createUser(id: Int!, name: String): String
+                @http(path: "/users", method: "POST", body: "{{.args}}")
+            }
+
+            type Query {
+              user: String @http(path: "/users/1")
+            }
+            "#,
+        );
+
+        assert!(blueprint.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_forwarded_args() {
+        let blueprint = blueprint(
+            r#"
+            schema @upstream(baseURL: "http://jsonplaceholder.typicode.com") {
+              query: Query
+            }
+
+            type Query {
+              users(name: String): String @http(path: "/users", forwardArgs: true)
+            }
+            "#,
+        );
+
+        assert!(blueprint.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_url_positions() {
+        let url =
+            Mustache::parse("http://{{.args.region}}.example.com/{{.args.id}}?sort={{.args.sort}}")
+                .unwrap();
+
+        assert_eq!(
+            url_expressions(&url),
+            vec![
+                ("args.region".to_string(), UrlPosition::Authority),
+                ("args.id".to_string(), UrlPosition::Path),
+                ("args.sort".to_string(), UrlPosition::Query),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dynamic_host() {
+        let blueprint = blueprint(
+            r#"
+            schema @upstream(baseURL: "http://jsonplaceholder.typicode.com") {
+              query: Query
+            }
+
+            type Query {
+              user(host: String!): String
+                @http(baseURL: "http://{{.args.host}}:8080", path: "/users")
+            }
+            "#,
+        );
+
+        let warnings = blueprint
+            .warnings
+            .iter()
+            .map(|warning| warning.to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            warnings,
+            vec![
+                "[Query, user, @http] The host of the baseURL http://{{.args.host}}:8080 is rendered from {{.args.host}}, set @upstream(allowedHosts:) to restrict it"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dynamic_host_allowed() {
+        let blueprint = blueprint(
+            r#"
+            schema @upstream(baseURL: "http://jsonplaceholder.typicode.com", allowedHosts: ["*.example.com"]) {
+              query: Query
+            }
+
+            type Query {
+              user(host: String!): String
+                @http(baseURL: "http://{{.args.host}}.example.com", path: "/users")
+            }
+            "#,
+        );
+
+        assert!(blueprint.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_dynamic_path() {
+        let blueprint = blueprint(
+            r#"
+            schema @upstream(baseURL: "http://{{.env.HOST}}") {
+              query: Query
+            }
+
+            type Query {
+              user(version: String!): String
+                @http(baseURL: "http://example.com/{{.args.version}}", path: "/users")
+              users: String @http(path: "/users")
+            }
+            "#,
+        );
+
+        assert!(blueprint.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_allowed_operations_with_playground() {
+        let blueprint = blueprint(
+            r#"
+            schema
+              @server(allowedOperations: ["sha256:abababababababababababababababababababababababababababababababab"], playground: GRAPHIQL)
+              @upstream(baseURL: "http://jsonplaceholder.typicode.com") {
+              query: Query
+            }
+
+            type Query {
+              users: String @http(path: "/users")
+            }
+            "#,
+        );
+
+        let warnings = blueprint
+            .warnings
+            .iter()
+            .map(|warning| warning.to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            warnings,
+            vec![
+                "[schema, @server, allowedOperations] allowedOperations isn't enforced while a playground is served, set playground: NONE to enforce it"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_allowed_operations_without_playground() {
+        let blueprint = blueprint(
+            r#"
+            schema
+              @server(allowedOperations: ["sha256:abababababababababababababababababababababababababababababababab"])
+              @upstream(baseURL: "http://jsonplaceholder.typicode.com") {
+              query: Query
+            }
+
+            type Query {
+              users: String @http(path: "/users")
+            }
+            "#,
+        );
+
+        assert!(blueprint.warnings.is_empty());
+    }
+}
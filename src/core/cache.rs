@@ -41,6 +41,20 @@ impl<K: Hash + Eq + Send + Sync, V: Clone + Send + Sync> crate::core::Cache
         Ok(self.data.read().unwrap().get(key).cloned())
     }
 
+    async fn set_if_absent<'a>(
+        &'a self,
+        key: K,
+        value: V,
+        ttl: NonZeroU64,
+    ) -> anyhow::Result<bool> {
+        let mut data = self.data.write().unwrap();
+        if data.contains_key(&key) {
+            return Ok(false);
+        }
+        data.insert(key, value, Duration::from_millis(ttl.get()));
+        Ok(true)
+    }
+
     fn hit_rate(&self) -> Option<f64> {
         let cache = self.data.read().unwrap();
         let hits = cache.hit_count();
@@ -72,6 +86,12 @@ mod tests {
         cache.set(10, "hello".into(), ttl).await.unwrap();
         assert_eq!(cache.get(&10).await.ok(), Some(Some("hello".into())));
 
+        assert_eq!(
+            cache.set_if_absent(10, "bye".into(), ttl).await.ok(),
+            Some(false)
+        );
+        assert_eq!(cache.get(&10).await.ok(), Some(Some("hello".into())));
+
         cache.set(10, "bye".into(), ttl).await.ok();
         tokio::time::sleep(Duration::from_millis(ttl.get())).await;
         assert_eq!(cache.get(&10).await.ok(), Some(None));
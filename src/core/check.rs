@@ -0,0 +1,345 @@
+use serde::Serialize;
+
+use crate::core::blueprint::Blueprint;
+use crate::core::config::reader::ConfigReader;
+use crate::core::config::{Config, ConfigModule};
+use crate::core::runtime::TargetRuntime;
+use crate::core::valid::{Cause, ValidationError};
+
+/// The message of the warnings about N + 1 queries
+pub const N_PLUS_ONE: &str = "N + 1 detected";
+
+/// How bad a [Diagnostic] is, errors make the configuration invalid while
+/// warnings only point at likely mistakes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// A single error or warning found while checking a configuration
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub trace: Vec<String>,
+}
+
+impl Diagnostic {
+    fn error(message: impl ToString, trace: Vec<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            message: message.to_string(),
+            description: None,
+            trace,
+        }
+    }
+
+    fn warning(message: impl ToString, trace: Vec<String>) -> Self {
+        Self { severity: Severity::Warning, ..Self::error(message, trace) }
+    }
+}
+
+impl From<&Cause<String>> for Diagnostic {
+    fn from(cause: &Cause<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            message: cause.message.clone(),
+            description: cause.description.clone(),
+            trace: cause.trace.iter().cloned().collect(),
+        }
+    }
+}
+
+/// The machine readable outcome of [check]. The configuration is valid when
+/// there are no errors, warnings never make it invalid.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct CheckReport {
+    pub errors: Vec<Diagnostic>,
+    pub warnings: Vec<Diagnostic>,
+}
+
+impl CheckReport {
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// The severity of the worst diagnostic of the report, `None` when there
+    /// are no diagnostics at all.
+    pub fn severity(&self) -> Option<Severity> {
+        self.errors
+            .iter()
+            .chain(self.warnings.iter())
+            .map(|diagnostic| diagnostic.severity)
+            .max()
+    }
+
+    /// The exit code of a check producing this report. It fails on errors,
+    /// and on warnings as well when `deny_warnings` is set.
+    pub fn exit_code(&self, deny_warnings: bool) -> exitcode::ExitCode {
+        let threshold = if deny_warnings {
+            Severity::Warning
+        } else {
+            Severity::Error
+        };
+
+        match self.severity() {
+            Some(severity) if severity >= threshold => exitcode::CONFIG,
+            _ => exitcode::OK,
+        }
+    }
+}
+
+/// Reports the error that stopped the check before the blueprint was built.
+impl From<anyhow::Error> for CheckReport {
+    fn from(error: anyhow::Error) -> Self {
+        Self {
+            errors: match error.downcast::<ValidationError<String>>() {
+                Ok(error) => error.as_vec().iter().map(Diagnostic::from).collect(),
+                Err(error) => vec![Diagnostic::error(format!("{:#}", error), vec![])],
+            },
+            warnings: vec![],
+        }
+    }
+}
+
+/// Loads and merges the configuration files, builds the blueprint and
+/// collects every error and warning instead of stopping at the first one.
+pub async fn check<T: ToString + Send + Sync>(
+    runtime: TargetRuntime,
+    sources: &[T],
+) -> CheckReport {
+    let reader = ConfigReader::init(runtime);
+
+    match reader.read_all(sources).await {
+        Ok(config_module) => check_config_module(&config_module),
+        Err(error) => CheckReport::from(error),
+    }
+}
+
+/// Builds the blueprint of an already loaded configuration and collects its
+/// errors and warnings.
+pub fn check_config_module(config_module: &ConfigModule) -> CheckReport {
+    check_blueprint(config_module).1
+}
+
+/// Same as [check_config_module], the blueprint is returned as well when it's
+/// valid so that it doesn't have to be built again.
+pub fn check_blueprint(config_module: &ConfigModule) -> (Option<Blueprint>, CheckReport) {
+    let mut report = CheckReport::default();
+
+    let blueprint = match Blueprint::try_from(config_module) {
+        Ok(blueprint) => Some(blueprint),
+        Err(error) => {
+            report.errors = error.as_vec().iter().map(Diagnostic::from).collect();
+            None
+        }
+    };
+
+    report.warnings = warnings(&config_module.config);
+    (blueprint, report)
+}
+
+fn warnings(config: &Config) -> Vec<Diagnostic> {
+    let mut unused_types = config.unused_types().into_iter().collect::<Vec<_>>();
+    unused_types.sort();
+    let unused_types = unused_types
+        .into_iter()
+        .map(|name| Diagnostic::warning("Type is not used", vec![name]));
+
+    let n_plus_one = config.n_plus_one().into_iter().map(|path| {
+        let query = path
+            .iter()
+            .rev()
+            .fold(String::new(), |query, (_, field_name)| {
+                if query.is_empty() {
+                    field_name.to_string()
+                } else {
+                    format!("{} {{ {} }}", field_name, query)
+                }
+            });
+        let trace = path
+            .last()
+            .map(|(type_name, field_name)| vec![type_name.to_string(), field_name.to_string()])
+            .unwrap_or_default();
+
+        Diagnostic {
+            description: Some(format!("query {{ {} }}", query)),
+            ..Diagnostic::warning(N_PLUS_ONE, trace)
+        }
+    });
+
+    let batch_keys = config.types.iter().flat_map(|(type_name, type_)| {
+        type_.fields.iter().filter_map(move |(field_name, field)| {
+            let http = field.http.as_ref()?;
+            let key = http.group_by.last()?;
+
+            // The batch key is matched against the query parameters of the
+            // request, so it's never found when the parameter isn't sent
+            let is_sent = http.forward_args || http.query.iter().any(|query| &query.key == key);
+            (!is_sent).then(|| Diagnostic {
+                description: Some(format!("`{}` is not a query parameter of the request", key)),
+                ..Diagnostic::warning(
+                    "Batch key is not validated",
+                    vec![
+                        type_name.to_string(),
+                        field_name.to_string(),
+                        "@http".to_string(),
+                        "batchKey".to_string(),
+                    ],
+                )
+            })
+        })
+    });
+
+    unused_types.chain(n_plus_one).chain(batch_keys).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    const CONFIG: &str = r#"
+        schema @upstream(baseURL: "http://jsonplaceholder.typicode.com", batch: {delay: 10}) {
+          query: Query
+        }
+
+        type Query {
+          posts: [Post] @http(path: "/posts")
+        }
+
+        type Post {
+          id: Int
+          userId: Int
+          author: User @http(path: "/users/{{.value.userId}}")
+          owner: User @http(path: "/users", method: "POST", batchKey: ["id"], query: [{key: "id", value: "{{.value.userId}}"}])
+          user: User @http(path: "/users", batchKey: ["id"], query: [{key: "user_id", value: "{{.value.userId}}"}])
+        }
+
+        type User {
+          id: Int
+        }
+
+        type Unused {
+          id: Int
+        }
+    "#;
+
+    async fn check_config(content: &str) -> CheckReport {
+        let mut file = tempfile::Builder::new()
+            .suffix(".graphql")
+            .tempfile()
+            .unwrap();
+        file.write_all(content.as_bytes()).unwrap();
+        let path = file.path().to_string_lossy().to_string();
+
+        check(crate::core::runtime::test::init(None), &[path]).await
+    }
+
+    #[tokio::test]
+    async fn test_check_errors_and_warnings() {
+        let report = check_config(CONFIG).await;
+
+        assert!(!report.is_valid());
+        assert_eq!(
+            report.errors,
+            vec![Diagnostic::error(
+                "GroupBy is only supported for GET requests",
+                vec!["Post".to_string(), "owner".to_string(), "@http".to_string()]
+            )]
+        );
+        assert_eq!(
+            report.warnings,
+            vec![
+                Diagnostic::warning("Type is not used", vec!["Unused".to_string()]),
+                Diagnostic {
+                    description: Some("query { posts { author } }".to_string()),
+                    ..Diagnostic::warning(
+                        "N + 1 detected",
+                        vec!["Post".to_string(), "author".to_string()]
+                    )
+                },
+                Diagnostic {
+                    description: Some("`id` is not a query parameter of the request".to_string()),
+                    ..Diagnostic::warning(
+                        "Batch key is not validated",
+                        vec![
+                            "Post".to_string(),
+                            "user".to_string(),
+                            "@http".to_string(),
+                            "batchKey".to_string()
+                        ]
+                    )
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_check_exit_code() {
+        let report = check_config(CONFIG).await;
+
+        assert_eq!(report.severity(), Some(Severity::Error));
+        assert_eq!(report.exit_code(false), exitcode::CONFIG);
+        assert_eq!(report.exit_code(true), exitcode::CONFIG);
+    }
+
+    #[tokio::test]
+    async fn test_check_exit_code_clean() {
+        let report = check_config(
+            r#"
+            schema @upstream(baseURL: "http://jsonplaceholder.typicode.com") {
+              query: Query
+            }
+
+            type Query {
+              users: [User] @http(path: "/users")
+            }
+
+            type User {
+              id: Int
+            }
+        "#,
+        )
+        .await;
+
+        assert_eq!(report, CheckReport::default());
+        assert_eq!(report.severity(), None);
+        assert_eq!(report.exit_code(true), exitcode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_check_exit_code_warnings() {
+        let report = CheckReport {
+            errors: vec![],
+            warnings: vec![Diagnostic::warning(
+                "Type is not used",
+                vec!["Unused".to_string()],
+            )],
+        };
+
+        assert_eq!(report.severity(), Some(Severity::Warning));
+        assert_eq!(report.exit_code(false), exitcode::OK);
+        assert_eq!(report.exit_code(true), exitcode::CONFIG);
+    }
+
+    #[tokio::test]
+    async fn test_check_unreadable_source() {
+        let report = check(
+            crate::core::runtime::test::init(None),
+            &["does-not-exist.graphql"],
+        )
+        .await;
+
+        assert!(!report.is_valid());
+        assert_eq!(report.errors.len(), 1);
+        assert!(report.warnings.is_empty());
+    }
+}
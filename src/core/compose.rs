@@ -0,0 +1,173 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::core::config::{Config, Field};
+use crate::core::merge_right::MergeRight;
+use crate::core::valid::{Valid, Validator};
+
+/// Tells which config every type and field of a composed config comes from.
+/// Configs are referred to by their position in the list passed to
+/// [compose].
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ComposeReport {
+    /// The config each type is first declared in
+    pub types: BTreeMap<String, usize>,
+    /// The config each field, as `Type.field`, is taken from
+    pub fields: BTreeMap<String, usize>,
+    /// The fields declared by more than one config along with every config
+    /// that declares them. The last one overrides the others.
+    pub overrides: BTreeMap<String, Vec<usize>>,
+}
+
+fn to_output_type(field: &Field) -> String {
+    let mut output = field.type_of.clone();
+    if field.list {
+        if field.list_type_required {
+            output.push('!');
+        }
+        output = format!("[{}]", output);
+    }
+    if field.required {
+        output.push('!');
+    }
+    output
+}
+
+/// Merges the configs in order and reports where the types and fields of the
+/// result come from. A field can be overridden by a later config as long as
+/// its output type stays the same.
+pub fn compose(configs: Vec<Config>) -> Valid<(Config, ComposeReport), String> {
+    let mut report = ComposeReport::default();
+    let mut conflicts = vec![];
+    // Output type and config of the latest declaration of every field
+    let mut declarations: BTreeMap<(&str, &str), (String, usize)> = BTreeMap::new();
+
+    for (index, config) in configs.iter().enumerate() {
+        for (type_name, type_of) in config.types.iter() {
+            report.types.entry(type_name.clone()).or_insert(index);
+
+            for (field_name, field) in type_of.fields.iter() {
+                let output = to_output_type(field);
+                let path = format!("{}.{}", type_name, field_name);
+                report.fields.insert(path.clone(), index);
+
+                let previous =
+                    declarations.insert((type_name, field_name), (output.clone(), index));
+                let Some((previous_output, previous_index)) = previous else {
+                    continue;
+                };
+
+                report
+                    .overrides
+                    .entry(path)
+                    .or_insert_with(|| vec![previous_index])
+                    .push(index);
+
+                if previous_output != output {
+                    let message = format!(
+                        "{} in config {} conflicts with {} in config {}",
+                        output, index, previous_output, previous_index
+                    );
+                    conflicts.push((type_name.as_str(), field_name.as_str(), message));
+                }
+            }
+        }
+    }
+
+    Valid::from_iter(conflicts, |(type_name, field_name, message)| {
+        Valid::<(), String>::fail(message)
+            .trace(field_name)
+            .trace(type_name)
+    })
+    .map(|_| {
+        let config = configs
+            .into_iter()
+            .fold(Config::default(), |composed, config| {
+                composed.merge_right(config)
+            });
+
+        (config, report)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn config(sdl: &str) -> Config {
+        Config::from_sdl(sdl).to_result().unwrap()
+    }
+
+    #[test]
+    fn test_compose() {
+        let users = config(
+            r#"
+            schema { query: Query }
+            type Query { users: [User] @http(baseURL: "http://users", path: "/users") }
+            type User { id: Int name: String }
+            "#,
+        );
+        let posts = config(
+            r#"
+            schema { query: Query }
+            type Query { posts: [Post] @http(baseURL: "http://posts", path: "/posts") }
+            type Post { id: Int userId: Int }
+            "#,
+        );
+        let overrides = config(
+            r#"
+            schema { query: Query }
+            type Query { users: [User] @http(baseURL: "http://users-v2", path: "/users") }
+            type User { email: String }
+            "#,
+        );
+
+        let (composed, report) = compose(vec![users, posts, overrides]).to_result().unwrap();
+
+        assert_eq!(
+            composed.types["Query"].fields["users"]
+                .http
+                .as_ref()
+                .and_then(|http| http.base_url.clone()),
+            Some("http://users-v2".to_string())
+        );
+        assert_eq!(
+            report,
+            ComposeReport {
+                types: BTreeMap::from([
+                    ("Post".to_string(), 1),
+                    ("Query".to_string(), 0),
+                    ("User".to_string(), 0),
+                ]),
+                fields: BTreeMap::from([
+                    ("Post.id".to_string(), 1),
+                    ("Post.userId".to_string(), 1),
+                    ("Query.posts".to_string(), 1),
+                    ("Query.users".to_string(), 2),
+                    ("User.email".to_string(), 2),
+                    ("User.id".to_string(), 0),
+                    ("User.name".to_string(), 0),
+                ]),
+                overrides: BTreeMap::from([("Query.users".to_string(), vec![0, 2])]),
+            }
+        );
+    }
+
+    #[test]
+    fn test_compose_conflict() {
+        let a = config("type User { id: Int }");
+        let b = config("type User { id: String! }");
+
+        let errors = compose(vec![a, b]).to_result().unwrap_err();
+        let cause = &errors.as_vec()[0];
+
+        assert_eq!(
+            cause.message,
+            "String! in config 1 conflicts with Int in config 0"
+        );
+        assert_eq!(cause.trace, ["User", "id"]);
+    }
+}
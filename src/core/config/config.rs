@@ -9,14 +9,16 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 use super::telemetry::Telemetry;
-use super::{KeyValue, Link, Server, Upstream};
+use super::{default, KeyValue, Link, Server, Upstream};
+use crate::core::compose::compose;
 use crate::core::config::from_document::from_document;
-use crate::core::config::source::Source;
+use crate::core::config::source::{Source, SyntaxError};
 use crate::core::directive::DirectiveCodec;
 use crate::core::http::Method;
 use crate::core::json::JsonSchema;
 use crate::core::macros::MergeRight;
 use crate::core::merge_right::MergeRight;
+use crate::core::mustache::{Mustache, Segment};
 use crate::core::valid::{Valid, Validator};
 use crate::core::{is_default, scalar};
 
@@ -68,13 +70,27 @@ pub struct Config {
     #[serde(default, skip_serializing_if = "is_default")]
     pub enums: BTreeMap<String, Enum>,
 
+    ///
+    /// A map of the custom directives declared in the schema.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub directives: BTreeMap<String, CustomDirective>,
+
     ///
     /// A list of all links in the schema.
     #[serde(default, skip_serializing_if = "is_default")]
     pub links: Vec<Link>,
+
+    ///
+    /// A list of all the fragments that can be used by `@graphQL` fields.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub fragments: Vec<Fragment>,
     #[serde(default, skip_serializing_if = "is_default")]
     /// Enable [opentelemetry](https://opentelemetry.io) support
     pub telemetry: Telemetry,
+
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// The version of the configuration, returned with every response.
+    pub version: Option<Version>,
 }
 
 ///
@@ -109,8 +125,47 @@ pub struct Type {
     pub protected: Option<Protected>,
     #[serde(default, skip_serializing_if = "is_default")]
     ///
+    /// Requires exactly one field of the input type to be set.
+    pub one_of: Option<OneOf>,
+    #[serde(default, skip_serializing_if = "is_default")]
+    ///
     /// Contains source information for the type.
     pub tag: Option<Tag>,
+    #[serde(skip)]
+    ///
+    /// Position of the type in the source it was declared in.
+    pub index: SourceIndex,
+    #[serde(skip)]
+    #[merge_right(merge_right_fn = "merge_right_extend")]
+    ///
+    /// Set when the type was only declared with `extend type`, the base type
+    /// has to be defined in one of the sources.
+    pub extend: bool,
+}
+
+fn merge_right_extend(left: bool, right: bool) -> bool {
+    left && right
+}
+
+/// The position of a type in its source. It's only used to print the types in
+/// their declaration order, so it's never serialized and two types are equal
+/// regardless of where they were declared.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SourceIndex(pub Option<usize>);
+
+impl PartialEq for SourceIndex {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for SourceIndex {}
+
+impl MergeRight for SourceIndex {
+    fn merge_right(self, other: Self) -> Self {
+        // A type keeps the position it was first declared at
+        SourceIndex(self.0.or(other.0))
+    }
 }
 
 impl Type {
@@ -135,8 +190,20 @@ impl Type {
 /// Used to represent an identifier for a type. Typically used via only by the
 /// configuration generators to provide additional information about the type.
 pub struct Tag {
+    #[serde(default, skip_serializing_if = "is_default")]
     /// A unique identifier for the type.
     pub id: String,
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// The name the type or field is tagged with eg. `internal`, the tagged
+    /// elements can be filtered out of the schema.
+    pub name: Option<String>,
+}
+
+impl Tag {
+    fn is_excluded(tag: Option<&Tag>, exclude: &[&str]) -> bool {
+        tag.and_then(|tag| tag.name.as_deref())
+            .is_some_and(|name| exclude.contains(&name))
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Deserialize, Serialize, Eq, schemars::JsonSchema, MergeRight)]
@@ -148,6 +215,18 @@ pub struct Cache {
     /// Specifies the duration, in milliseconds, of how long the value has to be
     /// stored in the cache.
     pub max_age: NonZeroU64,
+
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// Names of the request headers whose values are made part of the cache
+    /// key, so that requests made on behalf of different users don't share
+    /// entries. The headers need to be listed in
+    /// `@upstream(allowedHeaders: [...])`.
+    pub vary_by: Vec<String>,
+
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// Specifies the duration, in milliseconds, after `maxAge` during which the
+    /// stale value is still served while it's refreshed in the background.
+    pub stale_while_revalidate: Option<NonZeroU64>,
 }
 
 #[derive(
@@ -155,6 +234,13 @@ pub struct Cache {
 )]
 pub struct Protected {}
 
+#[derive(
+    Clone, Debug, Deserialize, Serialize, PartialEq, Eq, Default, schemars::JsonSchema, MergeRight,
+)]
+/// The @oneOf directive marks an input type of which exactly one field has to
+/// be set, eg. to look a user up either by `id` or by `email`.
+pub struct OneOf {}
+
 #[derive(
     Serialize,
     Deserialize,
@@ -249,11 +335,26 @@ pub struct Field {
     #[serde(default, skip_serializing_if = "is_default")]
     pub script: Option<JS>,
 
+    ///
+    /// Inserts a WASM resolver for the field.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub wasm: Option<Wasm>,
+
     ///
     /// Inserts a constant resolver for the field.
     #[serde(rename = "expr", default, skip_serializing_if = "is_default")]
     pub const_field: Option<Expr>,
 
+    ///
+    /// Sets the value of the field when it resolves to null.
+    #[serde(rename = "default", default, skip_serializing_if = "is_default")]
+    pub default_value: Option<default::Default>,
+
+    ///
+    /// Applies a JSON Patch to the value of the field.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub patch: Option<Patch>,
+
     ///
     /// Inserts a GraphQL resolver for the field.
     #[serde(default, skip_serializing_if = "is_default")]
@@ -266,6 +367,11 @@ pub struct Field {
     /// Marks field as protected by auth provider
     #[serde(default)]
     pub protected: Option<Protected>,
+
+    ///
+    /// Tags the field, eg. to filter it out of the schema.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub tag: Option<Tag>,
 }
 
 // It's a terminal implementation of MergeRight
@@ -279,6 +385,7 @@ impl Field {
     pub fn has_resolver(&self) -> bool {
         self.http.is_some()
             || self.script.is_some()
+            || self.wasm.is_some()
             || self.const_field.is_some()
             || self.graphql.is_some()
             || self.grpc.is_some()
@@ -297,6 +404,9 @@ impl Field {
         if self.script.is_some() {
             directives.push(JS::trace_name());
         }
+        if self.wasm.is_some() {
+            directives.push(Wasm::trace_name());
+        }
         if self.const_field.is_some() {
             directives.push(Expr::trace_name());
         }
@@ -309,10 +419,20 @@ impl Field {
         directives
     }
     pub fn has_batched_resolver(&self) -> bool {
+        self.is_batched(false)
+    }
+
+    /// Checks if the resolver of the field is batched, taking into account
+    /// the batching enabled by default with `@upstream(batch: {byDefault:
+    /// true})`
+    pub fn is_batched(&self, batch_by_default: bool) -> bool {
         self.http
             .as_ref()
-            .is_some_and(|http| !http.group_by.is_empty())
-            || self.graphql.as_ref().is_some_and(|graphql| graphql.batch)
+            .is_some_and(|http| !http.get_group_by(batch_by_default).is_empty())
+            || self
+                .graphql
+                .as_ref()
+                .is_some_and(|graphql| graphql.batch.unwrap_or(batch_by_default))
             || self
                 .grpc
                 .as_ref()
@@ -351,6 +471,17 @@ impl Field {
                 .and_then(|m| m.omit)
                 .unwrap_or_default()
     }
+
+    pub fn is_wrapped_in_list(&self) -> bool {
+        self.modify
+            .as_ref()
+            .and_then(|m| m.wrap_in_list)
+            .unwrap_or_default()
+    }
+
+    pub fn flattened(&self) -> Option<&String> {
+        self.modify.as_ref().and_then(|m| m.flatten.as_ref())
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, schemars::JsonSchema)]
@@ -358,13 +489,120 @@ pub struct JS {
     pub name: String,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, schemars::JsonSchema)]
+#[serde(deny_unknown_fields)]
+/// The @wasm operator resolves a field by calling a function exported by a
+/// WASM module. The module runs in a sandbox without any import, with a
+/// limited memory and execution time. It must export its `memory` and an
+/// `alloc(len: i32) -> i32` function, the called function receives the
+/// pointer and length of the JSON encoded value and args of the field
+/// `(value_ptr, value_len, args_ptr, args_len)` and returns the pointer and
+/// length of the JSON it outputs packed as `ptr << 32 | len` in an `i64`.
+pub struct Wasm {
+    /// Path to the `.wasm` module, relative to the config file.
+    pub module: String,
+    /// Name of the function exported by the module.
+    pub func: String,
+}
+
+impl Wasm {
+    pub fn new(module: impl Into<String>, func: impl Into<String>) -> Self {
+        Self { module: module.into(), func: func.into() }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, schemars::JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct Modify {
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// Arguments with a constant value passed to the resolver of the field,
+    /// eg. `{source: "web"}`. They're not exposed to the clients, but can be
+    /// used in the templates of the resolver eg. `{{.args.source}}`, are sent
+    /// with `@http(forwardArgs: true)` and are added to the arguments of the
+    /// upstream field of `@graphQL`.
+    pub args: BTreeMap<String, Value>,
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// Hoists the fields of the nested object under this key into the value the
+    /// field resolves to, eg. `flatten: "meta"` turns
+    /// `{id: 1, meta: {createdAt: "..."}}` into `{id: 1, createdAt: "..."}`.
+    pub flatten: Option<String>,
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// Builds the field as an object out of the fields of its parent, eg.
+    /// `{line1: "{{.value.addressLine1}}", city: "{{.value.city}}"}`. The keys
+    /// are the fields of the object and the values are mustache templates.
+    pub merge: BTreeMap<String, String>,
     #[serde(default, skip_serializing_if = "is_default")]
     pub name: Option<String>,
     #[serde(default, skip_serializing_if = "is_default")]
     pub omit: Option<bool>,
+    #[serde(rename = "renameArgs", default, skip_serializing_if = "is_default")]
+    /// Renames the arguments of the field in the upstream request, eg.
+    /// `{q: "searchQuery"}` sends the `q` argument of the client as
+    /// `searchQuery`. It applies to the arguments sent with
+    /// `@http(forwardArgs: true)` and to the arguments of the upstream field
+    /// of `@graphQL`.
+    pub rename_args: BTreeMap<String, String>,
+    #[serde(rename = "wrapInList", default, skip_serializing_if = "is_default")]
+    /// Wraps the value the field resolves to into a list of a single element,
+    /// or an empty list when it's null. Used to expose a single upstream value
+    /// as a list field.
+    pub wrap_in_list: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, schemars::JsonSchema)]
+#[serde(deny_unknown_fields)]
+/// The @patch operator applies a [JSON Patch](https://datatracker.ietf.org/doc/html/rfc6902)
+/// to the value the field resolves to, eg. to move or remove nested keys of the
+/// response of an upstream before it's returned.
+pub struct Patch {
+    /// The operations applied to the value, in order. The field fails to
+    /// resolve when any of them fails.
+    pub ops: Vec<PatchOperation>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, schemars::JsonSchema)]
+#[serde(deny_unknown_fields)]
+/// An operation of a JSON Patch, `path` and `from` are JSON Pointers eg.
+/// `/address/city`.
+pub struct PatchOperation {
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// The location the value is moved or copied from, required by `move` and
+    /// `copy`.
+    pub from: Option<String>,
+
+    /// The operation, one of `add`, `remove`, `replace`, `move`, `copy` or
+    /// `test`.
+    pub op: PatchOp,
+
+    /// The location the operation applies to.
+    pub path: String,
+
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// The value that is added, replaced or tested, required by `add`,
+    /// `replace` and `test`.
+    pub value: Option<Value>,
+}
+
+#[derive(
+    Serialize,
+    Deserialize,
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    schemars::JsonSchema,
+    strum_macros::Display,
+)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum PatchOp {
+    Add,
+    Remove,
+    Replace,
+    Move,
+    Copy,
+    Test,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
@@ -401,6 +639,28 @@ pub struct Enum {
     pub doc: Option<String>,
 }
 
+///
+/// A custom directive declared in the schema eg. `directive @myDir on FIELD`.
+/// Tailcall doesn't interpret it, the declaration is kept so that it's printed
+/// back with the schema.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, schemars::JsonSchema)]
+pub struct CustomDirective {
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub args: BTreeMap<String, Arg>,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub doc: Option<String>,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub repeatable: bool,
+    /// The locations the directive can be used on eg. `FIELD_DEFINITION`
+    pub locations: Vec<String>,
+}
+
+impl MergeRight for CustomDirective {
+    fn merge_right(self, other: Self) -> Self {
+        other
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, schemars::JsonSchema)]
 #[serde(deny_unknown_fields)]
 /// The @http operator indicates that a field or node is backed by a REST API.
@@ -416,11 +676,47 @@ pub struct Http {
     /// request interception handler.
     pub on_request: Option<String>,
 
+    #[serde(rename = "onError", default, skip_serializing_if = "is_default")]
+    /// onError sets the value the field resolves to when the request fails,
+    /// instead of returning an error.
+    pub on_error: Option<OnError>,
+
+    #[serde(rename = "allowedHeaders", default, skip_serializing_if = "is_default")]
+    /// `allowedHeaders` overrides the `allowedHeaders` of `@upstream` for this
+    /// API, only the listed headers of the incoming request are forwarded to
+    /// it.
+    pub allowed_headers: Option<BTreeSet<String>>,
+
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// The `auth` parameter configures how the upstream request is
+    /// authenticated, eg: signing it with AWS SigV4.
+    pub auth: Option<HttpAuth>,
+
     #[serde(rename = "baseURL", default, skip_serializing_if = "is_default")]
     /// This refers to the base URL of the API. If not specified, the default
     /// base URL is the one specified in the `@upstream` operator.
     pub base_url: Option<String>,
 
+    #[serde(rename = "baseURLs", default, skip_serializing_if = "is_default")]
+    /// A list of base URLs of the API. Requests are distributed across them in
+    /// a round-robin fashion and hosts that fail repeatedly are temporarily
+    /// skipped. Can not be used together with `baseURL`.
+    pub base_urls: Vec<String>,
+
+    #[serde(
+        rename = "baseURLByProfile",
+        default,
+        skip_serializing_if = "is_default"
+    )]
+    /// The base URL of the API for every `profile` of `@server`. Profiles
+    /// without an entry fall back to `baseURL`.
+    pub base_url_by_profile: BTreeMap<String, String>,
+
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// Set `batch` to `false` to opt the field out of the batching enabled for
+    /// every field with `@upstream(batch: {byDefault: true})`.
+    pub batch: Option<bool>,
+
     #[serde(default, skip_serializing_if = "is_default")]
     /// The body of the API call. It's used for methods like POST or PUT that
     /// send data to the server. You can pass it as a static object or use a
@@ -429,14 +725,46 @@ pub struct Http {
 
     #[serde(default, skip_serializing_if = "is_default")]
     /// The `encoding` parameter specifies the encoding of the request body. It
-    /// can be `ApplicationJson` or `ApplicationXWwwFormUrlEncoded`. @default
-    /// `ApplicationJson`.
+    /// can be `ApplicationJson`, `ApplicationXWwwFormUrlEncoded` or
+    /// `MultipartFormData`. With `MultipartFormData` every key of the body is
+    /// sent as a part, and objects with a base64 `content`, like the values of
+    /// the `Upload` scalar, are sent as files. @default `ApplicationJson`.
     pub encoding: Encoding,
 
+    #[serde(rename = "expectStatus", default, skip_serializing_if = "is_default")]
+    /// The `expectStatus` parameter lists the status codes that are considered
+    /// a successful response, eg: `[200, 201, 204]`. Any other status code
+    /// fails the resolution of the field. A response without content resolves
+    /// to `null`. By default, every 2xx status code is a success.
+    pub expect_status: Vec<u16>,
+
+    #[serde(rename = "forwardArgs", default, skip_serializing_if = "is_default")]
+    /// When `forwardArgs` is enabled, every argument of the field is forwarded
+    /// as a query parameter, unless `query` already has a parameter with the
+    /// same key or already sends the argument under another key. Arguments
+    /// that are not provided or are `null` are skipped.
+    pub forward_args: bool,
+
     #[serde(rename = "batchKey", default, skip_serializing_if = "is_default")]
     /// The `batchKey` parameter groups multiple data requests into a single call. For more details please refer out [n + 1 guide](https://tailcall.run/docs/guides/n+1#solving-using-batching).
     pub group_by: Vec<String>,
 
+    #[serde(rename = "headerMap", default, skip_serializing_if = "is_default")]
+    /// Maps headers of the response into fields of the resolved object, eg.
+    /// `{totalCount: "X-Total-Count"}`. The keys are the fields and the values
+    /// are the names of the headers, or templates reading them from
+    /// `{{.response.headers}}` eg. `"{{.response.headers.x-page}} of
+    /// {{.response.headers.x-total-pages}}"`.
+    pub header_map: BTreeMap<String, String>,
+
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// When `idempotent` is enabled, an `Idempotency-Key` header is sent with
+    /// the request so that the API can recognize a retried mutation. The key
+    /// is the SHA-256 of the method, the URL and the body of the request,
+    /// unless the client sends its own `Idempotency-Key` that is listed in the
+    /// `allowedHeaders`.
+    pub idempotent: bool,
+
     #[serde(default, skip_serializing_if = "is_default")]
     /// The `headers` parameter allows you to customize the headers of the HTTP
     /// request made by the `@http` operator. It is used by specifying a
@@ -465,11 +793,189 @@ pub struct Http {
     /// most cases.
     pub output: Option<JsonSchema>,
 
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// The `pagination` parameter adds the `limit` and `offset` arguments to
+    /// the list field and maps them to the pagination params of the API. It
+    /// overrides `@server(defaultPageSize:)`.
+    pub pagination: Option<Pagination>,
+
     #[serde(default, skip_serializing_if = "is_default")]
     /// This represents the query parameters of your API call. You can pass it
     /// as a static object or use Mustache template for dynamic parameters.
-    /// These parameters will be added to the URL.
+    /// These parameters will be added to the URL. Use `{{.selection}}` to
+    /// pass the comma separated names of the fields requested by the client.
+    /// The key is the name of the parameter in the upstream, eg. `{key: "q",
+    /// value: "{{.args.searchTerm}}"}`. Parameters with the same key are all
+    /// sent, in the order they're listed.
     pub query: Vec<KeyValue>,
+
+    #[serde(rename = "queryEncoding", default, skip_serializing_if = "is_default")]
+    /// The `queryEncoding` parameter specifies how object values of the query
+    /// parameters are serialized. With `Brackets` `{"status": "active"}` passed
+    /// as `filter` is sent as `filter[status]=active`, with `Dots` as
+    /// `filter.status=active`. @default `Json`, which sends the object as a
+    /// JSON string.
+    pub query_encoding: QueryEncoding,
+}
+
+impl Http {
+    /// Creates an `@http` resolver making a `GET` request to `path`.
+    pub fn new(path: impl Into<String>) -> Self {
+        Self { path: path.into(), ..Default::default() }
+    }
+
+    /// Infers the `batchKey` of a `GET` request with a single query parameter
+    /// mapped to a field of the parent value, eg. `{key: "id", value:
+    /// "{{.value.userId}}"}` is batched by `id`.
+    pub fn infer_group_by(&self) -> Option<Vec<String>> {
+        let [query] = self.query.as_slice() else {
+            return None;
+        };
+        if self.method != Method::GET {
+            return None;
+        }
+
+        let mustache = Mustache::parse(&query.value).ok()?;
+        match mustache.get_segments().as_slice() {
+            [Segment::Expression(parts)] if parts.len() > 1 && parts[0] == "value" => {
+                Some(vec![query.key.clone()])
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the `batchKey` the field is batched with, which is inferred
+    /// when the batching is enabled by default
+    pub fn get_group_by(&self, batch_by_default: bool) -> Vec<String> {
+        match self.batch {
+            Some(false) => vec![],
+            _ if !self.group_by.is_empty() => self.group_by.clone(),
+            _ if batch_by_default => self.infer_group_by().unwrap_or_default(),
+            _ => vec![],
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, schemars::JsonSchema)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "camelCase")]
+/// Maps the `limit` and `offset` arguments of a paginated list field to the
+/// query params of the API.
+pub struct Pagination {
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// The query param the `limit` argument is sent as. @default `limit`.
+    pub limit: Option<String>,
+
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// The query param the `offset` argument is sent as. @default `offset`.
+    pub offset: Option<String>,
+
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// The default value of the `limit` argument. @default the
+    /// `defaultPageSize` of `@server`.
+    pub page_size: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, schemars::JsonSchema)]
+#[serde(deny_unknown_fields)]
+/// Authentication applied to the requests made by the `@http` operator.
+pub struct HttpAuth {
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// Signs the body of the request with HMAC-SHA256, after it's rendered and
+    /// right before it's sent.
+    pub hmac: Option<Hmac>,
+
+    #[serde(rename = "signedQuery", default, skip_serializing_if = "is_default")]
+    /// Query parameters computed at request time by signing other query
+    /// parameters of the request, eg. a `token` required by the API.
+    pub signed_query: Vec<SignedQuery>,
+
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// Signs the request using [AWS Signature Version 4](https://docs.aws.amazon.com/IAM/latest/UserGuide/reference_aws-signing.html).
+    pub sigv4: Option<SigV4>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[serde(deny_unknown_fields)]
+/// The secret and the header used to send the HMAC signature of the request
+/// body. The secret supports Mustache templates eg: `{{.env.HMAC_SECRET}}`.
+pub struct Hmac {
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// The name of the header the hex encoded signature is sent in. @default
+    /// `X-Signature`.
+    pub header: Option<String>,
+
+    /// The secret key used to sign the body.
+    pub secret: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, schemars::JsonSchema)]
+#[serde(deny_unknown_fields)]
+/// A query parameter computed by the `transform` over the values of the
+/// `inputs` query parameters. The secret supports Mustache templates eg:
+/// `{{.env.TOKEN_SECRET}}`.
+pub struct SignedQuery {
+    /// The names of the query parameters the value is computed from, in the
+    /// order they are signed.
+    pub inputs: Vec<String>,
+
+    /// The name of the query parameter the computed value is sent in.
+    pub key: String,
+
+    /// The secret key used to sign the inputs.
+    pub secret: String,
+
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// `Hmac` sends the base64 encoded HMAC-SHA256 of the inputs, joined as
+    /// `key=value&key=value`. `JwtSign` sends a JWT signed with HS256, with
+    /// the inputs as claims. @default `Hmac`.
+    pub transform: QueryTransform,
+}
+
+#[derive(
+    Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash, Default, schemars::JsonSchema,
+)]
+pub enum QueryTransform {
+    #[default]
+    Hmac,
+    JwtSign,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[serde(deny_unknown_fields)]
+/// The credentials and scope used to sign a request with AWS SigV4. All the
+/// values support Mustache templates, so secrets can be read from `env` or
+/// `vars` eg: `{{.env.AWS_SECRET_KEY}}`.
+pub struct SigV4 {
+    /// The access key id used to sign the request.
+    pub access_key_id: String,
+
+    /// The AWS region of the upstream service eg: `us-east-1`.
+    pub region: String,
+
+    /// The secret access key used to sign the request.
+    pub secret_key: String,
+
+    /// The name of the AWS service eg: `execute-api`.
+    pub service: String,
+
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// The session token, required only when using temporary credentials.
+    pub session_token: Option<String>,
+}
+
+#[derive(
+    Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, schemars::JsonSchema, MergeRight,
+)]
+#[serde(deny_unknown_fields)]
+/// The `@version` directive tags the configuration with a version, eg: the
+/// date of a schema rollout. The tag is added to the `extensions` of every
+/// response, which helps to correlate client errors with a specific rollout.
+pub struct Version {
+    /// The version of the configuration eg: `2024-06`.
+    pub tag: String,
 }
 
 ///
@@ -483,6 +989,13 @@ pub struct Call {
     pub steps: Vec<Step>,
 }
 
+impl Call {
+    /// Creates a `@call` resolver running `steps` in order.
+    pub fn new(steps: Vec<Step>) -> Self {
+        Self { steps }
+    }
+}
+
 ///
 /// Provides the ability to refer to a field defined in the root Query or
 /// Mutation.
@@ -501,6 +1014,18 @@ pub struct Step {
     pub args: BTreeMap<String, Value>,
 }
 
+impl Step {
+    /// Creates a step calling the `field` of the `Query` type.
+    pub fn query(field: impl Into<String>) -> Self {
+        Self { query: Some(field.into()), ..Default::default() }
+    }
+
+    /// Creates a step calling the `field` of the `Mutation` type.
+    pub fn mutation(field: impl Into<String>) -> Self {
+        Self { mutation: Some(field.into()), ..Default::default() }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, schemars::JsonSchema)]
 #[serde(rename_all = "camelCase")]
 #[serde(deny_unknown_fields)]
@@ -523,6 +1048,21 @@ pub struct Grpc {
     /// static object or use Mustache template for dynamic parameters. These
     /// parameters will be added in the body in `protobuf` format.
     pub body: Option<String>,
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// The PEM encoded CA certificate trusted when connecting to the target
+    /// over TLS. It supports Mustache templates rendered once at startup, so
+    /// it can be read from `env` or `vars` eg: `{{.env.GRPC_CA_CERT}}`.
+    pub ca_cert: Option<String>,
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// The PEM encoded certificate presented to the target for mTLS, along
+    /// with `clientKey`. It supports Mustache templates rendered once at
+    /// startup, so it can be read from `env` or `vars` eg:
+    /// `{{.env.GRPC_CLIENT_CERT}}`.
+    pub client_cert: Option<String>,
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// The PEM encoded private key of the `clientCert`. It supports Mustache
+    /// templates rendered once at startup eg: `{{.env.GRPC_CLIENT_KEY}}`.
+    pub client_key: Option<String>,
     #[serde(rename = "batchKey", default, skip_serializing_if = "is_default")]
     /// The key path in the response which should be used to group multiple requests. For instance `["news","id"]`. For more details please refer out [n + 1 guide](https://tailcall.run/docs/guides/n+1#solving-using-batching).
     pub group_by: Vec<String>,
@@ -535,6 +1075,24 @@ pub struct Grpc {
     /// This refers to the gRPC method you're going to call. For instance
     /// `GetAllNews`.
     pub method: String,
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// The path of the value in the decoded response that is returned by the
+    /// field, like `@inline`. For instance `["result","items"]` returns the
+    /// items of the `result` message directly.
+    pub path: Vec<String>,
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// Selects the transport security of the target: `true` sends the requests
+    /// over TLS and `false` over plaintext HTTP2 (h2c). When not set, it
+    /// follows the scheme of the base URL.
+    pub tls: Option<bool>,
+}
+
+impl Grpc {
+    /// Creates a `@grpc` resolver calling `method`, the fully qualified name
+    /// of the gRPC method eg: `news.NewsService.GetAllNews`.
+    pub fn new(method: impl Into<String>) -> Self {
+        Self { method: method.into(), ..Default::default() }
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, schemars::JsonSchema)]
@@ -542,6 +1100,12 @@ pub struct Grpc {
 /// The @graphQL operator allows to specify GraphQL API server request to fetch
 /// data from.
 pub struct GraphQL {
+    #[serde(rename = "allowedHeaders", default, skip_serializing_if = "is_default")]
+    /// `allowedHeaders` overrides the `allowedHeaders` of `@upstream` for this
+    /// API, only the listed headers of the incoming request are forwarded to
+    /// it.
+    pub allowed_headers: Option<BTreeSet<String>>,
+
     #[serde(default, skip_serializing_if = "is_default")]
     /// Named arguments for the requested field. More info [here](https://tailcall.run/docs/guides/operators/#args)
     pub args: Option<Vec<KeyValue>>,
@@ -557,8 +1121,23 @@ pub struct GraphQL {
     /// batch request.
     ///
     /// Make sure you have also specified batch settings to the `@upstream` and
-    /// to the `@graphQL` operator.
-    pub batch: bool,
+    /// to the `@graphQL` operator. Defaults to `true` with
+    /// `@upstream(batch: {byDefault: true})`.
+    pub batch: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// The name of a federation entity of the upstream subgraph. When it's set,
+    /// `name` is a field of the entity, which is requested through the
+    /// `_entities` query with the `key` of the parent value as representation.
+    /// With `@upstream(batch:)` the requests of the field are batched into a
+    /// single `_entities` query.
+    pub entity: Option<String>,
+
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// The name of a fragment defined with `@fragment`. When specified, the
+    /// upstream field is requested with the fragment instead of the selection
+    /// set of the incoming query.
+    pub fragment: Option<String>,
 
     #[serde(default, skip_serializing_if = "is_default")]
     /// The headers parameter allows you to customize the headers of the GraphQL
@@ -566,11 +1145,62 @@ pub struct GraphQL {
     /// key-value map of header names and their values.
     pub headers: Vec<KeyValue>,
 
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// When set to `false` the field isn't checked against the introspection
+    /// of the upstream linked with `@link(type: GraphQL)`, the upstream isn't
+    /// introspected at all if none of its fields is. Useful for upstreams
+    /// that block introspection. @default `true`.
+    pub introspect: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// The name of the key field of the `entity`, its value is read from the
+    /// field of the same name of the parent value. @default `id`.
+    pub key: Option<String>,
+
     /// Specifies the root field on the upstream to request data from. This maps
     /// a field in your schema to a field in the upstream schema. When a query
     /// is received for this field, Tailcall requests data from the
     /// corresponding upstream field.
     pub name: String,
+
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// Set `persist` to `true` for upstreams supporting Automatic Persisted
+    /// Queries. The query is sent along with its hash the first time, and
+    /// only the hash is sent once the upstream has registered it. The requests
+    /// aren't batched. @default `false`.
+    pub persist: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// The maximum time in milliseconds to wait for the upstream to respond.
+    /// Once it's exceeded the call is dropped and the field resolves to an
+    /// error. By default there is no limit.
+    pub timeout: Option<u64>,
+}
+
+impl GraphQL {
+    /// Creates a `@graphQL` resolver requesting the `name` root field of the
+    /// upstream.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self { name: name.into(), ..Default::default() }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, schemars::JsonSchema)]
+#[serde(deny_unknown_fields)]
+/// The @fragment directive defines a named fragment on an upstream GraphQL
+/// type. Fields with `@graphQL(fragment:)` spread it instead of repeating the
+/// selection set, and its definition is sent once with every upstream query
+/// that uses it.
+pub struct Fragment {
+    /// The name of the fragment.
+    pub name: String,
+
+    /// The upstream type the fragment applies to.
+    pub on: String,
+
+    /// The fields selected by the fragment, without the surrounding braces.
+    /// Other fragments can be spread here using `...Name`.
+    pub selection: String,
 }
 
 #[derive(Default, Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -596,7 +1226,40 @@ impl Display for GraphQLOperationType {
 /// to a value. The expression can be a static value or built form a Mustache
 /// template. schema.
 pub struct Expr {
-    pub body: Value,
+    #[serde(
+        default,
+        skip_serializing_if = "is_default",
+        deserialize_with = "deserialize_body"
+    )]
+    /// The value the field resolves to. `body: null` explicitly resolves the
+    /// field to `null`, which is only allowed on nullable fields.
+    pub body: Option<Value>,
+
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// Generates the list of the integers of the range instead of using a
+    /// `body`, eg. `range: {from: 1, to: 5}` evaluates to `[1, 2, 3, 4, 5]`.
+    pub range: Option<Range>,
+}
+
+/// Keeps an explicit `body: null` apart from a missing `body`
+fn deserialize_body<'de, D>(deserializer: D) -> Result<Option<Value>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Value::deserialize(deserializer).map(Some)
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, schemars::JsonSchema)]
+#[serde(deny_unknown_fields)]
+/// An inclusive range of integers.
+pub struct Range {
+    pub from: i64,
+    pub to: i64,
+
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// The difference between two consecutive integers of the range. @default
+    /// `1`.
+    pub step: Option<u64>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, schemars::JsonSchema)]
@@ -626,6 +1289,60 @@ impl Config {
         self.enums.get(name)
     }
 
+    /// Returns the field `field_name` of the type `type_name`, if both exist.
+    pub fn find_field(&self, type_name: &str, field_name: &str) -> Option<&Field> {
+        self.find_type(type_name)
+            .and_then(|type_| type_.fields.get(field_name))
+    }
+
+    /// Returns the fields of the query, mutation and subscription types, in
+    /// that order, along with the name of their root type.
+    pub fn root_fields(&self) -> Vec<(&str, &str, &Field)> {
+        [
+            &self.schema.query,
+            &self.schema.mutation,
+            &self.schema.subscription,
+        ]
+        .into_iter()
+        .flatten()
+        .filter_map(|root| Some((root.as_str(), self.find_type(root)?)))
+        .flat_map(|(root, type_)| {
+            type_
+                .fields
+                .iter()
+                .map(move |(name, field)| (root, name.as_str(), field))
+        })
+        .collect()
+    }
+
+    /// Returns the types, unions and enums reachable from the given type
+    /// through the types of its fields and arguments, its interfaces and the
+    /// members of unions. The given type is included when it's defined.
+    pub fn referenced_types(&self, type_name: &str) -> BTreeSet<String> {
+        let mut types = BTreeSet::new();
+        let mut stack = vec![type_name.to_string()];
+
+        while let Some(name) = stack.pop() {
+            if types.contains(&name) || !self.contains(&name) {
+                continue;
+            }
+
+            if let Some(type_) = self.find_type(&name) {
+                for field in type_.fields.values() {
+                    stack.push(field.type_of.clone());
+                    stack.extend(field.args.values().map(|arg| arg.type_of.clone()));
+                }
+                stack.extend(type_.implements.iter().cloned());
+            }
+            if let Some(union_) = self.find_union(&name) {
+                stack.extend(union_.types.iter().cloned());
+            }
+            types.insert(name);
+        }
+
+        types
+    }
+
     pub fn to_yaml(&self) -> Result<String> {
         Ok(serde_yaml::to_string(self)?)
     }
@@ -643,8 +1360,15 @@ impl Config {
     }
 
     pub fn to_sdl(&self) -> String {
-        let doc = self.to_document();
-        crate::core::document::print(doc)
+        self.to_sdl_with_order(false)
+    }
+
+    /// Prints the configuration as SDL. The types are sorted by name, unless
+    /// `preserve_order` is set in which case they're printed in the order
+    /// they were declared in the source.
+    pub fn to_sdl_with_order(&self, preserve_order: bool) -> String {
+        let doc = super::into_document::to_document(self.clone(), preserve_order);
+        crate::core::document::print_with_order(doc, preserve_order)
     }
 
     pub fn query(mut self, query: &str) -> Self {
@@ -667,6 +1391,12 @@ impl Config {
             || self.enums.contains_key(name)
     }
 
+    /// The JSON schema of the configuration, it describes the shape accepted
+    /// by [`Config::from_json`] and [`Config::from_yaml`].
+    pub fn json_schema() -> schemars::schema::RootSchema {
+        schemars::schema_for!(Config)
+    }
+
     pub fn from_json(json: &str) -> Result<Self> {
         Ok(serde_json::from_str(json)?)
     }
@@ -685,7 +1415,11 @@ impl Config {
 
     pub fn from_source(source: Source, schema: &str) -> Result<Self> {
         match source {
-            Source::GraphQL => Ok(Config::from_sdl(schema).to_result()?),
+            Source::GraphQL => {
+                let doc = async_graphql::parser::parse_schema(schema)
+                    .map_err(|e| SyntaxError::from_parser(&e, schema))?;
+                Ok(from_document(doc).to_result()?)
+            }
             Source::Json => Ok(Config::from_json(schema)?),
             Source::Yml => Ok(Config::from_yaml(schema)?),
         }
@@ -719,6 +1453,28 @@ impl Config {
             .map_or(scalar::is_predefined_scalar(type_name), |ty| ty.scalar())
     }
 
+    /// Checks if the GraphQL upstream at `url` has to be introspected, which
+    /// isn't the case when all of its `@graphQL` fields opt out with
+    /// `introspect: false`.
+    pub fn introspects(&self, url: &str) -> bool {
+        let mut fields = self
+            .types
+            .values()
+            .flat_map(|type_| type_.fields.values())
+            .filter_map(|field| field.graphql.as_ref())
+            .filter(|graphql| {
+                graphql
+                    .base_url
+                    .as_ref()
+                    .or(self.upstream.base_url.as_ref())
+                    .map(String::as_str)
+                    == Some(url)
+            })
+            .peekable();
+
+        fields.peek().is_none() || fields.any(|graphql| graphql.introspect != Some(false))
+    }
+
     ///
     /// Goes through the complete config and finds all the types that are used
     /// as inputs directly ot indirectly.
@@ -783,6 +1539,64 @@ impl Config {
         all_types.difference(&used_types).cloned().collect()
     }
 
+    /// Removes the types and fields tagged with one of the excluded names, eg.
+    /// to publish a schema without its `@tag(name: "internal")` elements. It
+    /// fails when one of the remaining fields depends on an excluded type.
+    pub fn filter_by_tag(&self, exclude: &[&str]) -> Valid<Config, String> {
+        let mut config = self.clone();
+        let excluded = config
+            .types
+            .iter()
+            .filter(|(_, type_of)| Tag::is_excluded(type_of.tag.as_ref(), exclude))
+            .map(|(name, _)| name.clone())
+            .collect::<HashSet<_>>();
+        config = config.remove_types(excluded.clone());
+        for type_of in config.types.values_mut() {
+            type_of
+                .fields
+                .retain(|_, field| !Tag::is_excluded(field.tag.as_ref(), exclude));
+        }
+
+        let depends_on = |names: Vec<&String>| {
+            Valid::from_iter(names, |name| {
+                Valid::<(), String>::fail(format!("Depends on the excluded type {}", name))
+                    .when(|| excluded.contains(name))
+            })
+            .unit()
+        };
+
+        let roots = [
+            config.schema.query.as_ref(),
+            config.schema.mutation.as_ref(),
+            config.schema.subscription.as_ref(),
+        ];
+
+        Valid::from_iter(roots.into_iter().flatten(), |root| {
+            Valid::<(), String>::fail(format!("Root type {} is excluded", root))
+                .when(|| excluded.contains(root))
+        })
+        .and(Valid::from_iter(
+            config.types.iter(),
+            |(type_name, type_of)| {
+                depends_on(type_of.implements.iter().collect())
+                    .and(Valid::from_iter(
+                        type_of.fields.iter(),
+                        |(field_name, field)| {
+                            let args = field.args.values().map(|arg| &arg.type_of);
+                            depends_on(std::iter::once(&field.type_of).chain(args).collect())
+                                .trace(field_name)
+                        },
+                    ))
+                    .trace(type_name)
+            },
+        ))
+        .and(Valid::from_iter(
+            config.unions.iter(),
+            |(union_name, union)| depends_on(union.types.iter().collect()).trace(union_name),
+        ))
+        .map_to(config)
+    }
+
     /// Gets all the type names used in the schema.
     pub fn get_all_used_type_names(&self) -> HashSet<String> {
         let mut set = HashSet::new();
@@ -809,6 +1623,152 @@ impl Config {
 
         set
     }
+
+    /// Merges the configs like `merge_right`, except that the values of this
+    /// config take precedence over the ones of `other`.
+    pub fn merge_left(self, other: Self) -> Self {
+        other.merge_right(self)
+    }
+
+    /// Merges the configs with the precedence set by the `strategy`. With
+    /// [MergeStrategy::Error] the configs can't declare the same field, set a
+    /// value of `@server` or `@upstream`, or link the same id differently, and
+    /// as with [compose] the output types of the fields can never conflict.
+    pub fn merge_with(self, other: Self, strategy: MergeStrategy) -> Valid<Self, String> {
+        match strategy {
+            MergeStrategy::RightWins => Valid::succeed(self.merge_right(other)),
+            MergeStrategy::LeftWins => Valid::succeed(self.merge_left(other)),
+            MergeStrategy::Error => {
+                let conflicts = self.conflicts(&other);
+                compose(vec![self, other]).and_then(|(config, _)| {
+                    Valid::from_iter(conflicts, |(message, path)| {
+                        let error = Valid::<(), String>::fail(message.to_string());
+                        path.iter()
+                            .rev()
+                            .fold(error, |error, name| error.trace(name))
+                    })
+                    .map_to(config)
+                })
+            }
+        }
+    }
+
+    /// The definitions of both configs that can't be merged without one of
+    /// them overriding the other, along with the path to each of them.
+    fn conflicts(&self, other: &Self) -> Vec<(&'static str, Vec<String>)> {
+        let fields = self
+            .types
+            .iter()
+            .filter_map(|(type_name, type_of)| {
+                Some((type_name, type_of, other.types.get(type_name)?))
+            })
+            .flat_map(|(type_name, type_of, other_type)| {
+                type_of
+                    .fields
+                    .iter()
+                    .filter(|(name, field)| {
+                        other_type
+                            .fields
+                            .get(*name)
+                            .is_some_and(|other| other != *field)
+                    })
+                    .map(|(name, _)| vec![type_name.clone(), name.clone()])
+            })
+            .map(|path| ("Field is defined in both configs", path));
+
+        let directives = [
+            ("@server", to_json(&self.server), to_json(&other.server)),
+            (
+                "@upstream",
+                to_json(&self.upstream),
+                to_json(&other.upstream),
+            ),
+        ];
+        let values = directives
+            .into_iter()
+            .flat_map(|(directive, left, right)| {
+                conflicting_paths(&left, &right)
+                    .into_iter()
+                    .map(move |path| [vec![directive.to_string()], path].concat())
+            })
+            .map(|path| ("Value is set differently in both configs", path));
+
+        let links = self
+            .links
+            .iter()
+            .filter(|link| {
+                other
+                    .links
+                    .iter()
+                    .any(|other| other.id.is_some() && other.id == link.id && other != *link)
+            })
+            .filter_map(|link| link.id.clone())
+            .map(|id| {
+                (
+                    "Link is defined in both configs",
+                    vec!["@link".to_string(), id],
+                )
+            });
+
+        fields.chain(values).chain(links).collect()
+    }
+}
+
+fn to_json<A: Serialize>(value: &A) -> Value {
+    serde_json::to_value(value).unwrap_or_default()
+}
+
+/// Lists of key-value pairs, like the `vars` of `@server`, are merged by key
+fn to_key_value_object(list: &[Value]) -> Option<Value> {
+    list.iter()
+        .map(|item| {
+            Some((
+                item.get("key")?.as_str()?.to_string(),
+                item.get("value")?.clone(),
+            ))
+        })
+        .collect::<Option<serde_json::Map<_, _>>>()
+        .map(Value::Object)
+}
+
+/// The paths of the values set in both `left` and `right` to something
+/// different. Objects are compared key by key, so that values set on one side
+/// only never conflict.
+fn conflicting_paths(left: &Value, right: &Value) -> Vec<Vec<String>> {
+    match (left, right) {
+        (Value::Object(left), Value::Object(right)) => left
+            .iter()
+            .filter_map(|(key, left)| Some((key, left, right.get(key)?)))
+            .flat_map(|(key, left, right)| {
+                conflicting_paths(left, right)
+                    .into_iter()
+                    .map(move |path| [vec![key.clone()], path].concat())
+            })
+            .collect(),
+        (Value::Array(left), Value::Array(right)) => {
+            match (to_key_value_object(left), to_key_value_object(right)) {
+                (Some(left), Some(right)) => conflicting_paths(&left, &right),
+                _ if left != right => vec![vec![]],
+                _ => vec![],
+            }
+        }
+        (left, right) if left != right => vec![vec![]],
+        _ => vec![],
+    }
+}
+
+/// Decides which config wins when two configs merged with
+/// [Config::merge_with] set the same value.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MergeStrategy {
+    /// The values of the config on the right override the ones on the left
+    #[default]
+    RightWins,
+    /// The values of the config on the left are kept
+    LeftWins,
+    /// Declaring the same field, `@server` or `@upstream` value or link id
+    /// differently in both configs is an error
+    Error,
 }
 
 #[derive(
@@ -818,6 +1778,29 @@ pub enum Encoding {
     #[default]
     ApplicationJson,
     ApplicationXWwwFormUrlencoded,
+    MultipartFormData,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, schemars::JsonSchema)]
+#[serde(deny_unknown_fields)]
+/// The value a field resolves to when its upstream call fails
+pub struct OnError {
+    /// Value returned in place of the error, it has to match the type of the
+    /// field.
+    pub fallback: Value,
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// Logs the error that is replaced by the fallback
+    pub log: bool,
+}
+
+#[derive(
+    Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash, Default, schemars::JsonSchema,
+)]
+pub enum QueryEncoding {
+    #[default]
+    Json,
+    Brackets,
+    Dots,
 }
 
 #[cfg(test)]
@@ -825,6 +1808,7 @@ mod tests {
     use pretty_assertions::assert_eq;
 
     use super::*;
+    use crate::core::valid::ValidationError;
 
     #[test]
     fn test_field_has_or_not_batch_resolver() {
@@ -845,6 +1829,110 @@ mod tests {
         assert!(!f3.has_batched_resolver());
     }
 
+    #[test]
+    fn test_http_get_group_by() {
+        let http = Http {
+            query: vec![KeyValue {
+                key: "id".to_string(),
+                value: "{{.value.userId}}".to_string(),
+            }],
+            ..Default::default()
+        };
+
+        assert_eq!(http.get_group_by(false), Vec::<String>::new());
+        assert_eq!(http.get_group_by(true), vec!["id".to_string()]);
+
+        let opt_out = Http { batch: Some(false), ..http.clone() };
+        assert_eq!(opt_out.get_group_by(true), Vec::<String>::new());
+
+        let explicit = Http { group_by: vec!["user_id".to_string()], ..http.clone() };
+        assert_eq!(explicit.get_group_by(false), vec!["user_id".to_string()]);
+
+        let post = Http { method: Method::POST, ..http.clone() };
+        assert_eq!(post.get_group_by(true), Vec::<String>::new());
+
+        let static_value = Http {
+            query: vec![KeyValue { key: "id".to_string(), value: "1".to_string() }],
+            ..Default::default()
+        };
+        assert_eq!(static_value.get_group_by(true), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_field_is_batched_by_default() {
+        let http = Field {
+            http: Some(Http {
+                query: vec![KeyValue {
+                    key: "id".to_string(),
+                    value: "{{.value.userId}}".to_string(),
+                }],
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let graphql = Field {
+            graphql: Some(GraphQL { batch: None, ..Default::default() }),
+            ..Default::default()
+        };
+        let graphql_opt_out = Field {
+            graphql: Some(GraphQL { batch: Some(false), ..Default::default() }),
+            ..Default::default()
+        };
+
+        assert!(!http.is_batched(false));
+        assert!(http.is_batched(true));
+        assert!(!graphql.is_batched(false));
+        assert!(graphql.is_batched(true));
+        assert!(!graphql_opt_out.is_batched(true));
+    }
+
+    #[test]
+    fn test_to_sdl_with_order() {
+        let sdl = [
+            "schema @server @upstream {\n  query: Query\n}\n",
+            "type Query {\n  user: User\n}\n",
+            "type User {\n  address: Address\n  id: Int\n}\n",
+            "type Address {\n  city: String\n}\n",
+        ];
+        let config = Config::from_sdl(&sdl.join("\n")).to_result().unwrap();
+
+        assert_eq!(config.to_sdl_with_order(true), sdl.join("\n").trim_end());
+        assert_eq!(
+            config.to_sdl(),
+            [sdl[0], sdl[3], sdl[1], sdl[2]].join("\n").trim_end()
+        );
+    }
+
+    #[test]
+    fn test_from_sdl_extend_type() {
+        let actual = Config::from_sdl("type Query {a: Int} extend type Query {b: Int}")
+            .to_result()
+            .unwrap();
+        let expected = Config::default().types(vec![(
+            "Query",
+            Type::default().fields(vec![("a", Field::int()), ("b", Field::int())]),
+        )]);
+        assert_eq!(actual, expected);
+        assert!(!actual.types["Query"].extend);
+    }
+
+    #[test]
+    fn test_extend_type_in_separate_sources() {
+        let base = Config::from_sdl("type Query {a: Int}").to_result().unwrap();
+        let extension = Config::from_sdl("extend type Query {b: Int}")
+            .to_result()
+            .unwrap();
+        assert!(extension.types["Query"].extend);
+
+        let actual = base.merge_right(extension);
+        let expected = Config::default().types(vec![(
+            "Query",
+            Type::default().fields(vec![("a", Field::int()), ("b", Field::int())]),
+        )]);
+        assert_eq!(actual, expected);
+        assert!(!actual.types["Query"].extend);
+    }
+
     #[test]
     fn test_graphql_directive_name() {
         let name = GraphQL::directive_name();
@@ -886,4 +1974,349 @@ mod tests {
 
         assert_eq!(actual, expected);
     }
+
+    #[test]
+    fn test_find_field() {
+        let config = Config::from_sdl("type Query {user: User} type User {id: Int}")
+            .to_result()
+            .unwrap();
+
+        assert_eq!(config.find_field("User", "id"), Some(&Field::int()));
+        assert_eq!(config.find_field("User", "name"), None);
+        assert_eq!(config.find_field("Post", "id"), None);
+    }
+
+    #[test]
+    fn test_root_fields() {
+        let config = Config::from_sdl(
+            "
+            schema {
+                query: Query
+                mutation: Mutation
+            }
+
+            type Query {user: Int, post: Int}
+            type Mutation {createUser: Int}
+            type User {id: Int}
+            ",
+        )
+        .to_result()
+        .unwrap();
+
+        let actual = config
+            .root_fields()
+            .into_iter()
+            .map(|(root, name, _)| (root, name))
+            .collect::<Vec<_>>();
+        let expected = vec![
+            ("Query", "post"),
+            ("Query", "user"),
+            ("Mutation", "createUser"),
+        ];
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_referenced_types() {
+        let config = Config::from_sdl(
+            "
+            schema {
+                query: Query
+            }
+
+            type Query {
+                users(filter: UserFilter): [User]
+                search: SearchResult
+            }
+            input UserFilter {role: Role}
+            enum Role {ADMIN, GUEST}
+            interface Node {id: Int}
+            type User implements Node {id: Int, friends: [User], posts: [Post]}
+            type Post {id: Int, author: User}
+            type Comment {id: Int}
+            union SearchResult = User | Comment
+            type Unused {id: Int}
+            ",
+        )
+        .to_result()
+        .unwrap();
+
+        let actual = config.referenced_types("Post");
+        let expected = ["Node", "Post", "User"].map(String::from).into();
+        assert_eq!(actual, expected);
+
+        let actual = config.referenced_types("Query");
+        let expected = [
+            "Comment",
+            "Node",
+            "Post",
+            "Query",
+            "Role",
+            "SearchResult",
+            "User",
+            "UserFilter",
+        ]
+        .map(String::from)
+        .into();
+        assert_eq!(actual, expected);
+
+        assert!(config.referenced_types("Missing").is_empty());
+    }
+
+    #[test]
+    fn test_filter_by_tag() {
+        let config = Config::from_sdl(
+            r#"
+            schema {
+                query: Query
+            }
+
+            type Query {
+                users: [User]
+                audit: [AuditLog] @tag(name: "internal")
+            }
+            type User {id: Int, name: String, passwordHash: String @tag(name: "internal")}
+            type AuditLog @tag(name: "internal") {id: Int, user: User}
+            "#,
+        )
+        .to_result()
+        .unwrap();
+
+        let actual = config.filter_by_tag(&["internal"]).to_result().unwrap();
+        let expected = Config::from_sdl(
+            "
+            schema {
+                query: Query
+            }
+
+            type Query {
+                users: [User]
+            }
+            type User {id: Int, name: String}
+            ",
+        )
+        .to_result()
+        .unwrap();
+        assert_eq!(actual, expected);
+        assert_eq!(actual.to_sdl(), expected.to_sdl());
+
+        let actual = config.filter_by_tag(&["beta"]).to_result().unwrap();
+        assert_eq!(actual, config);
+    }
+
+    #[test]
+    fn test_filter_by_tag_excluded_dependency() {
+        let config = Config::from_sdl(
+            r#"
+            schema {
+                query: Query
+            }
+
+            type Query {
+                audit: [AuditLog]
+            }
+            type AuditLog @tag(name: "internal") {id: Int}
+            "#,
+        )
+        .to_result()
+        .unwrap();
+
+        let actual = config.filter_by_tag(&["internal"]).to_result().unwrap_err();
+        let expected = ValidationError::new("Depends on the excluded type AuditLog".to_string())
+            .trace("audit")
+            .trace("Query");
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let config = Config::default().query("Query").types(vec![
+            (
+                "Query",
+                Type::default().fields(vec![
+                    (
+                        "users",
+                        Field::default()
+                            .type_of("User".to_string())
+                            .into_list()
+                            .http(Http::new("/users")),
+                    ),
+                    (
+                        "news",
+                        Field::default()
+                            .type_of("News".to_string())
+                            .grpc(Grpc::new("news.NewsService.GetAllNews")),
+                    ),
+                    (
+                        "posts",
+                        Field::default()
+                            .type_of("Post".to_string())
+                            .graphql(GraphQL::new("posts")),
+                    ),
+                    (
+                        "firstUser",
+                        Field::default()
+                            .type_of("User".to_string())
+                            .call(Call::new(vec![Step::query("users")])),
+                    ),
+                ]),
+            ),
+            ("User", Type::default().fields(vec![("id", Field::int())])),
+        ]);
+
+        let json = config.to_json(false).unwrap();
+        let actual = Config::from_json(&json).unwrap();
+        assert_eq!(actual, config);
+    }
+
+    #[test]
+    fn test_json_schema() {
+        let schema = serde_json::to_value(Config::json_schema()).unwrap();
+        let properties = schema["properties"].as_object().unwrap();
+        assert!(properties.contains_key("types"));
+        assert!(schema["definitions"].get("Http").is_some());
+    }
+
+    #[test]
+    fn test_expr_explicit_null_body() {
+        let expr: Expr = serde_json::from_value(serde_json::json!({"body": null})).unwrap();
+        assert_eq!(expr.body, Some(Value::Null));
+
+        let expr: Expr = serde_json::from_value(serde_json::json!({})).unwrap();
+        assert_eq!(expr.body, None);
+    }
+
+    fn users_config(base_url: &str) -> Config {
+        Config::from_sdl(&format!(
+            r#"
+            schema {{ query: Query }}
+            type Query {{ users: [User] @http(baseURL: "{}", path: "/users") }}
+            type User {{ id: Int }}
+            "#,
+            base_url
+        ))
+        .to_result()
+        .unwrap()
+    }
+
+    fn users_base_url(config: &Config) -> Option<String> {
+        config
+            .find_type("Query")
+            .and_then(|query| query.fields.get("users"))
+            .and_then(|field| field.http.as_ref())
+            .and_then(|http| http.base_url.clone())
+    }
+
+    #[test]
+    fn test_merge_with_right_wins() {
+        let config = users_config("http://left")
+            .merge_with(users_config("http://right"), MergeStrategy::RightWins)
+            .to_result()
+            .unwrap();
+
+        assert_eq!(users_base_url(&config), Some("http://right".to_string()));
+    }
+
+    #[test]
+    fn test_merge_with_left_wins() {
+        let config = users_config("http://left")
+            .merge_with(users_config("http://right"), MergeStrategy::LeftWins)
+            .to_result()
+            .unwrap();
+
+        assert_eq!(users_base_url(&config), Some("http://left".to_string()));
+        assert_eq!(
+            users_config("http://left").merge_left(users_config("http://right")),
+            config
+        );
+    }
+
+    #[test]
+    fn test_merge_with_error() {
+        let actual = users_config("http://left")
+            .merge_with(users_config("http://right"), MergeStrategy::Error)
+            .to_result();
+        let expected = ValidationError::new("Field is defined in both configs".to_string())
+            .trace("users")
+            .trace("Query");
+
+        assert_eq!(actual, Err(expected));
+    }
+
+    #[test]
+    fn test_merge_with_error_same_field() {
+        let config = users_config("http://users")
+            .merge_with(users_config("http://users"), MergeStrategy::Error)
+            .to_result()
+            .unwrap();
+
+        assert_eq!(users_base_url(&config), Some("http://users".to_string()));
+    }
+
+    #[test]
+    fn test_merge_with_error_server_conflict() {
+        let left = Config::from_sdl(
+            r#"
+            schema @server(port: 8000, vars: [{key: "a", value: "1"}, {key: "b", value: "2"}])
+              @upstream(batch: {delay: 10})
+              @link(id: "news", src: "news.proto", type: Protobuf) {
+              query: Query
+            }
+            type Query { hello: String @expr(body: "hello") }
+            "#,
+        )
+        .to_result()
+        .unwrap();
+        let right = Config::from_sdl(
+            r#"
+            schema @server(port: 8080, vars: [{key: "b", value: "3"}, {key: "c", value: "4"}])
+              @upstream(batch: {delay: 10})
+              @link(id: "news", src: "news-v2.proto", type: Protobuf) {
+              query: Query
+            }
+            type Query { hello: String @expr(body: "hello") }
+            "#,
+        )
+        .to_result()
+        .unwrap();
+        let actual = left.merge_with(right, MergeStrategy::Error).to_result();
+        let expected = ValidationError::new("Value is set differently in both configs".to_string())
+            .trace("port")
+            .trace("@server")
+            .combine(
+                ValidationError::new("Value is set differently in both configs".to_string())
+                    .trace("b")
+                    .trace("vars")
+                    .trace("@server"),
+            )
+            .combine(
+                ValidationError::new("Link is defined in both configs".to_string())
+                    .trace("news")
+                    .trace("@link"),
+            );
+
+        assert_eq!(actual, Err(expected));
+    }
+
+    #[test]
+    fn test_merge_with_error_type_conflict() {
+        let other = Config::from_sdl(
+            r#"
+            schema { query: Query }
+            type Query { users: User @http(baseURL: "http://left", path: "/users") }
+            type User { id: Int }
+            "#,
+        )
+        .to_result()
+        .unwrap();
+        let actual = users_config("http://left")
+            .merge_with(other, MergeStrategy::Error)
+            .to_result();
+        let expected =
+            ValidationError::new("User in config 1 conflicts with [User] in config 0".to_string())
+                .trace("users")
+                .trace("Query");
+
+        assert_eq!(actual, Err(expected));
+    }
 }
@@ -9,6 +9,7 @@ use rustls_pki_types::{CertificateDer, PrivateKeyDer};
 
 use super::transformer::Transform;
 use crate::core::config::Config;
+use crate::core::graphql::IntrospectionResult;
 use crate::core::macros::MergeRight;
 use crate::core::merge_right::MergeRight;
 use crate::core::proto_reader::ProtoMetadata;
@@ -62,6 +63,9 @@ pub struct Extensions {
     pub htpasswd: Vec<Content<String>>,
 
     pub jwks: Vec<Content<JwkSet>>,
+
+    /// Contains the introspection of the linked GraphQL upstreams
+    pub graphql_introspections: Vec<IntrospectionResult>,
 }
 
 impl Extensions {
@@ -0,0 +1,15 @@
+use serde::{Deserialize, Serialize};
+
+// The name of the struct is the name of the directive, it's kept in its own
+// module so that it doesn't shadow the `Default` trait.
+
+/// The @default operator sets the value a field resolves to when it's absent
+/// or `null`, eg. when the upstream omits it. Unlike `@expr`, the resolved
+/// value is kept whenever it's present.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, schemars::JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Default {
+    /// The value the field resolves to by default, it has to match the type of
+    /// the field.
+    pub value: serde_json::Value,
+}
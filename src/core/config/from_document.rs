@@ -1,20 +1,22 @@
 use std::collections::BTreeMap;
 
 use async_graphql::parser::types::{
-    BaseType, ConstDirective, EnumType, FieldDefinition, InputObjectType, InputValueDefinition,
-    InterfaceType, ObjectType, SchemaDefinition, ServiceDocument, Type, TypeDefinition, TypeKind,
-    TypeSystemDefinition, UnionType,
+    BaseType, ConstDirective, DirectiveDefinition, EnumType, FieldDefinition, InputObjectType,
+    InputValueDefinition, InterfaceType, ObjectType, SchemaDefinition, ServiceDocument, Type,
+    TypeDefinition, TypeKind, TypeSystemDefinition, UnionType,
 };
 use async_graphql::parser::Positioned;
 use async_graphql::Name;
 
 use super::telemetry::Telemetry;
-use super::{Tag, JS};
+use super::{default, Tag, Wasm, JS};
 use crate::core::config::{
-    self, Cache, Call, Config, Enum, GraphQL, Grpc, Link, Modify, Omit, Protected, RootSchema,
-    Server, Union, Upstream,
+    self, Cache, Call, Config, CustomDirective, Enum, Fragment, GraphQL, Grpc, Link, Modify, Omit,
+    OneOf, Patch, Protected, RootSchema, Server, Union, Upstream, Version,
 };
 use crate::core::directive::DirectiveCodec;
+use crate::core::document::directive_location_name;
+use crate::core::merge_right::MergeRight;
 use crate::core::valid::{Valid, Validator};
 
 const DEFAULT_SCHEMA_DEFINITION: &SchemaDefinition = &SchemaDefinition {
@@ -38,6 +40,7 @@ pub fn from_document(doc: ServiceDocument) -> Valid<Config, String> {
     let types = to_types(&type_definitions);
     let unions = to_union_types(&type_definitions);
     let enums = to_enum_types(&type_definitions);
+    let directives = to_custom_directives(&doc);
     let schema = schema_definition(&doc).map(to_root_schema);
     schema_definition(&doc).and_then(|sd| {
         server(sd)
@@ -47,9 +50,11 @@ pub fn from_document(doc: ServiceDocument) -> Valid<Config, String> {
             .fuse(enums)
             .fuse(schema)
             .fuse(links(sd))
+            .fuse(fragments(sd))
             .fuse(telemetry(sd))
+            .fuse(version(sd))
             .map(
-                |(server, upstream, types, unions, enums, schema, links, telemetry)| Config {
+                |(
                     server,
                     upstream,
                     types,
@@ -57,7 +62,23 @@ pub fn from_document(doc: ServiceDocument) -> Valid<Config, String> {
                     enums,
                     schema,
                     links,
+                    fragments,
                     telemetry,
+                    version,
+                )| {
+                    Config {
+                        server,
+                        upstream,
+                        types,
+                        unions,
+                        enums,
+                        directives,
+                        schema,
+                        links,
+                        fragments,
+                        telemetry,
+                        version,
+                    }
                 },
             )
     })
@@ -120,6 +141,13 @@ fn links(schema_definition: &SchemaDefinition) -> Valid<Vec<Link>, String> {
     process_schema_multiple_directives(schema_definition, config::Link::directive_name().as_str())
 }
 
+fn fragments(schema_definition: &SchemaDefinition) -> Valid<Vec<Fragment>, String> {
+    process_schema_multiple_directives(
+        schema_definition,
+        config::Fragment::directive_name().as_str(),
+    )
+}
+
 fn telemetry(schema_definition: &SchemaDefinition) -> Valid<Telemetry, String> {
     process_schema_directives(
         schema_definition,
@@ -127,6 +155,10 @@ fn telemetry(schema_definition: &SchemaDefinition) -> Valid<Telemetry, String> {
     )
 }
 
+fn version(schema_definition: &SchemaDefinition) -> Valid<Option<Version>, String> {
+    Version::from_directives(schema_definition.directives.iter())
+}
+
 fn to_root_schema(schema_definition: &SchemaDefinition) -> RootSchema {
     let query = schema_definition.query.as_ref().map(pos_name_to_string);
     let mutation = schema_definition.mutation.as_ref().map(pos_name_to_string);
@@ -143,38 +175,57 @@ fn pos_name_to_string(pos: &Positioned<Name>) -> String {
 fn to_types(
     type_definitions: &Vec<&Positioned<TypeDefinition>>,
 ) -> Valid<BTreeMap<String, config::Type>, String> {
-    Valid::from_iter(type_definitions, |type_definition| {
-        let type_name = pos_name_to_string(&type_definition.node.name);
-        match type_definition.node.kind.clone() {
-            TypeKind::Object(object_type) => to_object_type(
-                &object_type,
-                &type_definition.node.description,
-                &type_definition.node.directives,
-            )
-            .some(),
-            TypeKind::Interface(interface_type) => to_object_type(
-                &interface_type,
-                &type_definition.node.description,
-                &type_definition.node.directives,
-            )
-            .some(),
-            TypeKind::Enum(_) => Valid::none(),
-            TypeKind::InputObject(input_object_type) => to_input_object(
-                input_object_type,
-                &type_definition.node.description,
-                &type_definition.node.directives,
-            )
-            .some(),
-            TypeKind::Union(_) => Valid::none(),
-            TypeKind::Scalar => Valid::succeed(Some(to_scalar_type())),
-        }
-        .map(|option| (type_name, option))
-    })
+    Valid::from_iter(
+        type_definitions.iter().enumerate(),
+        |(index, type_definition)| {
+            let type_name = pos_name_to_string(&type_definition.node.name);
+            match type_definition.node.kind.clone() {
+                TypeKind::Object(object_type) => to_object_type(
+                    &object_type,
+                    &type_definition.node.description,
+                    &type_definition.node.directives,
+                )
+                .some(),
+                TypeKind::Interface(interface_type) => to_object_type(
+                    &interface_type,
+                    &type_definition.node.description,
+                    &type_definition.node.directives,
+                )
+                .some(),
+                TypeKind::Enum(_) => Valid::none(),
+                TypeKind::InputObject(input_object_type) => to_input_object(
+                    input_object_type,
+                    &type_definition.node.description,
+                    &type_definition.node.directives,
+                )
+                .some(),
+                TypeKind::Union(_) => Valid::none(),
+                TypeKind::Scalar => Valid::succeed(Some(to_scalar_type())),
+            }
+            .map(|option| {
+                let option = option.map(|type_| config::Type {
+                    index: config::SourceIndex(Some(index)),
+                    extend: type_definition.node.extend,
+                    ..type_
+                });
+                (type_name, option)
+            })
+        },
+    )
     .map(|vec| {
-        BTreeMap::from_iter(
-            vec.into_iter()
-                .filter_map(|(name, option)| option.map(|tpe| (name, tpe))),
-        )
+        // `extend type` definitions are merged into the type with the same name
+        let mut types = BTreeMap::new();
+        for (name, tpe) in vec
+            .into_iter()
+            .filter_map(|(name, option)| option.map(|tpe| (name, tpe)))
+        {
+            let tpe = match types.remove(&name) {
+                Some(existing) => existing.merge_right(tpe),
+                None => tpe,
+            };
+            types.insert(name, tpe);
+        }
+        types
     })
 }
 fn to_scalar_type() -> config::Type {
@@ -250,7 +301,16 @@ where
             let doc = description.to_owned().map(|pos| pos.node);
             let implements = implements.iter().map(|pos| pos.node.to_string()).collect();
             let added_fields = to_add_fields_from_directives(directives);
-            config::Type { fields, added_fields, doc, implements, cache, protected, tag }
+            config::Type {
+                fields,
+                added_fields,
+                doc,
+                implements,
+                cache,
+                protected,
+                tag,
+                ..Default::default()
+            }
         })
 }
 fn to_input_object(
@@ -260,9 +320,10 @@ fn to_input_object(
 ) -> Valid<config::Type, String> {
     to_input_object_fields(&input_object_type.fields)
         .fuse(Protected::from_directives(directives.iter()))
-        .map(|(fields, protected)| {
+        .fuse(OneOf::from_directives(directives.iter()))
+        .map(|(fields, protected, one_of)| {
             let doc = description.to_owned().map(|pos| pos.node);
-            config::Type { fields, protected, doc, ..Default::default() }
+            config::Type { fields, protected, one_of, doc, ..Default::default() }
         })
 }
 
@@ -320,10 +381,29 @@ where
         .fuse(Omit::from_directives(directives.iter()))
         .fuse(Modify::from_directives(directives.iter()))
         .fuse(JS::from_directives(directives.iter()))
+        .fuse(Wasm::from_directives(directives.iter()))
         .fuse(Call::from_directives(directives.iter()))
-        .fuse(Protected::from_directives(directives.iter()))
+        .fuse(
+            Protected::from_directives(directives.iter())
+                .fuse(Tag::from_directives(directives.iter())),
+        )
+        .fuse(default::Default::from_directives(directives.iter()))
+        .fuse(Patch::from_directives(directives.iter()))
         .map(
-            |(http, graphql, cache, grpc, omit, modify, script, call, protected)| {
+            |(
+                http,
+                graphql,
+                cache,
+                grpc,
+                omit,
+                modify,
+                script,
+                wasm,
+                call,
+                (protected, tag),
+                default_value,
+                patch,
+            )| {
                 let const_field = to_const_field(directives);
                 config::Field {
                     type_of,
@@ -337,11 +417,15 @@ where
                     http,
                     grpc,
                     script,
+                    wasm,
                     const_field,
+                    default_value,
+                    patch,
                     graphql,
                     cache,
                     call,
                     protected,
+                    tag,
                 }
             },
         )
@@ -385,6 +469,38 @@ fn to_arg(input_value_definition: &InputValueDefinition) -> config::Arg {
     config::Arg { type_of, list, required, doc, modify, default_value }
 }
 
+fn to_custom_directives(doc: &ServiceDocument) -> BTreeMap<String, CustomDirective> {
+    doc.definitions
+        .iter()
+        .filter_map(|def| match def {
+            TypeSystemDefinition::Directive(directive) => Some(&directive.node),
+            _ => None,
+        })
+        .map(|directive| {
+            (
+                pos_name_to_string(&directive.name),
+                to_custom_directive(directive),
+            )
+        })
+        .collect()
+}
+
+fn to_custom_directive(directive: &DirectiveDefinition) -> CustomDirective {
+    let args = directive
+        .arguments
+        .iter()
+        .map(|arg| (pos_name_to_string(&arg.node.name), to_arg(&arg.node)))
+        .collect();
+    let doc = directive.description.to_owned().map(|pos| pos.node);
+    let locations = directive
+        .locations
+        .iter()
+        .map(|location| directive_location_name(&location.node).to_string())
+        .collect();
+
+    CustomDirective { args, doc, repeatable: directive.is_repeatable, locations }
+}
+
 fn to_union(union_type: UnionType, doc: &Option<String>) -> Union {
     let types = union_type
         .members
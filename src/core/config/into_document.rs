@@ -2,14 +2,58 @@ use async_graphql::parser::types::*;
 use async_graphql::{Pos, Positioned};
 use async_graphql_value::{ConstValue, Name};
 
-use super::{Config, ConfigModule};
+use super::{Config, ConfigModule, CustomDirective};
 use crate::core::blueprint::TypeLike;
 use crate::core::directive::DirectiveCodec;
+use crate::core::document::directive_location;
 
 fn pos<A>(a: A) -> Positioned<A> {
     Positioned::new(a, Pos::default())
 }
-fn config_document(config: &ConfigModule) -> ServiceDocument {
+
+fn to_directive_definition(name: &str, directive: &CustomDirective) -> DirectiveDefinition {
+    let arguments = directive
+        .args
+        .iter()
+        .map(|(name, arg)| {
+            let base_type = if arg.list {
+                BaseType::List(Box::new(Type {
+                    nullable: !arg.list_type_required(),
+                    base: BaseType::Named(Name::new(arg.type_of.clone())),
+                }))
+            } else {
+                BaseType::Named(Name::new(arg.type_of.clone()))
+            };
+
+            pos(InputValueDefinition {
+                description: arg.doc.clone().map(pos),
+                name: pos(Name::new(name)),
+                ty: pos(Type { nullable: !arg.required, base: base_type }),
+                default_value: arg
+                    .default_value
+                    .clone()
+                    .and_then(|value| ConstValue::from_json(value).ok())
+                    .map(pos),
+                directives: Vec::new(),
+            })
+        })
+        .collect();
+
+    DirectiveDefinition {
+        description: directive.doc.clone().map(pos),
+        name: pos(Name::new(name)),
+        arguments,
+        is_repeatable: directive.repeatable,
+        locations: directive
+            .locations
+            .iter()
+            .filter_map(|location| directive_location(location))
+            .map(pos)
+            .collect(),
+    }
+}
+
+fn config_document(config: &ConfigModule, preserve_order: bool) -> ServiceDocument {
     let mut definitions = Vec::new();
     let mut directives = vec![
         pos(config.server.to_directive()),
@@ -37,6 +81,20 @@ fn config_document(config: &ConfigModule) -> ServiceDocument {
         pos(directive)
     }));
 
+    directives.extend(
+        config
+            .fragments
+            .iter()
+            .map(|fragment| pos(fragment.to_directive())),
+    );
+
+    directives.extend(
+        config
+            .version
+            .as_ref()
+            .map(|version| pos(version.to_directive())),
+    );
+
     let schema_definition = SchemaDefinition {
         extend: false,
         directives,
@@ -53,7 +111,18 @@ fn config_document(config: &ConfigModule) -> ServiceDocument {
             .map(|name| pos(Name::new(name))),
     };
     definitions.push(TypeSystemDefinition::Schema(pos(schema_definition)));
-    for (type_name, type_def) in config.types.iter() {
+    for (name, directive) in config.directives.iter() {
+        definitions.push(TypeSystemDefinition::Directive(pos(
+            to_directive_definition(name, directive),
+        )));
+    }
+    let mut types = config.types.iter().collect::<Vec<_>>();
+    if preserve_order {
+        // Types without a source index, eg. the ones added by the generators,
+        // are printed after the declared ones
+        types.sort_by_key(|(_, type_def)| type_def.index.0.unwrap_or(usize::MAX));
+    }
+    for (type_name, type_def) in types {
         let kind = if config.interface_types.contains(type_name) {
             TypeKind::Interface(InterfaceType {
                 implements: type_def
@@ -208,6 +277,12 @@ fn config_document(config: &ConfigModule) -> ServiceDocument {
                         .as_ref()
                         .map(|protected| pos(protected.to_directive())),
                 )
+                .chain(
+                    type_def
+                        .one_of
+                        .as_ref()
+                        .map(|one_of| pos(one_of.to_directive())),
+                )
                 .chain(type_def.tag.as_ref().map(|tag| pos(tag.to_directive())))
                 .collect::<Vec<_>>(),
             kind,
@@ -258,7 +333,10 @@ fn get_directives(field: &crate::core::config::Field) -> Vec<Positioned<ConstDir
     let directives = vec![
         field.http.as_ref().map(|d| pos(d.to_directive())),
         field.script.as_ref().map(|d| pos(d.to_directive())),
+        field.wasm.as_ref().map(|d| pos(d.to_directive())),
         field.const_field.as_ref().map(|d| pos(d.to_directive())),
+        field.default_value.as_ref().map(|d| pos(d.to_directive())),
+        field.patch.as_ref().map(|d| pos(d.to_directive())),
         field.modify.as_ref().map(|d| pos(d.to_directive())),
         field.omit.as_ref().map(|d| pos(d.to_directive())),
         field.graphql.as_ref().map(|d| pos(d.to_directive())),
@@ -266,13 +344,21 @@ fn get_directives(field: &crate::core::config::Field) -> Vec<Positioned<ConstDir
         field.cache.as_ref().map(|d| pos(d.to_directive())),
         field.call.as_ref().map(|d| pos(d.to_directive())),
         field.protected.as_ref().map(|d| pos(d.to_directive())),
+        field.tag.as_ref().map(|d| pos(d.to_directive())),
     ];
 
     directives.into_iter().flatten().collect()
 }
 
+/// Converts the configuration to a document. When `preserve_order` is set,
+/// the types follow the order they were declared in the source instead of
+/// being sorted by name.
+pub fn to_document(config: Config, preserve_order: bool) -> ServiceDocument {
+    config_document(&config.into(), preserve_order)
+}
+
 impl From<Config> for ServiceDocument {
     fn from(value: Config) -> Self {
-        config_document(&value.into())
+        to_document(value, false)
     }
 }
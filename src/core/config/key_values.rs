@@ -46,6 +46,28 @@ pub fn merge_key_value_vecs(current: &[KeyValue], other: &[KeyValue]) -> Vec<Key
         .collect::<Vec<KeyValue>>()
 }
 
+/// Deserializes a list of key values that can also be written as a map, ie.
+/// `{apiVersion: "v2"}` is the same as `[{key: "apiVersion", value: "v2"}]`.
+pub fn deserialize_key_value_vec<'de, D>(deserializer: D) -> Result<Vec<KeyValue>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum ListOrMap {
+        List(Vec<KeyValue>),
+        Map(BTreeMap<String, String>),
+    }
+
+    Ok(match ListOrMap::deserialize(deserializer)? {
+        ListOrMap::List(list) => list,
+        ListOrMap::Map(map) => map
+            .into_iter()
+            .map(|(key, value)| KeyValue { key, value })
+            .collect(),
+    })
+}
+
 impl Serialize for KeyValues {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -104,6 +126,24 @@ mod tests {
         assert_eq!(kv.0["a"], "b");
     }
 
+    #[test]
+    fn test_deserialize_key_value_vec() {
+        #[derive(Deserialize)]
+        struct Vars {
+            #[serde(deserialize_with = "deserialize_key_value_vec")]
+            vars: Vec<KeyValue>,
+        }
+
+        let expected = vec![KeyValue { key: "apiVersion".to_string(), value: "v2".to_string() }];
+
+        let list: Vars =
+            serde_json::from_str(r#"{"vars": [{"key": "apiVersion", "value": "v2"}]}"#).unwrap();
+        assert_eq!(list.vars, expected);
+
+        let map: Vars = serde_json::from_str(r#"{"vars": {"apiVersion": "v2"}}"#).unwrap();
+        assert_eq!(map.vars, expected);
+    }
+
     #[test]
     fn test_default_keyvalues() {
         let kv = KeyValues::default();
@@ -24,6 +24,7 @@ pub enum LinkType {
     Htpasswd,
     Jwks,
     Grpc,
+    GraphQL,
 }
 
 /// The @link directive allows you to import external resources, such as
@@ -45,4 +46,10 @@ pub struct Link {
     /// The type of the link. It can be `Config`, or `Protobuf`.
     #[serde(default, skip_serializing_if = "is_default", rename = "type")]
     pub type_of: LinkType,
+    ///
+    /// The names of the types, enums and unions imported from a linked
+    /// `Config`. When set, only these definitions are merged into the config
+    /// importing it, otherwise the whole config is.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub import: Vec<String>,
 }
@@ -12,6 +12,7 @@ mod apollo;
 mod config;
 mod config_module;
 pub mod cors;
+pub mod default;
 mod from_document;
 pub mod group_by;
 mod headers;
@@ -22,6 +23,7 @@ mod n_plus_one;
 pub mod reader;
 pub mod reader_context;
 mod server;
+mod snapshot;
 mod source;
 mod telemetry;
 pub mod transformer;
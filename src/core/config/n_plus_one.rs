@@ -24,7 +24,10 @@ fn find_fan_out(context: FindFanOutContext) -> Vec<Vec<(String, String)>> {
                     .any(|item| &item.0 == type_name && &item.1 == field_name)
                 {
                     Vec::new()
-                } else if field.has_resolver() && !field.has_batched_resolver() && is_list {
+                } else if field.has_resolver()
+                    && !field.is_batched(config.upstream.get_batch_by_default())
+                    && is_list
+                {
                     vec![new_path]
                 } else {
                     find_fan_out(FindFanOutContext {
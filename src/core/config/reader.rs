@@ -1,6 +1,7 @@
-use std::path::Path;
+use std::path::{Component, Path, PathBuf};
 use std::sync::Arc;
 
+use anyhow::anyhow;
 use rustls_pemfile;
 use rustls_pki_types::{
     CertificateDer, PrivateKeyDer, PrivatePkcs1KeyDer, PrivatePkcs8KeyDer, PrivateSec1KeyDer,
@@ -8,8 +9,10 @@ use rustls_pki_types::{
 use url::Url;
 
 use super::{ConfigModule, Content, Link, LinkType};
-use crate::core::config::{Config, ConfigReaderContext, Source};
+use crate::core::config::{Config, ConfigReaderContext, Source, SyntaxError, Upstream};
+use crate::core::graphql::IntrospectionResult;
 use crate::core::merge_right::MergeRight;
+use crate::core::mustache::Mustache;
 use crate::core::proto_reader::ProtoReader;
 use crate::core::resource_reader::{Cached, ResourceReader};
 use crate::core::rest::EndpointSet;
@@ -33,12 +36,15 @@ impl ConfigReader {
         }
     }
 
-    /// Reads the links in a Config and fill the content
+    /// Reads the links in a Config and fill the content. `linked` are the
+    /// configs that are being read and link to this one, used to detect
+    /// cyclic links.
     #[async_recursion::async_recursion]
     async fn ext_links(
         &self,
         mut config_module: ConfigModule,
         parent_dir: Option<&'async_recursion Path>,
+        linked: &[String],
     ) -> anyhow::Result<ConfigModule> {
         let links: Vec<Link> = config_module
             .config
@@ -62,22 +68,34 @@ impl ConfigReader {
 
             match link.type_of {
                 LinkType::Config => {
+                    let path = Self::normalize_path(&path);
+                    if linked.contains(&path) {
+                        let mut cycle = linked.to_vec();
+                        cycle.push(path);
+                        return Err(anyhow!("Cyclic link: {}", cycle.join(" -> ")));
+                    }
+
                     let source = self.resource_reader.read_file(&path).await?;
                     let content = source.content;
 
-                    let config = Config::from_source(Source::detect(&source.path)?, &content)?;
-
-                    config_module = config_module.merge_right(ConfigModule::from(config.clone()));
-
-                    if !config.links.is_empty() {
-                        config_module = config_module.merge_right(
-                            self.ext_links(
-                                ConfigModule::from(config),
-                                Path::new(&link.src).parent(),
-                            )
-                            .await?,
-                        );
-                    }
+                    let config = Config::from_source(Source::detect(&source.path)?, &content)
+                        .map_err(|e| with_path(e, &source.path))?;
+
+                    let mut linked = linked.to_vec();
+                    linked.push(path.clone());
+                    let linked_module = self
+                        .ext_links(
+                            ConfigModule::from(config),
+                            Path::new(&path).parent(),
+                            &linked,
+                        )
+                        .await?;
+
+                    config_module = if link.import.is_empty() {
+                        config_module.merge_right(linked_module)
+                    } else {
+                        config_module.merge_right(import(linked_module, &link.import, &path)?)
+                    };
                 }
                 LinkType::Protobuf => {
                     let meta = self.proto_reader.read(path).await?;
@@ -134,6 +152,19 @@ impl ConfigReader {
                         config_module.extensions.add_proto(m);
                     }
                 }
+                LinkType::GraphQL => {
+                    if !config_module.config.introspects(&link.src) {
+                        continue;
+                    }
+
+                    let introspection =
+                        IntrospectionResult::fetch(link.src.as_str(), &self.runtime).await?;
+
+                    config_module
+                        .extensions
+                        .graphql_introspections
+                        .push(introspection);
+                }
             }
         }
 
@@ -190,9 +221,10 @@ impl ConfigReader {
 
             // Create initial config module
             let new_config_module = self
-                .resolve(
-                    Config::from_source(source, schema)?,
+                .resolve_linked(
+                    Config::from_source(source, schema).map_err(|e| with_path(e, &file.path))?,
                     Path::new(&file.path).parent(),
+                    &[Self::normalize_path(&file.path)],
                 )
                 .await?;
 
@@ -203,17 +235,56 @@ impl ConfigReader {
         Ok(config_module)
     }
 
+    /// Reads the `@upstream` of the files without resolving their links
+    pub async fn read_upstream<T: ToString + Send + Sync>(
+        &self,
+        files: &[T],
+    ) -> anyhow::Result<Upstream> {
+        let files = self.resource_reader.read_files(files).await?;
+        let mut upstream = Upstream::default();
+
+        for file in files.iter() {
+            let source = Source::detect(&file.path)?;
+            let config =
+                Config::from_source(source, &file.content).map_err(|e| with_path(e, &file.path))?;
+
+            upstream = upstream.merge_right(config.upstream);
+        }
+
+        Ok(upstream)
+    }
+
     /// Resolves all the links in a Config to create a ConfigModule
     pub async fn resolve(
         &self,
         config: Config,
         parent_dir: Option<&Path>,
+    ) -> anyhow::Result<ConfigModule> {
+        self.resolve_linked(config, parent_dir, &[]).await
+    }
+
+    /// Like `resolve`, `linked` is the path of the config when it was read
+    /// from a file, so that the links back to it are detected as cyclic.
+    async fn resolve_linked(
+        &self,
+        config: Config,
+        parent_dir: Option<&Path>,
+        linked: &[String],
     ) -> anyhow::Result<ConfigModule> {
         // Create initial config set
         let config_module = ConfigModule::from(config);
 
         // Extend it with the links
-        let mut config_module = self.ext_links(config_module, parent_dir).await?;
+        let mut config_module = self.ext_links(config_module, parent_dir, linked).await?;
+
+        // The modules of `@wasm` are relative to the config file, just like links
+        for type_ in config_module.config.types.values_mut() {
+            for field in type_.fields.values_mut() {
+                if let Some(wasm) = field.wasm.as_mut() {
+                    wasm.module = Self::resolve_path(&wasm.module, parent_dir);
+                }
+            }
+        }
 
         let server = &mut config_module.config.server;
         let reader_ctx = ConfigReaderContext {
@@ -231,9 +302,81 @@ impl ConfigReader {
             .telemetry
             .render_mustache(&reader_ctx)?;
 
+        // The profile is picked once at startup, eg. from an env variable
+        if let Some(profile) = config_module.config.server.profile.as_mut() {
+            *profile = Mustache::parse(profile)?.render(&reader_ctx);
+        }
+
+        // So is the TLS material of `@grpc`, eg. from a secret in the env
+        for type_ in config_module.config.types.values_mut() {
+            for grpc in type_
+                .fields
+                .values_mut()
+                .filter_map(|field| field.grpc.as_mut())
+            {
+                let pems = [
+                    &mut grpc.ca_cert,
+                    &mut grpc.client_cert,
+                    &mut grpc.client_key,
+                ];
+                for pem in pems.into_iter().flatten() {
+                    *pem = Mustache::parse(pem)?.render(&reader_ctx);
+                }
+            }
+        }
+
+        // The values of `@expr` that only depend on env and vars are rendered
+        // now too, so they're checked against the type of their field
+        for type_ in config_module.config.types.values_mut() {
+            for field in type_.fields.values_mut() {
+                let body = field
+                    .const_field
+                    .as_mut()
+                    .and_then(|expr| expr.body.as_mut());
+                if let Some(body) = body {
+                    Self::render_static_value(body, &reader_ctx)?;
+                }
+            }
+        }
+
         Ok(config_module)
     }
 
+    /// Renders the templates of the value that can be fully resolved at
+    /// startup. Like at request time, the rendered text is parsed as JSON when
+    /// possible.
+    fn render_static_value(
+        value: &mut serde_json::Value,
+        reader_ctx: &ConfigReaderContext,
+    ) -> anyhow::Result<()> {
+        match value {
+            serde_json::Value::String(text) => {
+                let mustache = Mustache::parse(text)?;
+                if !mustache.is_const() {
+                    let mustache = mustache.partially_render(reader_ctx);
+                    if mustache.is_const() {
+                        let rendered = mustache.render(reader_ctx);
+                        *value = serde_json::from_str(&rendered)
+                            .unwrap_or(serde_json::Value::String(rendered));
+                    }
+                }
+            }
+            serde_json::Value::Array(items) => {
+                for item in items {
+                    Self::render_static_value(item, reader_ctx)?;
+                }
+            }
+            serde_json::Value::Object(fields) => {
+                for field in fields.values_mut() {
+                    Self::render_static_value(field, reader_ctx)?;
+                }
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
     /// Checks if path is a URL or absolute path, returns directly if so.
     /// Otherwise, it joins file path with relative dir path.
     fn resolve_path(src: &str, root_dir: Option<&Path>) -> String {
@@ -246,6 +389,59 @@ impl ConfigReader {
             path.join(src).to_string_lossy().to_string()
         }
     }
+
+    /// Removes the `.` and `..` components of a local path, so that the paths
+    /// of the same file compare equal whatever the links they're reached from.
+    fn normalize_path(path: &str) -> String {
+        if let Ok(url) = Url::parse(path) {
+            return url.to_string();
+        }
+
+        let mut normalized = PathBuf::new();
+        for component in Path::new(path).components() {
+            match component {
+                Component::CurDir => {}
+                Component::ParentDir => match normalized.components().next_back() {
+                    Some(Component::Normal(_)) => {
+                        normalized.pop();
+                    }
+                    // The parent of the root is the root itself
+                    Some(Component::RootDir | Component::Prefix(_)) => {}
+                    _ => normalized.push(component),
+                },
+                component => normalized.push(component),
+            }
+        }
+        normalized.to_string_lossy().to_string()
+    }
+}
+
+/// Keeps the types, enums and unions of the linked config that are listed in
+/// `import`, along with its extensions.
+fn import(module: ConfigModule, import: &[String], src: &str) -> anyhow::Result<ConfigModule> {
+    let linked = &module.config;
+    let mut config = Config::default();
+    for name in import {
+        if let Some(type_) = linked.types.get(name) {
+            config.types.insert(name.clone(), type_.clone());
+        } else if let Some(enum_) = linked.enums.get(name) {
+            config.enums.insert(name.clone(), enum_.clone());
+        } else if let Some(union_) = linked.unions.get(name) {
+            config.unions.insert(name.clone(), union_.clone());
+        } else {
+            return Err(anyhow!("{} is not defined in {}", name, src));
+        }
+    }
+
+    Ok(ConfigModule { extensions: module.extensions, ..ConfigModule::from(config) })
+}
+
+/// Adds the path of the config to its syntax errors
+fn with_path(error: anyhow::Error, path: &str) -> anyhow::Error {
+    match error.downcast::<SyntaxError>() {
+        Ok(error) => error.path(path).into(),
+        Err(error) => error,
+    }
 }
 
 #[cfg(test)]
@@ -256,6 +452,7 @@ mod reader_tests {
 
     use crate::core::config::reader::ConfigReader;
     use crate::core::config::{Config, Type};
+    use crate::core::valid::Validator;
 
     fn start_mock_server() -> httpmock::MockServer {
         httpmock::MockServer::start()
@@ -359,6 +556,195 @@ mod reader_tests {
         assert_eq!(content.unwrap(), config.extensions.script.unwrap());
     }
 
+    #[tokio::test]
+    async fn test_skip_introspection() {
+        let runtime = crate::core::runtime::test::init(None);
+        let server = start_mock_server();
+        let introspection = server.mock(|when, then| {
+            when.method(httpmock::Method::POST).path("/graphql");
+            then.status(200);
+        });
+
+        let url = format!("http://localhost:{}/graphql", server.port());
+        let sdl = format!(
+            r#"
+            schema @link(src: "{url}", type: GraphQL) {{
+              query: Query
+            }}
+
+            type Query {{
+              user: String @graphQL(baseURL: "{url}", name: "user", introspect: false)
+            }}
+            "#
+        );
+        let config = Config::from_sdl(&sdl).to_result().unwrap();
+
+        let reader = ConfigReader::init(runtime);
+        let config_module = reader.resolve(config, None).await.unwrap();
+
+        assert!(config_module.extensions.graphql_introspections.is_empty());
+        introspection.assert_hits(0);
+    }
+
+    #[tokio::test]
+    async fn test_link_import() {
+        let dir = tempfile::tempdir().unwrap();
+        let types = r#"
+            type User {
+              id: Int
+              name: String
+            }
+
+            type Post {
+              id: Int
+              title: String
+            }
+        "#;
+        let root = r#"
+            schema @link(src: "types.graphql", type: Config, import: ["User"]) {
+              query: Query
+            }
+
+            type Query {
+              user: User @http(baseURL: "http://localhost", path: "/users/1")
+            }
+        "#;
+        std::fs::write(dir.path().join("types.graphql"), types).unwrap();
+        std::fs::write(dir.path().join("root.graphql"), root).unwrap();
+
+        let runtime = crate::core::runtime::test::init(None);
+        let reader = ConfigReader::init(runtime);
+        let config_module = reader
+            .read(dir.path().join("root.graphql").to_string_lossy())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            config_module.types.keys().collect::<Vec<_>>(),
+            vec!["Query", "User"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_link_import_missing() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = r#"
+            schema @link(src: "types.graphql", type: Config, import: ["Comment"]) {
+              query: Query
+            }
+
+            type Query {
+              hello: String
+            }
+        "#;
+        std::fs::write(dir.path().join("types.graphql"), "type User { id: Int }").unwrap();
+        std::fs::write(dir.path().join("root.graphql"), root).unwrap();
+
+        let runtime = crate::core::runtime::test::init(None);
+        let reader = ConfigReader::init(runtime);
+        let error = reader
+            .read(dir.path().join("root.graphql").to_string_lossy())
+            .await
+            .unwrap_err();
+
+        assert!(error.to_string().starts_with("Comment is not defined in"));
+    }
+
+    #[tokio::test]
+    async fn test_cyclic_link() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = r#"
+            schema @link(src: "b.graphql", type: Config) {
+              query: Query
+            }
+
+            type Query {
+              hello: String
+            }
+        "#;
+        let b = r#"
+            schema @link(src: "a.graphql", type: Config) {
+              query: Query
+            }
+        "#;
+        std::fs::write(dir.path().join("a.graphql"), a).unwrap();
+        std::fs::write(dir.path().join("b.graphql"), b).unwrap();
+
+        let runtime = crate::core::runtime::test::init(None);
+        let reader = ConfigReader::init(runtime);
+        let error = reader
+            .read(dir.path().join("a.graphql").to_string_lossy())
+            .await
+            .unwrap_err();
+
+        let a = dir.path().join("a.graphql").to_string_lossy().to_string();
+        let b = dir.path().join("b.graphql").to_string_lossy().to_string();
+        assert_eq!(
+            error.to_string(),
+            format!("Cyclic link: {} -> {} -> {}", a, b, a)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cyclic_relative_link() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = r#"
+            schema @link(src: "./nested/b.graphql", type: Config) {
+              query: Query
+            }
+
+            type Query {
+              hello: String
+            }
+        "#;
+        let b = r#"
+            schema @link(src: "../a.graphql", type: Config) {
+              query: Query
+            }
+        "#;
+        std::fs::create_dir(dir.path().join("nested")).unwrap();
+        std::fs::write(dir.path().join("a.graphql"), a).unwrap();
+        std::fs::write(dir.path().join("nested/b.graphql"), b).unwrap();
+
+        let runtime = crate::core::runtime::test::init(None);
+        let reader = ConfigReader::init(runtime);
+        let error = reader
+            .read(dir.path().join("a.graphql").to_string_lossy())
+            .await
+            .unwrap_err();
+
+        let a = dir.path().join("a.graphql").to_string_lossy().to_string();
+        let b = dir
+            .path()
+            .join("nested/b.graphql")
+            .to_string_lossy()
+            .to_string();
+        assert_eq!(
+            error.to_string(),
+            format!("Cyclic link: {} -> {} -> {}", a, b, a)
+        );
+    }
+
+    #[test]
+    fn test_normalize_path() {
+        assert_eq!(
+            ConfigReader::normalize_path("abc/./xyz/../my.graphql"),
+            "abc/my.graphql"
+        );
+        assert_eq!(
+            ConfigReader::normalize_path("/abc/../../my.graphql"),
+            "/my.graphql"
+        );
+        assert_eq!(
+            ConfigReader::normalize_path("../abc/my.graphql"),
+            "../abc/my.graphql"
+        );
+        assert_eq!(
+            ConfigReader::normalize_path("http://example.com/my.graphql"),
+            "http://example.com/my.graphql"
+        );
+    }
+
     #[test]
     fn test_relative_path() {
         let path_dir = Path::new("abc/xyz");
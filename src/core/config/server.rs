@@ -1,8 +1,8 @@
 use std::collections::{BTreeMap, BTreeSet};
 
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 
-use super::merge_key_value_vecs;
+use super::{deserialize_key_value_vec, merge_key_value_vecs};
 use crate::core::config::headers::Headers;
 use crate::core::config::KeyValue;
 use crate::core::is_default;
@@ -18,6 +18,14 @@ use crate::core::merge_right::MergeRight;
 /// comprehensive set of server configurations. It dictates how the server
 /// behaves and helps tune tailcall for various use-cases.
 pub struct Server {
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// `allowedOperations` restricts the server to a fixed set of operations,
+    /// identified by the `sha256:` hash of their normalized query. Any other
+    /// operation is rejected with a `403` status code. The list isn't enforced
+    /// for any client while a `playground` is served, and a warning is
+    /// reported at startup when both are set.
+    pub allowed_operations: Option<Vec<String>>,
+
     #[serde(default, skip_serializing_if = "is_default")]
     /// `apolloTracing` exposes GraphQL query performance data, including
     /// execution time of queries and individual resolvers.
@@ -29,6 +37,33 @@ pub struct Server {
     /// debugging. Use judiciously. @default `false`.
     pub batch_requests: Option<bool>,
 
+    #[serde(
+        default,
+        skip_serializing_if = "is_default",
+        deserialize_with = "deserialize_key_value_vec"
+    )]
+    #[merge_right(merge_right_fn = "merge_right_vars")]
+    /// `context` builds values out of the incoming request once per request,
+    /// eg: `context: {tenant: "{{.headers.x-tenant}}"}`. The templates can
+    /// read `headers`, `vars` and `env`, and the values are available in
+    /// every template as `{{.context.tenant}}`. Missing headers render empty.
+    pub context: Vec<KeyValue>,
+
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// `defaultPageSize` paginates the list fields resolved with `@http`: they
+    /// gain the `limit` and `offset` arguments, sent to the upstream as the
+    /// `limit` and `offset` query params. `limit` defaults to this page size.
+    /// Use `@http(pagination:)` to change the params of a field.
+    pub default_page_size: Option<usize>,
+
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// `errorFormat` chooses how the errors of the `@rest` routes are
+    /// serialized, `GRAPHQL` for GraphQL errors or `PROBLEM_JSON` for an
+    /// [RFC 7807](https://datatracker.ietf.org/doc/html/rfc7807)
+    /// `application/problem+json` document. The GraphQL endpoint always
+    /// responds with GraphQL errors. @default `GRAPHQL`.
+    pub error_format: Option<ErrorFormat>,
+
     #[serde(default, skip_serializing_if = "is_default")]
     /// `headers` contains key-value pairs that are included as default headers
     /// in server responses, allowing for consistent header management across
@@ -40,16 +75,47 @@ pub struct Server {
     /// termination, acting as a safeguard against long-running queries.
     pub global_response_timeout: Option<i64>,
 
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// `graphqlPath` sets the path the GraphQL endpoint is served on, eg.
+    /// `/api/graphql` to mount it next to other services. @default
+    /// `/graphql`.
+    pub graphql_path: Option<String>,
+
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// `grpcHttpStatus` overrides the HTTP status reported in the `httpStatus`
+    /// extension of the gRPC errors, by the name of their gRPC status code eg.
+    /// `{NOT_FOUND: 410}`. By default the status follows the gRPC HTTP
+    /// mapping, eg. `PERMISSION_DENIED` is reported as `403`.
+    pub grpc_http_status: BTreeMap<String, u16>,
+
     #[serde(default, skip_serializing_if = "is_default")]
     /// `hostname` sets the server hostname.
     pub hostname: Option<String>,
 
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// `includeStats` adds the number of upstream calls, their total latency
+    /// in milliseconds and the number of cache hits of the request to the
+    /// `tailcall` extension of the GraphQL response. @default `false`.
+    pub include_stats: Option<bool>,
+
     #[serde(default, skip_serializing_if = "is_default")]
     /// `introspection` allows clients to fetch schema information directly,
     /// aiding tools and applications in understanding available types, fields,
     /// and operations. @default `true`.
     pub introspection: Option<bool>,
 
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// `maxBatchSize` limits the number of operations of a batched request.
+    /// Larger batches are rejected with a `400` status code before any of
+    /// their operations is executed. @default `100`.
+    pub max_batch_size: Option<usize>,
+
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// `maxUploadSize` limits the size in bytes of multipart requests, ie. of
+    /// the files uploaded with them. Larger requests are rejected with a `413`
+    /// status code before they are read. @default `10485760` (10MB).
+    pub max_upload_size: Option<usize>,
+
     #[serde(default, skip_serializing_if = "is_default")]
     /// `pipelineFlush` allows to control flushing behavior of the server
     /// pipeline.
@@ -59,6 +125,13 @@ pub struct Server {
     /// `port` sets the Tailcall running port. @default `8000`.
     pub port: Option<u16>,
 
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// `profile` is the name of the environment the server runs in, eg. `dev`
+    /// or `prod`. It picks the base URL of every `@http` from its
+    /// `baseURLByProfile`, and can be read from the environment with
+    /// `{{.env.TAILCALL_PROFILE}}`.
+    pub profile: Option<String>,
+
     #[serde(default, skip_serializing_if = "is_default")]
     /// `queryValidation` checks incoming GraphQL queries against the schema,
     /// preventing errors from invalid queries. Can be disabled for performance.
@@ -66,9 +139,27 @@ pub struct Server {
     pub query_validation: Option<bool>,
 
     #[serde(default, skip_serializing_if = "is_default")]
-    /// `responseValidation` Tailcall automatically validates responses from
-    /// upstream services using inferred schema. @default `false`.
-    pub response_validation: Option<bool>,
+    /// `requestLimits` caps the number of GraphQL requests executed at the
+    /// same time. The requests beyond it wait in a queue, and are rejected
+    /// with a `503` status code once the queue is full.
+    pub request_limits: Option<RequestLimits>,
+
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// `requestTimeout` sets the time budget in milliseconds a request has to
+    /// call all of its upstreams. Pending upstream calls are aborted once it's
+    /// exceeded. Clients can shorten it with the `X-Request-Timeout` header.
+    pub request_timeout: Option<u64>,
+
+    #[serde(
+        default,
+        skip_serializing_if = "is_default",
+        deserialize_with = "deserialize_response_validation"
+    )]
+    /// `responseValidation` checks the responses of upstream services against
+    /// their inferred schema, and the resolved values against the types of
+    /// their fields. `STRICT` fails on a violation, `WARN` only logs it.
+    /// `true` and `false` are accepted as `STRICT` and `OFF`. @default `OFF`.
+    pub response_validation: Option<ResponseValidation>,
 
     #[serde(default, skip_serializing_if = "is_default")]
     /// A link to an external JS file that listens on every HTTP request
@@ -80,9 +171,32 @@ pub struct Server {
     pub showcase: Option<bool>,
 
     #[serde(default, skip_serializing_if = "is_default")]
+    /// `graphiql` serves the GraphiQL IDE on GET requests to the GraphQL
+    /// path. Deprecated, use `playground: GRAPHIQL` instead. @default `false`.
+    pub graphiql: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// `playground` chooses the IDE served on GET requests to the GraphQL
+    /// path, `GRAPHIQL` for GraphiQL, `SANDBOX` for Apollo Sandbox or `NONE`
+    /// to serve none. Takes precedence over `graphiql`. @default `NONE`.
+    pub playground: Option<Playground>,
+
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// `stripHeaders` lists the headers that are never copied from the
+    /// upstream responses to the response of the server. Hop-by-hop headers
+    /// like `Connection` are always stripped.
+    pub strip_headers: Option<BTreeSet<String>>,
+
+    #[serde(
+        default,
+        skip_serializing_if = "is_default",
+        deserialize_with = "deserialize_key_value_vec"
+    )]
     #[merge_right(merge_right_fn = "merge_right_vars")]
     /// This configuration defines local variables for server operations. Useful
     /// for storing constant configurations, secrets, or shared information.
+    /// The variables can also be written as a map eg: `vars: {apiVersion:
+    /// "v2"}` and are available in every template as `{{.vars.apiVersion}}`.
     pub vars: Vec<KeyValue>,
 
     #[serde(default, skip_serializing_if = "is_default")]
@@ -90,6 +204,12 @@ pub struct Server {
     /// `HTTP2`. @default `HTTP1`.
     pub version: Option<HttpVersion>,
 
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// `watch` reloads the server whenever one of the config files passed to
+    /// `tailcall start` changes. A config that fails to build is logged and
+    /// the last valid one keeps being served. @default `false`.
+    pub watch: Option<bool>,
+
     #[serde(default, skip_serializing_if = "is_default")]
     /// `workers` sets the number of worker threads. @default the number of
     /// system cores.
@@ -107,6 +227,30 @@ pub struct ScriptOptions {
     pub timeout: Option<u64>,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, schemars::JsonSchema)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestLimits {
+    /// The maximum number of GraphQL requests executed at the same time.
+    pub max_concurrent_requests: usize,
+
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// The number of requests that can wait for a running request to finish,
+    /// the others are rejected right away. @default `0`.
+    pub queue_size: Option<usize>,
+
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// The number of seconds sent in the `Retry-After` header of the rejected
+    /// requests. @default `1`.
+    pub retry_after: Option<u64>,
+}
+
+impl MergeRight for RequestLimits {
+    fn merge_right(self, other: Self) -> Self {
+        other
+    }
+}
+
 #[derive(
     Deserialize, Serialize, Debug, PartialEq, Eq, Clone, Default, schemars::JsonSchema, MergeRight,
 )]
@@ -116,15 +260,131 @@ pub enum HttpVersion {
     HTTP2,
 }
 
+#[derive(
+    Deserialize,
+    Serialize,
+    Debug,
+    PartialEq,
+    Eq,
+    Clone,
+    Copy,
+    Default,
+    schemars::JsonSchema,
+    MergeRight,
+)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Playground {
+    GraphiQL,
+    Sandbox,
+    #[default]
+    None,
+}
+
+#[derive(
+    Deserialize,
+    Serialize,
+    Debug,
+    PartialEq,
+    Eq,
+    Clone,
+    Copy,
+    Default,
+    schemars::JsonSchema,
+    MergeRight,
+)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum ResponseValidation {
+    Strict,
+    Warn,
+    #[default]
+    Off,
+}
+
+#[derive(
+    Deserialize,
+    Serialize,
+    Debug,
+    PartialEq,
+    Eq,
+    Clone,
+    Copy,
+    Default,
+    schemars::JsonSchema,
+    MergeRight,
+)]
+pub enum ErrorFormat {
+    #[default]
+    #[serde(rename = "GRAPHQL")]
+    GraphQL,
+    #[serde(rename = "PROBLEM_JSON")]
+    ProblemJson,
+}
+
+impl ResponseValidation {
+    pub fn is_enabled(&self) -> bool {
+        !matches!(self, ResponseValidation::Off)
+    }
+}
+
+fn deserialize_response_validation<'de, D>(
+    deserializer: D,
+) -> Result<Option<ResponseValidation>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum ModeOrBool {
+        Mode(ResponseValidation),
+        Bool(bool),
+    }
+
+    Ok(
+        Option::<ModeOrBool>::deserialize(deserializer)?.map(|value| match value {
+            ModeOrBool::Mode(mode) => mode,
+            ModeOrBool::Bool(true) => ResponseValidation::Strict,
+            ModeOrBool::Bool(false) => ResponseValidation::Off,
+        }),
+    )
+}
+
 impl Server {
+    pub fn get_allowed_operations(&self) -> Option<BTreeSet<String>> {
+        self.allowed_operations
+            .as_ref()
+            .map(|operations| operations.iter().cloned().collect())
+    }
+
     pub fn enable_apollo_tracing(&self) -> bool {
         self.apollo_tracing.unwrap_or(false)
     }
 
+    pub fn enable_include_stats(&self) -> bool {
+        self.include_stats.unwrap_or(false)
+    }
+
     pub fn get_global_response_timeout(&self) -> i64 {
         self.global_response_timeout.unwrap_or(0)
     }
 
+    pub fn get_graphql_path(&self) -> String {
+        self.graphql_path
+            .clone()
+            .unwrap_or_else(|| "/graphql".to_string())
+    }
+
+    pub fn get_max_batch_size(&self) -> usize {
+        self.max_batch_size.unwrap_or(100)
+    }
+
+    pub fn get_max_upload_size(&self) -> usize {
+        self.max_upload_size.unwrap_or(10 * 1024 * 1024)
+    }
+
+    pub fn get_request_timeout(&self) -> Option<u64> {
+        self.request_timeout
+    }
+
     pub fn get_workers(&self) -> usize {
         self.workers.unwrap_or(num_cpus::get())
     }
@@ -132,8 +392,8 @@ impl Server {
     pub fn get_port(&self) -> u16 {
         self.port.unwrap_or(8000)
     }
-    pub fn enable_http_validation(&self) -> bool {
-        self.response_validation.unwrap_or(false)
+    pub fn get_response_validation(&self) -> ResponseValidation {
+        self.response_validation.unwrap_or_default()
     }
     pub fn enable_cache_control(&self) -> bool {
         self.headers
@@ -159,6 +419,22 @@ impl Server {
     pub fn enable_showcase(&self) -> bool {
         self.showcase.unwrap_or(false)
     }
+    pub fn get_error_format(&self) -> ErrorFormat {
+        self.error_format.unwrap_or_default()
+    }
+
+    pub fn get_playground(&self) -> Playground {
+        self.playground.unwrap_or(match self.graphiql {
+            Some(true) => Playground::GraphiQL,
+            _ => Playground::None,
+        })
+    }
+
+    pub fn get_profile(&self) -> Option<&str> {
+        self.profile
+            .as_deref()
+            .filter(|profile| !profile.is_empty())
+    }
 
     pub fn get_hostname(&self) -> String {
         self.hostname.clone().unwrap_or("127.0.0.1".to_string())
@@ -172,6 +448,13 @@ impl Server {
             .collect()
     }
 
+    pub fn get_context(&self) -> BTreeMap<String, String> {
+        self.context
+            .iter()
+            .map(|kv| (kv.key.clone(), kv.value.clone()))
+            .collect()
+    }
+
     pub fn get_response_headers(&self) -> Vec<(String, String)> {
         self.headers
             .as_ref()
@@ -191,6 +474,10 @@ impl Server {
             .unwrap_or_default()
     }
 
+    pub fn get_strip_headers(&self) -> BTreeSet<String> {
+        self.strip_headers.clone().unwrap_or_default()
+    }
+
     pub fn get_version(self) -> HttpVersion {
         self.version.unwrap_or(HttpVersion::HTTP1)
     }
@@ -198,6 +485,10 @@ impl Server {
     pub fn get_pipeline_flush(&self) -> bool {
         self.pipeline_flush.unwrap_or(true)
     }
+
+    pub fn enable_watch(&self) -> bool {
+        self.watch.unwrap_or(false)
+    }
 }
 
 #[cfg(test)]
@@ -254,6 +545,42 @@ mod tests {
         assert_eq!(merged.script, Some(expected));
     }
 
+    #[test]
+    fn response_validation_from_bool() {
+        let parse = |value: serde_json::Value| {
+            serde_json::from_value::<Server>(serde_json::json!({ "responseValidation": value }))
+                .unwrap()
+                .get_response_validation()
+        };
+
+        assert_eq!(parse(serde_json::json!(true)), ResponseValidation::Strict);
+        assert_eq!(parse(serde_json::json!(false)), ResponseValidation::Off);
+        assert_eq!(parse(serde_json::json!("WARN")), ResponseValidation::Warn);
+    }
+
+    #[test]
+    fn playground_from_graphiql() {
+        let parse = |value: serde_json::Value| {
+            serde_json::from_value::<Server>(value)
+                .unwrap()
+                .get_playground()
+        };
+
+        assert_eq!(parse(serde_json::json!({})), Playground::None);
+        assert_eq!(
+            parse(serde_json::json!({ "graphiql": true })),
+            Playground::GraphiQL
+        );
+        assert_eq!(
+            parse(serde_json::json!({ "playground": "SANDBOX" })),
+            Playground::Sandbox
+        );
+        assert_eq!(
+            parse(serde_json::json!({ "graphiql": true, "playground": "NONE" })),
+            Playground::None
+        );
+    }
+
     fn get_default_left_vec() -> Vec<KeyValue> {
         [
             KeyValue { key: "left".to_string(), value: "From Left".to_string() },
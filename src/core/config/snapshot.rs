@@ -0,0 +1,323 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail};
+use jsonwebtoken::jwk::JwkSet;
+use prost::Message;
+use prost_reflect::prost_types::FileDescriptorProto;
+use rustls_pki_types::{
+    CertificateDer, PrivateKeyDer, PrivatePkcs1KeyDer, PrivatePkcs8KeyDer, PrivateSec1KeyDer,
+};
+use serde::{Deserialize, Serialize};
+
+use super::{Config, ConfigModule, Content, Extensions};
+use crate::core::graphql::IntrospectionResult;
+use crate::core::rest::EndpointSet;
+
+/// Snapshots written by another version of tailcall are rejected, the config
+/// and the way it's compiled might have changed in between.
+const VERSION: &str = match option_env!("APP_VERSION") {
+    Some(version) => version,
+    None => env!("CARGO_PKG_VERSION"),
+};
+
+/// A ConfigModule with all of its links resolved. Loading it skips the parsing
+/// of the config files and the IO needed to resolve the links, eg. reading the
+/// proto files or introspecting the GraphQL upstreams.
+#[derive(Serialize, Deserialize)]
+struct Snapshot {
+    version: String,
+    /// Identifies the config files the snapshot was built from, eg. a hash of
+    /// their paths and contents
+    source: String,
+    config: Config,
+    extensions: ExtensionsSnapshot,
+    input_types: HashSet<String>,
+    output_types: HashSet<String>,
+    interface_types: HashSet<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ExtensionsSnapshot {
+    /// Encoded file descriptors by name
+    grpc_file_descriptors: HashMap<String, Vec<u8>>,
+    script: Option<String>,
+    cert: Vec<Vec<u8>>,
+    keys: Vec<KeySnapshot>,
+    /// The operations the endpoints are parsed from
+    operations: Vec<String>,
+    htpasswd: Vec<(Option<String>, String)>,
+    jwks: Vec<(Option<String>, JwkSet)>,
+    graphql_introspections: Vec<IntrospectionResult>,
+}
+
+#[derive(Serialize, Deserialize)]
+enum KeySnapshot {
+    Pkcs1(Vec<u8>),
+    Sec1(Vec<u8>),
+    Pkcs8(Vec<u8>),
+}
+
+impl TryFrom<&Extensions> for ExtensionsSnapshot {
+    type Error = anyhow::Error;
+
+    fn try_from(extensions: &Extensions) -> anyhow::Result<Self> {
+        let keys = extensions
+            .keys
+            .iter()
+            .map(|key| match key {
+                PrivateKeyDer::Pkcs1(key) => {
+                    Ok(KeySnapshot::Pkcs1(key.secret_pkcs1_der().to_vec()))
+                }
+                PrivateKeyDer::Sec1(key) => Ok(KeySnapshot::Sec1(key.secret_sec1_der().to_vec())),
+                PrivateKeyDer::Pkcs8(key) => {
+                    Ok(KeySnapshot::Pkcs8(key.secret_pkcs8_der().to_vec()))
+                }
+                _ => Err(anyhow!("Unsupported private key format")),
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        Ok(ExtensionsSnapshot {
+            grpc_file_descriptors: extensions
+                .grpc_file_descriptors
+                .iter()
+                .map(|(name, file)| (name.clone(), file.encode_to_vec()))
+                .collect(),
+            script: extensions.script.clone(),
+            cert: extensions.cert.iter().map(|cert| cert.to_vec()).collect(),
+            keys,
+            operations: extensions.endpoint_set.get_sources().clone(),
+            htpasswd: extensions
+                .htpasswd
+                .iter()
+                .map(|content| (content.id.clone(), content.content.clone()))
+                .collect(),
+            jwks: extensions
+                .jwks
+                .iter()
+                .map(|content| (content.id.clone(), content.content.clone()))
+                .collect(),
+            graphql_introspections: extensions.graphql_introspections.clone(),
+        })
+    }
+}
+
+impl TryFrom<ExtensionsSnapshot> for Extensions {
+    type Error = anyhow::Error;
+
+    fn try_from(snapshot: ExtensionsSnapshot) -> anyhow::Result<Self> {
+        let mut endpoint_set = EndpointSet::default();
+        for operations in snapshot.operations.iter() {
+            endpoint_set.extend(EndpointSet::try_new(operations)?);
+        }
+
+        Ok(Extensions {
+            grpc_file_descriptors: snapshot
+                .grpc_file_descriptors
+                .into_iter()
+                .map(|(name, file)| Ok((name, FileDescriptorProto::decode(file.as_slice())?)))
+                .collect::<anyhow::Result<_>>()?,
+            script: snapshot.script,
+            cert: snapshot
+                .cert
+                .into_iter()
+                .map(CertificateDer::from)
+                .collect(),
+            keys: Arc::new(
+                snapshot
+                    .keys
+                    .into_iter()
+                    .map(|key| match key {
+                        KeySnapshot::Pkcs1(key) => {
+                            PrivateKeyDer::Pkcs1(PrivatePkcs1KeyDer::from(key))
+                        }
+                        KeySnapshot::Sec1(key) => PrivateKeyDer::Sec1(PrivateSec1KeyDer::from(key)),
+                        KeySnapshot::Pkcs8(key) => {
+                            PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(key))
+                        }
+                    })
+                    .collect(),
+            ),
+            endpoint_set,
+            htpasswd: snapshot
+                .htpasswd
+                .into_iter()
+                .map(|(id, content)| Content { id, content })
+                .collect(),
+            jwks: snapshot
+                .jwks
+                .into_iter()
+                .map(|(id, content)| Content { id, content })
+                .collect(),
+            graphql_introspections: snapshot.graphql_introspections,
+        })
+    }
+}
+
+impl ConfigModule {
+    /// Serializes the resolved config module, `source` identifies the config
+    /// files it was read from. The snapshot contains the private keys, the
+    /// htpasswd and the JWKS linked in the config, if any.
+    pub fn to_bytes(&self, source: &str) -> anyhow::Result<Vec<u8>> {
+        let snapshot = Snapshot {
+            version: VERSION.to_string(),
+            source: source.to_string(),
+            config: self.config.clone(),
+            extensions: ExtensionsSnapshot::try_from(&self.extensions)?,
+            input_types: self.input_types.clone(),
+            output_types: self.output_types.clone(),
+            interface_types: self.interface_types.clone(),
+        };
+
+        Ok(serde_json::to_vec(&snapshot)?)
+    }
+
+    /// Reads a config module written by [ConfigModule::to_bytes], failing on
+    /// the snapshots written by another version of tailcall or from other
+    /// config files than `source`.
+    pub fn from_bytes(bytes: &[u8], source: &str) -> anyhow::Result<ConfigModule> {
+        #[derive(Deserialize)]
+        struct Header {
+            version: String,
+            source: String,
+        }
+
+        // The header is checked first, the rest of an incompatible snapshot
+        // might not deserialize at all
+        let header: Header = serde_json::from_slice(bytes)?;
+        if header.version != VERSION {
+            bail!(
+                "Snapshot was written by tailcall {} and can't be loaded by {}",
+                header.version,
+                VERSION
+            );
+        }
+        if header.source != source {
+            bail!("Snapshot was written from other config files");
+        }
+
+        let snapshot: Snapshot = serde_json::from_slice(bytes)?;
+
+        Ok(ConfigModule {
+            config: snapshot.config,
+            extensions: Extensions::try_from(snapshot.extensions)?,
+            input_types: snapshot.input_types,
+            output_types: snapshot.output_types,
+            interface_types: snapshot.interface_types,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::{Body, Request};
+    use serde_json::json;
+
+    use super::*;
+    use crate::core::app_context::AppContext;
+    use crate::core::async_graphql_hyper::GraphQLRequest;
+    use crate::core::blueprint::Blueprint;
+    use crate::core::http::handle_request;
+    use crate::core::valid::Validator;
+
+    const CONFIG: &str = r#"
+        schema {
+          query: Query
+        }
+
+        type Query {
+          user(id: Int!): User @expr(body: {name: "Leanne Graham"})
+        }
+
+        type User {
+          id: Int
+          name: String
+        }
+    "#;
+
+    const SOURCE: &str = "config.graphql";
+
+    const OPERATIONS: &str = r#"
+        query ($id: Int!) @rest(method: GET, path: "/user/$id") {
+          user(id: $id) {
+            name
+          }
+        }
+    "#;
+
+    fn config_module() -> ConfigModule {
+        let config = Config::from_sdl(CONFIG).to_result().unwrap();
+        let mut config_module = ConfigModule::from(config);
+        config_module.extensions.endpoint_set = EndpointSet::try_new(OPERATIONS).unwrap();
+        config_module.extensions.script = Some("function foo() {}".to_string());
+        config_module
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let config_module = config_module();
+        let bytes = config_module.to_bytes(SOURCE).unwrap();
+        let actual = ConfigModule::from_bytes(&bytes, SOURCE).unwrap();
+
+        assert_eq!(actual.config, config_module.config);
+        assert_eq!(actual.extensions.script, config_module.extensions.script);
+        assert_eq!(actual.input_types, config_module.input_types);
+        assert_eq!(actual.output_types, config_module.output_types);
+        assert_eq!(
+            actual.extensions.endpoint_set.get_sources(),
+            config_module.extensions.endpoint_set.get_sources()
+        );
+        assert_eq!(actual.extensions.endpoint_set.get_endpoints().len(), 1);
+    }
+
+    #[test]
+    fn test_version_mismatch() {
+        let bytes = config_module().to_bytes(SOURCE).unwrap();
+        let mut snapshot: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        snapshot["version"] = json!("0.0.0");
+        let bytes = serde_json::to_vec(&snapshot).unwrap();
+
+        let error = ConfigModule::from_bytes(&bytes, SOURCE).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "Snapshot was written by tailcall 0.0.0 and can't be loaded by {}",
+                VERSION
+            )
+        );
+    }
+
+    #[test]
+    fn test_source_mismatch() {
+        let bytes = config_module().to_bytes(SOURCE).unwrap();
+
+        let error = ConfigModule::from_bytes(&bytes, "other").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Snapshot was written from other config files"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_serve_from_snapshot() {
+        let bytes = config_module().to_bytes(SOURCE).unwrap();
+        let blueprint = Blueprint::from_bytes(&bytes, SOURCE).unwrap();
+        let runtime = crate::core::runtime::test::init(None);
+        let app_ctx =
+            std::sync::Arc::new(AppContext::new(blueprint, runtime, EndpointSet::default()));
+
+        let query = json!({"query": "{ user(id: 1) { name } }"});
+        let req = Request::builder()
+            .method("POST")
+            .uri("http://localhost:8000/graphql")
+            .body(Body::from(query.to_string()))
+            .unwrap();
+        let res = handle_request::<GraphQLRequest>(req, app_ctx)
+            .await
+            .unwrap();
+
+        let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body, json!({"data": {"user": {"name": "Leanne Graham"}}}));
+    }
+}
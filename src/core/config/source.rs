@@ -20,6 +20,79 @@ const ALL: [Source; 3] = [Source::Json, Source::Yml, Source::GraphQL];
 #[error("Unsupported config extension: {0}")]
 pub struct UnsupportedConfigFormat(pub String);
 
+/// A syntax error in a config, located by its line and column. Only the
+/// syntax errors of GraphQL configs are located, the validation errors are
+/// located by their trace in the config instead eg. `[Query, users, @http]`.
+#[derive(Debug, Error, PartialEq, Clone)]
+#[error("{message}")]
+pub struct SyntaxError {
+    pub message: String,
+    /// The path of the config, it's set by the reader of the config
+    pub path: Option<String>,
+    pub line: usize,
+    pub column: usize,
+    /// The line of the config the error is on
+    pub source_line: String,
+}
+
+impl SyntaxError {
+    pub fn new(message: &str, content: &str, line: usize, column: usize) -> Self {
+        let source_line = content
+            .lines()
+            .nth(line.saturating_sub(1))
+            .unwrap_or_default()
+            .to_string();
+
+        SyntaxError {
+            message: message.to_string(),
+            path: None,
+            line,
+            column,
+            source_line,
+        }
+    }
+
+    pub fn from_parser(error: &async_graphql::parser::Error, content: &str) -> Self {
+        let message = error.to_string();
+        // Syntax errors are formatted by pest with their own snippet, followed
+        // by the actual message eg. `= expected name`
+        let message = message
+            .lines()
+            .rev()
+            .find_map(|line| line.trim_start().strip_prefix("= "))
+            .unwrap_or(&message);
+        let (line, column) = error
+            .positions()
+            .next()
+            .map(|pos| (pos.line, pos.column))
+            .unwrap_or((1, 1));
+
+        SyntaxError::new(message, content, line, column)
+    }
+
+    pub fn path(mut self, path: &str) -> Self {
+        self.path = Some(path.to_string());
+        self
+    }
+
+    /// Renders the line of the error with a caret under its column, like rustc
+    /// does.
+    pub fn snippet(&self) -> String {
+        let gutter = " ".repeat(self.line.to_string().len());
+        let location = match &self.path {
+            Some(path) => format!("{}:{}:{}", path, self.line, self.column),
+            None => format!("{}:{}", self.line, self.column),
+        };
+
+        format!(
+            "{gutter}--> {location}\n{gutter} |\n{line} | {source}\n{gutter} | {caret}^",
+            line = self.line,
+            source = self.source_line,
+            caret = " ".repeat(self.column.saturating_sub(1)),
+        )
+    }
+}
+
 impl std::str::FromStr for Source {
     type Err = UnsupportedConfigFormat;
 
@@ -74,3 +147,28 @@ impl Source {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_syntax_error() {
+        let sdl =
+            "schema {\n  query: Query\n}\n\ntype Query {\n  foo: String @http(path: \"/foo\"\n}\n";
+        let error = Config::from_source(Source::GraphQL, sdl)
+            .unwrap_err()
+            .downcast::<SyntaxError>()
+            .unwrap()
+            .path("config.graphql");
+
+        assert_eq!(error.line, 7);
+        assert_eq!(error.column, 1);
+        assert_eq!(
+            error.snippet(),
+            " --> config.graphql:7:1\n  |\n7 | }\n  | ^"
+        );
+    }
+}
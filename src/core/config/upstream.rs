@@ -1,4 +1,5 @@
 use std::collections::BTreeSet;
+use std::fmt::Display;
 
 use derive_setters::Setters;
 use serde::{Deserialize, Serialize};
@@ -14,6 +15,12 @@ const DEFAULT_MAX_SIZE: usize = 100;
 )]
 #[serde(rename_all = "camelCase", default)]
 pub struct Batch {
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// When enabled, every `@graphQL` field is batched and so is every `@http`
+    /// field whose `batchKey` can be inferred, ie. a `GET` request with a
+    /// single query parameter mapped to a field of the parent value. Fields
+    /// can opt out with `batch: false`.
+    pub by_default: bool,
     pub delay: usize,
     pub headers: BTreeSet<String>,
     #[serde(default, skip_serializing_if = "is_default")]
@@ -22,6 +29,7 @@ pub struct Batch {
 impl Default for Batch {
     fn default() -> Self {
         Batch {
+            by_default: false,
             max_size: Some(DEFAULT_MAX_SIZE),
             delay: 0,
             headers: BTreeSet::new(),
@@ -29,11 +37,123 @@ impl Default for Batch {
     }
 }
 
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug, schemars::JsonSchema, MergeRight)]
+#[serde(rename_all = "camelCase")]
+pub struct CircuitBreaker {
+    /// The time in milliseconds requests to a host with an open circuit fail
+    /// without being sent. After it, a single trial request is let through.
+    pub cooldown: u64,
+    /// The number of consecutive failed requests after which the circuit of a
+    /// host is opened.
+    pub failure_threshold: usize,
+}
+
+#[derive(
+    Serialize, Deserialize, PartialEq, Eq, Clone, Debug, Default, schemars::JsonSchema, MergeRight,
+)]
+#[serde(rename_all = "camelCase")]
+pub struct GrpcPool {
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// The maximum number of idle channels kept per gRPC host. @default
+    /// `poolMaxIdlePerHost`.
+    pub max_idle_per_host: Option<usize>,
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// The time in seconds an idle channel is kept before being closed.
+    /// @default `poolIdleTimeout`.
+    pub idle_timeout: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug, schemars::JsonSchema, MergeRight)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthCheck {
+    /// The path requested on every host of a `baseURLs` pool, eg. `/health`.
+    /// A host is healthy when it responds with a 2xx status.
+    pub path: String,
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// The time in milliseconds between two rounds of probes. @default
+    /// `10000`.
+    pub interval: Option<u64>,
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// The number of consecutive failed probes after which a host is removed
+    /// from rotation. @default `3`.
+    pub unhealthy_threshold: Option<usize>,
+}
+
+impl HealthCheck {
+    pub fn get_interval(&self) -> u64 {
+        self.interval.unwrap_or(10000)
+    }
+
+    pub fn get_unhealthy_threshold(&self) -> usize {
+        self.unhealthy_threshold.unwrap_or(3)
+    }
+}
+
 #[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug, schemars::JsonSchema, MergeRight)]
 pub struct Proxy {
     pub url: String,
 }
 
+#[derive(
+    Serialize,
+    Deserialize,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Clone,
+    Copy,
+    Debug,
+    schemars::JsonSchema,
+    MergeRight,
+)]
+/// The compression algorithms that can be used by the upstream to encode the
+/// response body.
+pub enum ContentEncoding {
+    Gzip,
+    Deflate,
+    Brotli,
+}
+
+impl ContentEncoding {
+    /// The token used for the encoding in the `Accept-Encoding` header
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ContentEncoding::Gzip => "gzip",
+            ContentEncoding::Deflate => "deflate",
+            ContentEncoding::Brotli => "br",
+        }
+    }
+}
+
+#[derive(
+    Serialize,
+    Deserialize,
+    PartialEq,
+    Eq,
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    schemars::JsonSchema,
+    MergeRight,
+)]
+/// The TLS versions supported for the connections to the upstream.
+pub enum TlsVersion {
+    #[default]
+    TLSv1_2,
+    TLSv1_3,
+}
+
+impl Display for TlsVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TlsVersion::TLSv1_2 => f.write_str("TLS 1.2"),
+            TlsVersion::TLSv1_3 => f.write_str("TLS 1.3"),
+        }
+    }
+}
+
 #[derive(
     Serialize,
     Deserialize,
@@ -57,12 +177,24 @@ pub struct Upstream {
     /// interception handler.
     pub on_request: Option<String>,
 
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// `acceptEncoding` lists the compression algorithms advertised to the
+    /// upstream in the `Accept-Encoding` header. Compressed responses are
+    /// decoded before they are parsed.
+    pub accept_encoding: Option<BTreeSet<ContentEncoding>>,
+
     #[serde(default, skip_serializing_if = "is_default")]
     /// `allowedHeaders` defines the HTTP headers allowed to be forwarded to
     /// upstream services. If not set, no headers are forwarded, enhancing
     /// security but possibly limiting data flow.
     pub allowed_headers: Option<BTreeSet<String>>,
 
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// `allowedHosts` restricts the hosts a templated `baseURL` can render
+    /// to, eg. `["*.api.example.com"]`. A leading `*.` matches any
+    /// subdomain. If not set, every host is allowed.
+    pub allowed_hosts: Option<BTreeSet<String>>,
+
     #[serde(rename = "baseURL", default, skip_serializing_if = "is_default")]
     /// This refers to the default base URL for your APIs. If it's not
     /// explicitly mentioned in the `@upstream` operator, then each
@@ -78,11 +210,30 @@ pub struct Upstream {
     /// the batch).
     pub batch: Option<Batch>,
 
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// `circuitBreaker` stops sending requests to an upstream host that fails
+    /// `failureThreshold` times in a row. Requests to that host fail right
+    /// away for the `cooldown` window, after which the circuit is half-opened
+    /// and a single trial request decides whether it's closed again.
+    pub circuit_breaker: Option<CircuitBreaker>,
+
     #[serde(default, skip_serializing_if = "is_default")]
     /// The time in seconds that the connection will wait for a response before
     /// timing out.
     pub connect_timeout: Option<u64>,
 
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// `grpcPool` configures the pool of the channels to the `@grpc`
+    /// upstreams. The channels are shared by the concurrent calls to the same
+    /// host and reused until they're idle for `idleTimeout`.
+    pub grpc_pool: Option<GrpcPool>,
+
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// `healthCheck` periodically probes every host of the `@http(baseURLs:)`
+    /// pools. Hosts failing `unhealthyThreshold` probes in a row are removed
+    /// from rotation until a probe succeeds again.
+    pub health_check: Option<HealthCheck>,
+
     #[serde(default, skip_serializing_if = "is_default")]
     /// Providing httpCache size enables Tailcall's HTTP caching, adhering to the [HTTP Caching RFC](https://tools.ietf.org/html/rfc7234), to enhance performance by minimizing redundant data fetches. Defaults to `0` if unspecified.
     pub http_cache: Option<u64>,
@@ -95,6 +246,13 @@ pub struct Upstream {
     /// the server, but is automatically set to true for GRPC.
     pub http2_only: Option<bool>,
 
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// When set to `false` the connections to the upstream are closed after
+    /// every request instead of being kept in the pool, and no keep-alive
+    /// messages are sent. Useful for upstreams that don't handle keep-alive
+    /// well. @default `true`.
+    pub keep_alive: Option<bool>,
+
     #[serde(default, skip_serializing_if = "is_default")]
     /// The time in seconds between each keep-alive message sent to maintain the
     /// connection.
@@ -110,6 +268,19 @@ pub struct Upstream {
     /// sent while the connection is idle.
     pub keep_alive_while_idle: Option<bool>,
 
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// `maxConcurrency` limits the number of calls in flight to every upstream
+    /// host, the other calls wait for one of them to complete. It protects
+    /// the upstreams from the fan-out of large batched requests. By default
+    /// there's no limit.
+    pub max_concurrency: Option<usize>,
+
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// `minTls` is the lowest TLS version accepted for the connections to the
+    /// upstream, connecting to an upstream that doesn't support it fails.
+    /// @default `TLSv1_2`.
+    pub min_tls: Option<TlsVersion>,
+
     #[serde(default, skip_serializing_if = "is_default")]
     /// The maximum number of idle connections that will be maintained per host.
     pub pool_max_idle_per_host: Option<usize>,
@@ -145,6 +316,14 @@ pub struct Upstream {
     /// When set to `true`, it will ensure no HTTP, GRPC, or any other IO call
     /// is made more than once within the context of a single GraphQL request.
     pub dedupe: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// The time in milliseconds the response of an upstream `GET` request is
+    /// shared with the identical requests of every GraphQL request served.
+    /// Concurrent identical requests are coalesced into a single upstream
+    /// call. Other methods are never shared. By default responses are only
+    /// shared within a GraphQL request, with `dedupe`.
+    pub dedupe_window: Option<u64>,
 }
 
 impl Upstream {
@@ -154,6 +333,9 @@ impl Upstream {
     pub fn get_pool_max_idle_per_host(&self) -> usize {
         self.pool_max_idle_per_host.unwrap_or(60)
     }
+    pub fn get_keep_alive(&self) -> bool {
+        self.keep_alive.unwrap_or(true)
+    }
     pub fn get_keep_alive_interval(&self) -> u64 {
         self.keep_alive_interval.unwrap_or(60)
     }
@@ -183,10 +365,17 @@ impl Upstream {
     pub fn get_allowed_headers(&self) -> BTreeSet<String> {
         self.allowed_headers.clone().unwrap_or_default()
     }
+    pub fn get_allowed_hosts(&self) -> BTreeSet<String> {
+        self.allowed_hosts.clone().unwrap_or_default()
+    }
     pub fn get_delay(&self) -> usize {
         self.batch.clone().unwrap_or_default().delay
     }
 
+    pub fn get_batch_by_default(&self) -> bool {
+        self.batch.as_ref().is_some_and(|batch| batch.by_default)
+    }
+
     pub fn get_max_size(&self) -> usize {
         self.batch
             .as_ref()
@@ -204,6 +393,20 @@ impl Upstream {
     pub fn get_on_request(&self) -> Option<String> {
         self.on_request.clone()
     }
+
+    pub fn get_min_tls(&self) -> TlsVersion {
+        self.min_tls.unwrap_or_default()
+    }
+
+    pub fn get_accept_encoding(&self) -> Option<String> {
+        self.accept_encoding.as_ref().map(|encodings| {
+            encodings
+                .iter()
+                .map(ContentEncoding::as_str)
+                .collect::<Vec<_>>()
+                .join(", ")
+        })
+    }
 }
 
 #[cfg(test)]
@@ -18,6 +18,46 @@ fn pos<A>(a: A) -> Positioned<A> {
     Positioned::new(a, Pos::default())
 }
 
+const DIRECTIVE_LOCATIONS: [(DirectiveLocation, &str); 19] = [
+    (DirectiveLocation::Query, "QUERY"),
+    (DirectiveLocation::Mutation, "MUTATION"),
+    (DirectiveLocation::Subscription, "SUBSCRIPTION"),
+    (DirectiveLocation::Field, "FIELD"),
+    (DirectiveLocation::FragmentDefinition, "FRAGMENT_DEFINITION"),
+    (DirectiveLocation::FragmentSpread, "FRAGMENT_SPREAD"),
+    (DirectiveLocation::InlineFragment, "INLINE_FRAGMENT"),
+    (DirectiveLocation::VariableDefinition, "VARIABLE_DEFINITION"),
+    (DirectiveLocation::Schema, "SCHEMA"),
+    (DirectiveLocation::Scalar, "SCALAR"),
+    (DirectiveLocation::Object, "OBJECT"),
+    (DirectiveLocation::FieldDefinition, "FIELD_DEFINITION"),
+    (DirectiveLocation::ArgumentDefinition, "ARGUMENT_DEFINITION"),
+    (DirectiveLocation::Interface, "INTERFACE"),
+    (DirectiveLocation::Union, "UNION"),
+    (DirectiveLocation::Enum, "ENUM"),
+    (DirectiveLocation::EnumValue, "ENUM_VALUE"),
+    (DirectiveLocation::InputObject, "INPUT_OBJECT"),
+    (
+        DirectiveLocation::InputFieldDefinition,
+        "INPUT_FIELD_DEFINITION",
+    ),
+];
+
+/// The name of the location as written in the SDL eg. `FIELD_DEFINITION`
+pub fn directive_location_name(location: &DirectiveLocation) -> &'static str {
+    DIRECTIVE_LOCATIONS
+        .iter()
+        .find_map(|(value, name)| (value == location).then_some(*name))
+        .unwrap_or_default()
+}
+
+/// Parses the name of a location as written in the SDL
+pub fn directive_location(name: &str) -> Option<DirectiveLocation> {
+    DIRECTIVE_LOCATIONS
+        .iter()
+        .find_map(|(location, location_name)| (*location_name == name).then_some(*location))
+}
+
 fn print_schema(schema: &SchemaDefinition) -> String {
     let directives = print_directives(&schema.directives);
 
@@ -239,10 +279,78 @@ fn print_directive(directive: &DirectiveDefinition) -> String {
         format!("@{}({})", directive.name.node, args)
     }
 }
+
+fn print_directive_definition(directive: &DirectiveDefinition) -> String {
+    let doc = directive.description.as_ref().map_or(String::new(), |d| {
+        format!(r#""""{}{}{}"""{}"#, "\n", d.node, "\n", "\n")
+    });
+    let args = if directive.arguments.is_empty() {
+        String::new()
+    } else {
+        let args = directive
+            .arguments
+            .iter()
+            .map(|arg| {
+                let default_value = arg
+                    .node
+                    .default_value
+                    .as_ref()
+                    .map_or(String::new(), |value| format!(" = {}", value.node));
+                format!(
+                    "{}: {}{}",
+                    arg.node.name.node, arg.node.ty.node, default_value
+                )
+            })
+            .collect::<Vec<String>>()
+            .join(", ");
+        format!("({})", args)
+    };
+    let repeatable = if directive.is_repeatable {
+        " repeatable"
+    } else {
+        ""
+    };
+    let locations = directive
+        .locations
+        .iter()
+        .map(|location| directive_location_name(&location.node))
+        .collect::<Vec<_>>()
+        .join(" | ");
+
+    format!(
+        "{}directive @{}{}{} on {}\n",
+        doc, directive.name.node, args, repeatable, locations
+    )
+}
+
 pub fn print(sd: ServiceDocument) -> String {
+    print_with_order(sd, false)
+}
+
+/// Prints the document. By default the definitions are grouped by their kind,
+/// when `preserve_order` is set they're printed in the order of the document.
+pub fn print_with_order(sd: ServiceDocument, preserve_order: bool) -> String {
+    if preserve_order {
+        let sdl_string = sd
+            .definitions
+            .iter()
+            .map(|def| match def {
+                TypeSystemDefinition::Schema(schema) => print_schema(&schema.node),
+                TypeSystemDefinition::Type(type_def) => print_type_def(&type_def.node),
+                TypeSystemDefinition::Directive(directive) => {
+                    print_directive_definition(&directive.node)
+                }
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        return sdl_string.trim_end_matches('\n').to_string();
+    }
+
     // Separate the definitions by type
     let definitions_len = sd.definitions.len();
     let mut schemas = Vec::with_capacity(definitions_len);
+    let mut directives = Vec::with_capacity(definitions_len);
     let mut scalars = Vec::with_capacity(definitions_len);
     let mut interfaces = Vec::with_capacity(definitions_len);
     let mut objects = Vec::with_capacity(definitions_len);
@@ -261,13 +369,16 @@ pub fn print(sd: ServiceDocument) -> String {
                 TypeKind::Union(_) => unions.push(print_type_def(&type_def.node)),
                 TypeKind::InputObject(_) => inputs.push(print_type_def(&type_def.node)),
             },
-            TypeSystemDefinition::Directive(_) => todo!("Directives are not supported yet"),
+            TypeSystemDefinition::Directive(directive) => {
+                directives.push(print_directive_definition(&directive.node))
+            }
         }
     }
 
     // Concatenate the definitions in the desired order
     let sdl_string = schemas
         .into_iter()
+        .chain(directives)
         .chain(scalars)
         .chain(inputs)
         .chain(interfaces)
@@ -1,9 +1,12 @@
+use std::collections::BTreeMap;
+
 use derive_setters::Setters;
 use hyper::HeaderMap;
 
 use crate::core::config::Encoding;
 use crate::core::http::Method;
 use crate::core::json::JsonSchema;
+use crate::core::mustache::Mustache;
 
 #[derive(Clone, Debug, Setters)]
 pub struct Endpoint {
@@ -16,6 +19,8 @@ pub struct Endpoint {
     pub body: Option<String>,
     pub description: Option<String>,
     pub encoding: Encoding,
+    pub expect_status: Vec<u16>,
+    pub header_map: BTreeMap<String, Mustache>,
 }
 
 impl Endpoint {
@@ -30,6 +35,8 @@ impl Endpoint {
             body: Default::default(),
             description: Default::default(),
             encoding: Default::default(),
+            expect_status: Default::default(),
+            header_map: Default::default(),
         }
     }
 }
@@ -200,7 +200,7 @@ impl Context {
                 ty.fields.insert(field_name.to_string(), cfg_field);
             }
 
-            ty.tag = Some(Tag { id: msg_type.id() });
+            ty.tag = Some(Tag { id: msg_type.id(), ..Default::default() });
 
             self = self.insert_type(msg_type.to_string(), ty);
         }
@@ -257,9 +257,14 @@ impl Context {
                 cfg_field.grpc = Some(Grpc {
                     base_url: None,
                     body,
+                    ca_cert: None,
+                    client_cert: None,
+                    client_key: None,
                     group_by: vec![],
                     headers: vec![],
                     method: field_name.id(),
+                    path: vec![],
+                    tls: None,
                 });
 
                 let method_path =
@@ -66,7 +66,12 @@ impl Generator {
 
                 let mut config = Config::default();
                 for metadata in proto_metadata {
-                    links.push(Link { id: None, src: metadata.path, type_of: LinkType::Protobuf });
+                    links.push(Link {
+                        id: None,
+                        src: metadata.path,
+                        type_of: LinkType::Protobuf,
+                        import: vec![],
+                    });
                     let descriptor_set = resolve_file_descriptor_set(metadata.descriptor_set)?;
                     config = config.merge_right(from_proto(&[descriptor_set], query)?);
                 }
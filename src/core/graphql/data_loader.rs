@@ -5,20 +5,29 @@ use std::time::Duration;
 
 use async_graphql::async_trait;
 use async_graphql::futures_util::future::join_all;
+use async_graphql_value::{ConstValue, Name};
+use indexmap::IndexMap;
 
 use crate::core::config::Batch;
 use crate::core::data_loader::{DataLoader, Loader};
-use crate::core::http::{DataLoaderRequest, Response};
+use crate::core::http::{DataLoaderRequest, RequestStats, Response};
+use crate::core::json::JsonLike;
 use crate::core::runtime::TargetRuntime;
 
 pub struct GraphqlDataLoader {
     pub runtime: TargetRuntime,
     pub batch: bool,
+    /// Batches the `_entities` requests of a federation entity field
+    pub entities: bool,
 }
 
 impl GraphqlDataLoader {
     pub fn new(runtime: TargetRuntime, batch: bool) -> Self {
-        GraphqlDataLoader { runtime, batch }
+        GraphqlDataLoader { runtime, batch, entities: false }
+    }
+
+    pub fn entities(self, entities: bool) -> Self {
+        GraphqlDataLoader { entities, ..self }
     }
 
     pub fn into_data_loader(
@@ -41,14 +50,36 @@ impl Loader<DataLoaderRequest> for GraphqlDataLoader {
         &self,
         keys: &[DataLoaderRequest],
     ) -> async_graphql::Result<HashMap<DataLoaderRequest, Self::Value>, Self::Error> {
-        if self.batch {
+        if self.entities {
+            let results = group_by_query(keys).into_iter().map(|group| async move {
+                let req = create_entities_request(&group);
+                let stats = group.iter().map(|(key, _)| key.request_stats());
+                let res = RequestStats::record(stats, self.runtime.http.execute(req))
+                    .await?
+                    .to_json()?;
+                let responses = scatter_entities(res, group.len());
+                let keys = group.into_iter().map(|(key, _)| key.clone());
+                Ok::<_, anyhow::Error>(keys.zip(responses).collect::<Vec<_>>())
+            });
+            #[allow(clippy::mutable_key_type)]
+            let mut hashmap = HashMap::new();
+            for result in join_all(results).await {
+                hashmap.extend(result?);
+            }
+
+            Ok(hashmap)
+        } else if self.batch {
             let batched_req = create_batched_request(keys);
-            let result = self.runtime.http.execute(batched_req).await?.to_json();
+            let stats = keys.iter().map(DataLoaderRequest::request_stats);
+            let result = RequestStats::record(stats, self.runtime.http.execute(batched_req))
+                .await?
+                .to_json();
             let hashmap = extract_responses(result, keys);
             Ok(hashmap)
         } else {
             let results = keys.iter().map(|key| async {
-                let result = self.runtime.http.execute(key.to_request()).await;
+                let call = self.runtime.http.execute(key.to_request());
+                let result = RequestStats::record([key.request_stats()], call).await;
                 (key.clone(), result)
             });
             let results = join_all(results).await;
@@ -116,6 +147,115 @@ fn extract_responses(
     hashmap
 }
 
+/// Groups the `_entities` requests sharing the same query, along with the
+/// representation each one of them is made for. The keys aren't loaded in a
+/// specific order, so the requests are sorted by representation to always send
+/// the same query for the same keys.
+fn group_by_query(
+    dataloader_requests: &[DataLoaderRequest],
+) -> Vec<Vec<(&DataLoaderRequest, serde_json::Value)>> {
+    let mut groups: Vec<(serde_json::Value, Vec<_>)> = vec![];
+    for dataloader_req in dataloader_requests {
+        let body = dataloader_req
+            .body()
+            .and_then(|body| body.as_bytes())
+            .and_then(|body| serde_json::from_slice::<serde_json::Value>(body).ok())
+            .unwrap_or_default();
+        let query = body["query"].clone();
+        let representation = body["variables"]["representations"][0].clone();
+
+        match groups.iter_mut().find(|(other, _)| other == &query) {
+            Some((_, group)) => group.push((dataloader_req, representation)),
+            None => groups.push((query, vec![(dataloader_req, representation)])),
+        }
+    }
+    groups
+        .into_iter()
+        .map(|(_, mut group)| {
+            group.sort_by_cached_key(|(_, representation)| representation.to_string());
+            group
+        })
+        .collect()
+}
+
+/// Creates a single `_entities` request with the representations of every
+/// request of the group.
+fn create_entities_request(group: &[(&DataLoaderRequest, serde_json::Value)]) -> reqwest::Request {
+    let (first_req, _) = group.first().unwrap();
+    let body = serde_json::json!({
+        "query": first_req
+            .body()
+            .and_then(|body| body.as_bytes())
+            .and_then(|body| serde_json::from_slice::<serde_json::Value>(body).ok())
+            .map(|body| body["query"].clone())
+            .unwrap_or_default(),
+        "variables": {
+            "representations": group
+                .iter()
+                .map(|(_, representation)| representation.clone())
+                .collect::<Vec<_>>(),
+        },
+    });
+
+    let mut entities_req = first_req.to_request();
+    entities_req
+        .body_mut()
+        .replace(reqwest::Body::from(body.to_string()));
+    entities_req
+}
+
+/// Splits the response of an `_entities` query into one response per
+/// representation, with the entity as `data`. Errors are routed to the entity
+/// at the root of their path, the others are added to every response.
+pub fn scatter_entities(
+    res: Response<async_graphql::Value>,
+    count: usize,
+) -> Vec<Response<async_graphql::Value>> {
+    let entities = match res.body.get_path(&["data", "_entities"]) {
+        Some(ConstValue::List(entities)) => entities.clone(),
+        _ => vec![],
+    };
+    let errors = match res.body.get_key("errors") {
+        Some(ConstValue::List(errors)) => errors.clone(),
+        _ => vec![],
+    };
+
+    (0..count)
+        .map(|index| {
+            let errors = errors
+                .iter()
+                .filter(|error| match error.get_key("path") {
+                    Some(ConstValue::List(path)) => match path.as_slice() {
+                        [ConstValue::String(root), ConstValue::Number(i), ..]
+                            if root == "_entities" =>
+                        {
+                            i.as_u64() == Some(index as u64)
+                        }
+                        _ => true,
+                    },
+                    _ => true,
+                })
+                .cloned()
+                .collect::<Vec<_>>();
+
+            let mut body = IndexMap::new();
+            body.insert(
+                Name::new("data"),
+                entities.get(index).cloned().unwrap_or_default(),
+            );
+            if !errors.is_empty() {
+                body.insert(Name::new("errors"), ConstValue::List(errors));
+            }
+
+            Response {
+                status: res.status,
+                headers: res.headers.clone(),
+                body: ConstValue::Object(body),
+            }
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::BTreeSet;
@@ -148,4 +288,36 @@ mod tests {
         let body = collect_request_bodies(&[dl_req1, dl_req2, dl_req3]);
         assert_eq!(body, "[a,b,c]");
     }
+
+    #[test]
+    fn test_scatter_entities() {
+        let body = async_graphql::Value::from_json(serde_json::json!({
+            "data": {"_entities": [{"name": "a"}, {"name": null}]},
+            "errors": [
+                {"message": "not found", "path": ["_entities", 1, "name"]},
+                {"message": "upstream error"}
+            ]
+        }))
+        .unwrap();
+        let res = Response { body, ..Default::default() };
+
+        let actual = scatter_entities(res, 2)
+            .into_iter()
+            .map(|res| res.body.into_json().unwrap())
+            .collect::<Vec<_>>();
+        let expected = vec![
+            serde_json::json!({
+                "data": {"name": "a"},
+                "errors": [{"message": "upstream error"}]
+            }),
+            serde_json::json!({
+                "data": {"name": null},
+                "errors": [
+                    {"message": "not found", "path": ["_entities", 1, "name"]},
+                    {"message": "upstream error"}
+                ]
+            }),
+        ];
+        assert_eq!(actual, expected);
+    }
 }
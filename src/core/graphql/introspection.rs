@@ -0,0 +1,378 @@
+use std::collections::BTreeMap;
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::core::config::GraphQLOperationType;
+use crate::core::runtime::TargetRuntime;
+use crate::core::valid::{Valid, Validator};
+
+/// Selects the root fields of the upstream and the type of their arguments
+const INTROSPECTION_QUERY: &str = "query { __schema { \
+    queryType { ...RootType } mutationType { ...RootType } } } \
+    fragment RootType on __Type { fields { name args { name defaultValue type { ...TypeRef } } } } \
+    fragment TypeRef on __Type { kind name ofType { kind name ofType { kind name ofType { kind name } } } }";
+
+/// The root fields of an upstream GraphQL server, read from its introspection.
+/// It's used to check that the fields the config resolves with `@graphQL`
+/// still exist on the upstream.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IntrospectionResult {
+    /// Url of the upstream
+    pub url: String,
+    pub query: BTreeMap<String, Vec<Argument>>,
+    pub mutation: BTreeMap<String, Vec<Argument>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Argument {
+    pub name: String,
+    /// The type as written in the SDL, eg. `[Int!]!`
+    pub type_of: String,
+    /// Non null arguments without a default value have to be passed
+    pub required: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IntrospectionResponse {
+    data: IntrospectionData,
+}
+
+#[derive(Deserialize)]
+struct IntrospectionData {
+    #[serde(rename = "__schema")]
+    schema: SchemaIntrospection,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SchemaIntrospection {
+    query_type: Option<RootType>,
+    mutation_type: Option<RootType>,
+}
+
+#[derive(Deserialize)]
+struct RootType {
+    #[serde(default)]
+    fields: Vec<FieldIntrospection>,
+}
+
+#[derive(Deserialize)]
+struct FieldIntrospection {
+    name: String,
+    #[serde(default)]
+    args: Vec<ArgIntrospection>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ArgIntrospection {
+    name: String,
+    default_value: Option<String>,
+    #[serde(rename = "type")]
+    type_of: TypeRef,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TypeRef {
+    kind: String,
+    name: Option<String>,
+    of_type: Option<Box<TypeRef>>,
+}
+
+impl TypeRef {
+    fn render(&self) -> String {
+        let of_type = || {
+            self.of_type
+                .as_ref()
+                .map(|of_type| of_type.render())
+                .unwrap_or_default()
+        };
+
+        match self.kind.as_str() {
+            "NON_NULL" => format!("{}!", of_type()),
+            "LIST" => format!("[{}]", of_type()),
+            _ => self.name.clone().unwrap_or_default(),
+        }
+    }
+}
+
+fn to_fields(root: Option<RootType>) -> BTreeMap<String, Vec<Argument>> {
+    root.map(|root| root.fields)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|field| {
+            let args = field
+                .args
+                .into_iter()
+                .map(|arg| Argument {
+                    name: arg.name,
+                    required: arg.type_of.kind == "NON_NULL" && arg.default_value.is_none(),
+                    type_of: arg.type_of.render(),
+                })
+                .collect();
+            (field.name, args)
+        })
+        .collect()
+}
+
+impl IntrospectionResult {
+    /// Parses the response of the introspection query
+    pub fn from_response(url: &str, body: &[u8]) -> anyhow::Result<Self> {
+        let response: IntrospectionResponse = serde_json::from_slice(body)
+            .with_context(|| format!("Invalid introspection response from {}", url))?;
+        let schema = response.data.schema;
+
+        Ok(Self {
+            url: url.to_string(),
+            query: to_fields(schema.query_type),
+            mutation: to_fields(schema.mutation_type),
+        })
+    }
+
+    /// Runs the introspection query on the upstream
+    pub async fn fetch(url: &str, runtime: &TargetRuntime) -> anyhow::Result<Self> {
+        let mut req = reqwest::Request::new(reqwest::Method::POST, url.parse()?);
+        req.headers_mut().insert(
+            reqwest::header::CONTENT_TYPE,
+            reqwest::header::HeaderValue::from_static("application/json"),
+        );
+        let body = serde_json::json!({ "query": INTROSPECTION_QUERY });
+        req.body_mut().replace(body.to_string().into());
+
+        let res = runtime.http.execute(req).await?;
+
+        Self::from_response(url, &res.body)
+    }
+
+    /// Checks that the upstream still has the field requested by `@graphQL`,
+    /// that the arguments passed to it exist with a compatible type and that
+    /// none of its required arguments is missing. Every argument comes with
+    /// the type of its value, when it's known.
+    pub fn validate_field(
+        &self,
+        operation_type: &GraphQLOperationType,
+        name: &str,
+        args: &[(String, Option<String>)],
+    ) -> Valid<(), String> {
+        let (root, fields) = match operation_type {
+            GraphQLOperationType::Query => ("Query", &self.query),
+            GraphQLOperationType::Mutation => ("Mutation", &self.mutation),
+        };
+
+        let Some(upstream_args) = fields.get(name) else {
+            return Valid::fail(format!(
+                "{} is not a field of the {} type of {}",
+                name, root, self.url
+            ))
+            .trace("name");
+        };
+
+        Valid::from_iter(args.iter(), |(arg, type_of)| {
+            match upstream_args.iter().find(|upstream| &upstream.name == arg) {
+                Some(upstream) => match type_of {
+                    Some(type_of) if !is_assignable(type_of, &upstream.type_of) => {
+                        Valid::fail(format!(
+                            "Argument {} of type {} can't be passed to the upstream field {} \
+                             which expects {}",
+                            arg, type_of, name, upstream.type_of
+                        ))
+                    }
+                    _ => Valid::succeed(()),
+                },
+                None => Valid::fail(format!(
+                    "Argument {} is not defined on the upstream field {}",
+                    arg, name
+                )),
+            }
+        })
+        .and(Valid::from_iter(upstream_args.iter(), |upstream| {
+            Valid::<(), String>::fail(format!(
+                "Argument {} of type {} is required by the upstream field {}",
+                upstream.name, upstream.type_of, name
+            ))
+            .when(|| upstream.required && !args.iter().any(|(arg, _)| arg == &upstream.name))
+        }))
+        .trace("args")
+        .unit()
+    }
+}
+
+/// Checks if a value of the `from` type can be passed where the `to` type is
+/// expected, both written as in the SDL eg. `[Int!]`. Only non null values can
+/// be passed to a non null type.
+fn is_assignable(from: &str, to: &str) -> bool {
+    match (from.strip_suffix('!'), to.strip_suffix('!')) {
+        (Some(from), Some(to)) => is_assignable(from, to),
+        (Some(from), None) => is_assignable(from, to),
+        (None, Some(_)) => false,
+        (None, None) => match (list_item(from), list_item(to)) {
+            (Some(from), Some(to)) => is_assignable(from, to),
+            (None, None) => from == to,
+            _ => false,
+        },
+    }
+}
+
+fn list_item(type_of: &str) -> Option<&str> {
+    type_of.strip_prefix('[')?.strip_suffix(']')
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::*;
+
+    fn introspection() -> IntrospectionResult {
+        let response = json!({
+            "data": {
+                "__schema": {
+                    "queryType": {
+                        "fields": [
+                            {
+                                "name": "user",
+                                "args": [
+                                    {
+                                        "name": "id",
+                                        "defaultValue": null,
+                                        "type": {
+                                            "kind": "NON_NULL",
+                                            "name": null,
+                                            "ofType": { "kind": "SCALAR", "name": "Int", "ofType": null }
+                                        }
+                                    },
+                                    {
+                                        "name": "tags",
+                                        "defaultValue": null,
+                                        "type": {
+                                            "kind": "LIST",
+                                            "name": null,
+                                            "ofType": { "kind": "SCALAR", "name": "String", "ofType": null }
+                                        }
+                                    }
+                                ]
+                            }
+                        ]
+                    },
+                    "mutationType": null
+                }
+            }
+        });
+
+        IntrospectionResult::from_response(
+            "http://upstream/graphql",
+            response.to_string().as_bytes(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_from_response() {
+        let actual = introspection();
+
+        assert_eq!(
+            actual.query["user"],
+            vec![
+                Argument {
+                    name: "id".to_string(),
+                    type_of: "Int!".to_string(),
+                    required: true
+                },
+                Argument {
+                    name: "tags".to_string(),
+                    type_of: "[String]".to_string(),
+                    required: false
+                },
+            ]
+        );
+        assert!(actual.mutation.is_empty());
+    }
+
+    #[test]
+    fn test_validate_field() {
+        let introspection = introspection();
+
+        assert!(introspection
+            .validate_field(
+                &GraphQLOperationType::Query,
+                "user",
+                &[("id".to_string(), Some("Int!".to_string()))],
+            )
+            .is_succeed());
+    }
+
+    #[test]
+    fn test_validate_missing_field() {
+        let actual = introspection()
+            .validate_field(&GraphQLOperationType::Mutation, "user", &[])
+            .to_result()
+            .unwrap_err()
+            .to_string();
+
+        assert!(actual.contains("user is not a field of the Mutation type"));
+    }
+
+    #[test]
+    fn test_validate_args() {
+        let errors = introspection()
+            .validate_field(
+                &GraphQLOperationType::Query,
+                "user",
+                &[("name".to_string(), None)],
+            )
+            .to_result()
+            .unwrap_err();
+        let messages = errors
+            .as_vec()
+            .iter()
+            .map(|cause| cause.message.clone())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            messages,
+            vec![
+                "Argument name is not defined on the upstream field user",
+                "Argument id of type Int! is required by the upstream field user",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_arg_types() {
+        let args = [
+            ("id".to_string(), Some("Int".to_string())),
+            ("tags".to_string(), Some("[Int]".to_string())),
+        ];
+        let errors = introspection()
+            .validate_field(&GraphQLOperationType::Query, "user", &args)
+            .to_result()
+            .unwrap_err();
+        let messages = errors
+            .as_vec()
+            .iter()
+            .map(|cause| cause.message.clone())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            messages,
+            vec![
+                "Argument id of type Int can't be passed to the upstream field user which expects Int!",
+                "Argument tags of type [Int] can't be passed to the upstream field user which expects [String]",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_is_assignable() {
+        assert!(is_assignable("Int!", "Int!"));
+        assert!(is_assignable("Int!", "Int"));
+        assert!(is_assignable("[String!]!", "[String]"));
+        assert!(!is_assignable("Int", "Int!"));
+        assert!(!is_assignable("[Int]", "Int"));
+        assert!(!is_assignable("String", "ID"));
+    }
+}
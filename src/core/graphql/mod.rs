@@ -1,5 +1,9 @@
 mod data_loader;
+mod introspection;
+mod persisted_query;
 mod request_template;
 
 pub use data_loader::*;
+pub use introspection::*;
+pub use persisted_query::*;
 pub use request_template::*;
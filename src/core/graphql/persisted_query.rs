@@ -0,0 +1,130 @@
+use std::num::NonZeroUsize;
+use std::sync::Mutex;
+
+use async_graphql_value::ConstValue;
+use lru::LruCache;
+use sha2::{Digest, Sha256};
+
+use crate::core::json::JsonLike;
+
+/// Error returned by upstreams that don't know the hash of a persisted query
+const NOT_FOUND: &str = "PersistedQueryNotFound";
+const NOT_FOUND_CODE: &str = "PERSISTED_QUERY_NOT_FOUND";
+
+/// The args are inlined into the queries, so there's a query for every value
+/// of the args. Only the most recently used ones are kept track of.
+const MAX_REGISTERED: usize = 1000;
+
+/// Keeps track of the queries registered on an upstream supporting [Automatic
+/// Persisted Queries](https://www.apollographql.com/docs/apollo-server/performance/apq),
+/// so that only their hash is sent once they're known to it.
+#[derive(Debug)]
+pub struct PersistedQueries {
+    registered: Mutex<LruCache<String, ()>>,
+}
+
+impl Default for PersistedQueries {
+    fn default() -> Self {
+        Self::new(NonZeroUsize::new(MAX_REGISTERED).unwrap())
+    }
+}
+
+impl PersistedQueries {
+    pub fn new(capacity: NonZeroUsize) -> Self {
+        Self { registered: Mutex::new(LruCache::new(capacity)) }
+    }
+
+    pub fn is_registered(&self, hash: &str) -> bool {
+        self.registered
+            .lock()
+            .map(|mut registered| registered.get(hash).is_some())
+            .unwrap_or_default()
+    }
+
+    pub fn register(&self, hash: String) {
+        if let Ok(mut registered) = self.registered.lock() {
+            registered.put(hash, ());
+        }
+    }
+
+    pub fn unregister(&self, hash: &str) {
+        if let Ok(mut registered) = self.registered.lock() {
+            registered.pop(hash);
+        }
+    }
+}
+
+/// The hex encoded SHA-256 hash the query is persisted with
+pub fn persisted_query_hash(query: &str) -> String {
+    hex::encode(Sha256::digest(query.as_bytes()))
+}
+
+/// Checks if the upstream responded that it doesn't know the hash of the
+/// query, in which case the query has to be sent again.
+pub fn is_persisted_query_not_found(body: &ConstValue) -> bool {
+    match body.get_key("errors") {
+        Some(ConstValue::List(errors)) => errors.iter().any(|error| {
+            let message = error.get_key("message");
+            let code = error.get_path(&["extensions", "code"]);
+            matches!(message, Some(ConstValue::String(message)) if message == NOT_FOUND)
+                || matches!(code, Some(ConstValue::String(code)) if code == NOT_FOUND_CODE)
+        }),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_hash() {
+        assert_eq!(
+            persisted_query_hash("query { user(id: 1) { name } }"),
+            "c0aa7d4f9fdc2478f6d1cfa815d6dc8cf42ca64e71ee63b68ab919bb10ad87a0"
+        );
+    }
+
+    #[test]
+    fn test_not_found() {
+        let not_found = |body| is_persisted_query_not_found(&ConstValue::from_json(body).unwrap());
+
+        assert!(not_found(
+            json!({"errors": [{"message": "PersistedQueryNotFound"}]})
+        ));
+        assert!(not_found(json!({
+            "errors": [{"message": "Not found", "extensions": {"code": "PERSISTED_QUERY_NOT_FOUND"}}]
+        })));
+        assert!(!not_found(json!({"errors": [{"message": "Unauthorized"}]})));
+        assert!(!not_found(json!({"data": {"user": null}})));
+    }
+
+    #[test]
+    fn test_register() {
+        let persisted = PersistedQueries::default();
+        assert!(!persisted.is_registered("hash"));
+
+        persisted.register("hash".to_string());
+        assert!(persisted.is_registered("hash"));
+
+        persisted.unregister("hash");
+        assert!(!persisted.is_registered("hash"));
+    }
+
+    #[test]
+    fn test_register_bounded() {
+        let persisted = PersistedQueries::new(NonZeroUsize::new(2).unwrap());
+        persisted.register("a".to_string());
+        persisted.register("b".to_string());
+
+        // Reading `a` makes `b` the least recently used one
+        assert!(persisted.is_registered("a"));
+        persisted.register("c".to_string());
+
+        assert!(persisted.is_registered("a"));
+        assert!(!persisted.is_registered("b"));
+        assert!(persisted.is_registered("c"));
+    }
+}
@@ -1,6 +1,8 @@
 #![allow(clippy::too_many_arguments)]
 
+use std::collections::BTreeSet;
 use std::hash::{Hash, Hasher};
+use std::sync::Arc;
 
 use derive_setters::Setters;
 use hyper::HeaderMap;
@@ -8,12 +10,13 @@ use reqwest::header::HeaderValue;
 use tailcall_hasher::TailcallHasher;
 
 use crate::core::config::{GraphQLOperationType, KeyValue};
+use crate::core::graphql::{persisted_query_hash, PersistedQueries};
 use crate::core::has_headers::HasHeaders;
 use crate::core::helpers::headers::MustacheHeaders;
 use crate::core::http::Method::POST;
 use crate::core::ir::{CacheKey, GraphQLOperationContext, IoId};
 use crate::core::mustache::Mustache;
-use crate::core::path::PathGraphql;
+use crate::core::path::{PathGraphql, PathString};
 
 /// RequestTemplate for GraphQL requests (See RequestTemplate documentation)
 #[derive(Setters, Debug, Clone)]
@@ -24,14 +27,64 @@ pub struct RequestTemplate {
     pub operation_name: String,
     pub operation_arguments: Option<Vec<(String, Mustache)>>,
     pub headers: MustacheHeaders,
+    /// Selection set requested instead of the one from the incoming query
+    pub selection_set: Option<String>,
+    /// Definitions of the fragments spread in the selection set
+    pub fragments: Vec<String>,
+    /// Requests the field on a federation entity through `_entities`
+    pub entity: Option<Entity>,
+    /// Headers of the incoming request forwarded to the API, the globally
+    /// allowed ones are forwarded when it's not set.
+    pub allowed_headers: Option<BTreeSet<String>>,
+    /// Queries registered on the upstream with Automatic Persisted Queries,
+    /// only their hash is sent once they're registered.
+    pub persisted: Option<Arc<PersistedQueries>>,
+}
+
+/// Reverts the escaping of a string embedded in JSON
+fn unescape(value: &str) -> String {
+    serde_json::from_str(&format!("\"{value}\"")).unwrap_or_else(|_| value.to_string())
+}
+
+/// A federation entity the field is requested on. The representation of the
+/// entity is built out of its type name and the rendered value of its key.
+#[derive(Debug, Clone)]
+pub struct Entity {
+    pub type_name: String,
+    pub key: String,
+    pub value: Mustache,
+}
+
+impl Entity {
+    pub fn new(type_name: String, key: String) -> anyhow::Result<Self> {
+        let value = Mustache::parse(&format!("{{{{.value.{}}}}}", key))?;
+        Ok(Self { type_name, key, value })
+    }
+
+    /// Renders the representation of the entity as JSON. Keys are scalars, so
+    /// their GraphQL literal is a valid JSON value.
+    fn render_representation<C: PathGraphql>(&self, ctx: &C) -> String {
+        let value = self.value.render_graphql(ctx);
+        let value = if value.is_empty() {
+            "null"
+        } else {
+            value.as_str()
+        };
+        format!(
+            r#"{{ "__typename": "{}", "{}": {} }}"#,
+            self.type_name, self.key, value
+        )
+    }
 }
 
 impl RequestTemplate {
-    fn create_headers<C: PathGraphql>(&self, ctx: &C) -> HeaderMap {
+    /// Header values are plain strings, so they are rendered using
+    /// [PathString] instead of [PathGraphql] which would wrap them in quotes.
+    fn create_headers<C: PathString>(&self, ctx: &C) -> HeaderMap {
         let mut header_map = HeaderMap::new();
 
         for (k, v) in &self.headers {
-            if let Ok(header_value) = HeaderValue::from_str(&v.render_graphql(ctx)) {
+            if let Ok(header_value) = HeaderValue::from_str(&v.render(ctx)) {
                 header_map.insert(k, header_value);
             }
         }
@@ -39,7 +92,7 @@ impl RequestTemplate {
         header_map
     }
 
-    fn set_headers<C: PathGraphql + HasHeaders>(
+    fn set_headers<C: PathString + HasHeaders>(
         &self,
         mut req: reqwest::Request,
         ctx: &C,
@@ -54,36 +107,93 @@ impl RequestTemplate {
             reqwest::header::CONTENT_TYPE,
             HeaderValue::from_static("application/json"),
         );
-        headers.extend(ctx.headers().to_owned());
+        headers.extend(ctx.forwarded_headers(self.allowed_headers.as_ref()));
         req
     }
 
-    pub fn to_request<C: PathGraphql + HasHeaders + GraphQLOperationContext>(
+    /// Creates the request sending the whole query, along with its hash when
+    /// the query is persisted so that the upstream registers it.
+    pub fn to_request<C: PathString + PathGraphql + HasHeaders + GraphQLOperationContext>(
+        &self,
+        ctx: &C,
+    ) -> anyhow::Result<reqwest::Request> {
+        let body = if self.persisted.is_some() {
+            self.render_persisted_query(ctx, true)
+        } else {
+            self.render_graphql_query(ctx)
+        };
+        self.create_request(ctx, body)
+    }
+
+    /// Creates the request sending only the hash of a persisted query
+    pub fn to_persisted_request<
+        C: PathString + PathGraphql + HasHeaders + GraphQLOperationContext,
+    >(
         &self,
         ctx: &C,
+    ) -> anyhow::Result<reqwest::Request> {
+        self.create_request(ctx, self.render_persisted_query(ctx, false))
+    }
+
+    fn create_request<C: PathString + HasHeaders>(
+        &self,
+        ctx: &C,
+        body: String,
     ) -> anyhow::Result<reqwest::Request> {
         let mut req = reqwest::Request::new(POST.to_hyper(), url::Url::parse(self.url.as_str())?);
         req = self.set_headers(req, ctx);
-        req = self.set_body(req, ctx);
+        req.body_mut().replace(body.into());
         Ok(req)
     }
 
-    fn set_body<C: PathGraphql + HasHeaders + GraphQLOperationContext>(
+    fn render_graphql_query<C: PathGraphql + HasHeaders + GraphQLOperationContext>(
         &self,
-        mut req: reqwest::Request,
         ctx: &C,
-    ) -> reqwest::Request {
-        req.body_mut()
-            .replace(self.render_graphql_query(ctx).into());
-        req
+    ) -> String {
+        let (query, variables) = self.render_operation(ctx);
+        match variables {
+            Some(variables) => {
+                format!(r#"{{ "query": "{query}", "variables": {variables} }}"#)
+            }
+            None => format!(r#"{{ "query": "{query}" }}"#),
+        }
     }
 
-    fn render_graphql_query<C: PathGraphql + HasHeaders + GraphQLOperationContext>(
+    /// Renders the body of an Automatic Persisted Query, the query is only
+    /// sent along with its hash until the upstream has registered it.
+    fn render_persisted_query<C: PathGraphql + HasHeaders + GraphQLOperationContext>(
         &self,
         ctx: &C,
+        with_query: bool,
     ) -> String {
+        let (query, variables) = self.render_operation(ctx);
+        let hash = persisted_query_hash(&unescape(&query));
+        let query = if with_query {
+            format!(r#""query": "{query}", "#)
+        } else {
+            String::new()
+        };
+        let variables = variables
+            .map(|variables| format!(r#""variables": {variables}, "#))
+            .unwrap_or_default();
+
+        format!(
+            r#"{{ {query}{variables}"extensions": {{ "persistedQuery": {{ "version": 1, "sha256Hash": "{hash}" }} }} }}"#
+        )
+    }
+
+    /// Renders the query, escaped to be embedded in a JSON string, along with
+    /// its variables
+    fn render_operation<C: PathGraphql + HasHeaders + GraphQLOperationContext>(
+        &self,
+        ctx: &C,
+    ) -> (String, Option<String>) {
         let operation_type = &self.operation_type;
-        let selection_set = ctx.selection_set().unwrap_or_default();
+        let selection_set = self
+            .selection_set
+            .clone()
+            .or_else(|| ctx.selection_set())
+            .unwrap_or_default();
         let operation = self
             .operation_arguments
             .as_ref()
@@ -96,7 +206,36 @@ impl RequestTemplate {
             .map(|args| format!("{}({})", self.operation_name, args))
             .unwrap_or(self.operation_name.clone());
 
-        format!(r#"{{ "query": "{operation_type} {{ {operation} {selection_set} }}" }}"#)
+        let fragments = self
+            .fragments
+            .iter()
+            .map(|fragment| format!(" {fragment}"))
+            .collect::<String>();
+
+        match &self.entity {
+            Some(entity) => {
+                let type_name = &entity.type_name;
+                let representation = entity.render_representation(ctx);
+                (
+                    format!("query($representations: [_Any!]!) {{ _entities(representations: $representations) {{ ... on {type_name} {{ {operation} {selection_set} }} }} }}{fragments}"),
+                    Some(format!(r#"{{ "representations": [{representation}] }}"#)),
+                )
+            }
+            None => (
+                format!("{operation_type} {{ {operation} {selection_set} }}{fragments}"),
+                None,
+            ),
+        }
+    }
+
+    /// The hash of the query sent to upstreams supporting Automatic Persisted
+    /// Queries
+    pub fn persisted_query_hash<C: PathGraphql + HasHeaders + GraphQLOperationContext>(
+        &self,
+        ctx: &C,
+    ) -> String {
+        let (query, _) = self.render_operation(ctx);
+        persisted_query_hash(&unescape(&query))
     }
 
     pub fn new(
@@ -122,34 +261,50 @@ impl RequestTemplate {
             operation_name: operation_name.to_owned(),
             operation_arguments,
             headers,
+            selection_set: None,
+            fragments: vec![],
+            entity: None,
+            allowed_headers: None,
+            persisted: None,
         })
     }
 }
 
-impl<Ctx: PathGraphql + HasHeaders + GraphQLOperationContext> CacheKey<Ctx> for RequestTemplate {
+impl<Ctx: PathString + PathGraphql + HasHeaders + GraphQLOperationContext> CacheKey<Ctx>
+    for RequestTemplate
+{
     fn cache_key(&self, ctx: &Ctx) -> Option<IoId> {
         let mut hasher = TailcallHasher::default();
         let graphql_query = self.render_graphql_query(ctx);
         graphql_query.hash(&mut hasher);
+
+        for (name, mustache) in self.headers.iter() {
+            name.hash(&mut hasher);
+            mustache.render(ctx).hash(&mut hasher);
+        }
+
         Some(IoId::new(hasher.finish()))
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::borrow::Cow;
     use std::collections::HashSet;
 
     use async_graphql::Value;
+    use hyper::header::HeaderName;
     use hyper::HeaderMap;
     use pretty_assertions::assert_eq;
     use serde_json::json;
 
     use crate::core::config::GraphQLOperationType;
-    use crate::core::graphql::RequestTemplate;
+    use crate::core::graphql::{Entity, RequestTemplate};
     use crate::core::has_headers::HasHeaders;
     use crate::core::ir::{CacheKey, GraphQLOperationContext};
     use crate::core::json::JsonLike;
-    use crate::core::path::PathGraphql;
+    use crate::core::mustache::Mustache;
+    use crate::core::path::{PathGraphql, PathString};
 
     struct Context {
         pub value: Value,
@@ -162,6 +317,15 @@ mod tests {
         }
     }
 
+    impl PathString for Context {
+        fn path_string<T: AsRef<str>>(&self, path: &[T]) -> Option<Cow<'_, str>> {
+            self.value.get_path(path).map(|v| match v {
+                Value::String(s) => Cow::Borrowed(s.as_str()),
+                _ => Cow::Owned(v.to_string()),
+            })
+        }
+    }
+
     impl HasHeaders for Context {
         fn headers(&self) -> &HeaderMap {
             &self.headers
@@ -240,6 +404,115 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_persisted_query() {
+        let tmpl = RequestTemplate::new(
+            "http://localhost:3000".to_string(),
+            &GraphQLOperationType::Query,
+            "myQuery",
+            None,
+            vec![],
+        )
+        .unwrap()
+        .persisted(Some(Default::default()));
+        let ctx = Context { value: Value::Null, headers: Default::default() };
+        let to_body = |req: reqwest::Request| {
+            String::from_utf8(req.body().unwrap().as_bytes().unwrap().to_owned()).unwrap()
+        };
+
+        assert_eq!(
+            tmpl.persisted_query_hash(&ctx),
+            "623b383d7258836e38476af763b29574f0b7494e3d22d204212e2f989046b6c8"
+        );
+        assert_eq!(
+            to_body(tmpl.to_request(&ctx).unwrap()),
+            r#"{ "query": "query { myQuery { a,b,c } }", "extensions": { "persistedQuery": { "version": 1, "sha256Hash": "623b383d7258836e38476af763b29574f0b7494e3d22d204212e2f989046b6c8" } } }"#
+        );
+        assert_eq!(
+            to_body(tmpl.to_persisted_request(&ctx).unwrap()),
+            r#"{ "extensions": { "persistedQuery": { "version": 1, "sha256Hash": "623b383d7258836e38476af763b29574f0b7494e3d22d204212e2f989046b6c8" } } }"#
+        );
+    }
+
+    #[test]
+    fn test_query_with_fragments() {
+        let tmpl = RequestTemplate::new(
+            "http://localhost:3000".to_string(),
+            &GraphQLOperationType::Query,
+            "myQuery",
+            None,
+            vec![],
+        )
+        .unwrap()
+        .selection_set(Some("{ ...Fields }".to_string()))
+        .fragments(vec!["fragment Fields on Foo { a b }".to_string()]);
+        let ctx = Context { value: Value::Null, headers: Default::default() };
+
+        let req = tmpl.to_request(&ctx).unwrap();
+        let body = req.body().unwrap().as_bytes().unwrap().to_owned();
+
+        assert_eq!(
+            std::str::from_utf8(&body).unwrap(),
+            r#"{ "query": "query { myQuery { ...Fields } } fragment Fields on Foo { a b }" }"#
+        );
+    }
+
+    #[test]
+    fn test_query_on_entity() {
+        let tmpl = RequestTemplate::new(
+            "http://localhost:3000".to_string(),
+            &GraphQLOperationType::Query,
+            "reviews",
+            None,
+            vec![],
+        )
+        .unwrap()
+        .entity(Some(
+            Entity::new("User".to_string(), "id".to_string()).unwrap(),
+        ));
+        let ctx = Context {
+            value: Value::from_json(json!({"value": {"id": 1}})).unwrap(),
+            headers: Default::default(),
+        };
+
+        let req = tmpl.to_request(&ctx).unwrap();
+        let body = req.body().unwrap().as_bytes().unwrap().to_owned();
+
+        assert_eq!(
+            std::str::from_utf8(&body).unwrap(),
+            r#"{ "query": "query($representations: [_Any!]!) { _entities(representations: $representations) { ... on User { reviews { a,b,c } } } }", "variables": { "representations": [{ "__typename": "User", "id": 1 }] } }"#
+        );
+    }
+
+    #[test]
+    fn test_headers_from_template() {
+        let headers = vec![(
+            HeaderName::from_static("authorization"),
+            Mustache::parse("Bearer {{foo.token}}").unwrap(),
+        )];
+        let tmpl = RequestTemplate::new(
+            "http://localhost:3000".to_string(),
+            &GraphQLOperationType::Query,
+            "myQuery",
+            None,
+            headers,
+        )
+        .unwrap();
+        let ctx = Context {
+            value: Value::from_json(json!({
+              "foo": {
+                "token": "abc"
+              }
+            }))
+            .unwrap(),
+            headers: Default::default(),
+        };
+
+        let req = tmpl.to_request(&ctx).unwrap();
+
+        assert_eq!(req.headers()["authorization"], "Bearer abc");
+    }
+
     fn create_gql_request_template_and_ctx(json: serde_json::Value) -> (RequestTemplate, Context) {
         let value = Value::from_json(json).unwrap();
 
@@ -14,7 +14,7 @@ use crate::core::config::group_by::GroupBy;
 use crate::core::config::Batch;
 use crate::core::data_loader::{DataLoader, Loader};
 use crate::core::grpc::request::create_grpc_request;
-use crate::core::http::Response;
+use crate::core::http::{RequestStats, Response};
 use crate::core::json::JsonLike;
 use crate::core::runtime::TargetRuntime;
 
@@ -38,7 +38,10 @@ impl GrpcDataLoader {
     ) -> anyhow::Result<HashMap<DataLoaderRequest, Response<async_graphql::Value>>> {
         let results = keys.iter().map(|key| async {
             let result = match key.to_request() {
-                Ok(req) => execute_grpc_request(&self.runtime, &self.operation, req).await,
+                Ok(req) => {
+                    let call = execute_grpc_request(&self.runtime, &self.operation, req);
+                    RequestStats::record([key.request_stats()], call).await
+                }
                 Err(error) => Err(error),
             };
 
@@ -75,8 +78,9 @@ impl GrpcDataLoader {
             multiple_body,
         );
 
-        let response =
-            execute_grpc_request(&self.runtime, &self.operation, multiple_request).await?;
+        let stats = keys.iter().map(DataLoaderRequest::request_stats);
+        let call = execute_grpc_request(&self.runtime, &self.operation, multiple_request);
+        let response = RequestStats::record(stats, call).await?;
 
         let path = &group_by.path();
         let response_body = response.body.group_by(path);
@@ -1,15 +1,18 @@
 use std::collections::BTreeSet;
 use std::hash::{Hash, Hasher};
+use std::sync::Arc;
 
 use anyhow::Result;
 use tailcall_hasher::TailcallHasher;
 
 use super::request_template::RenderedRequestTemplate;
+use crate::core::http::RequestStats;
 
 #[derive(Debug, Clone, Eq)]
 pub struct DataLoaderRequest {
     pub template: RenderedRequestTemplate,
     batch_headers: BTreeSet<String>,
+    stats: Arc<RequestStats>,
 }
 
 impl Hash for DataLoaderRequest {
@@ -42,7 +45,17 @@ impl PartialEq for DataLoaderRequest {
 
 impl DataLoaderRequest {
     pub fn new(template: RenderedRequestTemplate, batch_headers: BTreeSet<String>) -> Self {
-        Self { template, batch_headers }
+        Self { template, batch_headers, stats: Default::default() }
+    }
+
+    /// Sets the stats of the request the key is loaded for, which aren't part
+    /// of the identity of the key.
+    pub fn stats(self, stats: Arc<RequestStats>) -> Self {
+        Self { stats, ..self }
+    }
+
+    pub fn request_stats(&self) -> &Arc<RequestStats> {
+        &self.stats
     }
 
     pub fn to_request(&self) -> Result<reqwest::Request> {
@@ -73,6 +86,7 @@ mod tests {
             id: None,
             src: test_file.to_string(),
             type_of: LinkType::Protobuf,
+            import: vec![],
         }]);
         let method = GrpcMethod {
             package: "greetings".to_string(),
@@ -145,6 +145,7 @@ mod tests {
             id: Some(id.clone()),
             src: test_file.to_string(),
             type_of: LinkType::Protobuf,
+            import: vec![],
         }]);
         let method = GrpcMethod {
             package: id.to_string(),
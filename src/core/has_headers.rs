@@ -1,13 +1,44 @@
+use std::collections::BTreeSet;
+
 use hyper::HeaderMap;
 
 use crate::core::ir::{EvaluationContext, ResolverContextLike};
 
 pub trait HasHeaders {
     fn headers(&self) -> &HeaderMap;
+
+    /// Headers of the request allowed by the upstreams with their own
+    /// `allowedHeaders`, they can include headers that aren't allowed globally.
+    fn target_headers(&self) -> &HeaderMap {
+        self.headers()
+    }
+
+    /// Headers forwarded to an upstream. When the upstream has its own
+    /// allowlist only the headers it lists are forwarded, otherwise it gets
+    /// the globally allowed ones.
+    fn forwarded_headers(&self, allowed: Option<&BTreeSet<String>>) -> HeaderMap {
+        let Some(allowed) = allowed else {
+            return self.headers().to_owned();
+        };
+
+        self.target_headers()
+            .iter()
+            .filter(|(name, _)| {
+                allowed
+                    .iter()
+                    .any(|allowed| allowed.eq_ignore_ascii_case(name.as_str()))
+            })
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect()
+    }
 }
 
 impl<'a, Ctx: ResolverContextLike<'a>> HasHeaders for EvaluationContext<'a, Ctx> {
     fn headers(&self) -> &HeaderMap {
         self.headers()
     }
+
+    fn target_headers(&self) -> &HeaderMap {
+        self.target_headers()
+    }
 }
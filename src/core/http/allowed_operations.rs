@@ -0,0 +1,165 @@
+use sha2::{Digest, Sha256};
+
+pub const OPERATION_HASH_PREFIX: &str = "sha256:";
+
+/// Computes the hash that identifies an operation in
+/// `@server(allowedOperations:)`. The query is normalized before hashing, so
+/// formatting, comments and aliases don't change the hash.
+pub fn operation_hash(query: &str) -> String {
+    let normalized = normalize(query);
+    format!(
+        "{}{}",
+        OPERATION_HASH_PREFIX,
+        hex::encode(Sha256::digest(normalized.as_bytes()))
+    )
+}
+
+/// Checks if the value is a valid operation hash ie. `sha256:` followed by 64
+/// lowercase hex characters.
+pub fn is_operation_hash(value: &str) -> bool {
+    value
+        .strip_prefix(OPERATION_HASH_PREFIX)
+        .map_or(false, |hash| {
+            hash.len() == 64
+                && hash
+                    .chars()
+                    .all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c))
+        })
+}
+
+fn normalize(query: &str) -> String {
+    let tokens = tokenize(query);
+    let mut normalized: Vec<&str> = Vec::with_capacity(tokens.len());
+    let mut depth = 0;
+    let mut i = 0;
+
+    while i < tokens.len() {
+        match tokens[i] {
+            "(" => depth += 1,
+            ")" => depth -= 1,
+            // Outside of arguments and variable definitions, a name followed by
+            // a colon can only be an alias
+            token if depth == 0 && is_name(token) && tokens.get(i + 1) == Some(&":") => {
+                i += 2;
+                continue;
+            }
+            _ => {}
+        }
+        normalized.push(tokens[i]);
+        i += 1;
+    }
+
+    normalized.join(" ")
+}
+
+fn is_name(token: &str) -> bool {
+    token
+        .chars()
+        .next()
+        .map_or(false, |c| c == '_' || c.is_ascii_alphabetic())
+}
+
+fn tokenize(query: &str) -> Vec<&str> {
+    let bytes = query.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let start = i;
+        match bytes[i] {
+            b' ' | b'\t' | b'\n' | b'\r' | b',' => i += 1,
+            b'#' => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+            }
+            b'"' if bytes[i..].starts_with(b"\"\"\"") => {
+                i += 3;
+                while i < bytes.len() && !bytes[i..].starts_with(b"\"\"\"") {
+                    i += if bytes[i..].starts_with(b"\\\"\"\"") {
+                        4
+                    } else {
+                        1
+                    };
+                }
+                i = (i + 3).min(bytes.len());
+                tokens.push(&query[start..i]);
+            }
+            b'"' => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != b'"' {
+                    i += if bytes[i] == b'\\' { 2 } else { 1 };
+                }
+                i = (i + 1).min(bytes.len());
+                tokens.push(&query[start..i]);
+            }
+            b'.' if bytes[i..].starts_with(b"...") => {
+                i += 3;
+                tokens.push(&query[start..i]);
+            }
+            c if c == b'_' || c == b'-' || c.is_ascii_alphanumeric() => {
+                let is_number = !is_name(&query[start..]);
+                i += 1;
+                while i < bytes.len()
+                    && (bytes[i] == b'_'
+                        || bytes[i].is_ascii_alphanumeric()
+                        || (is_number && matches!(bytes[i], b'.' | b'+' | b'-')))
+                {
+                    i += 1;
+                }
+                tokens.push(&query[start..i]);
+            }
+            _ => {
+                // Skip the remaining bytes of multi-byte characters
+                i += 1;
+                while i < bytes.len() && !query.is_char_boundary(i) {
+                    i += 1;
+                }
+                tokens.push(&query[start..i]);
+            }
+        }
+    }
+
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        let query = r#"
+            # fetch the user
+            query User($id: Int!) {
+                account: user(id: $id, filter: {name: "a, b"}) {
+                    name
+                    ... on User { id }
+                }
+            }
+        "#;
+
+        assert_eq!(
+            normalize(query),
+            r#"query User ( $ id : Int ! ) { user ( id : $ id filter : { name : "a, b" } ) { name ... on User { id } } }"#
+        );
+    }
+
+    #[test]
+    fn test_operation_hash_ignores_formatting_and_aliases() {
+        let hash = operation_hash("query { user(id: 1) { name } }");
+
+        assert!(is_operation_hash(&hash));
+        assert_eq!(hash, operation_hash("query{\n  u: user(id:1){\n name }}"));
+        assert_ne!(hash, operation_hash("query { user(id: 2) { name } }"));
+    }
+
+    #[test]
+    fn test_is_operation_hash() {
+        assert!(!is_operation_hash("sha256:abc"));
+        assert!(!is_operation_hash(&"a".repeat(64)));
+        assert!(is_operation_hash(&format!("sha256:{}", "a".repeat(64))));
+    }
+}
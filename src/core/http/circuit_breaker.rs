@@ -0,0 +1,225 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use url::{Origin, Url};
+
+use crate::core::config;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Closed { failures: usize },
+    Open { until: Instant },
+    // A trial request is in flight, every other request fails right away
+    HalfOpen,
+}
+
+/// Tracks the failures of every upstream host and opens its circuit after
+/// `failure_threshold` consecutive failures. Requests to a host with an open
+/// circuit fail without being sent until the cooldown passes, then a single
+/// trial request decides if the circuit is closed or opened again.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    failure_threshold: usize,
+    cooldown: Duration,
+    hosts: Mutex<HashMap<Origin, State>>,
+}
+
+impl From<&config::CircuitBreaker> for CircuitBreaker {
+    fn from(circuit_breaker: &config::CircuitBreaker) -> Self {
+        Self {
+            failure_threshold: circuit_breaker.failure_threshold,
+            cooldown: Duration::from_millis(circuit_breaker.cooldown),
+            hosts: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl CircuitBreaker {
+    /// Checks if a request can be sent to the host of the url. The outcome of
+    /// the request has to be reported through the returned permit.
+    pub fn allow(&self, url: &Url) -> Option<Permit<'_>> {
+        self.allow_at(url, Instant::now())
+    }
+
+    fn allow_at(&self, url: &Url, now: Instant) -> Option<Permit<'_>> {
+        let origin = url.origin();
+        let mut hosts = self.hosts.lock().unwrap();
+        let state = hosts
+            .entry(origin.clone())
+            .or_insert(State::Closed { failures: 0 });
+
+        let is_trial = match *state {
+            State::Closed { .. } => false,
+            State::Open { until } if now >= until => {
+                *state = State::HalfOpen;
+                true
+            }
+            State::Open { .. } | State::HalfOpen => return None,
+        };
+
+        Some(Permit { breaker: self, origin, is_trial, is_reported: false })
+    }
+
+    fn report_at(&self, origin: &Origin, is_success: bool, now: Instant) {
+        let mut hosts = self.hosts.lock().unwrap();
+        let state = hosts
+            .entry(origin.clone())
+            .or_insert(State::Closed { failures: 0 });
+
+        *state = match (*state, is_success) {
+            (_, true) => State::Closed { failures: 0 },
+            (State::Closed { failures }, false) if failures + 1 < self.failure_threshold => {
+                State::Closed { failures: failures + 1 }
+            }
+            (_, false) => State::Open { until: now + self.cooldown },
+        };
+    }
+}
+
+/// A request allowed by the [CircuitBreaker]. If it's dropped before its
+/// outcome is reported, eg: when the request is cancelled, a trial request
+/// gives its turn to the next request instead of keeping the circuit half
+/// open forever.
+pub struct Permit<'a> {
+    breaker: &'a CircuitBreaker,
+    origin: Origin,
+    is_trial: bool,
+    is_reported: bool,
+}
+
+impl Permit<'_> {
+    /// Records the outcome of the request
+    pub fn report(self, is_success: bool) {
+        self.report_at(is_success, Instant::now())
+    }
+
+    fn report_at(mut self, is_success: bool, now: Instant) {
+        self.is_reported = true;
+        self.breaker.report_at(&self.origin, is_success, now);
+    }
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        if self.is_trial && !self.is_reported {
+            let mut hosts = self.breaker.hosts.lock().unwrap();
+            if let Some(state) = hosts.get_mut(&self.origin) {
+                if *state == State::HalfOpen {
+                    *state = State::Open { until: Instant::now() };
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn circuit_breaker() -> CircuitBreaker {
+        CircuitBreaker::from(&config::CircuitBreaker { cooldown: 1000, failure_threshold: 3 })
+    }
+
+    fn report(breaker: &CircuitBreaker, url: &Url, is_success: bool, now: Instant) {
+        breaker
+            .allow_at(url, now)
+            .unwrap()
+            .report_at(is_success, now);
+    }
+
+    #[test]
+    fn test_opens_after_threshold() {
+        let breaker = circuit_breaker();
+        let url = Url::parse("http://a.com/users").unwrap();
+        let now = Instant::now();
+
+        for _ in 0..2 {
+            report(&breaker, &url, false, now);
+        }
+        assert!(breaker.allow_at(&url, now).is_some());
+
+        report(&breaker, &url, false, now);
+        assert!(breaker.allow_at(&url, now).is_none());
+
+        // Other hosts are not affected
+        assert!(breaker
+            .allow_at(&Url::parse("http://b.com/users").unwrap(), now)
+            .is_some());
+    }
+
+    #[test]
+    fn test_success_resets_failures() {
+        let breaker = circuit_breaker();
+        let url = Url::parse("http://a.com/users").unwrap();
+        let now = Instant::now();
+
+        report(&breaker, &url, false, now);
+        report(&breaker, &url, false, now);
+        report(&breaker, &url, true, now);
+        report(&breaker, &url, false, now);
+
+        assert!(breaker.allow_at(&url, now).is_some());
+    }
+
+    #[test]
+    fn test_recovers_after_cooldown() {
+        let breaker = circuit_breaker();
+        let url = Url::parse("http://a.com/users").unwrap();
+        let now = Instant::now();
+        for _ in 0..3 {
+            report(&breaker, &url, false, now);
+        }
+
+        let later = now + Duration::from_millis(1000);
+        // Only a single trial request is let through
+        let trial = breaker.allow_at(&url, later).unwrap();
+        assert!(breaker.allow_at(&url, later).is_none());
+
+        trial.report_at(true, later);
+        assert!(breaker.allow_at(&url, later).is_some());
+    }
+
+    #[test]
+    fn test_failed_trial_opens_again() {
+        let breaker = circuit_breaker();
+        let url = Url::parse("http://a.com/users").unwrap();
+        let now = Instant::now();
+        for _ in 0..3 {
+            report(&breaker, &url, false, now);
+        }
+
+        let later = now + Duration::from_millis(1000);
+        report(&breaker, &url, false, later);
+
+        assert!(breaker
+            .allow_at(&url, later + Duration::from_millis(999))
+            .is_none());
+        assert!(breaker
+            .allow_at(&url, later + Duration::from_millis(1000))
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_trial_lets_another_through() {
+        let breaker = circuit_breaker();
+        let url = Url::parse("http://a.com/users").unwrap();
+        let now = Instant::now();
+        for _ in 0..3 {
+            report(&breaker, &url, false, now);
+        }
+
+        let later = now + Duration::from_millis(1000);
+        // The trial request never completes and is dropped by the timeout
+        let trial = async {
+            let _permit = breaker.allow_at(&url, later).unwrap();
+            std::future::pending::<()>().await
+        };
+        let result = tokio::time::timeout(Duration::from_millis(10), trial).await;
+        assert!(result.is_err());
+
+        let retry = breaker.allow_at(&url, later);
+        assert!(retry.is_some());
+        assert!(breaker.allow_at(&url, later).is_none());
+    }
+}
@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use hyper::body::Bytes;
+use tokio::sync::Semaphore;
+use url::{Origin, Url};
+
+use super::Response;
+use crate::core::HttpIO;
+
+/// Limits the number of calls in flight to every upstream host to
+/// `max_concurrency`. The other calls wait for a permit, in the order they were
+/// made.
+#[derive(Debug)]
+pub struct ConcurrencyLimit {
+    max_concurrency: usize,
+    hosts: Mutex<HashMap<Origin, Arc<Semaphore>>>,
+}
+
+impl ConcurrencyLimit {
+    pub fn new(max_concurrency: usize) -> Self {
+        Self { max_concurrency, hosts: Mutex::new(HashMap::new()) }
+    }
+
+    fn semaphore(&self, url: &Url) -> Arc<Semaphore> {
+        let mut hosts = self.hosts.lock().unwrap();
+        hosts
+            .entry(url.origin())
+            .or_insert_with(|| Arc::new(Semaphore::new(self.max_concurrency)))
+            .clone()
+    }
+
+    /// Wraps the client so that its calls share the permits of the hosts with
+    /// the other clients wrapped by the same limit.
+    pub fn limit(self: &Arc<Self>, http: Arc<dyn HttpIO>) -> Arc<dyn HttpIO> {
+        Arc::new(LimitedHttp { http, limit: self.clone() })
+    }
+}
+
+struct LimitedHttp {
+    http: Arc<dyn HttpIO>,
+    limit: Arc<ConcurrencyLimit>,
+}
+
+#[async_trait::async_trait]
+impl HttpIO for LimitedHttp {
+    async fn execute(&self, request: reqwest::Request) -> anyhow::Result<Response<Bytes>> {
+        let semaphore = self.limit.semaphore(request.url());
+        let _permit = semaphore.acquire_owned().await?;
+        self.http.execute(request).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    use futures_util::future::join_all;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    /// Counts the calls in flight and keeps track of the highest count.
+    #[derive(Default)]
+    struct CountingHttp {
+        in_flight: AtomicUsize,
+        max_in_flight: AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl HttpIO for CountingHttp {
+        async fn execute(&self, _: reqwest::Request) -> anyhow::Result<Response<Bytes>> {
+            let in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+
+            Ok(Response::empty())
+        }
+    }
+
+    fn request(url: &str) -> reqwest::Request {
+        reqwest::Request::new(reqwest::Method::GET, url.parse().unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_max_concurrency() {
+        let counting = Arc::new(CountingHttp::default());
+        let http = Arc::new(ConcurrencyLimit::new(2)).limit(counting.clone());
+
+        let calls = (0..10).map(|id| http.execute(request(&format!("http://a.com/{}", id))));
+        let results = join_all(calls).await;
+
+        assert!(results.iter().all(|result| result.is_ok()));
+        assert_eq!(counting.max_in_flight.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_hosts_are_limited_separately() {
+        let counting = Arc::new(CountingHttp::default());
+        let http = Arc::new(ConcurrencyLimit::new(1)).limit(counting.clone());
+
+        let calls = ["http://a.com/1", "http://b.com/1", "http://a.com/2"]
+            .map(|url| http.execute(request(url)));
+        join_all(calls).await;
+
+        assert_eq!(counting.max_in_flight.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_clients_share_the_limit() {
+        let counting = Arc::new(CountingHttp::default());
+        let limit = Arc::new(ConcurrencyLimit::new(1));
+        let http = limit.limit(counting.clone());
+        let http2_only = limit.limit(counting.clone());
+
+        tokio::join!(
+            http.execute(request("http://a.com/1")),
+            http2_only.execute(request("http://a.com/2"))
+        );
+
+        assert_eq!(counting.max_in_flight.load(Ordering::SeqCst), 1);
+    }
+}
@@ -9,7 +9,7 @@ use async_graphql_value::ConstValue;
 use crate::core::config::group_by::GroupBy;
 use crate::core::config::Batch;
 use crate::core::data_loader::{DataLoader, Loader};
-use crate::core::http::{DataLoaderRequest, Response};
+use crate::core::http::{DataLoaderRequest, RequestStats, Response};
 use crate::core::json::JsonLike;
 use crate::core::runtime::TargetRuntime;
 
@@ -36,6 +36,9 @@ pub struct HttpDataLoader {
     pub runtime: TargetRuntime,
     pub group_by: Option<GroupBy>,
     pub body: fn(&HashMap<String, Vec<&ConstValue>>, &str) -> ConstValue,
+    /// The status codes of `@http(expectStatus:)`, they resolve even when they
+    /// aren't a success
+    pub expect_status: Vec<u16>,
 }
 impl HttpDataLoader {
     pub fn new(runtime: TargetRuntime, group_by: Option<GroupBy>, is_list: bool) -> Self {
@@ -47,9 +50,14 @@ impl HttpDataLoader {
             } else {
                 get_body_value_single
             },
+            expect_status: Vec::new(),
         }
     }
 
+    pub fn expect_status(self, expect_status: Vec<u16>) -> Self {
+        HttpDataLoader { expect_status, ..self }
+    }
+
     pub fn to_data_loader(self, batch: Batch) -> DataLoader<DataLoaderRequest, HttpDataLoader> {
         DataLoader::new(self)
             .delay(Duration::from_millis(batch.delay as u64))
@@ -79,12 +87,9 @@ impl Loader<DataLoaderRequest> for HttpDataLoader {
                 first_url.query_pairs_mut().extend_pairs(url.query_pairs());
             }
 
-            let res = self
-                .runtime
-                .http
-                .execute(request)
-                .await?
-                .to_json::<ConstValue>()?;
+            let stats = keys.iter().map(DataLoaderRequest::request_stats);
+            let res = RequestStats::record(stats, self.runtime.http.execute(request)).await;
+            let res = Response::expect_status(res, &self.expect_status)?.to_json::<ConstValue>()?;
             #[allow(clippy::mutable_key_type)]
             let mut hashmap = HashMap::with_capacity(keys.len());
             let path = &group_by.path();
@@ -102,7 +107,8 @@ impl Loader<DataLoaderRequest> for HttpDataLoader {
             Ok(hashmap)
         } else {
             let results = keys.iter().map(|key| async {
-                let result = self.runtime.http.execute(key.to_request()).await;
+                let call = self.runtime.http.execute(key.to_request());
+                let result = RequestStats::record([key.request_stats()], call).await;
                 (key.clone(), result)
             });
 
@@ -111,10 +117,82 @@ impl Loader<DataLoaderRequest> for HttpDataLoader {
             #[allow(clippy::mutable_key_type)]
             let mut hashmap = HashMap::new();
             for (key, value) in results {
-                hashmap.insert(key, value?.to_json()?);
+                let value = Response::expect_status(value, &self.expect_status)?;
+                hashmap.insert(key, value.to_json()?);
             }
 
             Ok(hashmap)
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+    use std::sync::Mutex;
+
+    use hyper::body::Bytes;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::*;
+    use crate::core::HttpIO;
+
+    /// Records the requested urls and returns a canned list of posts.
+    #[derive(Default)]
+    struct FakeHttp {
+        urls: Mutex<Vec<String>>,
+    }
+
+    #[async_trait::async_trait]
+    impl HttpIO for FakeHttp {
+        async fn execute(&self, request: reqwest::Request) -> anyhow::Result<Response<Bytes>> {
+            self.urls.lock().unwrap().push(request.url().to_string());
+            let body = json!([
+                {"id": 1, "userId": 1},
+                {"id": 2, "userId": 2},
+                {"id": 3, "userId": 1}
+            ]);
+
+            Ok(Response::empty().body(Bytes::from(body.to_string())))
+        }
+    }
+
+    fn request(user_id: u32) -> DataLoaderRequest {
+        let url = format!(
+            "http://jsonplaceholder.typicode.com/posts?userId={}",
+            user_id
+        );
+        let req = reqwest::Request::new(reqwest::Method::GET, url.parse().unwrap());
+
+        DataLoaderRequest::new(req, BTreeSet::new())
+    }
+
+    #[tokio::test]
+    async fn test_load_batched_requests() {
+        let http = Arc::new(FakeHttp::default());
+        let mut runtime = crate::core::runtime::test::init(None);
+        runtime.http = http.clone();
+
+        let group_by = GroupBy::new(vec!["userId".to_string()]);
+        let loader = HttpDataLoader::new(runtime, Some(group_by), true);
+        let result = loader.load(&[request(1), request(2)]).await.unwrap();
+
+        assert_eq!(
+            *http.urls.lock().unwrap(),
+            vec!["http://jsonplaceholder.typicode.com/posts?userId=1&userId=2"]
+        );
+        assert_eq!(
+            result[&request(1)].body,
+            ConstValue::from_json(json!([
+                {"id": 1, "userId": 1},
+                {"id": 3, "userId": 1}
+            ]))
+            .unwrap()
+        );
+        assert_eq!(
+            result[&request(2)].body,
+            ConstValue::from_json(json!([{"id": 2, "userId": 2}])).unwrap()
+        );
+    }
+}
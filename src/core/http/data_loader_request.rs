@@ -1,17 +1,27 @@
 use std::collections::BTreeSet;
 use std::hash::{Hash, Hasher};
 use std::ops::Deref;
+use std::sync::Arc;
 
 use tailcall_hasher::TailcallHasher;
 
+use super::RequestStats;
+
 #[derive(Debug)]
-pub struct DataLoaderRequest(reqwest::Request, BTreeSet<String>);
+pub struct DataLoaderRequest(reqwest::Request, BTreeSet<String>, Arc<RequestStats>);
 
 impl DataLoaderRequest {
     pub fn new(req: reqwest::Request, headers: BTreeSet<String>) -> Self {
         // TODO: req should already have headers builtin, no?
-        DataLoaderRequest(req, headers)
+        DataLoaderRequest(req, headers, Default::default())
+    }
+
+    /// Sets the stats of the request the key is loaded for, which aren't part
+    /// of the identity of the key.
+    pub fn stats(self, stats: Arc<RequestStats>) -> Self {
+        DataLoaderRequest(self.0, self.1, stats)
     }
+
     pub fn to_request(&self) -> reqwest::Request {
         // TODO: excessive clone for the whole structure instead of cloning only part of
         // it check if we really need to clone anything at all or just pass
@@ -21,6 +31,9 @@ impl DataLoaderRequest {
     pub fn headers(&self) -> &BTreeSet<String> {
         &self.1
     }
+    pub fn request_stats(&self) -> &Arc<RequestStats> {
+        &self.2
+    }
 }
 impl Hash for DataLoaderRequest {
     fn hash<H: Hasher>(&self, state: &mut H) {
@@ -64,7 +77,7 @@ impl Clone for DataLoaderRequest {
             req
         });
 
-        DataLoaderRequest(req, self.1.clone())
+        DataLoaderRequest(req, self.1.clone(), self.2.clone())
     }
 }
 
@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures_util::Future;
+use tokio::sync::broadcast::Sender;
+use tokio::time::Instant;
+
+enum Entry<Value, Error> {
+    Pending(Sender<Arc<Result<Value, Error>>>),
+    Ready {
+        value: Arc<Result<Value, Error>>,
+        until: Instant,
+    },
+}
+
+/// Shares the result of an upstream read with every request of the server.
+/// The concurrent reads of a key wait for the first one to complete, and its
+/// result is reused until the window is over. Failures are never reused.
+pub struct DedupeWindow<Key, Value, Error> {
+    window: Duration,
+    entries: Mutex<HashMap<Key, Entry<Value, Error>>>,
+}
+
+/// Removes the pending entry when the read is dropped before completing, the
+/// waiting reads then make their own.
+struct PendingGuard<'a, Key: Eq + Hash, Value, Error> {
+    entries: &'a Mutex<HashMap<Key, Entry<Value, Error>>>,
+    key: Option<Key>,
+}
+
+impl<Key: Eq + Hash, Value, Error> Drop for PendingGuard<'_, Key, Value, Error> {
+    fn drop(&mut self) {
+        if let Some(key) = self.key.take() {
+            self.entries.lock().unwrap().remove(&key);
+        }
+    }
+}
+
+impl<Key: Eq + Hash + Send + Clone, Value: Debug + Clone + Send, Error: Debug + Clone + Send>
+    DedupeWindow<Key, Value, Error>
+{
+    pub fn new(window: Duration) -> Self {
+        Self { window, entries: Mutex::new(HashMap::new()) }
+    }
+
+    pub async fn get_or_eval<'a>(
+        &self,
+        key: Key,
+        or_else: impl FnOnce() -> Pin<Box<dyn Future<Output = Result<Value, Error>> + 'a + Send>> + Send,
+    ) -> Arc<Result<Value, Error>> {
+        let tx = {
+            let mut entries = self.entries.lock().unwrap();
+            match entries.get(&key) {
+                Some(Entry::Ready { value, until }) if *until > Instant::now() => {
+                    return value.clone();
+                }
+                Some(Entry::Pending(tx)) => Err(tx.subscribe()),
+                _ => {
+                    // Expired entries are only dropped when a new read is made
+                    let now = Instant::now();
+                    entries.retain(|_, entry| match entry {
+                        Entry::Ready { until, .. } => *until > now,
+                        Entry::Pending(_) => true,
+                    });
+
+                    let (tx, _) = tokio::sync::broadcast::channel(1);
+                    entries.insert(key.clone(), Entry::Pending(tx.clone()));
+                    Ok(tx)
+                }
+            }
+        };
+
+        match tx {
+            Ok(tx) => {
+                let mut guard = PendingGuard { entries: &self.entries, key: Some(key) };
+                let result = Arc::new(or_else().await);
+
+                if let Some(key) = guard.key.take() {
+                    let mut entries = self.entries.lock().unwrap();
+                    if result.is_ok() {
+                        let until = Instant::now() + self.window;
+                        entries.insert(key, Entry::Ready { value: result.clone(), until });
+                    } else {
+                        entries.remove(&key);
+                    }
+                }
+                tx.send(result.clone()).ok();
+                result
+            }
+            Err(mut rx) => match rx.recv().await {
+                Ok(result) => result,
+                Err(_) => Arc::new(or_else().await),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_concurrent_reads_are_coalesced() {
+        let window = DedupeWindow::<i32, i32, String>::new(Duration::from_millis(100));
+        let calls = AtomicUsize::new(0);
+        let read = || {
+            window.get_or_eval(1, || {
+                Box::pin(async {
+                    calls.fetch_add(1, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                    Ok(1)
+                })
+            })
+        };
+
+        let (a, b, c) = tokio::join!(read(), read(), read());
+        let actual = [a, b, c].map(|value| value.as_ref().clone());
+
+        assert_eq!(actual, [Ok(1), Ok(1), Ok(1)]);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_read_after_window() {
+        let window = DedupeWindow::<i32, i32, String>::new(Duration::from_millis(10));
+        window.get_or_eval(1, || Box::pin(async { Ok(1) })).await;
+
+        tokio::time::advance(Duration::from_millis(9)).await;
+        let actual = window.get_or_eval(1, || Box::pin(async { Ok(2) })).await;
+        assert_eq!(actual.as_ref().clone(), Ok(1));
+
+        tokio::time::advance(Duration::from_millis(1)).await;
+        let actual = window.get_or_eval(1, || Box::pin(async { Ok(3) })).await;
+        assert_eq!(actual.as_ref().clone(), Ok(3));
+    }
+
+    #[tokio::test]
+    async fn test_failure_is_not_reused() {
+        let window = DedupeWindow::<i32, i32, String>::new(Duration::from_millis(100));
+        let actual = window
+            .get_or_eval(1, || Box::pin(async { Err("error".to_string()) }))
+            .await;
+        assert_eq!(actual.as_ref().clone(), Err("error".to_string()));
+
+        let actual = window.get_or_eval(1, || Box::pin(async { Ok(1) })).await;
+        assert_eq!(actual.as_ref().clone(), Ok(1));
+    }
+}
@@ -0,0 +1,162 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures_util::future::join_all;
+use url::{Origin, Url};
+
+use crate::core::config;
+use crate::core::http::UrlPool;
+use crate::core::runtime::TargetRuntime;
+
+/// Probes every host of the `baseURLs` pools and takes the ones that fail
+/// `unhealthy_threshold` probes in a row out of rotation. They're put back as
+/// soon as a probe succeeds again.
+#[derive(Debug)]
+pub struct HealthChecker {
+    path: String,
+    interval: Duration,
+    unhealthy_threshold: usize,
+    pools: Vec<Arc<UrlPool>>,
+    // Consecutive failed probes of every host
+    hosts: Mutex<HashMap<Origin, usize>>,
+}
+
+impl HealthChecker {
+    pub fn new(health_check: &config::HealthCheck, pools: Vec<Arc<UrlPool>>) -> Self {
+        Self {
+            path: health_check.path.clone(),
+            interval: Duration::from_millis(health_check.get_interval()),
+            unhealthy_threshold: health_check.get_unhealthy_threshold(),
+            pools,
+            hosts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The time between two rounds of probes
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Probes every host once and updates the pools they belong to
+    pub async fn check(&self, runtime: &TargetRuntime) {
+        let origins = self
+            .pools
+            .iter()
+            .flat_map(|pool| pool.origins())
+            .cloned()
+            .collect::<HashSet<_>>();
+
+        // The probes run concurrently so a slow host doesn't delay the others
+        join_all(origins.iter().map(|origin| async move {
+            let is_healthy = self.probe(origin, runtime).await;
+            self.report(origin, is_healthy);
+        }))
+        .await;
+    }
+
+    async fn probe(&self, origin: &Origin, runtime: &TargetRuntime) -> bool {
+        let Ok(url) =
+            Url::parse(&origin.ascii_serialization()).and_then(|url| url.join(&self.path))
+        else {
+            return false;
+        };
+        let request = reqwest::Request::new(reqwest::Method::GET, url);
+
+        runtime
+            .http
+            .execute(request)
+            .await
+            .map(|response| response.status.is_success())
+            .unwrap_or(false)
+    }
+
+    fn report(&self, origin: &Origin, is_healthy: bool) {
+        let mut hosts = self.hosts.lock().unwrap();
+        let failures = hosts.entry(origin.clone()).or_insert(0);
+        *failures = if is_healthy { 0 } else { *failures + 1 };
+
+        for pool in self.pools.iter() {
+            pool.set_healthy(origin, *failures < self.unhealthy_threshold);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::core::mustache::Mustache;
+
+    fn health_checker() -> HealthChecker {
+        let pool = UrlPool::new(
+            ["http://a.com/users", "http://b.com/users"]
+                .iter()
+                .map(|url| Mustache::parse(url).unwrap())
+                .collect(),
+        );
+
+        HealthChecker::new(
+            &config::HealthCheck {
+                path: "/health".to_string(),
+                interval: None,
+                unhealthy_threshold: Some(2),
+            },
+            vec![Arc::new(pool)],
+        )
+    }
+
+    fn next(checker: &HealthChecker) -> Vec<String> {
+        (0..4)
+            .map(|_| checker.pools[0].next().render(&serde_json::Value::Null))
+            .collect()
+    }
+
+    #[test]
+    fn test_unhealthy_host_leaves_rotation() {
+        let checker = health_checker();
+        let origin = Url::parse("http://b.com").unwrap().origin();
+
+        checker.report(&origin, false);
+        assert_eq!(
+            next(&checker),
+            vec![
+                "http://a.com/users",
+                "http://b.com/users",
+                "http://a.com/users",
+                "http://b.com/users",
+            ]
+        );
+
+        checker.report(&origin, false);
+        assert_eq!(
+            next(&checker),
+            vec![
+                "http://a.com/users",
+                "http://a.com/users",
+                "http://a.com/users",
+                "http://a.com/users",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_recovered_host_is_back_in_rotation() {
+        let checker = health_checker();
+        let origin = Url::parse("http://b.com").unwrap().origin();
+        checker.report(&origin, false);
+        checker.report(&origin, false);
+
+        checker.report(&origin, true);
+        assert_eq!(
+            next(&checker),
+            vec![
+                "http://a.com/users",
+                "http://b.com/users",
+                "http://a.com/users",
+                "http://b.com/users",
+            ]
+        );
+    }
+}
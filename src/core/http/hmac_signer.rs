@@ -0,0 +1,109 @@
+use hmac::{Hmac, Mac};
+use reqwest::header::{HeaderName, HeaderValue};
+use sha2::Sha256;
+
+use crate::core::config;
+use crate::core::mustache::Mustache;
+use crate::core::path::PathString;
+
+const DEFAULT_HEADER: &str = "X-Signature";
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Signs the body of upstream requests with HMAC-SHA256. The secret is a
+/// mustache template, so it's rendered for every request and can be sourced
+/// from `env` or `vars`.
+#[derive(Clone, Debug)]
+pub struct HmacSigner {
+    pub header: HeaderName,
+    pub secret: Mustache,
+}
+
+impl TryFrom<&config::Hmac> for HmacSigner {
+    type Error = anyhow::Error;
+
+    fn try_from(hmac: &config::Hmac) -> anyhow::Result<Self> {
+        Ok(Self {
+            header: HeaderName::from_bytes(
+                hmac.header.as_deref().unwrap_or(DEFAULT_HEADER).as_bytes(),
+            )?,
+            secret: Mustache::parse(&hmac.secret)?,
+        })
+    }
+}
+
+impl HmacSigner {
+    /// Adds the hex encoded signature of the final body to the request. A
+    /// request without a body is signed as an empty body.
+    pub fn sign<C: PathString>(&self, req: &mut reqwest::Request, ctx: &C) -> anyhow::Result<()> {
+        let secret = self.secret.render(ctx);
+        let body = req
+            .body()
+            .and_then(|body| body.as_bytes())
+            .unwrap_or_default();
+
+        // NOTE: HMAC accepts keys of any size so this can never fail
+        let mut mac =
+            HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC can take key of any size");
+        mac.update(body);
+        let signature = hex::encode(mac.finalize().into_bytes());
+
+        req.headers_mut()
+            .insert(self.header.clone(), HeaderValue::from_str(&signature)?);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::*;
+
+    fn signer(header: Option<&str>) -> HmacSigner {
+        HmacSigner::try_from(&config::Hmac {
+            header: header.map(str::to_string),
+            secret: "{{.value.secret}}".to_string(),
+        })
+        .unwrap()
+    }
+
+    fn request(body: Option<&str>) -> reqwest::Request {
+        let mut req = reqwest::Request::new(
+            reqwest::Method::POST,
+            "http://localhost:8080/users".parse().unwrap(),
+        );
+        *req.body_mut() = body.map(|body| body.to_string().into());
+        req
+    }
+
+    #[test]
+    fn test_sign_body() {
+        let ctx = json!({"value": {"secret": "key"}});
+        let mut req = request(Some("The quick brown fox jumps over the lazy dog"));
+
+        signer(None).sign(&mut req, &ctx).unwrap();
+
+        assert_eq!(
+            req.headers()["x-signature"],
+            "f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8"
+        );
+    }
+
+    #[test]
+    fn test_sign_empty_body_with_custom_header() {
+        let ctx = json!({"value": {"secret": "key"}});
+        let mut req = request(None);
+
+        signer(Some("X-Hub-Signature"))
+            .sign(&mut req, &ctx)
+            .unwrap();
+
+        assert_eq!(
+            req.headers()["x-hub-signature"],
+            "5d5d139563c95b5967b9bd9a8c9b233a9dedb45072794cd232dc1b74832607d0"
+        );
+    }
+}
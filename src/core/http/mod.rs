@@ -1,26 +1,51 @@
+pub use allowed_operations::{is_operation_hash, operation_hash};
 pub use cache::*;
+pub use circuit_breaker::CircuitBreaker;
+pub use concurrency_limit::ConcurrencyLimit;
 pub use data_loader::*;
 pub use data_loader_request::*;
+pub use dedupe_window::DedupeWindow;
 use headers::HeaderValue;
+pub use health_check::HealthChecker;
+pub use hmac_signer::HmacSigner;
 pub use method::Method;
+pub use playground::playground_source;
+pub use query_signer::QuerySigner;
 pub use request_context::RequestContext;
-pub use request_handler::{handle_request, API_URL_PREFIX};
+pub use request_handler::{handle_request, API_URL_PREFIX, SHOWCASE_PATH};
+pub use request_limit::RequestLimit;
+pub use request_stats::RequestStats;
 pub use request_template::RequestTemplate;
 pub use response::*;
+pub use sigv4::SigV4;
+pub use url_pool::UrlPool;
 
 pub use crate::core::app_context::AppContext;
 
 mod data_loader;
 
+mod allowed_operations;
 mod cache;
+mod circuit_breaker;
+mod concurrency_limit;
 mod data_loader_request;
+mod dedupe_window;
+mod health_check;
+mod hmac_signer;
 mod method;
+mod multipart;
+mod playground;
+mod query_signer;
 mod request_context;
 mod request_handler;
+mod request_limit;
+mod request_stats;
 mod request_template;
 mod response;
 pub mod showcase;
+mod sigv4;
 mod telemetry;
+mod url_pool;
 
 pub static TAILCALL_HTTPS_ORIGIN: HeaderValue = HeaderValue::from_static("https://tailcall.run");
 pub static TAILCALL_HTTP_ORIGIN: HeaderValue = HeaderValue::from_static("http://tailcall.run");
@@ -0,0 +1,275 @@
+use std::io::Read;
+
+use async_graphql::http::{receive_batch_body, MultipartOptions};
+use async_graphql::BatchRequest;
+use async_graphql_value::{ConstValue, Name};
+use base64::prelude::BASE64_STANDARD;
+use base64::Engine;
+use indexmap::IndexMap;
+use sha2::{Digest, Sha256};
+
+/// Placeholder left by async-graphql in the variables for every uploaded file
+const FILE_PLACEHOLDER: &str = "#__graphql_file__:";
+
+const DEFAULT_CONTENT_TYPE: &str = "application/octet-stream";
+
+/// Checks if the content type is `multipart/form-data`
+pub fn is_multipart(content_type: &str) -> bool {
+    content_type
+        .split(';')
+        .next()
+        .is_some_and(|mime| mime.trim().eq_ignore_ascii_case("multipart/form-data"))
+}
+
+/// Parses a [GraphQL multipart request](https://github.com/jaydenseric/graphql-multipart-request-spec).
+/// The uploaded files are inlined in the variables as `Upload` values, ie.
+/// objects with the `filename`, `contentType` and the base64 `content`. Files
+/// larger than `max_upload_size` bytes fail the request.
+pub async fn receive_request(
+    content_type: &str,
+    body: &[u8],
+    max_upload_size: usize,
+) -> anyhow::Result<BatchRequest> {
+    let options = MultipartOptions::default().max_file_size(max_upload_size);
+    let mut batch = receive_batch_body(Some(content_type), body, options).await?;
+
+    for request in batch.iter_mut() {
+        let uploads = std::mem::take(&mut request.uploads)
+            .into_iter()
+            .map(|upload| {
+                let filename = upload.filename.clone();
+                let content_type = upload.content_type.clone();
+                let mut content = Vec::new();
+                upload.into_read().read_to_end(&mut content)?;
+
+                let mut map = IndexMap::new();
+                map.insert(Name::new("filename"), ConstValue::String(filename));
+                if let Some(content_type) = content_type {
+                    map.insert(Name::new("contentType"), ConstValue::String(content_type));
+                }
+                map.insert(
+                    Name::new("content"),
+                    ConstValue::String(BASE64_STANDARD.encode(content)),
+                );
+                Ok(ConstValue::Object(map))
+            })
+            .collect::<std::io::Result<Vec<_>>>()?;
+
+        for value in request.variables.values_mut() {
+            inline_uploads(value, &uploads);
+        }
+    }
+
+    Ok(batch)
+}
+
+fn inline_uploads(value: &mut ConstValue, uploads: &[ConstValue]) {
+    let upload = match value {
+        ConstValue::String(s) => s
+            .strip_prefix(FILE_PLACEHOLDER)
+            .and_then(|index| index.parse::<usize>().ok())
+            .and_then(|index| uploads.get(index)),
+        ConstValue::List(list) => {
+            list.iter_mut()
+                .for_each(|value| inline_uploads(value, uploads));
+            None
+        }
+        ConstValue::Object(map) => {
+            map.values_mut()
+                .for_each(|value| inline_uploads(value, uploads));
+            None
+        }
+        _ => None,
+    };
+
+    if let Some(upload) = upload {
+        *value = upload.clone();
+    }
+}
+
+/// Encodes the body as `multipart/form-data` and returns the boundary along
+/// with the encoded body. Every key of the body is sent as a part, lists are
+/// sent as multiple parts with the same name and `null` values are skipped.
+/// Objects with a base64 `content` are sent as files.
+pub fn encode(body: &ConstValue) -> anyhow::Result<(String, Vec<u8>)> {
+    let ConstValue::Object(map) = body else {
+        anyhow::bail!("Multipart body must be an object");
+    };
+
+    // The boundary is derived from the body, so the same body is always
+    // encoded the same way
+    let boundary = format!(
+        "----tailcall{}",
+        &hex::encode(Sha256::digest(serde_json::to_vec(body)?))[..32]
+    );
+
+    let mut encoded = Vec::new();
+    for (name, value) in map {
+        let values = match value {
+            ConstValue::List(list) => list.iter().collect(),
+            value => vec![value],
+        };
+
+        for value in values {
+            let (headers, content) = match value {
+                ConstValue::Null => continue,
+                ConstValue::String(text) => (disposition(name, None), text.as_bytes().to_vec()),
+                ConstValue::Object(file) if file.contains_key("content") => {
+                    let content = match file.get("content") {
+                        Some(ConstValue::String(content)) => BASE64_STANDARD.decode(content)?,
+                        _ => anyhow::bail!("The content of the file `{}` must be a string", name),
+                    };
+                    let filename = match file.get("filename") {
+                        Some(ConstValue::String(filename)) => Some(filename.as_str()),
+                        _ => None,
+                    };
+                    let content_type = match file.get("contentType") {
+                        Some(ConstValue::String(content_type)) => content_type.as_str(),
+                        _ => DEFAULT_CONTENT_TYPE,
+                    };
+                    let headers = format!(
+                        "{}\r\nContent-Type: {}",
+                        disposition(name, filename),
+                        content_type
+                    );
+                    (headers, content)
+                }
+                value => (disposition(name, None), serde_json::to_vec(value)?),
+            };
+
+            encoded.extend_from_slice(format!("--{}\r\n{}\r\n\r\n", boundary, headers).as_bytes());
+            encoded.extend_from_slice(&content);
+            encoded.extend_from_slice(b"\r\n");
+        }
+    }
+    encoded.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+
+    Ok((boundary, encoded))
+}
+
+fn disposition(name: &str, filename: Option<&str>) -> String {
+    match filename {
+        Some(filename) => format!(
+            "Content-Disposition: form-data; name=\"{}\"; filename=\"{}\"",
+            escape(name),
+            escape(filename)
+        ),
+        None => format!("Content-Disposition: form-data; name=\"{}\"", escape(name)),
+    }
+}
+
+/// Escapes the quotes and line breaks the same way browsers do
+fn escape(value: &str) -> String {
+    value
+        .replace('"', "%22")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::*;
+
+    fn encode_json(body: serde_json::Value) -> (String, String) {
+        let (boundary, body) = encode(&ConstValue::from_json(body).unwrap()).unwrap();
+        (boundary, String::from_utf8(body).unwrap())
+    }
+
+    #[test]
+    fn test_is_multipart() {
+        assert!(is_multipart("multipart/form-data; boundary=abc"));
+        assert!(is_multipart("Multipart/Form-Data"));
+        assert!(!is_multipart("application/json"));
+    }
+
+    #[test]
+    fn test_encode_parts() {
+        let (boundary, body) = encode_json(json!({
+            "name": "avatar",
+            "size": 5,
+            "tags": ["a", "b"],
+            "skipped": null,
+            "file": {"filename": "a.txt", "contentType": "text/plain", "content": "aGVsbG8="}
+        }));
+
+        let expected = [
+            "Content-Disposition: form-data; name=\"name\"\r\n\r\navatar\r\n",
+            "Content-Disposition: form-data; name=\"size\"\r\n\r\n5\r\n",
+            "Content-Disposition: form-data; name=\"tags\"\r\n\r\na\r\n",
+            "Content-Disposition: form-data; name=\"tags\"\r\n\r\nb\r\n",
+            "Content-Disposition: form-data; name=\"file\"; filename=\"a.txt\"\r\nContent-Type: text/plain\r\n\r\nhello\r\n",
+        ]
+        .iter()
+        .map(|part| format!("--{}\r\n{}", boundary, part))
+        .collect::<String>()
+            + &format!("--{}--\r\n", boundary);
+
+        assert_eq!(body, expected);
+    }
+
+    #[test]
+    fn test_encode_is_deterministic() {
+        let (first, _) = encode_json(json!({"name": "a"}));
+        let (second, _) = encode_json(json!({"name": "a"}));
+        let (other, _) = encode_json(json!({"name": "b"}));
+
+        assert_eq!(first, second);
+        assert_ne!(first, other);
+    }
+
+    #[test]
+    fn test_encode_file_defaults() {
+        let (_, body) = encode_json(json!({"file": {"content": "aGVsbG8="}}));
+
+        assert!(body.contains(
+            "Content-Disposition: form-data; name=\"file\"\r\nContent-Type: application/octet-stream\r\n\r\nhello\r\n"
+        ));
+    }
+
+    #[test]
+    fn test_encode_invalid_body() {
+        assert!(encode(&ConstValue::from_json(json!(["a"])).unwrap()).is_err());
+        assert!(
+            encode(&ConstValue::from_json(json!({"file": {"content": "%%"}})).unwrap()).is_err()
+        );
+    }
+
+    fn multipart_request() -> String {
+        [
+            "--abc\r\nContent-Disposition: form-data; name=\"operations\"\r\n\r\n",
+            r#"{"query": "mutation($file: Upload!) { upload(file: $file) }", "variables": {"file": null}}"#,
+            "\r\n--abc\r\nContent-Disposition: form-data; name=\"map\"\r\n\r\n",
+            r#"{"0": ["variables.file"]}"#,
+            "\r\n--abc\r\nContent-Disposition: form-data; name=\"0\"; filename=\"a.txt\"\r\nContent-Type: text/plain\r\n\r\n",
+            "hello",
+            "\r\n--abc--\r\n",
+        ]
+        .concat()
+    }
+
+    #[tokio::test]
+    async fn test_receive_request_inlines_uploads() {
+        let body = multipart_request();
+        let batch = receive_request("multipart/form-data; boundary=abc", body.as_bytes(), 5)
+            .await
+            .unwrap();
+        let request = batch.into_single().unwrap();
+
+        assert!(request.uploads.is_empty());
+        assert_eq!(
+            request.variables.into_value().into_json().unwrap(),
+            json!({"file": {"filename": "a.txt", "contentType": "text/plain", "content": "aGVsbG8="}})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_receive_request_max_upload_size() {
+        let body = multipart_request();
+        let result = receive_request("multipart/form-data; boundary=abc", body.as_bytes(), 4).await;
+
+        assert!(result.is_err());
+    }
+}
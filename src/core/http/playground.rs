@@ -0,0 +1,62 @@
+use async_graphql::http::GraphiQLSource;
+
+use crate::core::config::Playground;
+
+const SANDBOX_SCRIPT: &str =
+    "https://embeddable-sandbox.cdn.apollographql.com/_latest/embeddable-sandbox.umd.production.min.js";
+
+/// Builds the page of the playground served on GET requests to `endpoint`,
+/// there's none when the playground is disabled.
+pub fn playground_source(playground: Playground, endpoint: &str) -> Option<String> {
+    match playground {
+        Playground::GraphiQL => Some(GraphiQLSource::build().endpoint(endpoint).finish()),
+        Playground::Sandbox => Some(sandbox_source(endpoint)),
+        Playground::None => None,
+    }
+}
+
+/// The Apollo Sandbox page, the endpoint is resolved against the url of the
+/// page so that it works behind proxies.
+fn sandbox_source(endpoint: &str) -> String {
+    // The endpoint is written as a JS string literal
+    let endpoint = serde_json::to_string(endpoint).unwrap_or_default();
+
+    format!(
+        r##"<!DOCTYPE html>
+<html lang="en">
+  <head>
+    <meta charset="utf-8" />
+    <title>Apollo Sandbox</title>
+  </head>
+  <body style="margin: 0; overflow: hidden">
+    <div id="sandbox" style="height: 100vh; width: 100vw"></div>
+    <script src="{SANDBOX_SCRIPT}"></script>
+    <script>
+      new window.EmbeddedSandbox({{
+        target: "#sandbox",
+        initialEndpoint: new URL({endpoint}, window.location.href).toString(),
+      }});
+    </script>
+  </body>
+</html>
+"##
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sandbox_endpoint() {
+        let source = playground_source(Playground::Sandbox, "/graphql").unwrap();
+
+        assert!(source.contains(SANDBOX_SCRIPT));
+        assert!(source.contains(r#"new URL("/graphql", window.location.href)"#));
+    }
+
+    #[test]
+    fn test_none() {
+        assert_eq!(playground_source(Playground::None, "/graphql"), None);
+    }
+}
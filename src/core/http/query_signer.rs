@@ -0,0 +1,141 @@
+use std::borrow::Cow;
+
+use base64::prelude::BASE64_STANDARD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use jsonwebtoken::{encode, EncodingKey, Header};
+use sha2::Sha256;
+
+use crate::core::config::{self, QueryTransform};
+use crate::core::mustache::Mustache;
+use crate::core::path::PathString;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Computes a query parameter from the values of other query parameters of
+/// the request. The secret is a mustache template, so it's rendered for every
+/// request and can be sourced from `env` or `vars`.
+#[derive(Clone, Debug)]
+pub struct QuerySigner {
+    pub key: String,
+    pub inputs: Vec<String>,
+    pub secret: Mustache,
+    pub transform: QueryTransform,
+}
+
+impl TryFrom<&config::SignedQuery> for QuerySigner {
+    type Error = anyhow::Error;
+
+    fn try_from(signed: &config::SignedQuery) -> anyhow::Result<Self> {
+        Ok(Self {
+            key: signed.key.clone(),
+            inputs: signed.inputs.clone(),
+            secret: Mustache::parse(&signed.secret)?,
+            transform: signed.transform.clone(),
+        })
+    }
+}
+
+impl QuerySigner {
+    /// Signs the rendered query params, inputs that aren't sent are signed as
+    /// empty strings. The value is url encoded, as it's added to the query as
+    /// is.
+    pub fn sign<C: PathString>(
+        &self,
+        query: &[(Cow<'_, str>, Cow<'_, str>)],
+        ctx: &C,
+    ) -> anyhow::Result<String> {
+        let secret = self.secret.render(ctx);
+        let inputs = self.inputs.iter().map(|name| {
+            let value = query
+                .iter()
+                .find(|(key, _)| key.as_ref() == name.as_str())
+                .map(|(_, value)| value.as_ref())
+                .unwrap_or_default();
+            (name.as_str(), value)
+        });
+
+        let value = match self.transform {
+            QueryTransform::Hmac => {
+                let message = inputs
+                    .map(|(name, value)| format!("{}={}", name, value))
+                    .collect::<Vec<_>>()
+                    .join("&");
+
+                // NOTE: HMAC accepts keys of any size so this can never fail
+                let mut mac = HmacSha256::new_from_slice(secret.as_bytes())
+                    .expect("HMAC can take key of any size");
+                mac.update(message.as_bytes());
+                BASE64_STANDARD.encode(mac.finalize().into_bytes())
+            }
+            QueryTransform::JwtSign => {
+                let claims = inputs
+                    .map(|(name, value)| (name.to_string(), value.into()))
+                    .collect::<serde_json::Map<_, serde_json::Value>>();
+                encode(
+                    &Header::default(),
+                    &claims,
+                    &EncodingKey::from_secret(secret.as_bytes()),
+                )?
+            }
+        };
+
+        Ok(url::form_urlencoded::byte_serialize(value.as_bytes()).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::*;
+
+    fn signer(transform: QueryTransform) -> QuerySigner {
+        QuerySigner::try_from(&config::SignedQuery {
+            inputs: vec!["id".to_string(), "ts".to_string()],
+            key: "token".to_string(),
+            secret: "{{.env.TOKEN_SECRET}}".to_string(),
+            transform,
+        })
+        .unwrap()
+    }
+
+    fn query() -> Vec<(Cow<'static, str>, Cow<'static, str>)> {
+        vec![
+            (Cow::Borrowed("ts"), Cow::Borrowed("1700000000")),
+            (Cow::Borrowed("id"), Cow::Borrowed("42")),
+        ]
+    }
+
+    #[test]
+    fn test_sign_hmac() {
+        let ctx = json!({"env": {"TOKEN_SECRET": "key"}});
+        let actual = signer(QueryTransform::Hmac).sign(&query(), &ctx).unwrap();
+
+        assert_eq!(actual, "k2GlZdeZ%2Fvt1Si%2FdqhGCRger3TYGODHsGLDiaRkYjT8%3D");
+    }
+
+    #[test]
+    fn test_sign_jwt() {
+        let ctx = json!({"env": {"TOKEN_SECRET": "key"}});
+        let actual = signer(QueryTransform::JwtSign)
+            .sign(&query(), &ctx)
+            .unwrap();
+
+        assert_eq!(
+            actual,
+            "eyJ0eXAiOiJKV1QiLCJhbGciOiJIUzI1NiJ9.eyJpZCI6IjQyIiwidHMiOiIxNzAwMDAwMDAwIn0.\
+             oZsdZC5T8evwckkMJK3ANwKuGNUAB_G64_ww8PbRJpw"
+        );
+    }
+
+    #[test]
+    fn test_sign_missing_input() {
+        let ctx = json!({"env": {"TOKEN_SECRET": "key"}});
+        let query = vec![(Cow::Borrowed("id"), Cow::Borrowed("42"))];
+        let actual = signer(QueryTransform::Hmac).sign(&query, &ctx).unwrap();
+
+        assert_eq!(actual, "Jdz30CiljCkFIBKe5%2BuzCfWx0sNtBhrR3HRZNjVYKPQ%3D");
+    }
+}
@@ -1,6 +1,8 @@
+use std::collections::BTreeMap;
 use std::num::NonZeroU64;
 use std::str::FromStr;
 use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
 use async_graphql_value::ConstValue;
 use cache_control::{Cachability, CacheControl};
@@ -14,7 +16,9 @@ use crate::core::data_loader::DataLoader;
 use crate::core::graphql::GraphqlDataLoader;
 use crate::core::grpc;
 use crate::core::grpc::data_loader::GrpcDataLoader;
-use crate::core::http::{AppContext, DataLoaderRequest, HttpDataLoader};
+use crate::core::http::{
+    AppContext, CircuitBreaker, DataLoaderRequest, DedupeWindow, HttpDataLoader, RequestStats,
+};
 use crate::core::ir::{EvaluationError, IoId};
 use crate::core::runtime::TargetRuntime;
 
@@ -27,6 +31,10 @@ pub struct RequestContext {
     // A subset of all the headers received in the GraphQL Request that will be sent to the
     // upstream.
     pub allowed_headers: HeaderMap,
+    // The headers of the request listed by the upstreams with their own allowlist
+    pub target_headers: HeaderMap,
+    // The `context` of the server rendered with the headers of the request
+    pub context: BTreeMap<String, String>,
     pub auth_ctx: AuthContext,
     pub http_data_loaders: Arc<Vec<DataLoader<DataLoaderRequest, HttpDataLoader>>>,
     pub gql_data_loaders: Arc<Vec<DataLoader<DataLoaderRequest, GraphqlDataLoader>>>,
@@ -35,6 +43,13 @@ pub struct RequestContext {
     pub cache_public: Arc<Mutex<Option<bool>>>,
     pub runtime: TargetRuntime,
     pub cache: AsyncCache<IoId, ConstValue, EvaluationError>,
+    // Instant after which all the pending upstream calls are aborted
+    pub deadline: Option<Instant>,
+    pub circuit_breaker: Option<Arc<CircuitBreaker>>,
+    // Shares the upstream reads with the other requests of the server
+    pub dedupe_window: Option<Arc<DedupeWindow<IoId, ConstValue, EvaluationError>>>,
+    // Counts the upstream calls and cache hits made to resolve the request
+    pub stats: Arc<RequestStats>,
 }
 
 impl RequestContext {
@@ -52,7 +67,40 @@ impl RequestContext {
             runtime: target_runtime,
             cache: AsyncCache::new(),
             allowed_headers: HeaderMap::new(),
+            target_headers: HeaderMap::new(),
+            context: BTreeMap::new(),
             auth_ctx: AuthContext::default(),
+            deadline: None,
+            circuit_breaker: None,
+            dedupe_window: None,
+            stats: Arc::new(RequestStats::default()),
+        }
+    }
+
+    /// Copies the context to evaluate an expression after the request is
+    /// over, eg. to refresh a cached value in the background. The response
+    /// headers and the deadline of the request aren't carried over.
+    pub fn detach(&self) -> RequestContext {
+        RequestContext {
+            server: self.server.clone(),
+            upstream: self.upstream.clone(),
+            x_response_headers: Arc::new(Mutex::new(HeaderMap::new())),
+            cookie_headers: None,
+            allowed_headers: self.allowed_headers.clone(),
+            target_headers: self.target_headers.clone(),
+            context: self.context.clone(),
+            auth_ctx: AuthContext::default(),
+            http_data_loaders: self.http_data_loaders.clone(),
+            gql_data_loaders: self.gql_data_loaders.clone(),
+            grpc_data_loaders: self.grpc_data_loaders.clone(),
+            min_max_age: Arc::new(Mutex::new(None)),
+            cache_public: Arc::new(Mutex::new(None)),
+            runtime: self.runtime.clone(),
+            cache: AsyncCache::new(),
+            deadline: None,
+            circuit_breaker: self.circuit_breaker.clone(),
+            dedupe_window: self.dedupe_window.clone(),
+            stats: Arc::new(RequestStats::default()),
         }
     }
     fn set_min_max_age_conc(&self, min_max_age: i32) {
@@ -157,11 +205,17 @@ impl RequestContext {
         !self.server.experimental_headers.is_empty()
     }
 
-    /// Inserts the experimental headers into the x_response_headers map
+    /// Inserts the experimental headers into the x_response_headers map. The
+    /// headers listed in the `Connection` header of the upstream response are
+    /// hop-by-hop headers (RFC 7230, section 6.1), so they're never copied.
     pub fn add_x_headers(&self, headers: &HeaderMap) {
         if self.has_experimental_headers() {
+            let connection = connection_headers(headers);
             let mut x_response_headers = self.x_response_headers.lock().unwrap();
             for name in &self.server.experimental_headers {
+                if connection.contains(name) {
+                    continue;
+                }
                 if let Some(value) = headers.get(name) {
                     x_response_headers.insert(name, value.clone());
                 }
@@ -180,6 +234,17 @@ impl RequestContext {
     }
 }
 
+/// Returns the names of the headers listed in the `Connection` header
+fn connection_headers(headers: &HeaderMap) -> Vec<HeaderName> {
+    headers
+        .get_all(reqwest::header::CONNECTION)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|name| HeaderName::from_bytes(name.trim().as_bytes()).ok())
+        .collect()
+}
+
 impl From<&AppContext> for RequestContext {
     fn from(app_ctx: &AppContext) -> Self {
         let cookie_headers = if app_ctx.blueprint.server.enable_set_cookie_header {
@@ -193,6 +258,8 @@ impl From<&AppContext> for RequestContext {
             x_response_headers: Arc::new(Mutex::new(HeaderMap::new())),
             cookie_headers,
             allowed_headers: HeaderMap::new(),
+            target_headers: HeaderMap::new(),
+            context: BTreeMap::new(),
             auth_ctx: (&app_ctx.auth_ctx).into(),
             http_data_loaders: app_ctx.http_data_loaders.clone(),
             gql_data_loaders: app_ctx.gql_data_loaders.clone(),
@@ -201,6 +268,14 @@ impl From<&AppContext> for RequestContext {
             cache_public: Arc::new(Mutex::new(None)),
             runtime: app_ctx.runtime.clone(),
             cache: AsyncCache::new(),
+            deadline: app_ctx
+                .blueprint
+                .server
+                .request_timeout
+                .map(|timeout| Instant::now() + timeout),
+            circuit_breaker: app_ctx.circuit_breaker.clone(),
+            dedupe_window: app_ctx.dedupe_window.clone(),
+            stats: Arc::new(RequestStats::default()),
         }
     }
 }
@@ -294,4 +369,28 @@ mod test {
             create_req_ctx_with_batch(Batch { delay: 1, max_size: Some(1), ..Default::default() });
         assert!(req_ctx.is_batching_enabled());
     }
+
+    #[test]
+    fn test_add_x_headers_skips_connection_headers() {
+        use std::collections::HashSet;
+
+        use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+
+        let mut req_ctx = RequestContext::default();
+        req_ctx.server.experimental_headers = HashSet::from([
+            HeaderName::from_static("x-hop"),
+            HeaderName::from_static("x-trace"),
+        ]);
+
+        let mut headers = HeaderMap::new();
+        headers.insert("connection", HeaderValue::from_static("keep-alive, x-hop"));
+        headers.insert("x-hop", HeaderValue::from_static("1"));
+        headers.insert("x-trace", HeaderValue::from_static("1"));
+        req_ctx.add_x_headers(&headers);
+
+        let mut x_headers = HeaderMap::new();
+        req_ctx.extend_x_headers(&mut x_headers);
+        assert_eq!(x_headers.len(), 1);
+        assert_eq!(x_headers.get("x-trace").unwrap(), "1");
+    }
 }
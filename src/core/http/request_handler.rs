@@ -1,10 +1,13 @@
-use std::collections::BTreeSet;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::ops::Deref;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use anyhow::Result;
-use async_graphql::ServerError;
-use hyper::header::{self, HeaderValue, CONTENT_TYPE};
+use async_graphql::{ServerError, Value};
+use hyper::body::Bytes;
+use hyper::header::{self, HeaderName, HeaderValue, CONTENT_LENGTH, CONTENT_TYPE};
 use hyper::http::Method;
 use hyper::{Body, HeaderMap, Request, Response, StatusCode};
 use opentelemetry::trace::SpanKind;
@@ -16,13 +19,21 @@ use tracing_opentelemetry::OpenTelemetrySpanExt;
 
 use super::request_context::RequestContext;
 use super::telemetry::{get_response_status_code, RequestCounter};
-use super::{showcase, telemetry, AppContext, TAILCALL_HTTPS_ORIGIN, TAILCALL_HTTP_ORIGIN};
+use super::{
+    multipart, operation_hash, showcase, telemetry, AppContext, RequestLimit,
+    TAILCALL_HTTPS_ORIGIN, TAILCALL_HTTP_ORIGIN,
+};
 use crate::core::async_graphql_hyper::{GraphQLRequestLike, GraphQLResponse};
 use crate::core::blueprint::telemetry::TelemetryExporter;
 use crate::core::config::{PrometheusExporter, PrometheusFormat};
+use crate::core::path::PathString;
+use crate::core::EnvIO;
 
 pub const API_URL_PREFIX: &str = "/api";
 
+pub const SHOWCASE_PATH: &str = "/showcase/graphql";
+pub const X_REQUEST_TIMEOUT: &str = "x-request-timeout";
+
 fn prometheus_metrics(prometheus_exporter: &PrometheusExporter) -> Result<Response<Body>> {
     let metric_families = prometheus::default_registry().gather();
     let mut buffer = vec![];
@@ -45,19 +56,117 @@ fn prometheus_metrics(prometheus_exporter: &PrometheusExporter) -> Result<Respon
         .body(Body::from(buffer))?)
 }
 
+fn playground_response(playground: &Bytes) -> Result<Response<Body>> {
+    Ok(Response::builder()
+        .status(200)
+        .header(CONTENT_TYPE, "text/html; charset=utf-8")
+        .body(Body::from(playground.clone()))?)
+}
+
 fn not_found() -> Result<Response<Body>> {
     Ok(Response::builder()
         .status(StatusCode::NOT_FOUND)
         .body(Body::empty())?)
 }
 
+/// Rejects the request when too many requests are already running or queued
+fn service_unavailable(limit: &RequestLimit) -> Result<Response<Body>> {
+    let mut response = async_graphql::Response::default();
+    response.errors = vec![ServerError::new(
+        "Too many concurrent requests, retry later",
+        None,
+    )];
+
+    let mut resp = GraphQLResponse::from(response).into_response()?;
+    *resp.status_mut() = StatusCode::SERVICE_UNAVAILABLE;
+    resp.headers_mut().insert(
+        header::RETRY_AFTER,
+        HeaderValue::from(limit.retry_after.as_secs()),
+    );
+    Ok(resp)
+}
+
+/// Rejects the multipart requests larger than `@server(maxUploadSize:)`
+fn payload_too_large() -> Result<Response<Body>> {
+    let mut response = async_graphql::Response::default();
+    response.errors = vec![ServerError::new("Payload too large", None)];
+
+    let mut resp = GraphQLResponse::from(response).into_response()?;
+    *resp.status_mut() = StatusCode::PAYLOAD_TOO_LARGE;
+    Ok(resp)
+}
+
 fn create_request_context(req: &Request<Body>, app_ctx: &AppContext) -> RequestContext {
     let upstream = app_ctx.blueprint.upstream.clone();
     let allowed = upstream.allowed_headers;
     let allowed_headers = create_allowed_headers(req.headers(), &allowed);
+    let target_headers = create_allowed_headers(req.headers(), &upstream.target_headers);
 
     let _allowed = app_ctx.blueprint.server.get_experimental_headers();
-    RequestContext::from(app_ctx).allowed_headers(allowed_headers)
+    let context = create_context(req.headers(), app_ctx);
+    let req_ctx = RequestContext::from(app_ctx)
+        .allowed_headers(allowed_headers)
+        .target_headers(target_headers)
+        .context(context);
+    let deadline = shorten_deadline(req_ctx.deadline, req.headers());
+    req_ctx.deadline(deadline)
+}
+
+/// What the `context` of the server is rendered with: the headers of the
+/// request, the `vars` of the server and the env.
+struct ContextSource<'a> {
+    headers: &'a HeaderMap,
+    vars: &'a BTreeMap<String, String>,
+    env: &'a dyn EnvIO,
+}
+
+impl PathString for ContextSource<'_> {
+    fn path_string<T: AsRef<str>>(&self, path: &[T]) -> Option<Cow<'_, str>> {
+        let (head, tail) = path.split_first()?;
+        let key = tail.first()?.as_ref();
+
+        match head.as_ref() {
+            "headers" => self.headers.get(key)?.to_str().ok().map(Cow::from),
+            "vars" => self.vars.get(key).map(|value| Cow::from(value.as_str())),
+            "env" => self.env.get(key),
+            _ => None,
+        }
+    }
+}
+
+/// Renders the `context` of the server once per request, so that templates
+/// read the values built out of the headers through `{{.context}}`.
+fn create_context(headers: &HeaderMap, app_ctx: &AppContext) -> BTreeMap<String, String> {
+    let server = &app_ctx.blueprint.server;
+    if server.context.is_empty() {
+        return BTreeMap::new();
+    }
+
+    let ctx = ContextSource {
+        headers,
+        vars: &server.vars,
+        env: app_ctx.runtime.env.as_ref(),
+    };
+    server
+        .context
+        .iter()
+        .map(|(key, value)| (key.clone(), value.render(&ctx)))
+        .collect()
+}
+
+/// Clients can shorten the deadline of the request by sending the
+/// `X-Request-Timeout` header in milliseconds, but never extend it.
+fn shorten_deadline(deadline: Option<Instant>, headers: &HeaderMap) -> Option<Instant> {
+    let requested = headers
+        .get(X_REQUEST_TIMEOUT)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .map(|timeout| Instant::now() + Duration::from_millis(timeout));
+
+    match (deadline, requested) {
+        (Some(deadline), Some(requested)) => Some(deadline.min(requested)),
+        (deadline, requested) => deadline.or(requested),
+    }
 }
 
 fn update_cache_control_header(
@@ -84,14 +193,31 @@ pub fn update_response_headers(
             .extend(app_ctx.blueprint.server.response_headers.clone());
     }
 
+    // Headers copied from the upstream responses
+    let mut upstream_headers = HeaderMap::new();
+
     // Insert Cookie Headers
     if let Some(ref cookie_headers) = req_ctx.cookie_headers {
         let cookie_headers = cookie_headers.lock().unwrap();
-        resp.headers_mut().extend(cookie_headers.deref().clone());
+        upstream_headers.extend(cookie_headers.deref().clone());
     }
 
     // Insert Experimental Headers
-    req_ctx.extend_x_headers(resp.headers_mut());
+    req_ctx.extend_x_headers(&mut upstream_headers);
+
+    strip_headers(
+        &mut upstream_headers,
+        &app_ctx.blueprint.server.strip_headers,
+    );
+    resp.headers_mut().extend(upstream_headers);
+}
+
+/// Removes the headers listed in `@server(stripHeaders:)`. The hop-by-hop
+/// headers are never copied from the upstream responses in the first place.
+fn strip_headers(headers: &mut HeaderMap, strip: &HashSet<HeaderName>) {
+    for name in strip {
+        headers.remove(name);
+    }
 }
 
 #[tracing::instrument(skip_all, fields(otel.name = "graphQL", otel.kind = ?SpanKind::Server))]
@@ -100,16 +226,87 @@ pub async fn graphql_request<T: DeserializeOwned + GraphQLRequestLike>(
     app_ctx: &AppContext,
     req_counter: &mut RequestCounter,
 ) -> Result<Response<Body>> {
-    req_counter.set_http_route("/graphql");
+    req_counter.set_http_route(&app_ctx.blueprint.server.graphql_path);
+    // Held until the response is built
+    let _permit = match &app_ctx.request_limit {
+        Some(limit) => match limit.acquire().await {
+            Some(permit) => Some(permit),
+            None => return service_unavailable(limit),
+        },
+        None => None,
+    };
     let req_ctx = Arc::new(create_request_context(&req, app_ctx));
-    let bytes = hyper::body::to_bytes(req.into_body()).await?;
-    let graphql_request = serde_json::from_slice::<T>(&bytes);
+    let content_type = req
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let max_upload_size = app_ctx.blueprint.server.max_upload_size;
+    let is_multipart = content_type.as_deref().is_some_and(multipart::is_multipart);
+    let bytes = if is_multipart {
+        // Uploads are buffered, so their size is checked before and while
+        // they are read
+        let content_length = req
+            .headers()
+            .get(CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<usize>().ok());
+        if content_length.is_some_and(|length| length > max_upload_size) {
+            return payload_too_large();
+        }
+
+        let body = http_body::Limited::new(req.into_body(), max_upload_size);
+        match hyper::body::to_bytes(body).await {
+            Ok(bytes) => bytes,
+            Err(err) if err.is::<http_body::LengthLimitError>() => return payload_too_large(),
+            Err(err) => return Err(anyhow::anyhow!(err)),
+        }
+    } else {
+        hyper::body::to_bytes(req.into_body()).await?
+    };
+    let graphql_request = match content_type {
+        Some(content_type) if is_multipart => {
+            multipart::receive_request(&content_type, &bytes, max_upload_size)
+                .await
+                .and_then(T::from_batch)
+        }
+        _ => serde_json::from_slice::<T>(&bytes).map_err(anyhow::Error::from),
+    };
     match graphql_request {
+        Ok(request) if request.queries().len() > app_ctx.blueprint.server.max_batch_size => {
+            let mut response = async_graphql::Response::default();
+            response.errors = vec![ServerError::new(
+                format!(
+                    "Batch of {} operations exceeds the maxBatchSize of {}",
+                    request.queries().len(),
+                    app_ctx.blueprint.server.max_batch_size
+                ),
+                None,
+            )];
+
+            let mut resp = GraphQLResponse::from(response).into_response()?;
+            *resp.status_mut() = StatusCode::BAD_REQUEST;
+            Ok(resp)
+        }
+        Ok(request) if !is_allowed_operation(&request, app_ctx) => {
+            let mut response = async_graphql::Response::default();
+            response.errors = vec![ServerError::new("Operation is not allowed", None)];
+
+            let mut resp = GraphQLResponse::from(response).into_response()?;
+            *resp.status_mut() = StatusCode::FORBIDDEN;
+            Ok(resp)
+        }
         Ok(mut request) => {
             let _ = request.parse_query();
             let mut response = request.data(req_ctx.clone()).execute(&app_ctx.schema).await;
 
             response = update_cache_control_header(response, app_ctx, req_ctx.clone());
+            if let Some(version) = app_ctx.blueprint.version.as_ref() {
+                response = response.set_extension("version", Value::from(version.as_str()));
+            }
+            if app_ctx.blueprint.server.include_stats {
+                response = response.set_extension("tailcall", req_ctx.stats.to_value());
+            }
             let mut resp = response.into_response()?;
             update_response_headers(&mut resp, &req_ctx, app_ctx);
             Ok(resp)
@@ -117,7 +314,7 @@ pub async fn graphql_request<T: DeserializeOwned + GraphQLRequestLike>(
         Err(err) => {
             tracing::error!(
                 "Failed to parse request: {}",
-                String::from_utf8(bytes.to_vec()).unwrap()
+                String::from_utf8_lossy(&bytes)
             );
 
             let mut response = async_graphql::Response::default();
@@ -130,6 +327,20 @@ pub async fn graphql_request<T: DeserializeOwned + GraphQLRequestLike>(
     }
 }
 
+/// Checks that every operation of the request is listed in
+/// `@server(allowedOperations:)`, when it's configured. Serving a playground
+/// means the server runs in dev mode, so any operation is allowed then.
+fn is_allowed_operation<T: GraphQLRequestLike>(request: &T, app_ctx: &AppContext) -> bool {
+    match &app_ctx.blueprint.server.allowed_operations {
+        Some(_) if app_ctx.playground.is_some() => true,
+        Some(allowed_operations) => request
+            .queries()
+            .iter()
+            .all(|query| allowed_operations.contains(&operation_hash(query))),
+        None => true,
+    }
+}
+
 fn create_allowed_headers(headers: &HeaderMap, allowed: &BTreeSet<String>) -> HeaderMap {
     let mut new_headers = HeaderMap::new();
     for (k, v) in headers.iter() {
@@ -252,7 +463,7 @@ async fn handle_rest_apis(
                 .execute(&app_ctx.schema)
                 .await;
             response = update_cache_control_header(response, app_ctx.as_ref(), req_ctx.clone());
-            let mut resp = response.into_rest_response()?;
+            let mut resp = response.into_rest_response(app_ctx.blueprint.server.error_format)?;
             update_response_headers(&mut resp, &req_ctx, &app_ctx);
             Ok(resp)
         }
@@ -268,20 +479,21 @@ async fn handle_request_inner<T: DeserializeOwned + GraphQLRequestLike>(
     app_ctx: Arc<AppContext>,
     req_counter: &mut RequestCounter,
 ) -> Result<Response<Body>> {
-    if req.uri().path().starts_with(API_URL_PREFIX) {
+    let is_graphql_path = req.uri().path() == app_ctx.blueprint.server.graphql_path;
+    // The GraphQL endpoint can be mounted under the prefix of the REST APIs
+    if !is_graphql_path && req.uri().path().starts_with(API_URL_PREFIX) {
         return handle_rest_apis(req, app_ctx, req_counter).await;
     }
 
     match *req.method() {
         // NOTE:
-        // The first check for the route should be for `/graphql`
+        // The first check for the route should be for the GraphQL path
         // This is always going to be the most used route.
-        hyper::Method::POST if req.uri().path() == "/graphql" => {
+        hyper::Method::POST if is_graphql_path => {
             graphql_request::<T>(req, app_ctx.as_ref(), req_counter).await
         }
         hyper::Method::POST
-            if app_ctx.blueprint.server.enable_showcase
-                && req.uri().path() == "/showcase/graphql" =>
+            if app_ctx.blueprint.server.enable_showcase && req.uri().path() == SHOWCASE_PATH =>
         {
             let app_ctx =
                 match showcase::create_app_ctx::<T>(&req, app_ctx.runtime.clone(), false).await? {
@@ -293,6 +505,10 @@ async fn handle_request_inner<T: DeserializeOwned + GraphQLRequestLike>(
         }
 
         hyper::Method::GET => {
+            if let Some(playground) = app_ctx.playground.as_ref().filter(|_| is_graphql_path) {
+                return playground_response(playground);
+            }
+
             if let Some(TelemetryExporter::Prometheus(prometheus)) =
                 app_ctx.blueprint.telemetry.export.as_ref()
             {
@@ -347,6 +563,38 @@ pub async fn handle_request<T: DeserializeOwned + GraphQLRequestLike>(
 
 #[cfg(test)]
 mod test {
+    use std::sync::Arc;
+
+    use hyper::{Body, Request, Response, StatusCode};
+
+    use super::handle_request;
+    use crate::core::app_context::AppContext;
+    use crate::core::async_graphql_hyper::GraphQLRequest;
+    use crate::core::blueprint::Blueprint;
+    use crate::core::config::{Config, ConfigModule, Playground};
+    use crate::core::http::playground_source;
+    use crate::core::rest::EndpointSet;
+    use crate::core::valid::Validator;
+
+    fn app_ctx(sdl: &str) -> Arc<AppContext> {
+        let config = Config::from_sdl(sdl).to_result().unwrap();
+        let blueprint = Blueprint::try_from(&ConfigModule::from(config)).unwrap();
+        let runtime = crate::core::runtime::test::init(None);
+        Arc::new(AppContext::new(blueprint, runtime, EndpointSet::default()))
+    }
+
+    async fn post_graphql(app_ctx: Arc<AppContext>, query: &str) -> Response<Body> {
+        let body = serde_json::json!({ "query": query }).to_string();
+        let req = Request::builder()
+            .method("POST")
+            .uri("http://localhost:8000/graphql")
+            .body(Body::from(body))
+            .unwrap();
+        handle_request::<GraphQLRequest>(req, app_ctx)
+            .await
+            .unwrap()
+    }
+
     #[test]
     fn test_create_allowed_headers() {
         use std::collections::BTreeSet;
@@ -367,4 +615,360 @@ mod test {
         assert_eq!(new_headers.get("x-foo").unwrap(), "bar");
         assert_eq!(new_headers.get("x-bar").unwrap(), "foo");
     }
+
+    #[test]
+    fn test_strip_headers() {
+        use std::collections::HashSet;
+
+        use hyper::header::{HeaderMap, HeaderName, HeaderValue};
+
+        use super::strip_headers;
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-debug", HeaderValue::from_static("1"));
+        headers.insert("x-trace", HeaderValue::from_static("1"));
+
+        let strip = HashSet::from([HeaderName::from_static("x-debug")]);
+        strip_headers(&mut headers, &strip);
+
+        assert_eq!(headers.len(), 1);
+        assert_eq!(headers.get("x-trace").unwrap(), "1");
+    }
+
+    #[test]
+    fn test_context_source() {
+        use std::collections::BTreeMap;
+
+        use hyper::header::{HeaderMap, HeaderValue};
+
+        use super::ContextSource;
+        use crate::core::path::PathString;
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-tenant", HeaderValue::from_static("acme"));
+        let vars = BTreeMap::from([("region".to_string(), "eu".to_string())]);
+        let runtime = crate::core::runtime::test::init(None);
+        let ctx = ContextSource { headers: &headers, vars: &vars, env: runtime.env.as_ref() };
+
+        assert_eq!(ctx.path_string(&["headers", "x-tenant"]).unwrap(), "acme");
+        assert_eq!(ctx.path_string(&["vars", "region"]).unwrap(), "eu");
+        assert_eq!(ctx.path_string(&["headers", "x-missing"]), None);
+        assert_eq!(ctx.path_string(&["value", "x-tenant"]), None);
+    }
+
+    #[test]
+    fn test_shorten_deadline() {
+        use std::time::{Duration, Instant};
+
+        use hyper::header::{HeaderMap, HeaderValue};
+
+        use super::{shorten_deadline, X_REQUEST_TIMEOUT};
+
+        let mut headers = HeaderMap::new();
+        headers.insert(X_REQUEST_TIMEOUT, HeaderValue::from_static("100"));
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let shortened = shorten_deadline(Some(deadline), &headers).unwrap();
+        assert!(shortened < deadline);
+
+        let deadline = Instant::now() + Duration::from_millis(10);
+        assert_eq!(shorten_deadline(Some(deadline), &headers), Some(deadline));
+
+        assert!(shorten_deadline(None, &headers).is_some());
+        assert_eq!(shorten_deadline(None, &HeaderMap::new()), None);
+    }
+
+    async fn get_playground(server: &str) -> Response<Body> {
+        let app_ctx = app_ctx(&format!(
+            r#"
+            schema {server} {{
+              query: Query
+            }}
+
+            type Query {{
+              greet: String @expr(body: "Hello World!")
+            }}
+            "#
+        ));
+
+        let req = Request::builder()
+            .method("GET")
+            .uri("http://localhost:8000/graphql")
+            .body(Body::empty())
+            .unwrap();
+        handle_request::<GraphQLRequest>(req, app_ctx)
+            .await
+            .unwrap()
+    }
+
+    async fn assert_playground(res: Response<Body>, playground: Playground) {
+        assert_eq!(res.status(), 200);
+        assert_eq!(
+            res.headers().get("content-type").unwrap(),
+            "text/html; charset=utf-8"
+        );
+
+        let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+        let expected = playground_source(playground, "/graphql").unwrap();
+        assert_eq!(body, expected.as_bytes());
+    }
+
+    #[tokio::test]
+    async fn test_graphiql() {
+        let res = get_playground("@server(playground: GRAPHIQL)").await;
+        assert_playground(res, Playground::GraphiQL).await;
+    }
+
+    #[tokio::test]
+    async fn test_graphiql_deprecated() {
+        let res = get_playground("@server(graphiql: true)").await;
+        assert_playground(res, Playground::GraphiQL).await;
+    }
+
+    #[tokio::test]
+    async fn test_sandbox() {
+        let res = get_playground("@server(playground: SANDBOX)").await;
+        assert_playground(res, Playground::Sandbox).await;
+    }
+
+    #[tokio::test]
+    async fn test_playground_none() {
+        let res = get_playground("@server(graphiql: true, playground: NONE)").await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+        let res = get_playground("").await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_upload_rejected_on_content_length() {
+        let app_ctx = app_ctx(
+            r#"
+            schema @server(maxUploadSize: 10) {
+              query: Query
+            }
+
+            type Query {
+              greet: String @expr(body: "Hello World!")
+            }
+            "#,
+        );
+
+        // The body is never read, so it doesn't matter that it's empty
+        let req = Request::builder()
+            .method("POST")
+            .uri("http://localhost:8000/graphql")
+            .header("content-type", "multipart/form-data; boundary=abc")
+            .header("content-length", "1000")
+            .body(Body::empty())
+            .unwrap();
+        let res = handle_request::<GraphQLRequest>(req, app_ctx)
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn test_sibling_fields_resolve_concurrently() {
+        use std::time::{Duration, Instant};
+
+        let server = httpmock::MockServer::start();
+        let mocks = ["/foo", "/bar"].map(|path| {
+            server.mock(|when, then| {
+                when.method(httpmock::Method::GET).path(path);
+                then.status(200).body("1").delay(Duration::from_millis(100));
+            })
+        });
+
+        let app_ctx = app_ctx(&format!(
+            r#"
+            schema @upstream(baseURL: "{}") {{
+              query: Query
+            }}
+
+            type Query {{
+              foo: Int @http(path: "/foo")
+              bar: Int @http(path: "/bar")
+            }}
+            "#,
+            server.base_url()
+        ));
+
+        let start = Instant::now();
+        let res = post_graphql(app_ctx, "{ foo bar }").await;
+        let elapsed = start.elapsed();
+
+        let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+        assert_eq!(body, r#"{"data":{"foo":1,"bar":1}}"#);
+        mocks.iter().for_each(|mock| mock.assert());
+
+        // Both upstreams take 100ms, they would take 200ms one after the other
+        assert!(elapsed < Duration::from_millis(200), "took {:?}", elapsed);
+    }
+
+    #[tokio::test]
+    async fn test_request_limits() {
+        use std::time::Duration;
+
+        use futures_util::future::join_all;
+
+        let server = httpmock::MockServer::start();
+        server.mock(|when, then| {
+            when.method(httpmock::Method::GET).path("/foo");
+            then.status(200).body("1").delay(Duration::from_millis(100));
+        });
+
+        let app_ctx = app_ctx(&format!(
+            r#"
+            schema
+              @server(requestLimits: {{maxConcurrentRequests: 1, queueSize: 1, retryAfter: 5}})
+              @upstream(baseURL: "{}") {{
+              query: Query
+            }}
+
+            type Query {{
+              foo: Int @http(path: "/foo")
+            }}
+            "#,
+            server.base_url()
+        ));
+
+        // One request runs, one is queued and the last one is rejected
+        let responses = join_all((0..3).map(|_| post_graphql(app_ctx.clone(), "{ foo }"))).await;
+
+        let statuses = responses.iter().map(|res| res.status()).collect::<Vec<_>>();
+        assert_eq!(
+            statuses,
+            vec![
+                StatusCode::OK,
+                StatusCode::OK,
+                StatusCode::SERVICE_UNAVAILABLE
+            ]
+        );
+        assert_eq!(responses[2].headers().get("retry-after").unwrap(), "5");
+    }
+
+    #[tokio::test]
+    async fn test_include_stats() {
+        use std::time::Duration;
+
+        use serde_json::json;
+
+        let server = httpmock::MockServer::start();
+        let mocks = ["/foo", "/bar"].map(|path| {
+            server.mock(|when, then| {
+                when.method(httpmock::Method::GET).path(path);
+                then.status(200).body("1").delay(Duration::from_millis(50));
+            })
+        });
+
+        let app_ctx = app_ctx(&format!(
+            r#"
+            schema @server(includeStats: true) @upstream(baseURL: "{}") {{
+              query: Query
+            }}
+
+            type Query {{
+              foo: Int @http(path: "/foo")
+              bar: Int @http(path: "/bar") @cache(maxAge: 60000)
+            }}
+            "#,
+            server.base_url()
+        ));
+
+        let mut stats = vec![];
+        for _ in 0..2 {
+            let res = post_graphql(app_ctx.clone(), "{ foo bar }").await;
+            let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+            let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(body["data"], json!({"foo": 1, "bar": 1}));
+            stats.push(body["extensions"]["tailcall"].clone());
+        }
+
+        // The second request reads `bar` from the cache
+        assert_eq!(stats[0]["upstreamCalls"], 2);
+        assert_eq!(stats[0]["cacheHits"], 0);
+        assert!(stats[0]["upstreamLatency"].as_u64().unwrap() >= 100);
+        assert_eq!(stats[1]["upstreamCalls"], 1);
+        assert_eq!(stats[1]["cacheHits"], 1);
+        assert!(stats[1]["upstreamLatency"].as_u64().unwrap() >= 50);
+        mocks[0].assert_hits(2);
+        mocks[1].assert_hits(1);
+    }
+
+    #[tokio::test]
+    async fn test_batched_stats() {
+        use serde_json::json;
+
+        let server = httpmock::MockServer::start();
+        let posts = server.mock(|when, then| {
+            when.method(httpmock::Method::GET).path("/posts");
+            then.status(200)
+                .json_body(json!([{"userId": 1}, {"userId": 2}]));
+        });
+        let users = server.mock(|when, then| {
+            when.method(httpmock::Method::GET).path("/users");
+            then.status(200).json_body(json!([{"id": 1}, {"id": 2}]));
+        });
+
+        let app_ctx = app_ctx(&format!(
+            r#"
+            schema @server(includeStats: true) @upstream(baseURL: "{}", batch: {{delay: 10}}) {{
+              query: Query
+            }}
+
+            type Query {{
+              posts: [Post] @http(path: "/posts")
+            }}
+
+            type Post {{
+              userId: Int!
+              user: User
+                @http(
+                  path: "/users"
+                  query: [{{key: "id", value: "{{{{.value.userId}}}}"}}]
+                  batchKey: ["id"]
+                )
+            }}
+
+            type User {{
+              id: Int
+            }}
+            "#,
+            server.base_url()
+        ));
+
+        let res = post_graphql(app_ctx, "{ posts { user { id } } }").await;
+        let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body["data"],
+            json!({"posts": [{"user": {"id": 1}}, {"user": {"id": 2}}]})
+        );
+
+        // Both users are loaded with a single batched call
+        assert_eq!(body["extensions"]["tailcall"]["upstreamCalls"], 2);
+        posts.assert_hits(1);
+        users.assert_hits(1);
+    }
+
+    #[tokio::test]
+    async fn test_stats_omitted_by_default() {
+        let app_ctx = app_ctx(
+            r#"
+            schema {
+              query: Query
+            }
+
+            type Query {
+              greet: String @expr(body: "Hello World!")
+            }
+            "#,
+        );
+
+        let res = post_graphql(app_ctx, "{ greet }").await;
+        let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+        assert_eq!(body, r#"{"data":{"greet":"Hello World!"}}"#);
+    }
 }
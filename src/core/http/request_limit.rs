@@ -0,0 +1,64 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Limits the number of requests executed at the same time to
+/// `max_concurrent_requests`. Up to `queue_size` requests wait for a permit in
+/// the order they came in, the others are rejected right away.
+#[derive(Debug)]
+pub struct RequestLimit {
+    /// Permits of the running and the queued requests
+    admitted: Arc<Semaphore>,
+    running: Arc<Semaphore>,
+    pub retry_after: Duration,
+}
+
+/// Held for as long as the request is executed
+pub struct RequestPermit {
+    _admitted: OwnedSemaphorePermit,
+    _running: OwnedSemaphorePermit,
+}
+
+impl RequestLimit {
+    pub fn new(max_concurrent_requests: usize, queue_size: usize, retry_after: Duration) -> Self {
+        Self {
+            admitted: Arc::new(Semaphore::new(max_concurrent_requests + queue_size)),
+            running: Arc::new(Semaphore::new(max_concurrent_requests)),
+            retry_after,
+        }
+    }
+
+    /// Waits for the request to be allowed to run, returns `None` when the
+    /// queue is full.
+    pub async fn acquire(&self) -> Option<RequestPermit> {
+        let admitted = self.admitted.clone().try_acquire_owned().ok()?;
+        let running = self.running.clone().acquire_owned().await.ok()?;
+
+        Some(RequestPermit { _admitted: admitted, _running: running })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_queue_full() {
+        let limit = RequestLimit::new(1, 1, Duration::from_secs(1));
+
+        let running = limit.acquire().await;
+        assert!(running.is_some());
+
+        // Waits for the running request to finish
+        let queued = limit.acquire();
+        tokio::pin!(queued);
+        assert!(futures_util::poll!(queued.as_mut()).is_pending());
+
+        assert!(limit.acquire().await.is_none());
+
+        drop(running);
+        assert!(queued.await.is_some());
+        assert!(limit.acquire().await.is_some());
+    }
+}
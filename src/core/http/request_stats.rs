@@ -0,0 +1,119 @@
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use async_graphql::Value;
+use serde_json::json;
+
+/// Counters of the work done to resolve a single request, sent back to the
+/// client in the `extensions` of the response with
+/// `@server(includeStats: true)`.
+#[derive(Default, Debug)]
+pub struct RequestStats {
+    upstream_calls: AtomicU64,
+    upstream_latency: AtomicU64,
+    cache_hits: AtomicU64,
+}
+
+impl RequestStats {
+    /// Records a call to an upstream along with the time it took
+    pub fn add_upstream_call(&self, latency: Duration) {
+        self.upstream_calls.fetch_add(1, Ordering::Relaxed);
+        self.upstream_latency
+            .fetch_add(latency.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    /// Times a call sent to an upstream and records it once in the stats of
+    /// every request with fields batched into the call.
+    pub fn record<'a, F: Future>(
+        stats: impl IntoIterator<Item = &'a Arc<RequestStats>>,
+        call: F,
+    ) -> impl Future<Output = F::Output> {
+        let mut requests: Vec<Arc<RequestStats>> = vec![];
+        for stats in stats {
+            if !requests.iter().any(|other| Arc::ptr_eq(other, stats)) {
+                requests.push(stats.clone());
+            }
+        }
+
+        async move {
+            let start = Instant::now();
+            let output = call.await;
+            let latency = start.elapsed();
+            for stats in requests {
+                stats.add_upstream_call(latency);
+            }
+            output
+        }
+    }
+
+    /// Records a value served from the `@cache` instead of the upstream
+    pub fn add_cache_hit(&self) {
+        self.cache_hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn upstream_calls(&self) -> u64 {
+        self.upstream_calls.load(Ordering::Relaxed)
+    }
+
+    /// The total time spent in the calls to the upstreams in milliseconds.
+    /// Concurrent calls add up, so it can exceed the duration of the request.
+    pub fn upstream_latency(&self) -> u64 {
+        self.upstream_latency.load(Ordering::Relaxed)
+    }
+
+    pub fn cache_hits(&self) -> u64 {
+        self.cache_hits.load(Ordering::Relaxed)
+    }
+
+    pub fn to_value(&self) -> Value {
+        Value::from_json(json!({
+            "upstreamCalls": self.upstream_calls(),
+            "upstreamLatency": self.upstream_latency(),
+            "cacheHits": self.cache_hits(),
+        }))
+        .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use async_graphql::Value;
+    use serde_json::json;
+
+    use super::RequestStats;
+
+    #[test]
+    fn test_to_value() {
+        let stats = RequestStats::default();
+        stats.add_upstream_call(Duration::from_millis(20));
+        stats.add_upstream_call(Duration::from_millis(30));
+        stats.add_cache_hit();
+
+        assert_eq!(
+            stats.to_value(),
+            Value::from_json(json!({
+                "upstreamCalls": 2,
+                "upstreamLatency": 50,
+                "cacheHits": 1,
+            }))
+            .unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_record_batched_call() {
+        let first = Arc::new(RequestStats::default());
+        let second = Arc::new(RequestStats::default());
+
+        let output = RequestStats::record([&first, &first, &second], async { 42 }).await;
+
+        assert_eq!(output, 42);
+        assert_eq!(first.upstream_calls(), 1);
+        assert_eq!(second.upstream_calls(), 1);
+    }
+}
@@ -1,16 +1,20 @@
 use std::borrow::Cow;
+use std::collections::BTreeSet;
 use std::hash::{Hash, Hasher};
+use std::sync::Arc;
 
+use async_graphql_value::ConstValue;
 use derive_setters::Setters;
-use hyper::HeaderMap;
 use reqwest::header::HeaderValue;
+use sha2::{Digest, Sha256};
 use tailcall_hasher::TailcallHasher;
 use url::Url;
 
-use crate::core::config::Encoding;
+use crate::core::config::{Encoding, QueryEncoding};
 use crate::core::endpoint::Endpoint;
 use crate::core::has_headers::HasHeaders;
 use crate::core::helpers::headers::MustacheHeaders;
+use crate::core::http::{multipart, HmacSigner, QuerySigner, SigV4, UrlPool};
 use crate::core::ir::{CacheKey, IoId};
 use crate::core::mustache::Mustache;
 use crate::core::path::PathString;
@@ -28,39 +32,126 @@ pub struct RequestTemplate {
     pub body_path: Option<Mustache>,
     pub endpoint: Endpoint,
     pub encoding: Encoding,
+    pub query_encoding: QueryEncoding,
+    pub sigv4: Option<SigV4>,
+    pub hmac: Option<HmacSigner>,
+    /// Query params computed from the other query params of the request.
+    pub signed_query: Vec<QuerySigner>,
+    /// When set, the root url is picked from the pool for every request and
+    /// `root_url` is only used to compute the cache key.
+    pub url_pool: Option<Arc<UrlPool>>,
+    /// Hosts the rendered url is allowed to point to, every host is allowed
+    /// when empty.
+    pub allowed_hosts: Arc<Vec<String>>,
+    /// Headers of the incoming request forwarded to the API, the globally
+    /// allowed ones are forwarded when it's not set.
+    pub allowed_headers: Option<BTreeSet<String>>,
+    /// Sends an `Idempotency-Key` header, derived from the request unless the
+    /// client already forwards one.
+    pub idempotent: bool,
+}
+
+const IDEMPOTENCY_KEY: &str = "idempotency-key";
+
+/// SHA-256 of the method, the url and the body of the request, hex encoded.
+fn idempotency_key(req: &reqwest::Request) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(req.method().as_str());
+    hasher.update(b"\n");
+    hasher.update(req.url().as_str());
+    hasher.update(b"\n");
+    if let Some(body) = req.body().and_then(|body| body.as_bytes()) {
+        hasher.update(body);
+    }
+
+    hex::encode(hasher.finalize())
 }
 
 impl RequestTemplate {
+    /// Objects are rendered as JSON, they are only parsed back when the query
+    /// isn't sent as JSON
+    fn to_query_object(&self, value: &str) -> Option<serde_json::Value> {
+        if self.query_encoding == QueryEncoding::Json || !value.starts_with('{') {
+            return None;
+        }
+
+        serde_json::from_str::<serde_json::Value>(value)
+            .ok()
+            .filter(|value| value.is_object())
+    }
+
+    /// Checks the host of the url against `allowed_hosts`, so that values
+    /// coming from the request can't point the url to another host.
+    fn check_host(&self, url: &Url) -> anyhow::Result<()> {
+        if self.allowed_hosts.is_empty() {
+            return Ok(());
+        }
+
+        let host = url.host_str().unwrap_or_default();
+        let allowed = self
+            .allowed_hosts
+            .iter()
+            .any(|allowed| match allowed.strip_prefix("*.") {
+                Some(domain) => host
+                    .strip_suffix(domain)
+                    .is_some_and(|subdomain| subdomain.ends_with('.') && subdomain.len() > 1),
+                None => host == allowed,
+            });
+
+        if allowed {
+            Ok(())
+        } else {
+            anyhow::bail!("Host {} is not allowed", host)
+        }
+    }
+
     /// Creates a URL for the context
     /// Fills in all the mustache templates with required values.
-    fn create_url<C: PathString>(&self, ctx: &C) -> anyhow::Result<Url> {
-        let mut url = url::Url::parse(self.root_url.render(ctx).as_str())?;
-        if self.query.is_empty() && self.root_url.is_const() {
+    fn create_url<C: PathString>(&self, root_url: &Mustache, ctx: &C) -> anyhow::Result<Url> {
+        let mut url = url::Url::parse(root_url.render(ctx).as_str())?;
+        self.check_host(&url)?;
+        if !root_url.is_const() && url.path().contains("//") {
+            let path = collapse_slashes(url.path());
+            url.set_path(&path);
+        }
+        if self.query.is_empty() && root_url.is_const() {
             return Ok(url);
         }
-        let extra_qp = self.query.iter().filter_map(|(k, v)| {
+        let mut extra_qp = Vec::with_capacity(self.query.len());
+        for (k, v) in self.query.iter() {
             let value = v.render(ctx);
             if value.is_empty() {
-                None
-            } else {
-                Some((Cow::Borrowed(k.as_str()), Cow::Owned(value)))
+                continue;
+            }
+            match self.to_query_object(&value) {
+                Some(object) => {
+                    encode_query(&self.query_encoding, k.to_owned(), &object, &mut extra_qp)
+                }
+                None => extra_qp.push((Cow::Borrowed(k.as_str()), Cow::Owned(value))),
             }
-        });
+        }
 
         let base_qp = url
             .query_pairs()
             .filter_map(|(k, v)| if v.is_empty() { None } else { Some((k, v)) });
+        let mut query = base_qp.chain(extra_qp).collect::<Vec<_>>();
 
-        let qp_string = base_qp
-            .chain(extra_qp)
-            .map(|(k, v)| format!("{}={}", k, v))
-            .fold("".to_string(), |str, item| {
-                if str.is_empty() {
-                    item
-                } else {
-                    format!("{}&{}", str, item)
-                }
-            });
+        for signer in self.signed_query.iter() {
+            let value = signer.sign(&query, ctx)?;
+            query.push((Cow::Borrowed(signer.key.as_str()), Cow::Owned(value)));
+        }
+
+        // The query string is written into a single buffer instead of
+        // formatting a new string for every parameter
+        let mut qp_string = String::new();
+        for (k, v) in query {
+            if !qp_string.is_empty() {
+                qp_string.push('&');
+            }
+            qp_string.push_str(&k);
+            qp_string.push('=');
+            qp_string.push_str(&v);
+        }
 
         if qp_string.is_empty() {
             url.set_query(None);
@@ -75,22 +166,35 @@ impl RequestTemplate {
     /// Returns true if there are not templates
     pub fn is_const(&self) -> bool {
         self.root_url.is_const()
+            && self
+                .url_pool
+                .as_ref()
+                .map_or(true, |pool| pool.root_urls().all(Mustache::is_const))
             && self.body_path.as_ref().map_or(true, Mustache::is_const)
             && self.query.iter().all(|(_, v)| v.is_const())
+            && self
+                .signed_query
+                .iter()
+                .all(|signer| signer.secret.is_const())
             && self.headers.iter().all(|(_, v)| v.is_const())
     }
 
-    /// Creates a HeaderMap for the context
-    fn create_headers<C: PathString>(&self, ctx: &C) -> HeaderMap {
-        let mut header_map = HeaderMap::new();
-
-        for (k, v) in &self.headers {
-            if let Ok(header_value) = HeaderValue::from_str(&v.render(ctx)) {
-                header_map.insert(k, header_value);
-            }
-        }
-
-        header_map
+    /// Pre-renders the parts of the template that resolve to the same value
+    /// for every request, leaving the dynamic ones as templates.
+    pub fn partially_render<C: PathString>(mut self, ctx: &C) -> Self {
+        self.root_url = self.root_url.partially_render(ctx);
+        self.query = self
+            .query
+            .into_iter()
+            .map(|(key, value)| (key, value.partially_render(ctx)))
+            .collect();
+        self.headers = self
+            .headers
+            .into_iter()
+            .map(|(name, value)| (name, value.partially_render(ctx)))
+            .collect();
+        self.body_path = self.body_path.map(|body| body.partially_render(ctx));
+        self
     }
 
     /// Creates a Request for the given context
@@ -99,12 +203,33 @@ impl RequestTemplate {
         ctx: &C,
     ) -> anyhow::Result<reqwest::Request> {
         // Create url
-        let url = self.create_url(ctx)?;
+        let root_url = self
+            .url_pool
+            .as_ref()
+            .map_or(&self.root_url, |pool| pool.next());
+        let url = self.create_url(root_url, ctx)?;
         let method = self.method.clone();
         let mut req = reqwest::Request::new(method, url);
         req = self.set_headers(req, ctx);
         req = self.set_body(req, ctx)?;
 
+        // The key is derived from the rendered request, so that a retry of
+        // the request sends the same key
+        if self.idempotent && !req.headers().contains_key(IDEMPOTENCY_KEY) {
+            let key = HeaderValue::from_str(&idempotency_key(&req))?;
+            req.headers_mut().insert(IDEMPOTENCY_KEY, key);
+        }
+
+        // The body signature is a header, so it has to be added before the
+        // request is signed with SigV4
+        if let Some(hmac) = &self.hmac {
+            hmac.sign(&mut req, ctx)?;
+        }
+
+        if let Some(sigv4) = &self.sigv4 {
+            sigv4.sign(&mut req, ctx)?;
+        }
+
         Ok(req)
     }
 
@@ -130,6 +255,19 @@ impl RequestTemplate {
 
                     req.body_mut().replace(form_data.into());
                 }
+                Encoding::MultipartFormData => {
+                    let body = serde_json::from_str::<ConstValue>(&body_path.render(ctx))?;
+                    let (boundary, body) = multipart::encode(&body)?;
+
+                    req.headers_mut().insert(
+                        reqwest::header::CONTENT_TYPE,
+                        HeaderValue::from_str(&format!(
+                            "multipart/form-data; boundary={}",
+                            boundary
+                        ))?,
+                    );
+                    req.body_mut().replace(body.into());
+                }
             }
         }
         Ok(req)
@@ -141,28 +279,31 @@ impl RequestTemplate {
         mut req: reqwest::Request,
         ctx: &C,
     ) -> reqwest::Request {
-        let headers = self.create_headers(ctx);
-        if !headers.is_empty() {
-            req.headers_mut().extend(headers);
+        let headers = req.headers_mut();
+        // The headers are rendered straight into the request, the request is
+        // new so there's nothing to replace
+        for (k, v) in &self.headers {
+            if let Ok(header_value) = HeaderValue::from_str(&v.render(ctx)) {
+                headers.insert(k, header_value);
+            }
         }
 
-        let headers = req.headers_mut();
         // We want to set the header value based on encoding
         // TODO: potential of optimizations.
         // Can set content-type headers while creating the request template
-        if self.method != reqwest::Method::GET {
-            headers.insert(
-                reqwest::header::CONTENT_TYPE,
-                match self.encoding {
-                    Encoding::ApplicationJson => HeaderValue::from_static("application/json"),
-                    Encoding::ApplicationXWwwFormUrlencoded => {
-                        HeaderValue::from_static("application/x-www-form-urlencoded")
-                    }
-                },
-            );
+        let content_type = match self.encoding {
+            Encoding::ApplicationJson => Some(HeaderValue::from_static("application/json")),
+            Encoding::ApplicationXWwwFormUrlencoded => Some(HeaderValue::from_static(
+                "application/x-www-form-urlencoded",
+            )),
+            // The boundary is only known once the body is encoded
+            Encoding::MultipartFormData => None,
+        };
+        if let Some(content_type) = content_type.filter(|_| self.method != reqwest::Method::GET) {
+            headers.insert(reqwest::header::CONTENT_TYPE, content_type);
         }
 
-        headers.extend(ctx.headers().to_owned());
+        headers.extend(ctx.forwarded_headers(self.allowed_headers.as_ref()));
         req
     }
 
@@ -175,6 +316,14 @@ impl RequestTemplate {
             body_path: Default::default(),
             endpoint: Endpoint::new(root_url.to_string()),
             encoding: Default::default(),
+            query_encoding: Default::default(),
+            sigv4: Default::default(),
+            hmac: Default::default(),
+            signed_query: Default::default(),
+            url_pool: Default::default(),
+            allowed_hosts: Default::default(),
+            allowed_headers: Default::default(),
+            idempotent: Default::default(),
         })
     }
 
@@ -189,6 +338,55 @@ impl RequestTemplate {
     }
 }
 
+/// Flattens an object into query params, eg. `{"status": "active", "tag":
+/// ["x"]}` passed as `filter` is encoded as
+/// `filter[status]=active&filter[tag][]=x` with brackets and as
+/// `filter.status=active&filter.tag=x` with dots.
+fn encode_query<'a>(
+    encoding: &QueryEncoding,
+    key: String,
+    value: &serde_json::Value,
+    query: &mut Vec<(Cow<'a, str>, Cow<'a, str>)>,
+) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (name, value) in map {
+                let key = match encoding {
+                    QueryEncoding::Dots => format!("{key}.{name}"),
+                    _ => format!("{key}[{name}]"),
+                };
+                encode_query(encoding, key, value, query);
+            }
+        }
+        serde_json::Value::Array(list) => {
+            let key = match encoding {
+                QueryEncoding::Dots => key,
+                _ => format!("{key}[]"),
+            };
+            for value in list {
+                encode_query(encoding, key.clone(), value, query);
+            }
+        }
+        serde_json::Value::Null => {}
+        serde_json::Value::String(value) => {
+            query.push((Cow::Owned(key), Cow::Owned(value.to_owned())))
+        }
+        value => query.push((Cow::Owned(key), Cow::Owned(value.to_string()))),
+    }
+}
+
+/// Collapses the empty segments left in the path by the templates that
+/// rendered empty, eg. `/users//posts` for `/users/{{.args.group}}/posts`.
+fn collapse_slashes(path: &str) -> String {
+    let mut collapsed = String::with_capacity(path.len());
+    for ch in path.chars() {
+        if ch != '/' || !collapsed.ends_with('/') {
+            collapsed.push(ch);
+        }
+    }
+    collapsed
+}
+
 impl TryFrom<Endpoint> for RequestTemplate {
     type Error = anyhow::Error;
     fn try_from(endpoint: Endpoint) -> anyhow::Result<Self> {
@@ -220,6 +418,14 @@ impl TryFrom<Endpoint> for RequestTemplate {
             body_path: body,
             endpoint,
             encoding,
+            query_encoding: Default::default(),
+            sigv4: None,
+            hmac: None,
+            signed_query: Default::default(),
+            url_pool: None,
+            allowed_hosts: Default::default(),
+            allowed_headers: Default::default(),
+            idempotent: Default::default(),
         })
     }
 }
@@ -238,7 +444,7 @@ impl<Ctx: PathString + HasHeaders> CacheKey<Ctx> for RequestTemplate {
             headers.push((name.to_string(), mustache.render(ctx)));
         }
 
-        for (name, value) in ctx.headers().iter() {
+        for (name, value) in ctx.forwarded_headers(self.allowed_headers.as_ref()).iter() {
             name.hash(state);
             value.hash(state);
             headers.push((name.to_string(), value.to_str().unwrap().to_string()));
@@ -248,7 +454,9 @@ impl<Ctx: PathString + HasHeaders> CacheKey<Ctx> for RequestTemplate {
             body.render(ctx).hash(state)
         }
 
-        let url = self.create_url(ctx).unwrap();
+        // A url that can't be created isn't cached, the request fails with the
+        // same error when it's sent
+        let url = self.create_url(&self.root_url, ctx).ok()?;
         url.hash(state);
 
         Some(IoId::new(hasher.finish()))
@@ -259,6 +467,7 @@ impl<Ctx: PathString + HasHeaders> CacheKey<Ctx> for RequestTemplate {
 mod tests {
     use std::borrow::Cow;
 
+    use async_graphql_value::ConstValue;
     use derive_setters::Setters;
     use hyper::header::HeaderName;
     use hyper::HeaderMap;
@@ -266,7 +475,9 @@ mod tests {
     use serde_json::json;
 
     use super::RequestTemplate;
+    use crate::core::config::QueryEncoding;
     use crate::core::has_headers::HasHeaders;
+    use crate::core::ir::CacheKey;
     use crate::core::mustache::Mustache;
     use crate::core::path::PathString;
 
@@ -323,6 +534,15 @@ mod tests {
         assert_eq!(req.url().to_string(), "http://localhost:3000/foo/bar");
     }
 
+    #[test]
+    fn test_url_path_template_empty_segment() {
+        let tmpl =
+            RequestTemplate::new("http://localhost:3000/users/{{group}}/posts/{{id}}").unwrap();
+        let ctx = Context::default().value(json!({"id": 1}));
+        let req = tmpl.to_request(&ctx).unwrap();
+        assert_eq!(req.url().to_string(), "http://localhost:3000/users/posts/1");
+    }
+
     #[test]
     fn test_url_path_template() {
         let tmpl = RequestTemplate::new("http://localhost:3000/foo/{{bar.baz}}").unwrap();
@@ -336,6 +556,47 @@ mod tests {
         assert_eq!(req.url().to_string(), "http://localhost:3000/foo/bar");
     }
 
+    #[test]
+    fn test_partially_render() {
+        let tmpl = RequestTemplate::new("http://{{env.HOST}}/users/{{args.id}}")
+            .unwrap()
+            .query(vec![
+                ("key".to_string(), Mustache::parse("{{env.KEY}}").unwrap()),
+                ("id".to_string(), Mustache::parse("{{args.id}}").unwrap()),
+            ])
+            .partially_render(&json!({"env": {"HOST": "localhost:3000", "KEY": "secret"}}));
+
+        assert_eq!(
+            tmpl.root_url.to_string(),
+            "http://localhost:3000/users/{{args.id}}"
+        );
+        assert!(tmpl.query[0].1.is_const());
+        assert!(!tmpl.query[1].1.is_const());
+
+        let ctx = Context::default().value(json!({"args": {"id": 1}}));
+        let req = tmpl.to_request(&ctx).unwrap();
+        assert_eq!(
+            req.url().to_string(),
+            "http://localhost:3000/users/1?key=secret&id=1"
+        );
+    }
+
+    #[test]
+    fn test_url_allowed_hosts() {
+        let tmpl = RequestTemplate::new("http://{{tenant}}.api.example.com/users")
+            .unwrap()
+            .allowed_hosts(std::sync::Arc::new(vec!["*.api.example.com".to_string()]));
+
+        let ctx = Context::default().value(json!({"tenant": "acme"}));
+        let req = tmpl.to_request(&ctx).unwrap();
+        assert_eq!(req.url().to_string(), "http://acme.api.example.com/users");
+
+        let ctx = Context::default().value(json!({"tenant": "169.254.169.254#"}));
+        let error = tmpl.to_request(&ctx).unwrap_err();
+        assert_eq!(error.to_string(), "Host 169.254.169.254 is not allowed");
+        assert_eq!(tmpl.cache_key(&ctx), None);
+    }
+
     #[test]
     fn test_url_path_template_multi() {
         let tmpl =
@@ -397,6 +658,64 @@ mod tests {
         );
     }
 
+    fn filter_template(query_encoding: QueryEncoding) -> RequestTemplate {
+        RequestTemplate::new("http://localhost:3000/")
+            .unwrap()
+            .query(vec![(
+                "filter".to_string(),
+                Mustache::parse("{{args.filter}}").unwrap(),
+            )])
+            .query_encoding(query_encoding)
+    }
+
+    fn filter_context() -> Context {
+        Context::default().value(json!({
+          "args": {
+            "filter": {
+              "status": "active",
+              "tag": ["x", "y"],
+              "owner": { "id": 1 },
+              "deleted": null
+            }
+          }
+        }))
+    }
+
+    #[test]
+    fn test_url_query_object_brackets() {
+        let tmpl = filter_template(QueryEncoding::Brackets);
+        let req = tmpl.to_request(&filter_context()).unwrap();
+        assert_eq!(
+            req.url().to_string(),
+            "http://localhost:3000/?filter[status]=active&filter[tag][]=x&filter[tag][]=y&filter[owner][id]=1"
+        );
+    }
+
+    #[test]
+    fn test_url_query_object_dots() {
+        let tmpl = filter_template(QueryEncoding::Dots);
+        let req = tmpl.to_request(&filter_context()).unwrap();
+        assert_eq!(
+            req.url().to_string(),
+            "http://localhost:3000/?filter.status=active&filter.tag=x&filter.tag=y&filter.owner.id=1"
+        );
+    }
+
+    #[test]
+    fn test_url_query_object_json() {
+        let tmpl = filter_template(QueryEncoding::Json);
+        let ctx = Context::default().value(json!({
+          "args": {
+            "filter": { "status": "active" }
+          }
+        }));
+        let req = tmpl.to_request(&ctx).unwrap();
+        assert_eq!(
+            req.url().query(),
+            Some("filter={%22status%22:%22active%22}")
+        );
+    }
+
     #[test]
     fn test_headers() {
         let headers = vec![
@@ -533,12 +852,46 @@ mod tests {
         assert_eq!(body, "baz");
     }
 
+    #[test]
+    fn test_body_encoding_multipart_form_data() {
+        let tmpl = RequestTemplate::new("http://localhost:3000")
+            .unwrap()
+            .method(reqwest::Method::POST)
+            .encoding(crate::core::config::Encoding::MultipartFormData)
+            .body_path(Some(
+                Mustache::parse(r#"{"name": "{{foo.name}}", "file": {{foo.file}}}"#).unwrap(),
+            ));
+        let ctx = Context::default().value(json!({
+          "foo": {
+            "name": "avatar",
+            "file": {"filename": "a.txt", "content": "aGVsbG8="}
+          }
+        }));
+        let req = tmpl.to_request(&ctx).unwrap();
+        let content_type = req.headers().get("Content-Type").unwrap().to_str().unwrap();
+        let boundary = content_type
+            .strip_prefix("multipart/form-data; boundary=")
+            .unwrap();
+
+        let body = tmpl.to_body(&ctx).unwrap();
+        assert_eq!(
+            body,
+            format!(
+                "--{b}\r\nContent-Disposition: form-data; name=\"name\"\r\n\r\navatar\r\n\
+                 --{b}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"a.txt\"\r\n\
+                 Content-Type: application/octet-stream\r\n\r\nhello\r\n--{b}--\r\n",
+                b = boundary
+            )
+        );
+    }
+
     mod endpoint {
         use hyper::HeaderMap;
         use serde_json::json;
 
         use crate::core::http::request_template::tests::Context;
         use crate::core::http::RequestTemplate;
+        use crate::core::mustache::Mustache;
 
         #[test]
         fn test_from_endpoint() {
@@ -630,6 +983,37 @@ mod tests {
             );
         }
 
+        #[test]
+        fn test_from_endpoint_with_sigv4() {
+            let endpoint = crate::core::endpoint::Endpoint::new(
+                "https://abc.execute-api.us-east-1.amazonaws.com/prod/users".to_string(),
+            );
+            let sigv4 = crate::core::http::SigV4::try_from(&crate::core::config::SigV4 {
+                access_key_id: "{{env.ACCESS_KEY_ID}}".to_string(),
+                region: "us-east-1".to_string(),
+                secret_key: "{{env.SECRET_KEY}}".to_string(),
+                service: "execute-api".to_string(),
+                session_token: None,
+            })
+            .unwrap();
+            let tmpl = RequestTemplate::try_from(endpoint)
+                .unwrap()
+                .sigv4(Some(sigv4));
+            let ctx = Context::default().value(json!({
+              "env": {
+                "ACCESS_KEY_ID": "AKIDEXAMPLE",
+                "SECRET_KEY": "secret"
+              }
+            }));
+            let req = tmpl.to_request(&ctx).unwrap();
+            let authorization = req.headers().get("authorization").unwrap();
+            assert!(authorization
+                .to_str()
+                .unwrap()
+                .starts_with("AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/"));
+            assert!(req.headers().contains_key("x-amz-date"));
+        }
+
         #[test]
         fn test_from_endpoint_template_few_null_value_mixed() {
             let endpoint = crate::core::endpoint::Endpoint::new(
@@ -665,6 +1049,61 @@ mod tests {
             let req = tmpl.to_request(&ctx).unwrap();
             assert_eq!(req.headers().get("baz").unwrap(), "qux");
         }
+
+        #[test]
+        fn test_idempotency_key() {
+            let tmpl = RequestTemplate::new("http://localhost:3000/users")
+                .unwrap()
+                .method(reqwest::Method::POST)
+                .idempotent(true)
+                .body_path(Some(Mustache::parse("{{name}}").unwrap()));
+            let idempotency_key = |ctx: &Context| {
+                let req = tmpl.to_request(ctx).unwrap();
+                req.headers().get("idempotency-key").unwrap().clone()
+            };
+
+            let ctx = Context::default().value(json!({"name": "foo"}));
+            let retried = Context::default().value(json!({"name": "foo"}));
+            assert_eq!(idempotency_key(&ctx), idempotency_key(&retried));
+            assert_eq!(
+                idempotency_key(&ctx),
+                "e9f604b486ffd6cd826054f9f7498ed3f530751dc3a947a675ee2045c0730483"
+            );
+
+            let other = Context::default().value(json!({"name": "bar"}));
+            assert_ne!(idempotency_key(&ctx), idempotency_key(&other));
+        }
+
+        #[test]
+        fn test_idempotency_key_from_client() {
+            let endpoint =
+                crate::core::endpoint::Endpoint::new("http://localhost:3000/".to_string());
+            let tmpl = RequestTemplate::try_from(endpoint)
+                .unwrap()
+                .idempotent(true)
+                .allowed_headers(Some(["Idempotency-Key".to_string()].into()));
+            let mut headers = HeaderMap::new();
+            headers.insert("idempotency-key", "foo".parse().unwrap());
+            let ctx = Context::default().headers(headers);
+            let req = tmpl.to_request(&ctx).unwrap();
+            assert_eq!(req.headers().get("idempotency-key").unwrap(), "foo");
+        }
+
+        #[test]
+        fn test_allowed_headers_forward() {
+            let endpoint =
+                crate::core::endpoint::Endpoint::new("http://localhost:3000/".to_string());
+            let tmpl = RequestTemplate::try_from(endpoint)
+                .unwrap()
+                .allowed_headers(Some(["X-Foo".to_string()].into()));
+            let mut headers = HeaderMap::new();
+            headers.insert("x-foo", "foo".parse().unwrap());
+            headers.insert("x-bar", "bar".parse().unwrap());
+            let ctx = Context::default().headers(headers);
+            let req = tmpl.to_request(&ctx).unwrap();
+            assert_eq!(req.headers().get("x-foo").unwrap(), "foo");
+            assert!(req.headers().get("x-bar").is_none());
+        }
     }
 
     mod form_encoded_url {
@@ -1,14 +1,36 @@
-use anyhow::Result;
+use std::collections::BTreeMap;
+use std::io::Read;
+
+use anyhow::{bail, Result};
 use async_graphql_value::{ConstValue, Name};
 use derive_setters::Setters;
+use flate2::read::{GzDecoder, ZlibDecoder};
 use hyper::body::Bytes;
 use indexmap::IndexMap;
 use prost::Message;
+use thiserror::Error;
 use tonic::Status;
 use tonic_types::Status as GrpcStatus;
 
 use crate::core::grpc::protobuf::ProtobufOperation;
 use crate::core::ir::EvaluationError;
+use crate::core::json::JsonSchema;
+use crate::core::mustache::{Mustache, Segment};
+use crate::core::path::PathString;
+
+/// The maximum size of a decompressed response body, protects against bodies
+/// that expand to exhaust the memory.
+const MAX_DECOMPRESSED_SIZE: u64 = 100 * 1024 * 1024;
+
+/// Reads the decoded body, failing once it grows past `limit` bytes.
+fn read_limited(decoder: impl Read, limit: u64) -> Result<Vec<u8>> {
+    let mut body = Vec::new();
+    decoder.take(limit + 1).read_to_end(&mut body)?;
+    if body.len() as u64 > limit {
+        bail!("Decompressed response body exceeds {} bytes", limit);
+    }
+    Ok(body)
+}
 
 #[derive(Clone, Debug, Default, Setters)]
 pub struct Response<Body> {
@@ -17,6 +39,13 @@ pub struct Response<Body> {
     pub body: Body,
 }
 
+/// The error of an upstream response whose status code isn't a success. The
+/// response is kept so that the status codes listed in `@http(expectStatus:)`
+/// still resolve.
+#[derive(Debug, Error, Clone)]
+#[error("Unexpected status code: {}", .0.status)]
+pub struct StatusError(pub Response<Bytes>);
+
 // Trait to convert a serde_json_borrow::Value to a ConstValue.
 // serde_json_borrow::Value is a borrowed version of serde_json::Value.
 // It has a limited lifetime tied to the input JSON, making it more
@@ -54,6 +83,36 @@ impl Response<Bytes> {
         Ok(Response { status, headers, body })
     }
 
+    /// Like `from_reqwest` but fails with the error of
+    /// `reqwest::Response::error_for_status`, the response is attached to the
+    /// error as a `StatusError`.
+    pub async fn from_reqwest_for_status(resp: reqwest::Response) -> Result<Self> {
+        let error = resp.error_for_status_ref().err();
+        let response = Self::from_reqwest(resp).await?;
+        match error {
+            Some(error) => {
+                Err(anyhow::Error::new(StatusError(response)).context(error.without_url()))
+            }
+            None => Ok(response),
+        }
+    }
+
+    /// Recovers the response of a `StatusError` whose status code is one of
+    /// `expect_status`.
+    pub fn expect_status(result: Result<Self>, expect_status: &[u16]) -> Result<Self> {
+        match result {
+            Err(error) => match error.downcast_ref::<StatusError>() {
+                Some(StatusError(response))
+                    if expect_status.contains(&response.status.as_u16()) =>
+                {
+                    Ok(response.clone())
+                }
+                _ => Err(error),
+            },
+            result => result,
+        }
+    }
+
     pub fn empty() -> Self {
         Response {
             status: reqwest::StatusCode::OK,
@@ -62,20 +121,50 @@ impl Response<Bytes> {
         }
     }
 
+    /// Decodes the body according to the `Content-Encoding` header. Multiple
+    /// encodings are undone in the reverse order of their application.
+    pub fn decompress(mut self) -> Result<Self> {
+        let Some(content_encoding) = self.headers.get(reqwest::header::CONTENT_ENCODING) else {
+            return Ok(self);
+        };
+        let encodings = content_encoding
+            .to_str()?
+            .split(',')
+            .map(|encoding| encoding.trim().to_lowercase())
+            .collect::<Vec<_>>();
+
+        for encoding in encodings.iter().rev() {
+            let decoder: Box<dyn Read + '_> = match encoding.as_str() {
+                "gzip" | "x-gzip" => Box::new(GzDecoder::new(self.body.as_ref())),
+                "deflate" => Box::new(ZlibDecoder::new(self.body.as_ref())),
+                "br" => Box::new(brotli::Decompressor::new(self.body.as_ref(), 4096)),
+                "identity" | "" => continue,
+                encoding => bail!("Unsupported content encoding: {}", encoding),
+            };
+            self.body = Bytes::from(read_limited(decoder, MAX_DECOMPRESSED_SIZE)?);
+        }
+
+        // The length of the encoded body doesn't apply to the decoded one
+        self.headers.remove(reqwest::header::CONTENT_ENCODING);
+        self.headers.remove(reqwest::header::CONTENT_LENGTH);
+        Ok(self)
+    }
+
     pub fn to_json<T: Default + FromValue>(self) -> Result<Response<T>> {
-        if self.body.is_empty() {
+        let resp = self.decompress()?;
+        if resp.body.is_empty() {
             return Ok(Response {
-                status: self.status,
-                headers: self.headers,
+                status: resp.status,
+                headers: resp.headers,
                 body: Default::default(),
             });
         }
         // Note: We convert the body to a serde_json_borrow::Value for better
         // performance. Warning: Do not change this to direct conversion to `T`
         // without benchmarking the performance impact.
-        let body: serde_json_borrow::Value = serde_json::from_slice(&self.body)?;
+        let body: serde_json_borrow::Value = serde_json::from_slice(&resp.body)?;
         let body = T::from_value(body);
-        Ok(Response { status: self.status, headers: self.headers, body })
+        Ok(Response { status: resp.status, headers: resp.headers, body })
     }
 
     pub fn to_grpc_value(
@@ -151,3 +240,165 @@ impl Response<Bytes> {
         })
     }
 }
+
+impl Response<ConstValue> {
+    /// Inserts the headers of `header_map` into the fields of the body, when
+    /// it is an object. A template made of a single header is parsed according
+    /// to the type of the field in `output`, missing or unparsable headers are
+    /// `null`. Other templates are rendered as strings.
+    pub fn map_headers(
+        mut self,
+        header_map: &BTreeMap<String, Mustache>,
+        output: &JsonSchema,
+    ) -> Self {
+        let ConstValue::Object(body) = &mut self.body else {
+            return self;
+        };
+        let fields = match output {
+            JsonSchema::Opt(schema) => schema.as_ref(),
+            schema => schema,
+        };
+        let headers = response_headers(&self.headers);
+
+        for (name, template) in header_map {
+            let schema = match fields {
+                JsonSchema::Obj(fields) => fields.get(name),
+                _ => None,
+            };
+            let value = match template.get_segments().as_slice() {
+                [Segment::Expression(parts)] => headers
+                    .path_string(parts)
+                    .map(|value| header_value(&value, schema))
+                    .unwrap_or_default(),
+                _ => ConstValue::String(template.render(&headers)),
+            };
+
+            body.insert(Name::new(name), value);
+        }
+
+        self
+    }
+}
+
+/// The headers of the response, as they're read by the templates of
+/// `headerMap` eg. `{{.response.headers.x-total-count}}`
+fn response_headers(headers: &reqwest::header::HeaderMap) -> serde_json::Value {
+    let headers = headers
+        .iter()
+        .filter_map(|(name, value)| {
+            let value = value.to_str().ok()?.to_string();
+            Some((name.to_string(), serde_json::Value::String(value)))
+        })
+        .collect::<serde_json::Map<_, _>>();
+
+    serde_json::json!({ "response": { "headers": headers } })
+}
+
+fn header_value(value: &str, schema: Option<&JsonSchema>) -> ConstValue {
+    let schema = match schema {
+        Some(JsonSchema::Opt(schema)) => Some(schema.as_ref()),
+        schema => schema,
+    };
+
+    match schema {
+        Some(JsonSchema::Num) => value
+            .parse::<serde_json::Number>()
+            .map(ConstValue::Number)
+            .unwrap_or_default(),
+        Some(JsonSchema::Bool) => value
+            .parse::<bool>()
+            .map(ConstValue::Boolean)
+            .unwrap_or_default(),
+        _ => ConstValue::String(value.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::*;
+
+    fn gzip(body: &[u8]) -> Bytes {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(body).unwrap();
+        Bytes::from(encoder.finish().unwrap())
+    }
+
+    #[test]
+    fn test_decompress() {
+        let body = gzip(br#"{"id":1}"#);
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert("content-encoding", "gzip".parse().unwrap());
+        headers.insert("content-length", body.len().into());
+        let response = Response::empty().headers(headers).body(body);
+
+        let response = response.decompress().unwrap();
+
+        assert_eq!(response.body, Bytes::from_static(br#"{"id":1}"#));
+        assert!(response.headers.is_empty());
+    }
+
+    #[test]
+    fn test_read_limited() {
+        let body = gzip(&[0; 11]);
+
+        let actual = read_limited(GzDecoder::new(body.as_ref()), 11).unwrap();
+        assert_eq!(actual, vec![0; 11]);
+
+        let error = read_limited(GzDecoder::new(body.as_ref()), 10).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Decompressed response body exceeds 10 bytes"
+        );
+    }
+
+    #[test]
+    fn test_map_headers() {
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert("x-total-count", "42".parse().unwrap());
+        headers.insert("x-request-id", "123".parse().unwrap());
+        headers.insert("x-has-more", "yes".parse().unwrap());
+        let response = Response::<ConstValue>::default()
+            .headers(headers)
+            .body(ConstValue::from_json(json!({"items": []})).unwrap());
+
+        let header_map = [
+            ("totalCount", "{{.response.headers.x-total-count}}"),
+            ("requestId", "{{.response.headers.x-request-id}}"),
+            ("hasMore", "{{.response.headers.x-has-more}}"),
+            ("page", "{{.response.headers.x-page}}"),
+            ("summary", "{{.response.headers.x-total-count}} items"),
+        ]
+        .into_iter()
+        .map(|(name, header)| (name.to_string(), Mustache::parse(header).unwrap()))
+        .collect::<BTreeMap<_, _>>();
+        let output = JsonSchema::from([
+            ("totalCount", JsonSchema::Num.optional()),
+            ("requestId", JsonSchema::Str.optional()),
+            ("hasMore", JsonSchema::Bool.optional()),
+            ("page", JsonSchema::Num.optional()),
+            ("summary", JsonSchema::Str.optional()),
+        ]);
+
+        let response = response.map_headers(&header_map, &output);
+
+        assert_eq!(
+            response.body,
+            ConstValue::from_json(json!({
+                "items": [],
+                "hasMore": null,
+                "page": null,
+                "requestId": "123",
+                "summary": "42 items",
+                "totalCount": 42
+            }))
+            .unwrap()
+        );
+    }
+}
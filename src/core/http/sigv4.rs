@@ -0,0 +1,286 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use reqwest::header::{HeaderName, HeaderValue, AUTHORIZATION, HOST};
+use sha2::{Digest, Sha256};
+use url::Url;
+
+use crate::core::config;
+use crate::core::mustache::Mustache;
+use crate::core::path::PathString;
+
+const ALGORITHM: &str = "AWS4-HMAC-SHA256";
+const X_AMZ_DATE: &str = "x-amz-date";
+const X_AMZ_SECURITY_TOKEN: &str = "x-amz-security-token";
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Signs upstream requests using [AWS Signature Version 4](https://docs.aws.amazon.com/IAM/latest/UserGuide/reference_aws-signing.html).
+/// The credentials are mustache templates, so they are rendered for every
+/// request and can be sourced from `env` or `vars`.
+#[derive(Clone, Debug)]
+pub struct SigV4 {
+    pub region: Mustache,
+    pub service: Mustache,
+    pub access_key_id: Mustache,
+    pub secret_key: Mustache,
+    pub session_token: Option<Mustache>,
+}
+
+impl TryFrom<&config::SigV4> for SigV4 {
+    type Error = anyhow::Error;
+
+    fn try_from(sigv4: &config::SigV4) -> anyhow::Result<Self> {
+        Ok(Self {
+            region: Mustache::parse(&sigv4.region)?,
+            service: Mustache::parse(&sigv4.service)?,
+            access_key_id: Mustache::parse(&sigv4.access_key_id)?,
+            secret_key: Mustache::parse(&sigv4.secret_key)?,
+            session_token: sigv4
+                .session_token
+                .as_deref()
+                .map(Mustache::parse)
+                .transpose()?,
+        })
+    }
+}
+
+impl SigV4 {
+    /// Adds the `Authorization`, `X-Amz-Date` and `Host` headers to the request
+    /// computed over its final url, headers and body.
+    pub fn sign<C: PathString>(&self, req: &mut reqwest::Request, ctx: &C) -> anyhow::Result<()> {
+        self.sign_at(req, ctx, Utc::now())
+    }
+
+    fn sign_at<C: PathString>(
+        &self,
+        req: &mut reqwest::Request,
+        ctx: &C,
+        time: DateTime<Utc>,
+    ) -> anyhow::Result<()> {
+        let region = self.region.render(ctx);
+        let service = self.service.render(ctx);
+        let access_key_id = self.access_key_id.render(ctx);
+        let secret_key = self.secret_key.render(ctx);
+
+        let amz_date = time.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = time.format("%Y%m%d").to_string();
+
+        let host = host(req.url())?;
+        let headers = req.headers_mut();
+        headers.insert(HOST, HeaderValue::from_str(&host)?);
+        headers.insert(X_AMZ_DATE, HeaderValue::from_str(&amz_date)?);
+        if let Some(session_token) = &self.session_token {
+            headers.insert(
+                X_AMZ_SECURITY_TOKEN,
+                HeaderValue::from_str(&session_token.render(ctx))?,
+            );
+        }
+
+        let payload = req
+            .body()
+            .and_then(|body| body.as_bytes())
+            .unwrap_or_default();
+        let (canonical_headers, signed_headers) = canonical_headers(req);
+        let canonical_request = [
+            req.method().to_string(),
+            canonical_uri(req.url()),
+            canonical_query(req.url()),
+            canonical_headers,
+            signed_headers.clone(),
+            hex::encode(Sha256::digest(payload)),
+        ]
+        .join("\n");
+
+        let scope = format!("{}/{}/{}/aws4_request", date, region, service);
+        let string_to_sign = [
+            ALGORITHM.to_string(),
+            amz_date,
+            scope.clone(),
+            hex::encode(Sha256::digest(canonical_request.as_bytes())),
+        ]
+        .join("\n");
+
+        let signing_key = [region.as_str(), service.as_str(), "aws4_request"]
+            .iter()
+            .fold(
+                hmac(format!("AWS4{}", secret_key).as_bytes(), date.as_bytes()),
+                |key, part| hmac(&key, part.as_bytes()),
+            );
+        let signature = hex::encode(hmac(&signing_key, string_to_sign.as_bytes()));
+
+        let authorization = format!(
+            "{} Credential={}/{}, SignedHeaders={}, Signature={}",
+            ALGORITHM, access_key_id, scope, signed_headers, signature
+        );
+        req.headers_mut()
+            .insert(AUTHORIZATION, HeaderValue::from_str(&authorization)?);
+
+        Ok(())
+    }
+}
+
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    // NOTE: HMAC accepts keys of any size so this can never fail
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC can take key of any size");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn host(url: &Url) -> anyhow::Result<String> {
+    let host = url
+        .host_str()
+        .ok_or(anyhow::anyhow!("Can not sign a request without a host"))?;
+    Ok(match url.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_string(),
+    })
+}
+
+/// Encodes everything except the unreserved characters as required by SigV4
+fn uri_encode(input: &str) -> String {
+    input
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+/// Every path segment is encoded twice for all the services except S3. The url
+/// path is already encoded once, so it's only encoded once more here.
+fn canonical_uri(url: &Url) -> String {
+    url.path()
+        .split('/')
+        .map(uri_encode)
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn canonical_query(url: &Url) -> String {
+    let mut query = url
+        .query_pairs()
+        .map(|(key, value)| (uri_encode(&key), uri_encode(&value)))
+        .collect::<Vec<_>>();
+    query.sort();
+
+    query
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+fn canonical_headers(req: &reqwest::Request) -> (String, String) {
+    let mut headers: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    for (name, value) in req.headers().iter() {
+        let value = String::from_utf8_lossy(value.as_bytes());
+        headers
+            .entry(HeaderName::as_str(name))
+            .or_default()
+            .push(value.split_whitespace().collect::<Vec<_>>().join(" "));
+    }
+
+    let canonical_headers = headers
+        .iter()
+        .map(|(name, values)| format!("{}:{}\n", name, values.join(",")))
+        .collect::<String>();
+    let signed_headers = headers.keys().copied().collect::<Vec<_>>().join(";");
+
+    (canonical_headers, signed_headers)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use pretty_assertions::assert_eq;
+    use reqwest::header::CONTENT_TYPE;
+    use serde_json::json;
+
+    use super::*;
+
+    fn sigv4() -> SigV4 {
+        SigV4::try_from(&config::SigV4 {
+            region: "us-east-1".to_string(),
+            service: "iam".to_string(),
+            access_key_id: "AKIDEXAMPLE".to_string(),
+            secret_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+            session_token: None,
+        })
+        .unwrap()
+    }
+
+    fn request() -> reqwest::Request {
+        let url = "https://iam.amazonaws.com/?Action=ListUsers&Version=2010-05-08";
+        let mut req = reqwest::Request::new(reqwest::Method::GET, url.parse().unwrap());
+        req.headers_mut().insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("application/x-www-form-urlencoded; charset=utf-8"),
+        );
+        req
+    }
+
+    #[test]
+    fn test_sign() {
+        let mut req = request();
+        let time = Utc.with_ymd_and_hms(2015, 8, 30, 12, 36, 0).unwrap();
+        sigv4().sign_at(&mut req, &json!({}), time).unwrap();
+
+        assert_eq!(req.headers()[X_AMZ_DATE], "20150830T123600Z");
+        assert_eq!(req.headers()[HOST], "iam.amazonaws.com");
+        assert_eq!(
+            req.headers()[AUTHORIZATION],
+            "AWS4-HMAC-SHA256 \
+            Credential=AKIDEXAMPLE/20150830/us-east-1/iam/aws4_request, \
+            SignedHeaders=content-type;host;x-amz-date, \
+            Signature=5d672d79c15b13162d9279b0855cfba6789a8edb4c82c400e06b5924a6f2b5d7"
+        );
+    }
+
+    #[test]
+    fn test_sign_with_template_credentials() {
+        let sigv4 = SigV4::try_from(&config::SigV4 {
+            region: "us-east-1".to_string(),
+            service: "iam".to_string(),
+            access_key_id: "{{env.ACCESS_KEY_ID}}".to_string(),
+            secret_key: "{{env.SECRET_KEY}}".to_string(),
+            session_token: Some("{{env.SESSION_TOKEN}}".to_string()),
+        })
+        .unwrap();
+        let ctx = json!({
+            "env": {
+                "ACCESS_KEY_ID": "AKIDEXAMPLE",
+                "SECRET_KEY": "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+                "SESSION_TOKEN": "token"
+            }
+        });
+
+        let mut req = request();
+        let time = Utc.with_ymd_and_hms(2015, 8, 30, 12, 36, 0).unwrap();
+        sigv4.sign_at(&mut req, &ctx, time).unwrap();
+
+        let authorization = req.headers()[AUTHORIZATION].to_str().unwrap();
+        assert_eq!(req.headers()[X_AMZ_SECURITY_TOKEN], "token");
+        assert!(authorization.starts_with(
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/iam/aws4_request"
+        ));
+        assert!(authorization
+            .contains("SignedHeaders=content-type;host;x-amz-date;x-amz-security-token"));
+    }
+
+    #[test]
+    fn test_canonical_uri_double_encodes() {
+        let url = Url::parse("https://example.com/foo bar/baz").unwrap();
+        assert_eq!(canonical_uri(&url), "/foo%2520bar/baz");
+    }
+
+    #[test]
+    fn test_canonical_query_is_sorted() {
+        let url = Url::parse("https://example.com/?b=2&a=1&a=0&c=x y").unwrap();
+        assert_eq!(canonical_query(&url), "a=0&a=1&b=2&c=x%20y");
+    }
+}
@@ -0,0 +1,200 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use url::{Origin, Url};
+
+use crate::core::mustache::Mustache;
+
+/// Number of consecutive failures after which a host is ejected from the pool
+const MAX_FAILURES: usize = 3;
+
+/// Number of times an ejected host is skipped before it's tried again
+const EJECTED_TURNS: usize = 10;
+
+#[derive(Debug)]
+struct PooledUrl {
+    root_url: Mustache,
+    // Only known when the url doesn't contain any templates
+    origin: Option<Origin>,
+    failures: AtomicUsize,
+    ejected_turns: AtomicUsize,
+    // Set by the health checks, unhealthy hosts are out of rotation
+    healthy: AtomicBool,
+}
+
+impl PooledUrl {
+    fn is_ejected(&self) -> bool {
+        if !self.healthy.load(Ordering::Relaxed) {
+            return true;
+        }
+
+        self.ejected_turns
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |turns| {
+                turns.checked_sub(1)
+            })
+            .is_ok()
+    }
+}
+
+/// Distributes requests across multiple root urls in a round-robin fashion.
+/// Hosts that fail [MAX_FAILURES] times in a row are skipped for their next
+/// [EJECTED_TURNS] turns, and so are the hosts marked unhealthy by the health
+/// checks until they recover.
+#[derive(Debug)]
+pub struct UrlPool {
+    urls: Vec<PooledUrl>,
+    next: AtomicUsize,
+}
+
+impl UrlPool {
+    pub fn new(root_urls: Vec<Mustache>) -> Self {
+        let urls = root_urls
+            .into_iter()
+            .map(|root_url| {
+                let origin = root_url
+                    .is_const()
+                    .then(|| Url::parse(&root_url.render(&serde_json::Value::Null)).ok())
+                    .flatten()
+                    .map(|url| url.origin());
+
+                PooledUrl {
+                    root_url,
+                    origin,
+                    failures: AtomicUsize::new(0),
+                    ejected_turns: AtomicUsize::new(0),
+                    healthy: AtomicBool::new(true),
+                }
+            })
+            .collect();
+
+        Self { urls, next: AtomicUsize::new(0) }
+    }
+
+    /// Returns the root url that should be used for the next request. When
+    /// every host is ejected the pool falls back to plain round-robin.
+    pub fn next(&self) -> &Mustache {
+        let len = self.urls.len();
+        let mut fallback = None;
+        for _ in 0..len {
+            let url = &self.urls[self.next.fetch_add(1, Ordering::Relaxed) % len];
+            if !url.is_ejected() {
+                return &url.root_url;
+            }
+            fallback.get_or_insert(url);
+        }
+
+        &fallback
+            .expect("UrlPool must have at least one url")
+            .root_url
+    }
+
+    pub fn root_urls(&self) -> impl Iterator<Item = &Mustache> {
+        self.urls.iter().map(|url| &url.root_url)
+    }
+
+    /// The hosts of the pool, only known for the urls without templates
+    pub fn origins(&self) -> impl Iterator<Item = &Origin> {
+        self.urls.iter().filter_map(|url| url.origin.as_ref())
+    }
+
+    /// Puts the host back in rotation or takes it out of it
+    pub fn set_healthy(&self, origin: &Origin, healthy: bool) {
+        for pooled in self
+            .urls
+            .iter()
+            .filter(|pooled| pooled.origin.as_ref() == Some(origin))
+        {
+            pooled.healthy.store(healthy, Ordering::Relaxed);
+        }
+    }
+
+    /// Records the outcome of a request made to the given url
+    pub fn report(&self, url: &Url, is_success: bool) {
+        let origin = url.origin();
+        for pooled in self
+            .urls
+            .iter()
+            .filter(|pooled| pooled.origin.as_ref() == Some(&origin))
+        {
+            if is_success {
+                pooled.failures.store(0, Ordering::Relaxed);
+            } else if pooled.failures.fetch_add(1, Ordering::Relaxed) + 1 >= MAX_FAILURES {
+                pooled.failures.store(0, Ordering::Relaxed);
+                pooled.ejected_turns.store(EJECTED_TURNS, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn pool() -> UrlPool {
+        UrlPool::new(
+            [
+                "http://a.com/users",
+                "http://b.com/users",
+                "http://c.com/users",
+            ]
+            .iter()
+            .map(|url| Mustache::parse(url).unwrap())
+            .collect(),
+        )
+    }
+
+    fn next(pool: &UrlPool) -> String {
+        pool.next().render(&serde_json::Value::Null)
+    }
+
+    #[test]
+    fn test_round_robin() {
+        let pool = pool();
+        let urls = (0..6).map(|_| next(&pool)).collect::<Vec<_>>();
+
+        assert_eq!(
+            urls,
+            vec![
+                "http://a.com/users",
+                "http://b.com/users",
+                "http://c.com/users",
+                "http://a.com/users",
+                "http://b.com/users",
+                "http://c.com/users",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_failing_host_is_ejected() {
+        let pool = pool();
+        let url = Url::parse("http://b.com/users?id=1").unwrap();
+        for _ in 0..MAX_FAILURES {
+            pool.report(&url, false);
+        }
+
+        let urls = (0..4).map(|_| next(&pool)).collect::<Vec<_>>();
+        assert_eq!(
+            urls,
+            vec![
+                "http://a.com/users",
+                "http://c.com/users",
+                "http://a.com/users",
+                "http://c.com/users",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_success_resets_failures() {
+        let pool = pool();
+        let url = Url::parse("http://a.com/users").unwrap();
+        pool.report(&url, false);
+        pool.report(&url, false);
+        pool.report(&url, true);
+        pool.report(&url, false);
+
+        assert_eq!(next(&pool), "http://a.com/users");
+    }
+}
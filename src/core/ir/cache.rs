@@ -1,11 +1,17 @@
 use core::future::Future;
+use std::hash::{Hash, Hasher};
 use std::num::NonZeroU64;
 use std::ops::Deref;
 use std::pin::Pin;
 
+use async_graphql::context::SelectionField;
+use async_graphql::{Name, ServerError};
 use async_graphql_value::ConstValue;
+use indexmap::IndexMap;
+use tailcall_hasher::TailcallHasher;
 
 use super::{Eval, EvaluationContext, EvaluationError, ResolverContextLike, IR};
+use crate::core::EntityCache;
 
 #[derive(PartialEq, Eq, Clone, Hash, Debug)]
 pub struct IoId(u64);
@@ -25,6 +31,11 @@ pub trait CacheKey<Ctx> {
 #[derive(Clone, Debug)]
 pub struct Cache {
     pub max_age: NonZeroU64,
+    /// Request headers whose values are added to the key of the IO
+    pub vary_by: Vec<String>,
+    /// Duration after `max_age` during which the stale value is served while
+    /// it's refreshed in the background
+    pub stale_while_revalidate: Option<NonZeroU64>,
     pub expr: Box<IR>,
 }
 
@@ -33,12 +44,170 @@ impl Cache {
     /// Wraps an expression with the cache primitive.
     /// Performance DFS on the cache on the expression and identifies all the IO
     /// nodes. Then wraps each IO node with the cache primitive.
-    pub fn wrap(max_age: NonZeroU64, expr: IR) -> IR {
+    pub fn wrap(
+        max_age: NonZeroU64,
+        vary_by: &[String],
+        stale_while_revalidate: Option<NonZeroU64>,
+        expr: IR,
+    ) -> IR {
         expr.modify(move |expr| match expr {
-            IR::IO(_) => Some(IR::Cache(Cache { max_age, expr: Box::new(expr.clone()) })),
+            IR::IO(_) => Some(IR::Cache(Cache {
+                max_age,
+                vary_by: vary_by.to_vec(),
+                stale_while_revalidate,
+                expr: Box::new(expr.clone()),
+            })),
             _ => None,
         })
     }
+
+    /// Mixes the values of the `vary_by` headers into the key of the IO, so
+    /// that the same upstream call made with different credentials is cached
+    /// separately.
+    fn vary_key<'a, Ctx: ResolverContextLike<'a>>(
+        &self,
+        key: IoId,
+        ctx: &EvaluationContext<'a, Ctx>,
+    ) -> IoId {
+        if self.vary_by.is_empty() {
+            return key;
+        }
+
+        let mut hasher = TailcallHasher::default();
+        key.hash(&mut hasher);
+        for name in self.vary_by.iter() {
+            name.to_lowercase().hash(&mut hasher);
+            ctx.header(name).hash(&mut hasher);
+        }
+
+        IoId::new(hasher.finish())
+    }
+
+    /// Key of the marker that is set along with the value and expires after
+    /// `max_age`, the value is stale once the marker is gone.
+    fn fresh_key(key: &IoId) -> IoId {
+        let mut hasher = TailcallHasher::default();
+        key.hash(&mut hasher);
+        "fresh".hash(&mut hasher);
+
+        IoId::new(hasher.finish())
+    }
+
+    /// Key of the marker that is set while the value is refreshed in the
+    /// background, so that it's refreshed once even by the fields sharing it.
+    fn refreshing_key(key: &IoId) -> IoId {
+        let mut hasher = TailcallHasher::default();
+        key.hash(&mut hasher);
+        "refreshing".hash(&mut hasher);
+
+        IoId::new(hasher.finish())
+    }
+
+    async fn set_fresh(
+        &self,
+        cache: &EntityCache,
+        key: IoId,
+        val: ConstValue,
+        stale_while_revalidate: NonZeroU64,
+    ) -> anyhow::Result<()> {
+        cache
+            .set(Self::fresh_key(&key), ConstValue::Null, self.max_age)
+            .await?;
+        cache
+            .set(
+                key,
+                val,
+                self.max_age.saturating_add(stale_while_revalidate.get()),
+            )
+            .await
+    }
+
+    async fn eval_stale_while_revalidate<'a, Ctx: ResolverContextLike<'a> + Sync + Send>(
+        &'a self,
+        key: IoId,
+        stale_while_revalidate: NonZeroU64,
+        ctx: EvaluationContext<'a, Ctx>,
+    ) -> Result<ConstValue, EvaluationError> {
+        let cache = &ctx.request_ctx.runtime.cache;
+        if let Some(val) = cache.get(&key).await? {
+            ctx.request_ctx.stats.add_cache_hit();
+            if cache.get(&Self::fresh_key(&key)).await?.is_none() {
+                self.refresh(key, stale_while_revalidate, &ctx);
+            }
+            Ok(val)
+        } else {
+            let val = self.expr.eval(ctx.clone()).await?;
+            self.set_fresh(cache.as_ref(), key, val.clone(), stale_while_revalidate)
+                .await?;
+            Ok(val)
+        }
+    }
+
+    /// Evaluates the expression again in a background task and replaces the
+    /// stale value, unless the value is already being refreshed.
+    fn refresh<'a, Ctx: ResolverContextLike<'a>>(
+        &self,
+        key: IoId,
+        stale_while_revalidate: NonZeroU64,
+        ctx: &EvaluationContext<'a, Ctx>,
+    ) {
+        // A failed refresh is tried again once the upstream has timed out
+        let timeout = ctx.request_ctx.upstream.timeout.saturating_mul(1000);
+        let timeout = NonZeroU64::new(timeout).unwrap_or(NonZeroU64::MIN);
+
+        let cache = self.clone();
+        let req_ctx = ctx.request_ctx.detach();
+        let graphql_ctx = DetachedContext {
+            value: ctx.path_value::<&str>(&[]).map(|value| value.into_owned()),
+            args: ctx
+                .path_arg::<&str>(&[])
+                .and_then(|args| match args.into_owned() {
+                    ConstValue::Object(args) => Some(args),
+                    _ => None,
+                }),
+        };
+
+        tokio::spawn(async move {
+            let runtime_cache = req_ctx.runtime.cache.as_ref();
+            let refreshing = runtime_cache
+                .set_if_absent(Self::refreshing_key(&key), ConstValue::Null, timeout)
+                .await;
+            if !matches!(refreshing, Ok(true)) {
+                return;
+            }
+
+            let ctx = EvaluationContext::new(&req_ctx, &graphql_ctx);
+            if let Ok(val) = cache.expr.eval(ctx).await {
+                let _ = cache
+                    .set_fresh(runtime_cache, key, val, stale_while_revalidate)
+                    .await;
+            }
+        });
+    }
+}
+
+/// Owned copy of the value and the arguments of the resolver, so that the
+/// expression can be evaluated after the request is over.
+#[derive(Clone)]
+struct DetachedContext {
+    value: Option<ConstValue>,
+    args: Option<IndexMap<Name, ConstValue>>,
+}
+
+impl<'a> ResolverContextLike<'a> for DetachedContext {
+    fn value(&'a self) -> Option<&'a ConstValue> {
+        self.value.as_ref()
+    }
+
+    fn args(&'a self) -> Option<&'a IndexMap<Name, ConstValue>> {
+        self.args.as_ref()
+    }
+
+    fn field(&'a self) -> Option<SelectionField> {
+        None
+    }
+
+    fn add_error(&'a self, _: ServerError) {}
 }
 
 impl Eval for Cache {
@@ -48,9 +217,15 @@ impl Eval for Cache {
     ) -> Pin<Box<dyn Future<Output = Result<ConstValue, EvaluationError>> + 'a + Send>> {
         Box::pin(async move {
             if let IR::IO(io) = self.expr.deref() {
-                let key = io.cache_key(&ctx);
-                if let Some(key) = key {
+                let key = io.cache_key(&ctx).map(|key| self.vary_key(key, &ctx));
+                if let (Some(key), Some(stale_while_revalidate)) =
+                    (key.clone(), self.stale_while_revalidate)
+                {
+                    self.eval_stale_while_revalidate(key, stale_while_revalidate, ctx)
+                        .await
+                } else if let Some(key) = key {
                     if let Some(val) = ctx.request_ctx.runtime.cache.get(&key).await? {
+                        ctx.request_ctx.stats.add_cache_hit();
                         Ok(val)
                     } else {
                         let val = self.expr.eval(ctx.clone()).await?;
@@ -70,3 +245,143 @@ impl Eval for Cache {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use futures_util::future::join_all;
+    use hyper::body::Bytes;
+    use pretty_assertions::assert_eq;
+    use tokio::time::Instant;
+
+    use super::*;
+    use crate::core::http::{RequestContext, RequestTemplate, Response};
+    use crate::core::ir::{EmptyResolverContext, IO};
+    use crate::core::HttpIO;
+
+    /// Responds with the number of calls made so far.
+    #[derive(Default)]
+    struct CountingHttp {
+        calls: AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl HttpIO for CountingHttp {
+        async fn execute(&self, _: reqwest::Request) -> anyhow::Result<Response<Bytes>> {
+            let calls = self.calls.fetch_add(1, Ordering::SeqCst) + 1;
+            tokio::time::sleep(Duration::from_millis(50)).await;
+
+            Ok(Response { body: Bytes::from(calls.to_string()), ..Response::empty() })
+        }
+    }
+
+    /// Expires the entries with the clock of tokio, so that it can be paused.
+    #[derive(Default)]
+    struct TestCache {
+        data: Mutex<HashMap<IoId, (ConstValue, Instant)>>,
+    }
+
+    #[async_trait::async_trait]
+    impl crate::core::Cache for TestCache {
+        type Key = IoId;
+        type Value = ConstValue;
+
+        async fn set<'a>(
+            &'a self,
+            key: IoId,
+            value: ConstValue,
+            ttl: NonZeroU64,
+        ) -> anyhow::Result<()> {
+            let expires_at = Instant::now() + Duration::from_millis(ttl.get());
+            self.data.lock().unwrap().insert(key, (value, expires_at));
+            Ok(())
+        }
+
+        async fn get<'a>(&'a self, key: &'a IoId) -> anyhow::Result<Option<ConstValue>> {
+            let data = self.data.lock().unwrap();
+            Ok(data
+                .get(key)
+                .filter(|(_, expires_at)| *expires_at > Instant::now())
+                .map(|(value, _)| value.clone()))
+        }
+
+        fn hit_rate(&self) -> Option<f64> {
+            None
+        }
+    }
+
+    fn request_context(http: Arc<CountingHttp>) -> RequestContext {
+        let mut runtime = crate::core::runtime::test::init(None);
+        runtime.http = http;
+        runtime.cache = Arc::new(TestCache::default());
+        RequestContext::new(runtime)
+    }
+
+    fn cached_io() -> IR {
+        let io = IR::IO(IO::Http {
+            req_template: RequestTemplate::new("http://localhost:3000/users").unwrap(),
+            group_by: None,
+            dl_id: None,
+            http_filter: None,
+        });
+        Cache::wrap(
+            NonZeroU64::new(10).unwrap(),
+            &[],
+            NonZeroU64::new(10_000),
+            io,
+        )
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_stale_while_revalidate() {
+        let http = Arc::new(CountingHttp::default());
+        let req_ctx = request_context(http.clone());
+        let ir = cached_io();
+        let eval = || ir.eval(EvaluationContext::new(&req_ctx, &EmptyResolverContext));
+
+        assert_eq!(eval().await.unwrap(), ConstValue::from(1));
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        // The stale value is served without waiting for the refresh
+        let start = Instant::now();
+        let stale = join_all((0..5).map(|_| eval())).await;
+        assert!(start.elapsed() < Duration::from_millis(50));
+        assert!(stale
+            .iter()
+            .all(|val| val.as_ref().unwrap() == &ConstValue::from(1)));
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(eval().await.unwrap(), ConstValue::from(2));
+        assert_eq!(http.calls.load(Ordering::SeqCst), 2);
+    }
+
+    async fn eval(ir: &IR, req_ctx: &RequestContext) -> ConstValue {
+        ir.eval(EvaluationContext::new(req_ctx, &EmptyResolverContext))
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_refresh_shared_key() {
+        let http = Arc::new(CountingHttp::default());
+        let req_ctx = request_context(http.clone());
+
+        // Two fields calling the same upstream
+        let fields = [cached_io(), cached_io()];
+
+        assert_eq!(eval(&fields[0], &req_ctx).await, ConstValue::from(1));
+        assert_eq!(eval(&fields[1], &req_ctx).await, ConstValue::from(1));
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        let stale = join_all(fields.iter().map(|ir| eval(ir, &req_ctx))).await;
+        assert_eq!(stale, vec![ConstValue::from(1), ConstValue::from(1)]);
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(eval(&fields[1], &req_ctx).await, ConstValue::from(2));
+        assert_eq!(http.calls.load(Ordering::SeqCst), 2);
+    }
+}
@@ -1,9 +1,14 @@
+use std::collections::BTreeMap;
 use std::sync::Arc;
 
-use async_graphql::{ErrorExtensions, Value as ConstValue};
+use async_graphql::{ErrorExtensions, Name, Value as ConstValue};
+use indexmap::IndexMap;
 use thiserror::Error;
+use tonic::Code;
 
 use crate::core::auth;
+use crate::core::valid::Cause;
+
 #[derive(Debug, Error, Clone)]
 pub enum EvaluationError {
     #[error("IOException: {0}")]
@@ -17,8 +22,8 @@ pub enum EvaluationError {
         grpc_status_details: ConstValue,
     },
 
-    #[error("APIValidationError: {0:?}")]
-    APIValidationError(Vec<String>),
+    #[error("APIValidationError: {:?}", .0.iter().map(|cause| &cause.message).collect::<Vec<_>>())]
+    APIValidationError(Vec<Cause<String>>),
 
     #[error("ExprEvalError: {0}")]
     ExprEvalError(String),
@@ -28,6 +33,12 @@ pub enum EvaluationError {
 
     #[error("Authentication Failure: {0}")]
     AuthError(auth::error::Error),
+
+    #[error("Request Timeout: the deadline of the request was exceeded")]
+    DeadlineExceeded,
+
+    #[error("Upstream Timeout: {field} did not respond within {timeout}ms")]
+    UpstreamTimeout { field: String, timeout: u128 },
 }
 
 impl ErrorExtensions for EvaluationError {
@@ -44,11 +55,102 @@ impl ErrorExtensions for EvaluationError {
                 e.set("grpcDescription", grpc_description);
                 e.set("grpcStatusMessage", grpc_status_message);
                 e.set("grpcStatusDetails", grpc_status_details.clone());
+
+                let (status, http_status) = classify_grpc_code(Code::from_i32(*grpc_code));
+                e.set("grpcStatus", status);
+                e.set("httpStatus", http_status);
+            }
+
+            if let EvaluationError::APIValidationError(causes) = self {
+                e.set(
+                    "causes",
+                    ConstValue::List(causes.iter().map(to_value).collect()),
+                );
             }
         })
     }
 }
 
+impl EvaluationError {
+    /// Extends the error like [ErrorExtensions::extend], except that the
+    /// `httpStatus` of a gRPC error is taken from `statuses` when its code is
+    /// mapped there, eg. `{"NOT_FOUND": 410}`.
+    pub fn extend_with_statuses(&self, statuses: &BTreeMap<String, u16>) -> async_graphql::Error {
+        let error = self.extend();
+        let EvaluationError::GRPCError { grpc_code, .. } = self else {
+            return error;
+        };
+        let (status, _) = classify_grpc_code(Code::from_i32(*grpc_code));
+
+        match statuses.get(status) {
+            Some(http_status) => error.extend_with(|_, e| e.set("httpStatus", *http_status)),
+            None => error,
+        }
+    }
+
+    /// Checks if the upstream reported that the requested entity doesn't exist
+    pub fn is_not_found(&self) -> bool {
+        matches!(
+            self,
+            EvaluationError::GRPCError { grpc_code, .. }
+                if Code::from_i32(*grpc_code) == Code::NotFound
+        )
+    }
+}
+
+/// The names of all the gRPC status codes, eg. `NOT_FOUND`
+pub fn grpc_code_names() -> impl Iterator<Item = &'static str> {
+    (0..=16).map(|code| classify_grpc_code(Code::from_i32(code)).0)
+}
+
+/// Returns the name of the gRPC status code along with the closest matching
+/// HTTP status code, as described in the gRPC HTTP mapping guidelines.
+fn classify_grpc_code(code: Code) -> (&'static str, u16) {
+    match code {
+        Code::Ok => ("OK", 200),
+        Code::Cancelled => ("CANCELLED", 499),
+        Code::Unknown => ("UNKNOWN", 500),
+        Code::InvalidArgument => ("INVALID_ARGUMENT", 400),
+        Code::DeadlineExceeded => ("DEADLINE_EXCEEDED", 504),
+        Code::NotFound => ("NOT_FOUND", 404),
+        Code::AlreadyExists => ("ALREADY_EXISTS", 409),
+        Code::PermissionDenied => ("PERMISSION_DENIED", 403),
+        Code::ResourceExhausted => ("RESOURCE_EXHAUSTED", 429),
+        Code::FailedPrecondition => ("FAILED_PRECONDITION", 400),
+        Code::Aborted => ("ABORTED", 409),
+        Code::OutOfRange => ("OUT_OF_RANGE", 400),
+        Code::Unimplemented => ("UNIMPLEMENTED", 501),
+        Code::Internal => ("INTERNAL", 500),
+        Code::Unavailable => ("UNAVAILABLE", 503),
+        Code::DataLoss => ("DATA_LOSS", 500),
+        Code::Unauthenticated => ("UNAUTHENTICATED", 401),
+    }
+}
+
+/// Converts the cause into the same shape that is used to report config
+/// validation errors, ie. with the `message`, `trace` and `description` fields.
+fn to_value(cause: &Cause<String>) -> ConstValue {
+    let trace = cause
+        .trace
+        .iter()
+        .cloned()
+        .map(ConstValue::String)
+        .collect();
+    let description = cause
+        .description
+        .clone()
+        .map_or(ConstValue::Null, ConstValue::String);
+
+    ConstValue::Object(IndexMap::from([
+        (
+            Name::new("message"),
+            ConstValue::String(cause.message.clone()),
+        ),
+        (Name::new("trace"), ConstValue::List(trace)),
+        (Name::new("description"), description),
+    ]))
+}
+
 impl From<auth::error::Error> for EvaluationError {
     fn from(value: auth::error::Error) -> Self {
         EvaluationError::AuthError(value)
@@ -61,7 +163,7 @@ impl<'a> From<crate::core::valid::ValidationError<&'a str>> for EvaluationError
             value
                 .as_vec()
                 .iter()
-                .map(|e| e.message.to_owned())
+                .map(|cause| cause.clone().transform(|e| e.to_string()))
                 .collect(),
         )
     }
@@ -87,3 +189,56 @@ impl From<anyhow::Error> for EvaluationError {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use async_graphql::ErrorExtensions;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::EvaluationError;
+    use crate::core::valid::{Valid, Validator};
+
+    #[test]
+    fn test_api_validation_error_extensions() {
+        let valid = Valid::<(), &str>::fail("expected string")
+            .trace("name")
+            .trace("0");
+        let error = EvaluationError::from(valid.to_result().unwrap_err()).extend();
+
+        assert_eq!(error.message, r#"APIValidationError: ["expected string"]"#);
+        assert_eq!(
+            serde_json::to_value(error.extensions).unwrap(),
+            json!({
+                "causes": [
+                    {"message": "expected string", "trace": ["0", "name"], "description": null}
+                ]
+            })
+        );
+    }
+
+    fn grpc_error(grpc_code: i32) -> EvaluationError {
+        EvaluationError::GRPCError {
+            grpc_code,
+            grpc_description: String::new(),
+            grpc_status_message: String::new(),
+            grpc_status_details: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_grpc_error_http_status() {
+        let statuses = BTreeMap::from([("PERMISSION_DENIED".to_string(), 401)]);
+
+        let error = grpc_error(7).extend_with_statuses(&statuses);
+        let extensions = serde_json::to_value(error.extensions).unwrap();
+        assert_eq!(extensions["grpcStatus"], json!("PERMISSION_DENIED"));
+        assert_eq!(extensions["httpStatus"], json!(401));
+
+        let error = grpc_error(5).extend_with_statuses(&statuses);
+        let extensions = serde_json::to_value(error.extensions).unwrap();
+        assert_eq!(extensions["httpStatus"], json!(404));
+    }
+}
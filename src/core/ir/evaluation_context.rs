@@ -80,6 +80,10 @@ impl<'a, Ctx: ResolverContextLike<'a>> EvaluationContext<'a, Ctx> {
         &self.request_ctx.allowed_headers
     }
 
+    pub fn target_headers(&self) -> &HeaderMap {
+        &self.request_ctx.target_headers
+    }
+
     pub fn header(&self, key: &str) -> Option<&str> {
         let value = self.headers().get(key)?;
 
@@ -100,9 +104,27 @@ impl<'a, Ctx: ResolverContextLike<'a>> EvaluationContext<'a, Ctx> {
         &self.request_ctx.server.vars
     }
 
+    pub fn context_var(&self, key: &str) -> Option<&str> {
+        self.request_ctx.context.get(key).map(|v| v.as_str())
+    }
+
+    pub fn context(&self) -> &BTreeMap<String, String> {
+        &self.request_ctx.context
+    }
+
     pub fn add_error(&self, error: ServerError) {
         self.graphql_ctx.add_error(error)
     }
+
+    /// Returns the names of the subfields requested by the client on the
+    /// field that is being resolved. Useful to push the projection down to the
+    /// upstream, so that it only returns the fields that are actually needed.
+    pub fn selection_fields(&self) -> Vec<String> {
+        self.graphql_ctx
+            .field()
+            .map(|field| selection_field_names(field.selection_set()))
+            .unwrap_or_default()
+    }
 }
 
 impl<'a, Ctx: ResolverContextLike<'a>> GraphQLOperationContext for EvaluationContext<'a, Ctx> {
@@ -113,6 +135,20 @@ impl<'a, Ctx: ResolverContextLike<'a>> GraphQLOperationContext for EvaluationCon
     }
 }
 
+fn selection_field_names<'a>(
+    selection_set: impl Iterator<Item = SelectionField<'a>>,
+) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for field in selection_set {
+        let name = field.name();
+        if !name.starts_with("__") && !names.iter().any(|n| n == name) {
+            names.push(name.to_string());
+        }
+    }
+
+    names
+}
+
 fn format_selection_set<'a>(
     selection_set: impl Iterator<Item = SelectionField<'a>>,
 ) -> Option<String> {
@@ -1,14 +1,19 @@
 use core::future::Future;
 use std::pin::Pin;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use async_graphql::from_value;
 use async_graphql_value::ConstValue;
+use futures_timer::Delay;
+use futures_util::future::{select, Either};
 use reqwest::Request;
 
-use super::{CacheKey, Eval, EvaluationContext, IoId, ResolverContextLike};
+use super::{
+    CacheKey, Eval, EvaluationContext, IoId, NativeContext, NativeResolver, ResolverContextLike,
+};
 use crate::core::config::group_by::GroupBy;
-use crate::core::config::GraphQLOperationType;
+use crate::core::config::{GraphQLOperationType, ResponseValidation};
 use crate::core::data_loader::{DataLoader, Loader};
 use crate::core::graphql::{self, GraphqlDataLoader};
 use crate::core::grpc::data_loader::GrpcDataLoader;
@@ -16,7 +21,8 @@ use crate::core::grpc::protobuf::ProtobufOperation;
 use crate::core::grpc::request::execute_grpc_request;
 use crate::core::grpc::request_template::RenderedRequestTemplate;
 use crate::core::http::{
-    cache_policy, DataLoaderRequest, HttpDataLoader, HttpFilter, RequestTemplate, Response,
+    cache_policy, DataLoaderRequest, HttpDataLoader, HttpFilter, RequestStats, RequestTemplate,
+    Response,
 };
 use crate::core::ir::EvaluationError;
 use crate::core::json::JsonLike;
@@ -36,6 +42,8 @@ pub enum IO {
         req_template: graphql::RequestTemplate,
         field_name: String,
         batch: bool,
+        /// Limits the time the upstream has to respond
+        timeout: Option<Duration>,
         dl_id: Option<DataLoaderId>,
     },
     Grpc {
@@ -46,6 +54,11 @@ pub enum IO {
     Js {
         name: String,
     },
+    Wasm {
+        module: String,
+        func: String,
+    },
+    Native(NativeResolver),
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -88,6 +101,46 @@ impl IO {
     fn eval_inner<'a, Ctx: super::ResolverContextLike<'a> + Sync + Send>(
         &'a self,
         ctx: super::EvaluationContext<'a, Ctx>,
+    ) -> Pin<Box<dyn Future<Output = Result<ConstValue, EvaluationError>> + 'a + Send>> {
+        match ctx.request_ctx.deadline {
+            Some(deadline) => Box::pin(with_deadline(
+                self.eval_shared(ctx),
+                deadline,
+                EvaluationError::DeadlineExceeded,
+            )),
+            None => self.eval_shared(ctx),
+        }
+    }
+
+    /// Shares the `GET` requests with the other requests of the server made
+    /// within the `dedupeWindow`, other methods aren't idempotent.
+    fn eval_shared<'a, Ctx: super::ResolverContextLike<'a> + Sync + Send>(
+        &'a self,
+        ctx: super::EvaluationContext<'a, Ctx>,
+    ) -> Pin<Box<dyn Future<Output = Result<ConstValue, EvaluationError>> + 'a + Send>> {
+        match (&ctx.request_ctx.dedupe_window, self) {
+            (Some(dedupe_window), IO::Http { req_template, .. })
+                if req_template.method == reqwest::Method::GET =>
+            {
+                let dedupe_window = dedupe_window.clone();
+                Box::pin(async move {
+                    match req_template.cache_key(&ctx) {
+                        Some(key) => dedupe_window
+                            .get_or_eval(key, move || self.eval_call(ctx))
+                            .await
+                            .as_ref()
+                            .clone(),
+                        None => self.eval_call(ctx).await,
+                    }
+                })
+            }
+            _ => self.eval_call(ctx),
+        }
+    }
+
+    fn eval_call<'a, Ctx: super::ResolverContextLike<'a> + Sync + Send>(
+        &'a self,
+        ctx: super::EvaluationContext<'a, Ctx>,
     ) -> Pin<Box<dyn Future<Output = Result<ConstValue, EvaluationError>> + 'a + Send>> {
         Box::pin(async move {
             match self {
@@ -106,21 +159,52 @@ impl IO {
 
                     Ok(response.body)
                 }
-                IO::GraphQL { req_template, field_name, dl_id, .. } => {
-                    let req = req_template.to_request(&ctx)?;
-
-                    let res = if ctx.request_ctx.upstream.batch.is_some()
-                        && matches!(req_template.operation_type, GraphQLOperationType::Query)
-                    {
-                        let data_loader: Option<&DataLoader<DataLoaderRequest, GraphqlDataLoader>> =
-                            dl_id.and_then(|index| ctx.request_ctx.gql_data_loaders.get(index.0));
-                        execute_request_with_dl(&ctx, req, data_loader).await?
-                    } else {
-                        execute_raw_request(&ctx, req).await?
-                    };
-
-                    set_headers(&ctx, &res);
-                    parse_graphql_response(&ctx, res, field_name)
+                IO::GraphQL { req_template, field_name, dl_id, timeout, .. } => {
+                    let call = Box::pin(async {
+                        // Persisted queries aren't batched, as the upstream can
+                        // fail to find the hash of any of the batched queries
+                        let res = if ctx.request_ctx.upstream.batch.is_some()
+                            && req_template.persisted.is_none()
+                            && matches!(req_template.operation_type, GraphQLOperationType::Query)
+                        {
+                            let req = req_template.to_request(&ctx)?;
+                            let data_loader: Option<
+                                &DataLoader<DataLoaderRequest, GraphqlDataLoader>,
+                            > = dl_id
+                                .and_then(|index| ctx.request_ctx.gql_data_loaders.get(index.0));
+                            execute_request_with_dl(&ctx, req, data_loader).await?
+                        } else {
+                            let res = match &req_template.persisted {
+                                Some(persisted) => {
+                                    execute_persisted_request(&ctx, req_template, persisted).await?
+                                }
+                                None => {
+                                    execute_raw_request(&ctx, req_template.to_request(&ctx)?)
+                                        .await?
+                                }
+                            };
+                            match req_template.entity {
+                                Some(_) => {
+                                    graphql::scatter_entities(res, 1).pop().unwrap_or_default()
+                                }
+                                None => res,
+                            }
+                        };
+
+                        set_headers(&ctx, &res);
+                        parse_graphql_response(&ctx, res, field_name)
+                    });
+
+                    match timeout {
+                        Some(timeout) => {
+                            let exceeded = EvaluationError::UpstreamTimeout {
+                                field: field_name.to_string(),
+                                timeout: timeout.as_millis(),
+                            };
+                            with_deadline(call, Instant::now() + *timeout, exceeded).await
+                        }
+                        None => call.await,
+                    }
                 }
                 IO::Grpc { req_template, dl_id, .. } => {
                     let rendered = req_template.render(&ctx)?;
@@ -156,6 +240,32 @@ impl IO {
                         Ok(ConstValue::Null)
                     }
                 }
+                IO::Wasm { module, func } => {
+                    let Some(wasm) = ctx.request_ctx.runtime.wasm.as_ref() else {
+                        return Err(EvaluationError::IOException(
+                            "WASM capabilities are disabled in this build".to_string(),
+                        ));
+                    };
+                    let value = ctx.value().cloned().unwrap_or_default();
+                    let args = ctx
+                        .path_arg::<&str>(&[])
+                        .map(|args| args.into_owned())
+                        .unwrap_or_default();
+
+                    Ok(wasm.call(module, func, value, args).await?)
+                }
+                IO::Native(resolver) => {
+                    let value = ctx.value().cloned().unwrap_or_default();
+                    let args = ctx
+                        .path_arg::<&str>(&[])
+                        .map(|args| args.into_owned())
+                        .unwrap_or_default();
+                    let headers = ctx.headers().clone();
+
+                    Ok(resolver
+                        .call(NativeContext { value, args, headers })
+                        .await?)
+                }
             }
         })
     }
@@ -168,10 +278,34 @@ impl<'a, Ctx: ResolverContextLike<'a> + Sync + Send> CacheKey<EvaluationContext<
             IO::Grpc { req_template, .. } => req_template.cache_key(ctx),
             IO::GraphQL { req_template, .. } => req_template.cache_key(ctx),
             IO::Js { .. } => None,
+            IO::Wasm { .. } => None,
+            IO::Native(_) => None,
         }
     }
 }
 
+/// Races the upstream call against a deadline, either the remaining budget of
+/// the request or the timeout of the field. Once the deadline is exceeded the
+/// call is dropped, which aborts it, and the `exceeded` error is returned.
+async fn with_deadline<F>(
+    future: F,
+    deadline: Instant,
+    exceeded: EvaluationError,
+) -> Result<ConstValue, EvaluationError>
+where
+    F: Future<Output = Result<ConstValue, EvaluationError>> + Unpin,
+{
+    let remaining = deadline.saturating_duration_since(Instant::now());
+    if remaining.is_zero() {
+        return Err(exceeded);
+    }
+
+    match select(future, Delay::new(remaining)).await {
+        Either::Left((result, _)) => result,
+        Either::Right(_) => Err(exceeded),
+    }
+}
+
 fn set_headers<'ctx, Ctx: ResolverContextLike<'ctx>>(
     ctx: &EvaluationContext<'ctx, Ctx>,
     res: &Response<async_graphql::Value>,
@@ -212,11 +346,8 @@ async fn execute_raw_request<'ctx, Ctx: ResolverContextLike<'ctx>>(
     ctx: &EvaluationContext<'ctx, Ctx>,
     req: Request,
 ) -> Result<Response<async_graphql::Value>, EvaluationError> {
-    let response = ctx
-        .request_ctx
-        .runtime
-        .http
-        .execute(req)
+    let call = ctx.request_ctx.runtime.http.execute(req);
+    let response = RequestStats::record([&ctx.request_ctx.stats], call)
         .await
         .map_err(EvaluationError::from)?
         .to_json()?;
@@ -224,12 +355,55 @@ async fn execute_raw_request<'ctx, Ctx: ResolverContextLike<'ctx>>(
     Ok(response)
 }
 
+/// Executes an http request, the responses whose status code is listed in
+/// `expect_status` resolve even though they aren't a success.
+async fn execute_http_request<'ctx, Ctx: ResolverContextLike<'ctx>>(
+    ctx: &EvaluationContext<'ctx, Ctx>,
+    req: Request,
+    expect_status: &[u16],
+) -> Result<Response<async_graphql::Value>, EvaluationError> {
+    let call = ctx.request_ctx.runtime.http.execute(req);
+    let response = RequestStats::record([&ctx.request_ctx.stats], call).await;
+    let response = Response::expect_status(response, expect_status)
+        .map_err(EvaluationError::from)?
+        .to_json()?;
+
+    Ok(response)
+}
+
+/// Sends only the hash of a query the upstream has registered, the whole query
+/// is sent along with its hash when it isn't registered yet or the upstream
+/// doesn't know the hash anymore.
+async fn execute_persisted_request<'ctx, Ctx: ResolverContextLike<'ctx>>(
+    ctx: &EvaluationContext<'ctx, Ctx>,
+    req_template: &graphql::RequestTemplate,
+    persisted: &graphql::PersistedQueries,
+) -> Result<Response<async_graphql::Value>, EvaluationError> {
+    let hash = req_template.persisted_query_hash(ctx);
+
+    if persisted.is_registered(&hash) {
+        let res = execute_raw_request(ctx, req_template.to_persisted_request(ctx)?).await?;
+        if !graphql::is_persisted_query_not_found(&res.body) {
+            return Ok(res);
+        }
+        persisted.unregister(&hash);
+    }
+
+    let res = execute_raw_request(ctx, req_template.to_request(ctx)?).await?;
+    if !graphql::is_persisted_query_not_found(&res.body) {
+        persisted.register(hash);
+    }
+
+    Ok(res)
+}
+
 async fn execute_raw_grpc_request<'ctx, Ctx: ResolverContextLike<'ctx>>(
     ctx: &EvaluationContext<'ctx, Ctx>,
     req: Request,
     operation: &ProtobufOperation,
 ) -> Result<Response<async_graphql::Value>, EvaluationError> {
-    execute_grpc_request(&ctx.request_ctx.runtime, operation, req)
+    let call = execute_grpc_request(&ctx.request_ctx.runtime, operation, req);
+    RequestStats::record([&ctx.request_ctx.stats], call)
         .await
         .map_err(EvaluationError::from)
 }
@@ -254,7 +428,8 @@ async fn execute_grpc_request_with_dl<
         .clone()
         .map(|s| s.headers)
         .unwrap_or_default();
-    let endpoint_key = grpc::DataLoaderRequest::new(rendered, headers);
+    let endpoint_key =
+        grpc::DataLoaderRequest::new(rendered, headers).stats(ctx.request_ctx.stats.clone());
 
     Ok(data_loader
         .unwrap()
@@ -280,7 +455,8 @@ async fn execute_request_with_dl<
         .clone()
         .map(|s| s.headers)
         .unwrap_or_default();
-    let endpoint_key = crate::core::http::DataLoaderRequest::new(req, headers);
+    let endpoint_key = crate::core::http::DataLoaderRequest::new(req, headers)
+        .stats(ctx.request_ctx.stats.clone());
 
     Ok(data_loader
         .unwrap()
@@ -337,7 +513,17 @@ impl<'a, Context: ResolverContextLike<'a> + Send + Sync> HttpRequestExecutor<'a,
 
     pub fn init_request(&self) -> Result<Request, EvaluationError> {
         let ctx = &self.evaluation_ctx;
-        Ok(self.request_template.to_request(ctx)?)
+        let mut request = self.request_template.to_request(ctx)?;
+
+        if let Some(accept_encoding) = &ctx.request_ctx.upstream.accept_encoding {
+            if let Ok(value) = reqwest::header::HeaderValue::from_str(accept_encoding) {
+                request
+                    .headers_mut()
+                    .insert(reqwest::header::ACCEPT_ENCODING, value);
+            }
+        }
+
+        Ok(request)
     }
 
     async fn execute(
@@ -347,19 +533,65 @@ impl<'a, Context: ResolverContextLike<'a> + Send + Sync> HttpRequestExecutor<'a,
         let ctx = &self.evaluation_ctx;
         let is_get = req.method() == reqwest::Method::GET;
         let dl = &self.data_loader;
+        let url = req.url().clone();
+        let permit = match ctx.request_ctx.circuit_breaker.as_ref() {
+            Some(circuit_breaker) => match circuit_breaker.allow(&url) {
+                Some(permit) => Some(permit),
+                None => {
+                    return Err(EvaluationError::IOException(format!(
+                        "Circuit open for {}",
+                        url.origin().ascii_serialization()
+                    )))
+                }
+            },
+            None => None,
+        };
+
+        let endpoint = &self.request_template.endpoint;
         let response = if is_get && dl.is_some() {
-            execute_request_with_dl(ctx, req, self.data_loader).await?
+            execute_request_with_dl(ctx, req, self.data_loader).await
+        } else {
+            execute_http_request(ctx, req, &endpoint.expect_status).await
+        };
+
+        if let Some(url_pool) = &self.request_template.url_pool {
+            url_pool.report(&url, response.is_ok());
+        }
+        if let Some(permit) = permit {
+            permit.report(response.is_ok());
+        }
+        let response = response?;
+
+        if !endpoint.expect_status.is_empty()
+            && !endpoint.expect_status.contains(&response.status.as_u16())
+        {
+            return Err(EvaluationError::IOException(format!(
+                "Unexpected status code: {}",
+                response.status
+            )));
+        }
+
+        let response = if endpoint.header_map.is_empty() {
+            response
         } else {
-            execute_raw_request(ctx, req).await?
+            response.map_headers(&endpoint.header_map, &endpoint.output)
         };
 
-        if ctx.request_ctx.server.get_enable_http_validation() {
-            self.request_template
+        let response_validation = ctx.request_ctx.server.response_validation;
+        if response_validation.is_enabled() && response.status != reqwest::StatusCode::NO_CONTENT {
+            let result = self
+                .request_template
                 .endpoint
                 .output
                 .validate(&response.body)
-                .to_result()
-                .map_err(EvaluationError::from)?;
+                .to_result();
+
+            if let Err(err) = result {
+                if response_validation == ResponseValidation::Strict {
+                    return Err(EvaluationError::from(err));
+                }
+                tracing::warn!("Invalid response from {}: {}", url, err);
+            }
         }
 
         set_headers(ctx, &response);
@@ -403,3 +635,76 @@ impl<'a, Context: ResolverContextLike<'a> + Send + Sync> HttpRequestExecutor<'a,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use super::*;
+
+    /// Marks the upstream call as aborted when it's dropped before completing
+    struct AbortGuard(Arc<AtomicBool>);
+
+    impl Drop for AbortGuard {
+        fn drop(&mut self) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_with_deadline_aborts_call() {
+        let aborted = Arc::new(AtomicBool::new(false));
+        let guard = AbortGuard(aborted.clone());
+        let call = Box::pin(async move {
+            let _guard = guard;
+            Delay::new(Duration::from_secs(10)).await;
+            Ok(ConstValue::Null)
+        });
+
+        let deadline = Instant::now() + Duration::from_millis(10);
+        let result = with_deadline(call, deadline, EvaluationError::DeadlineExceeded).await;
+
+        assert!(matches!(result, Err(EvaluationError::DeadlineExceeded)));
+        assert!(aborted.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn test_with_deadline_completes_in_time() {
+        let call = Box::pin(async { Ok(ConstValue::from(1)) });
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let result = with_deadline(call, deadline, EvaluationError::DeadlineExceeded).await;
+
+        assert_eq!(result.unwrap(), ConstValue::from(1));
+    }
+
+    #[tokio::test]
+    async fn test_with_deadline_already_exceeded() {
+        let call = Box::pin(async { Ok(ConstValue::Null) });
+
+        let result = with_deadline(call, Instant::now(), EvaluationError::DeadlineExceeded).await;
+
+        assert!(matches!(result, Err(EvaluationError::DeadlineExceeded)));
+    }
+
+    #[tokio::test]
+    async fn test_with_deadline_field_timeout() {
+        let aborted = Arc::new(AtomicBool::new(false));
+        let guard = AbortGuard(aborted.clone());
+        let call = Box::pin(async move {
+            let _guard = guard;
+            Delay::new(Duration::from_secs(10)).await;
+            Ok(ConstValue::Null)
+        });
+
+        let deadline = Instant::now() + Duration::from_millis(10);
+        let exceeded = EvaluationError::UpstreamTimeout { field: "users".to_string(), timeout: 10 };
+        let result = with_deadline(call, deadline, exceeded).await;
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Upstream Timeout: users did not respond within 10ms"
+        );
+        assert!(aborted.load(Ordering::SeqCst));
+    }
+}
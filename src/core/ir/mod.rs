@@ -5,24 +5,29 @@ mod evaluation_context;
 mod graphql_operation_context;
 mod io;
 mod modify;
+mod native;
 mod resolver_context_like;
 
 use core::future::Future;
 use std::fmt::Debug;
+use std::hash::{Hash, Hasher};
 use std::pin::Pin;
 
-use async_graphql_value::ConstValue;
+use async_graphql_value::{ConstValue, Name};
 pub use cache::*;
 pub use error::*;
 pub use eval::*;
 pub use evaluation_context::EvaluationContext;
+use futures_util::future::try_join_all;
 pub use graphql_operation_context::GraphQLOperationContext;
 pub use io::*;
+pub use native::*;
 pub use resolver_context_like::{EmptyResolverContext, ResolverContext, ResolverContextLike};
 use strum_macros::Display;
+use tailcall_hasher::TailcallHasher;
 
 use crate::core::blueprint::DynamicValue;
-use crate::core::json::JsonLike;
+use crate::core::json::{JsonLike, JsonPatch};
 use crate::core::serde_value_ext::ValueExt;
 
 #[derive(Clone, Debug, Display)]
@@ -34,14 +39,58 @@ pub enum IR {
     Cache(Cache),
     Path(Box<IR>, Vec<String>),
     Protect(Box<IR>),
+    /// Resolves the fallback when the expression fails
+    OnError {
+        expr: Box<IR>,
+        fallback: Box<IR>,
+        log: bool,
+    },
+    /// Resolves the default when the expression resolves to null
+    WithDefault {
+        expr: Box<IR>,
+        default: Box<IR>,
+    },
+    /// Applies the JSON Patch to the value the expression resolves to
+    Patch {
+        expr: Box<IR>,
+        patch: JsonPatch,
+    },
+    /// Wraps the value the expression resolves to into a list, null resolves
+    /// to an empty list
+    WrapInList(Box<IR>),
+    /// Hoists the fields of the nested object under `key` into the value the
+    /// expression resolves to
+    Flatten {
+        expr: Box<IR>,
+        key: String,
+    },
+    /// Resolves the expression once per parent value and args within a
+    /// request, eg. for a sibling field that's read by `@expr`. `name` is the
+    /// type and the name of the field eg. `Product.discount`.
+    Memoize {
+        name: String,
+        expr: Box<IR>,
+    },
 }
 
 #[derive(Clone, Debug)]
 pub enum Context {
     Value,
     Path(Vec<String>),
-    PushArgs { expr: Box<IR>, and_then: Box<IR> },
-    PushValue { expr: Box<IR>, and_then: Box<IR> },
+    PushArgs {
+        expr: Box<IR>,
+        and_then: Box<IR>,
+    },
+    PushValue {
+        expr: Box<IR>,
+        and_then: Box<IR>,
+    },
+    /// Resolves the sibling fields and adds them to the value, so that
+    /// `and_then` can read them from `{{.value}}`
+    PushSiblings {
+        siblings: Vec<(String, IR)>,
+        and_then: Box<IR>,
+    },
 }
 
 impl IR {
@@ -80,6 +129,23 @@ impl Eval for IR {
                         let ctx = ctx.with_value(value);
                         and_then.eval(ctx).await
                     }
+                    Context::PushSiblings { siblings, and_then } => {
+                        let resolved = try_join_all(
+                            siblings
+                                .iter()
+                                .map(|(_, sibling)| sibling.eval(ctx.clone())),
+                        )
+                        .await?;
+                        let mut value = match ctx.value() {
+                            Some(ConstValue::Object(map)) => map.clone(),
+                            _ => Default::default(),
+                        };
+                        for ((name, _), sibling) in siblings.iter().zip(resolved) {
+                            value.insert(Name::new(name), sibling);
+                        }
+                        let ctx = ctx.with_value(ConstValue::Object(value));
+                        and_then.eval(ctx).await
+                    }
                 },
                 IR::Path(input, path) => {
                     let inp = &input.eval(ctx).await?;
@@ -97,9 +163,91 @@ impl Eval for IR {
                         .to_result()?;
                     expr.eval(ctx).await
                 }
+                IR::OnError { expr, fallback, log } => match expr.eval(ctx.clone()).await {
+                    Ok(value) => Ok(value),
+                    Err(error) => {
+                        if *log {
+                            tracing::warn!("Resolving the fallback value instead of: {}", error);
+                        }
+                        fallback.eval(ctx).await
+                    }
+                },
+                IR::WithDefault { expr, default } => match expr.eval(ctx.clone()).await? {
+                    ConstValue::Null => default.eval(ctx).await,
+                    value => Ok(value),
+                },
+                IR::Patch { expr, patch } => patch
+                    .apply(expr.eval(ctx).await?)
+                    .map_err(EvaluationError::ExprEvalError),
+                IR::WrapInList(expr) => match expr.eval(ctx).await? {
+                    ConstValue::Null => Ok(ConstValue::List(vec![])),
+                    value => Ok(ConstValue::List(vec![value])),
+                },
+                IR::Flatten { expr, key } => flatten(expr.eval(ctx).await?, key),
+                IR::Memoize { name, expr } => {
+                    let key = memoize_key(name, &ctx);
+                    ctx.request_ctx
+                        .cache
+                        .get_or_eval(key, move || expr.eval(ctx))
+                        .await
+                        .as_ref()
+                        .clone()
+                }
                 IR::IO(operation) => operation.eval(ctx).await,
                 IR::Cache(cached) => cached.eval(ctx).await,
             }
         })
     }
 }
+
+/// Identifies the field by the value of its parent and by its args
+fn memoize_key<'a, Ctx: ResolverContextLike<'a>>(
+    name: &str,
+    ctx: &EvaluationContext<'a, Ctx>,
+) -> IoId {
+    let mut hasher = TailcallHasher::default();
+    name.hash(&mut hasher);
+    ctx.value().map(|value| value.to_string()).hash(&mut hasher);
+    ctx.path_arg::<&str>(&[])
+        .map(|args| args.to_string())
+        .hash(&mut hasher);
+
+    IoId::new(hasher.finish())
+}
+
+/// Hoists the fields of the object under `key` into the value, every item of a
+/// list is flattened. Fails when a hoisted field is already defined on the
+/// value.
+fn flatten(value: ConstValue, key: &str) -> Result<ConstValue, EvaluationError> {
+    match value {
+        ConstValue::List(list) => Ok(ConstValue::List(
+            list.into_iter()
+                .map(|item| flatten(item, key))
+                .collect::<Result<_, _>>()?,
+        )),
+        ConstValue::Object(mut map) => {
+            match map.shift_remove(key) {
+                Some(ConstValue::Object(nested)) => {
+                    for (name, value) in nested {
+                        if map.contains_key(&name) {
+                            return Err(EvaluationError::ExprEvalError(format!(
+                                "Cannot flatten {}: {} is already defined",
+                                key, name
+                            )));
+                        }
+                        map.insert(name, value);
+                    }
+                }
+                None | Some(ConstValue::Null) => {}
+                Some(_) => {
+                    return Err(EvaluationError::ExprEvalError(format!(
+                        "Cannot flatten {}: it is not an object",
+                        key
+                    )))
+                }
+            }
+            Ok(ConstValue::Object(map))
+        }
+        value => Ok(value),
+    }
+}
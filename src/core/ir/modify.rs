@@ -30,14 +30,47 @@ impl IR {
                                 and_then: and_then.modify_box(modifier),
                             })
                         }
+                        super::Context::PushSiblings { siblings, and_then } => {
+                            IR::Context(super::Context::PushSiblings {
+                                siblings: siblings
+                                    .into_iter()
+                                    .map(|(name, sibling)| (name, sibling.modify_inner(modifier)))
+                                    .collect(),
+                                and_then: and_then.modify_box(modifier),
+                            })
+                        }
                     },
                     IR::Dynamic(_) => expr,
                     IR::IO(_) => expr,
-                    IR::Cache(Cache { expr, max_age }) => {
-                        IR::Cache(Cache { expr: expr.modify_box(modifier), max_age })
+                    IR::Cache(Cache { expr, max_age, vary_by, stale_while_revalidate }) => {
+                        IR::Cache(Cache {
+                            expr: expr.modify_box(modifier),
+                            max_age,
+                            vary_by,
+                            stale_while_revalidate,
+                        })
                     }
                     IR::Path(expr, path) => IR::Path(expr.modify_box(modifier), path),
                     IR::Protect(expr) => IR::Protect(expr.modify_box(modifier)),
+                    IR::OnError { expr, fallback, log } => IR::OnError {
+                        expr: expr.modify_box(modifier),
+                        fallback: fallback.modify_box(modifier),
+                        log,
+                    },
+                    IR::WithDefault { expr, default } => IR::WithDefault {
+                        expr: expr.modify_box(modifier),
+                        default: default.modify_box(modifier),
+                    },
+                    IR::Patch { expr, patch } => {
+                        IR::Patch { expr: expr.modify_box(modifier), patch }
+                    }
+                    IR::WrapInList(expr) => IR::WrapInList(expr.modify_box(modifier)),
+                    IR::Flatten { expr, key } => {
+                        IR::Flatten { expr: expr.modify_box(modifier), key }
+                    }
+                    IR::Memoize { name, expr } => {
+                        IR::Memoize { name, expr: expr.modify_box(modifier) }
+                    }
                 }
             }
         }
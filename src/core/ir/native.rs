@@ -0,0 +1,50 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use async_graphql_value::ConstValue;
+use hyper::HeaderMap;
+
+type NativeFuture = Pin<Box<dyn Future<Output = anyhow::Result<ConstValue>> + Send>>;
+
+/// Native resolvers by type and field name, they take precedence over the
+/// resolvers of the directives of those fields.
+pub type NativeResolvers = HashMap<(String, String), NativeResolver>;
+
+/// What a [NativeResolver] is called with
+#[derive(Clone, Debug, Default)]
+pub struct NativeContext {
+    /// The value of the parent of the field
+    pub value: ConstValue,
+    /// The arguments of the field
+    pub args: ConstValue,
+    /// The headers of the incoming request
+    pub headers: HeaderMap,
+}
+
+/// A resolver written in Rust, for the fields that can't be expressed with the
+/// directives. State shared between the requests is captured by the closure.
+#[derive(Clone)]
+pub struct NativeResolver(Arc<dyn Fn(NativeContext) -> NativeFuture + Send + Sync>);
+
+impl NativeResolver {
+    pub fn new<F, Fut>(resolver: F) -> Self
+    where
+        F: Fn(NativeContext) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = anyhow::Result<ConstValue>> + Send + 'static,
+    {
+        Self(Arc::new(move |ctx| Box::pin(resolver(ctx))))
+    }
+
+    pub async fn call(&self, ctx: NativeContext) -> anyhow::Result<ConstValue> {
+        (self.0)(ctx).await
+    }
+}
+
+impl Debug for NativeResolver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("NativeResolver")
+    }
+}
@@ -0,0 +1,282 @@
+use std::fmt::{Display, Formatter};
+
+use async_graphql::{Name, Value as ConstValue};
+
+/// A [JSON Pointer](https://datatracker.ietf.org/doc/html/rfc6901) eg.
+/// `/address/city`, the empty pointer refers to the whole value.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JsonPointer {
+    raw: String,
+    tokens: Vec<String>,
+}
+
+impl JsonPointer {
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        if raw.is_empty() {
+            return Ok(Self { raw: raw.to_string(), tokens: vec![] });
+        }
+
+        let Some(pointer) = raw.strip_prefix('/') else {
+            return Err(format!("Pointer {} has to start with /", raw));
+        };
+
+        let tokens = pointer
+            .split('/')
+            .map(|token| {
+                // `~1` and `~0` are the only escapes, they're unescaped in
+                // that order so that `~01` is read as `~1`
+                let invalid = token
+                    .match_indices('~')
+                    .any(|(i, _)| !matches!(token.as_bytes().get(i + 1), Some(b'0' | b'1')));
+                if invalid {
+                    Err(format!("Pointer {} has an invalid escape", raw))
+                } else {
+                    Ok(token.replace("~1", "/").replace("~0", "~"))
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self { raw: raw.to_string(), tokens })
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    /// Checks if the pointer refers to a location inside the value referred
+    /// to by `other`
+    pub fn is_child_of(&self, other: &JsonPointer) -> bool {
+        self.tokens.len() > other.tokens.len() && self.tokens.starts_with(&other.tokens)
+    }
+}
+
+impl Display for JsonPointer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.raw)
+    }
+}
+
+/// An operation of a [JSON Patch](https://datatracker.ietf.org/doc/html/rfc6902).
+#[derive(Clone, Debug, PartialEq)]
+pub enum Operation {
+    Add {
+        path: JsonPointer,
+        value: ConstValue,
+    },
+    Remove {
+        path: JsonPointer,
+    },
+    Replace {
+        path: JsonPointer,
+        value: ConstValue,
+    },
+    Move {
+        from: JsonPointer,
+        path: JsonPointer,
+    },
+    Copy {
+        from: JsonPointer,
+        path: JsonPointer,
+    },
+    Test {
+        path: JsonPointer,
+        value: ConstValue,
+    },
+}
+
+/// A list of operations applied in order, the patch fails as a whole when any
+/// of them fails.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct JsonPatch(Vec<Operation>);
+
+impl From<Vec<Operation>> for JsonPatch {
+    fn from(operations: Vec<Operation>) -> Self {
+        Self(operations)
+    }
+}
+
+impl JsonPatch {
+    pub fn apply(&self, mut value: ConstValue) -> Result<ConstValue, String> {
+        for operation in self.0.iter() {
+            match operation {
+                Operation::Add { path, value: new } => add(&mut value, path, new.clone())?,
+                Operation::Remove { path } => {
+                    remove(&mut value, path)?;
+                }
+                Operation::Replace { path, value: new } => {
+                    *get_mut(&mut value, &path.tokens)
+                        .ok_or_else(|| format!("Path {} does not exist", path))? = new.clone();
+                }
+                Operation::Move { from, path } => {
+                    let moved = remove(&mut value, from)?;
+                    add(&mut value, path, moved)?;
+                }
+                Operation::Copy { from, path } => {
+                    let copied = get_mut(&mut value, &from.tokens)
+                        .ok_or_else(|| format!("Path {} does not exist", from))?
+                        .clone();
+                    add(&mut value, path, copied)?;
+                }
+                Operation::Test { path, value: expected } => {
+                    let actual = get_mut(&mut value, &path.tokens);
+                    if actual.map_or(true, |actual| actual != expected) {
+                        return Err(format!("Value at {} is not {}", path, expected));
+                    }
+                }
+            }
+        }
+
+        Ok(value)
+    }
+}
+
+/// Array indexes are written without leading zeros, `-` refers to the end of
+/// the array
+fn to_index(token: &str, len: usize) -> Option<usize> {
+    match token {
+        "-" => Some(len),
+        "0" => Some(0),
+        token if token.starts_with('0') => None,
+        token => token.parse().ok(),
+    }
+}
+
+fn get_mut<'a>(value: &'a mut ConstValue, tokens: &[String]) -> Option<&'a mut ConstValue> {
+    tokens.iter().try_fold(value, |value, token| match value {
+        ConstValue::Object(map) => map.get_mut(token.as_str()),
+        ConstValue::List(list) => {
+            let index = to_index(token, list.len())?;
+            list.get_mut(index)
+        }
+        _ => None,
+    })
+}
+
+fn add(value: &mut ConstValue, path: &JsonPointer, new: ConstValue) -> Result<(), String> {
+    let Some((last, parent)) = path.tokens.split_last() else {
+        *value = new;
+        return Ok(());
+    };
+
+    match get_mut(value, parent) {
+        Some(ConstValue::Object(map)) => {
+            map.insert(Name::new(last), new);
+            Ok(())
+        }
+        Some(ConstValue::List(list)) => match to_index(last, list.len()) {
+            Some(index) if index <= list.len() => {
+                list.insert(index, new);
+                Ok(())
+            }
+            _ => Err(format!("Index of {} is out of bounds", path)),
+        },
+        _ => Err(format!("Parent of {} does not exist", path)),
+    }
+}
+
+fn remove(value: &mut ConstValue, path: &JsonPointer) -> Result<ConstValue, String> {
+    let Some((last, parent)) = path.tokens.split_last() else {
+        return Err("The root can not be removed".to_string());
+    };
+
+    let removed = match get_mut(value, parent) {
+        Some(ConstValue::Object(map)) => map.shift_remove(last.as_str()),
+        Some(ConstValue::List(list)) => to_index(last, list.len())
+            .filter(|index| *index < list.len())
+            .map(|index| list.remove(index)),
+        _ => None,
+    };
+
+    removed.ok_or_else(|| format!("Path {} does not exist", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::*;
+
+    fn pointer(raw: &str) -> JsonPointer {
+        JsonPointer::parse(raw).unwrap()
+    }
+
+    fn apply(operations: Vec<Operation>, value: serde_json::Value) -> Result<ConstValue, String> {
+        JsonPatch::from(operations).apply(ConstValue::from_json(value).unwrap())
+    }
+
+    fn value(value: serde_json::Value) -> ConstValue {
+        ConstValue::from_json(value).unwrap()
+    }
+
+    #[test]
+    fn test_parse_pointer() {
+        assert_eq!(pointer("").tokens, Vec::<String>::new());
+        assert_eq!(pointer("/a~1b/~0c/0").tokens, vec!["a/b", "~c", "0"]);
+        assert_eq!(pointer("/~01").tokens, vec!["~1"]);
+        assert_eq!(
+            JsonPointer::parse("a/b"),
+            Err("Pointer a/b has to start with /".to_string())
+        );
+        assert_eq!(
+            JsonPointer::parse("/a~2"),
+            Err("Pointer /a~2 has an invalid escape".to_string())
+        );
+    }
+
+    #[test]
+    fn test_move_and_remove() {
+        let actual = apply(
+            vec![
+                Operation::Move { from: pointer("/address/city"), path: pointer("/city") },
+                Operation::Remove { path: pointer("/address") },
+            ],
+            json!({"id": 1, "address": {"city": "Gwenborough", "street": "Kulas Light"}}),
+        );
+
+        assert_eq!(actual, Ok(value(json!({"id": 1, "city": "Gwenborough"}))));
+    }
+
+    #[test]
+    fn test_add_replace_copy() {
+        let actual = apply(
+            vec![
+                Operation::Add { path: pointer("/tags/-"), value: value(json!("c")) },
+                Operation::Add { path: pointer("/tags/0"), value: value(json!("a")) },
+                Operation::Replace { path: pointer("/name"), value: value(json!("bar")) },
+                Operation::Copy { from: pointer("/name"), path: pointer("/alias") },
+            ],
+            json!({"name": "foo", "tags": ["b"]}),
+        );
+
+        assert_eq!(
+            actual,
+            Ok(value(
+                json!({"name": "bar", "tags": ["a", "b", "c"], "alias": "bar"})
+            ))
+        );
+    }
+
+    #[test]
+    fn test_failures() {
+        let input = json!({"name": "foo", "tags": ["a"]});
+
+        let actual = apply(
+            vec![Operation::Remove { path: pointer("/address") }],
+            input.clone(),
+        );
+        assert_eq!(actual, Err("Path /address does not exist".to_string()));
+
+        let actual = apply(
+            vec![Operation::Add { path: pointer("/tags/2"), value: value(json!("b")) }],
+            input.clone(),
+        );
+        assert_eq!(actual, Err("Index of /tags/2 is out of bounds".to_string()));
+
+        let actual = apply(
+            vec![Operation::Test { path: pointer("/name"), value: value(json!("bar")) }],
+            input,
+        );
+        assert_eq!(actual, Err("Value at /name is not \"bar\"".to_string()));
+    }
+}
@@ -1,4 +1,6 @@
 mod json_like;
+mod json_patch;
 mod json_schema;
 pub use json_like::*;
+pub use json_patch::*;
 pub use json_schema::*;
@@ -7,6 +7,8 @@ pub mod async_graphql_hyper;
 mod auth;
 pub mod blueprint;
 pub mod cache;
+pub mod check;
+pub mod compose;
 pub mod config;
 mod counter;
 pub mod data_loader;
@@ -52,14 +54,15 @@ pub trait EnvIO: Send + Sync + 'static {
     fn get(&self, key: &str) -> Option<Cow<'_, str>>;
 }
 
+/// Every upstream HTTP request made by the resolvers goes through the
+/// `HttpIO` held by the [runtime::TargetRuntime]. Replacing it allows to use a
+/// custom transport or to record and mock the upstream responses in tests.
 #[async_trait::async_trait]
 pub trait HttpIO: Sync + Send + 'static {
     async fn execute(
         &self,
         request: reqwest::Request,
-    ) -> anyhow::Result<Response<hyper::body::Bytes>> {
-        self.execute(request).await
-    }
+    ) -> anyhow::Result<Response<hyper::body::Bytes>>;
 }
 
 #[async_trait::async_trait]
@@ -70,8 +73,8 @@ pub trait FileIO: Send + Sync {
 
 #[async_trait::async_trait]
 pub trait Cache: Send + Sync {
-    type Key: Hash + Eq;
-    type Value;
+    type Key: Hash + Eq + Send + Sync;
+    type Value: Send;
     async fn set<'a>(
         &'a self,
         key: Self::Key,
@@ -80,6 +83,22 @@ pub trait Cache: Send + Sync {
     ) -> anyhow::Result<()>;
     async fn get<'a>(&'a self, key: &'a Self::Key) -> anyhow::Result<Option<Self::Value>>;
 
+    /// Sets the value unless the key is already set, returns whether it was
+    /// set. Caches that can't check and set the key at once should override
+    /// it.
+    async fn set_if_absent<'a>(
+        &'a self,
+        key: Self::Key,
+        value: Self::Value,
+        ttl: NonZeroU64,
+    ) -> anyhow::Result<bool> {
+        if self.get(&key).await?.is_some() {
+            return Ok(false);
+        }
+        self.set(key, value, ttl).await?;
+        Ok(true)
+    }
+
     fn hit_rate(&self) -> Option<f64>;
 }
 
@@ -91,6 +110,23 @@ pub trait WorkerIO<In, Out>: Send + Sync + 'static {
     async fn call(&self, name: &str, input: In) -> anyhow::Result<Option<Out>>;
 }
 
+#[async_trait::async_trait]
+pub trait WasmIO: Send + Sync + 'static {
+    /// Compiles a module ahead of its first call, so that a module that can't
+    /// be loaded is reported at startup
+    async fn load(&self, module: &str) -> anyhow::Result<()>;
+
+    /// Calls a function exported by a WASM module with the value and the args
+    /// of the resolver, and returns the value it outputs
+    async fn call(
+        &self,
+        module: &str,
+        func: &str,
+        value: ConstValue,
+        args: ConstValue,
+    ) -> anyhow::Result<ConstValue>;
+}
+
 pub fn is_default<T: Default + Eq>(val: &T) -> bool {
     *val == T::default()
 }
@@ -1,14 +1,15 @@
 use std::fmt::Display;
 
+use async_graphql_value::ConstValue;
 use nom::branch::alt;
 use nom::bytes::complete::{tag, take_until};
 use nom::character::complete::char;
-use nom::combinator::map;
-use nom::multi::many0;
-use nom::sequence::delimited;
+use nom::combinator::{map, map_opt, opt, verify};
+use nom::multi::{many0, separated_list1};
+use nom::sequence::{delimited, preceded, tuple};
 use nom::{Finish, IResult};
 
-use crate::core::path::{PathGraphql, PathString};
+use crate::core::path::{PathGraphql, PathString, PathValue};
 
 #[derive(Debug, Clone, PartialEq, Hash)]
 pub struct Mustache(Vec<Segment>);
@@ -17,6 +18,57 @@ pub struct Mustache(Vec<Segment>);
 pub enum Segment {
     Literal(String),
     Expression(Vec<String>),
+    /// An expression whose rendered value goes through helpers, applied from
+    /// left to right eg. `{{.args.sort | lower}}`
+    Transform(Vec<String>, Vec<Helper>),
+    /// Renders its content only when the expression resolves to something
+    /// other than null, false or an empty string eg.
+    /// `{{#args.id}}/{{args.id}}{{/args.id}}`
+    Section(Vec<String>, Mustache),
+}
+
+/// Transforms the rendered value of an expression
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Helper {
+    Lower,
+    Upper,
+}
+
+impl Helper {
+    fn from_name(name: &str) -> Option<Helper> {
+        match name {
+            "lower" => Some(Helper::Lower),
+            "upper" => Some(Helper::Upper),
+            _ => None,
+        }
+    }
+
+    fn apply(&self, value: &str) -> String {
+        match self {
+            Helper::Lower => value.to_lowercase(),
+            Helper::Upper => value.to_uppercase(),
+        }
+    }
+
+    fn apply_all(helpers: &[Helper], value: &str) -> String {
+        helpers
+            .iter()
+            .fold(value.to_string(), |value, helper| helper.apply(&value))
+    }
+}
+
+impl Display for Helper {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Helper::Lower => f.write_str("lower"),
+            Helper::Upper => f.write_str("upper"),
+        }
+    }
+}
+
+/// Checks if the rendered value of a section's expression is present.
+fn is_present(value: Option<&str>) -> bool {
+    !matches!(value, None | Some("" | "false" | "null"))
 }
 
 impl From<Vec<Segment>> for Mustache {
@@ -30,7 +82,9 @@ impl Mustache {
         match self {
             Mustache(segments) => {
                 for s in segments {
-                    if let Segment::Expression(_) = s {
+                    if let Segment::Expression(_) | Segment::Transform(..) | Segment::Section(..) =
+                        s
+                    {
                         return false;
                     }
                 }
@@ -58,11 +112,76 @@ impl Mustache {
                         .path_string(parts)
                         .map(|a| a.to_string())
                         .unwrap_or_default(),
+                    Segment::Transform(parts, helpers) => value
+                        .path_string(parts)
+                        .map(|a| Helper::apply_all(helpers, &a))
+                        .unwrap_or_default(),
+                    Segment::Section(parts, content) => {
+                        if is_present(value.path_string(parts).as_deref()) {
+                            content.render(value)
+                        } else {
+                            String::new()
+                        }
+                    }
                 })
                 .collect(),
         }
     }
 
+    /// Renders the template as a value. A template made of a single expression
+    /// keeps the type of the value it resolves to eg. `{{.args.count}}` stays
+    /// a number. Other templates are rendered as a string and parsed as JSON
+    /// when possible.
+    pub fn render_typed(&self, value: &(impl PathString + PathValue)) -> ConstValue {
+        if let [Segment::Expression(parts)] = self.0.as_slice() {
+            if let Some(value) = value.raw_value(parts) {
+                return value;
+            }
+        }
+
+        let rendered = self.render(value);
+        serde_json::from_str::<ConstValue>(&rendered).unwrap_or(ConstValue::String(rendered))
+    }
+
+    /// Renders the expressions that can be resolved by the given value and
+    /// keeps the rest of them as is. Useful to pre-render the parts of a
+    /// template that never change between requests eg. `{{.env.HOST}}`.
+    pub fn partially_render(&self, value: &impl PathString) -> Mustache {
+        let mut segments: Vec<Segment> = Vec::new();
+        for segment in self.0.iter() {
+            let rendered = match segment {
+                Segment::Expression(parts) => match value.path_string(parts) {
+                    Some(text) => vec![Segment::Literal(text.to_string())],
+                    None => vec![segment.clone()],
+                },
+                Segment::Transform(parts, helpers) => match value.path_string(parts) {
+                    Some(text) => vec![Segment::Literal(Helper::apply_all(helpers, &text))],
+                    None => vec![segment.clone()],
+                },
+                Segment::Section(parts, content) => match value.path_string(parts) {
+                    Some(text) if is_present(Some(text.as_ref())) => {
+                        content.partially_render(value).0
+                    }
+                    Some(_) => vec![],
+                    None => vec![Segment::Section(
+                        parts.clone(),
+                        content.partially_render(value),
+                    )],
+                },
+                segment => vec![segment.clone()],
+            };
+
+            for segment in rendered {
+                match (segments.last_mut(), segment) {
+                    (Some(Segment::Literal(last)), Segment::Literal(text)) => last.push_str(&text),
+                    (_, segment) => segments.push(segment),
+                }
+            }
+        }
+
+        Mustache(segments)
+    }
+
     pub fn render_graphql(&self, value: &impl PathGraphql) -> String {
         match self {
             Mustache(segments) => segments
@@ -70,6 +189,17 @@ impl Mustache {
                 .map(|segment| match segment {
                     Segment::Literal(text) => text.to_string(),
                     Segment::Expression(parts) => value.path_graphql(parts).unwrap_or_default(),
+                    Segment::Transform(parts, helpers) => value
+                        .path_graphql(parts)
+                        .map(|a| Helper::apply_all(helpers, &a))
+                        .unwrap_or_default(),
+                    Segment::Section(parts, content) => {
+                        if is_present(value.path_graphql(parts).as_deref()) {
+                            content.render_graphql(value)
+                        } else {
+                            String::new()
+                        }
+                    }
                 })
                 .collect(),
         }
@@ -82,15 +212,38 @@ impl Mustache {
     }
 
     pub fn expression_segments(&self) -> Vec<&Vec<String>> {
-        match self {
-            Mustache(segments) => segments
-                .iter()
-                .filter_map(|seg| match seg {
-                    Segment::Expression(parts) => Some(parts),
-                    _ => None,
-                })
-                .collect(),
+        self.expression_segments_in_sections()
+            .into_iter()
+            .map(|(parts, _)| parts)
+            .collect()
+    }
+
+    /// Returns the expressions of the template, including the ones of its
+    /// sections, along with whether they're part of a section. The expressions
+    /// of a section are only rendered when present, so they can be nullable.
+    pub fn expression_segments_in_sections(&self) -> Vec<(&Vec<String>, bool)> {
+        fn collect<'a>(
+            segments: &'a [Segment],
+            in_section: bool,
+            acc: &mut Vec<(&'a Vec<String>, bool)>,
+        ) {
+            for segment in segments {
+                match segment {
+                    Segment::Literal(_) => {}
+                    Segment::Expression(parts) | Segment::Transform(parts, _) => {
+                        acc.push((parts, in_section))
+                    }
+                    Segment::Section(parts, content) => {
+                        acc.push((parts, true));
+                        collect(&content.0, true, acc);
+                    }
+                }
+            }
         }
+
+        let mut acc = Vec::new();
+        collect(&self.0, false, &mut acc);
+        acc
     }
 }
 
@@ -102,6 +255,17 @@ impl Display for Mustache {
                 .map(|segment| match segment {
                     Segment::Literal(text) => text.clone(),
                     Segment::Expression(parts) => format!("{{{{{}}}}}", parts.join(".")),
+                    Segment::Transform(parts, helpers) => {
+                        let helpers = helpers
+                            .iter()
+                            .map(|helper| format!(" | {}", helper))
+                            .collect::<String>();
+                        format!("{{{{{}{}}}}}", parts.join("."), helpers)
+                    }
+                    Segment::Section(parts, content) => {
+                        let path = parts.join(".");
+                        format!("{{{{#{}}}}}{}{{{{/{}}}}}", path, content, path)
+                    }
                 })
                 .collect::<Vec<String>>()
                 .join(""),
@@ -113,9 +277,11 @@ impl Display for Mustache {
 fn parse_name(input: &str) -> IResult<&str, String> {
     let spaces = nom::character::complete::multispace0;
     let alpha = nom::character::complete::alpha1;
+    // Dashes are allowed for the names of headers eg: `{{.headers.x-tenant}}`
     let alphanumeric_or_underscore = nom::multi::many0(nom::branch::alt((
         nom::character::complete::alphanumeric1,
         nom::bytes::complete::tag("_"),
+        nom::bytes::complete::tag("-"),
     )));
 
     let parser = nom::sequence::tuple((spaces, alpha, alphanumeric_or_underscore, spaces));
@@ -126,22 +292,58 @@ fn parse_name(input: &str) -> IResult<&str, String> {
     })(input)
 }
 
+fn parse_path(input: &str) -> IResult<&str, Vec<String>> {
+    map(
+        tuple((
+            opt(char('.')), // Optional leading dot
+            separated_list1(char('.'), parse_name),
+        )),
+        |(_, expr_parts)| expr_parts,
+    )(input)
+}
+
+fn parse_helpers(input: &str) -> IResult<&str, Vec<Helper>> {
+    many0(preceded(
+        char('|'),
+        map_opt(parse_name, |name| Helper::from_name(&name)),
+    ))(input)
+}
+
 fn parse_expression(input: &str) -> IResult<&str, Segment> {
     delimited(
         tag("{{"),
-        map(
-            nom::sequence::tuple((
-                nom::combinator::opt(char('.')), // Optional leading dot
-                nom::multi::separated_list1(char('.'), parse_name),
-            )),
-            |(_, expr_parts)| Segment::Expression(expr_parts),
-        ),
+        map(tuple((parse_path, parse_helpers)), |(parts, helpers)| {
+            if helpers.is_empty() {
+                Segment::Expression(parts)
+            } else {
+                Segment::Transform(parts, helpers)
+            }
+        }),
         tag("}}"),
     )(input)
 }
 
+fn parse_section(input: &str) -> IResult<&str, Segment> {
+    let (input, parts) = delimited(tag("{{#"), parse_path, tag("}}"))(input)?;
+    let (input, content) = many0(alt((
+        parse_section,
+        parse_expression,
+        map(
+            verify(take_until("{{"), |txt: &str| !txt.is_empty()),
+            |txt: &str| Segment::Literal(txt.to_string()),
+        ),
+    )))(input)?;
+    let (input, _) = verify(
+        delimited(tag("{{/"), parse_path, tag("}}")),
+        |end: &Vec<String>| *end == parts,
+    )(input)?;
+
+    Ok((input, Segment::Section(parts, Mustache(content))))
+}
+
 fn parse_segment(input: &str) -> IResult<&str, Vec<Segment>> {
     let expression_result = many0(alt((
+        parse_section,
         parse_expression,
         map(take_until("{{"), |txt: &str| {
             Segment::Literal(txt.to_string())
@@ -180,7 +382,7 @@ mod tests {
     mod parse {
         use pretty_assertions::assert_eq;
 
-        use crate::core::mustache::{Mustache, Segment};
+        use crate::core::mustache::{Helper, Mustache, Segment};
 
         #[test]
         fn test_to_string() {
@@ -194,6 +396,7 @@ mod tests {
                 r"{{value.d}}",
                 r"/posts/{{args.id}}",
                 r"http://localhost:8000",
+                r"/items{{#args.id}}/{{args.id}}{{/args.id}}",
             ];
 
             for expected in expectations {
@@ -226,6 +429,19 @@ mod tests {
             );
         }
 
+        #[test]
+        fn test_template_with_dashes() {
+            let s = r"{{.headers.x-tenant}}";
+            let mustache: Mustache = Mustache::parse(s).unwrap();
+            assert_eq!(
+                mustache,
+                Mustache::from(vec![Segment::Expression(vec![
+                    "headers".to_string(),
+                    "x-tenant".to_string(),
+                ])])
+            );
+        }
+
         #[test]
         fn test_mixed() {
             let s = r"http://localhost:8090/{{foo.bar}}/api/{{hello.world}}/end";
@@ -354,6 +570,34 @@ mod tests {
             );
         }
 
+        #[test]
+        fn test_section() {
+            let mustache = Mustache::parse("/items{{#.args.id}}/{{args.id}}{{/args.id}}").unwrap();
+            assert_eq!(
+                mustache,
+                Mustache::from(vec![
+                    Segment::Literal("/items".to_string()),
+                    Segment::Section(
+                        vec!["args".to_string(), "id".to_string()],
+                        Mustache::from(vec![
+                            Segment::Literal("/".to_string()),
+                            Segment::Expression(vec!["args".to_string(), "id".to_string()]),
+                        ])
+                    ),
+                ])
+            );
+        }
+
+        #[test]
+        fn test_unclosed_section() {
+            let s = "/items{{#args.id}}/{{args.id}}{{/args.name}}";
+            let mustache = Mustache::parse(s).unwrap();
+            assert_eq!(
+                mustache,
+                Mustache::from(vec![Segment::Literal(s.to_string())])
+            );
+        }
+
         #[test]
         fn test_optional_dot_expression() {
             let s = r"{{.foo.bar}}";
@@ -366,6 +610,29 @@ mod tests {
                 ])])
             );
         }
+
+        #[test]
+        fn test_helpers() {
+            let mustache = Mustache::parse("{{.args.sort | lower | upper}}").unwrap();
+            assert_eq!(
+                mustache,
+                Mustache::from(vec![Segment::Transform(
+                    vec!["args".to_string(), "sort".to_string()],
+                    vec![Helper::Lower, Helper::Upper],
+                )])
+            );
+            assert_eq!(mustache.to_string(), "{{args.sort | lower | upper}}");
+        }
+
+        #[test]
+        fn test_unknown_helper() {
+            let s = "{{.args.sort | reverse}}";
+            let mustache = Mustache::parse(s).unwrap();
+            assert_eq!(
+                mustache,
+                Mustache::from(vec![Segment::Literal(s.to_string())])
+            );
+        }
     }
 
     mod render {
@@ -453,6 +720,22 @@ mod tests {
             assert_eq!(result, r#"{registered: "foo", display: "bar"}"#);
         }
 
+        #[test]
+        fn test_render_section() {
+            let mustache = Mustache::parse("/items{{#args.id}}/{{args.id}}{{/args.id}}").unwrap();
+
+            assert_eq!(mustache.render(&json!({"args": {}})), "/items");
+            assert_eq!(mustache.render(&json!({"args": {"id": null}})), "/items");
+            assert_eq!(mustache.render(&json!({"args": {"id": 5}})), "/items/5");
+        }
+
+        #[test]
+        fn test_render_helpers() {
+            let mustache = Mustache::parse("{{args.field | lower}}:{{args.dir | lower}}").unwrap();
+            let ctx = json!({"args": {"field": "NAME", "dir": "ASC"}});
+            assert_eq!(mustache.render(&ctx), "name:asc");
+        }
+
         #[test]
         fn test_render_preserves_spaces() {
             struct DummyPath;
@@ -534,4 +817,60 @@ mod tests {
             assert_eq!(mustache.render_graphql(&DummyPath), "prefix  suffix");
         }
     }
+    mod partially_render {
+        use std::borrow::Cow;
+
+        use pretty_assertions::assert_eq;
+
+        use crate::core::mustache::{Mustache, Segment};
+        use crate::core::path::PathString;
+
+        struct Env;
+
+        impl PathString for Env {
+            fn path_string<T: AsRef<str>>(&self, parts: &[T]) -> Option<Cow<'_, str>> {
+                match parts {
+                    [head, key] if head.as_ref() == "env" && key.as_ref() == "HOST" => {
+                        Some(Cow::Borrowed("localhost"))
+                    }
+                    _ => None,
+                }
+            }
+        }
+
+        #[test]
+        fn test_partially_render() {
+            let mustache = Mustache::parse("http://{{.env.HOST}}/users/{{.args.id}}").unwrap();
+
+            assert_eq!(
+                mustache.partially_render(&Env),
+                Mustache::from(vec![
+                    Segment::Literal("http://localhost/users/".to_string()),
+                    Segment::Expression(vec!["args".to_string(), "id".to_string()]),
+                ])
+            );
+        }
+
+        #[test]
+        fn test_partially_render_section() {
+            let mustache = Mustache::parse(
+                "http://{{#env.HOST}}{{env.HOST}}{{/env.HOST}}/users{{#args.id}}/{{args.id}}{{/args.id}}",
+            )
+            .unwrap();
+
+            assert_eq!(
+                mustache.partially_render(&Env).to_string(),
+                "http://localhost/users{{#args.id}}/{{args.id}}{{/args.id}}"
+            );
+        }
+
+        #[test]
+        fn test_partially_render_const() {
+            let mustache = Mustache::parse("http://{{.env.HOST}}/users").unwrap();
+            let actual = mustache.partially_render(&Env);
+
+            assert!(actual.is_const());
+            assert_eq!(actual.to_string(), "http://localhost/users");
+        }
+    }
 }
@@ -1,5 +1,7 @@
 use std::borrow::Cow;
 
+use hyper::HeaderMap;
+use indexmap::IndexMap;
 use serde_json::json;
 
 use crate::core::ir::{EvaluationContext, ResolverContextLike};
@@ -24,6 +26,21 @@ pub trait PathGraphql {
     fn path_graphql<T: AsRef<str>>(&self, path: &[T]) -> Option<String>;
 }
 
+///
+/// The PathValue trait provides a method for accessing values from a JSON-like
+/// structure. The returned value keeps its type, so that numbers, booleans and
+/// objects aren't turned into strings.
+pub trait PathValue {
+    fn raw_value<T: AsRef<str>>(&self, path: &[T]) -> Option<async_graphql::Value>;
+}
+
+impl PathValue for serde_json::Value {
+    fn raw_value<T: AsRef<str>>(&self, path: &[T]) -> Option<async_graphql::Value> {
+        self.get_path(path)
+            .and_then(|value| async_graphql::Value::from_json(value.clone()).ok())
+    }
+}
+
 impl PathString for serde_json::Value {
     fn path_string<T: AsRef<str>>(&self, path: &[T]) -> Option<Cow<'_, str>> {
         self.get_path(path).map(|a| match a {
@@ -62,6 +79,11 @@ impl<'a, Ctx: ResolverContextLike<'a>> PathString for EvaluationContext<'a, Ctx>
                 "value" => convert_value(ctx.path_value(&[] as &[T])?),
                 "args" => Some(json!(ctx.path_arg::<&str>(&[])?).to_string().into()),
                 "vars" => Some(json!(ctx.vars()).to_string().into()),
+                "context" => Some(json!(ctx.context()).to_string().into()),
+                "selection" => {
+                    let fields = ctx.selection_fields();
+                    (!fields.is_empty()).then(|| fields.join(",").into())
+                }
                 _ => None,
             };
         }
@@ -72,12 +94,61 @@ impl<'a, Ctx: ResolverContextLike<'a>> PathString for EvaluationContext<'a, Ctx>
                 "args" => convert_value(ctx.path_arg(tail)?),
                 "headers" => ctx.header(tail[0].as_ref()).map(|v| v.into()),
                 "vars" => ctx.var(tail[0].as_ref()).map(|v| v.into()),
+                "context" => ctx.context_var(tail[0].as_ref()).map(|v| v.into()),
                 "env" => ctx.env_var(tail[0].as_ref()),
                 _ => None,
             })
     }
 }
 
+/// Returns the values of a header as a string, or as a list of strings when
+/// the header is repeated.
+fn header_value(headers: &HeaderMap, name: &str) -> Option<async_graphql::Value> {
+    let mut values = headers
+        .get_all(name)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .map(|value| async_graphql::Value::String(value.to_owned()))
+        .collect::<Vec<_>>();
+
+    match values.len() {
+        0 => None,
+        1 => values.pop(),
+        _ => Some(async_graphql::Value::List(values)),
+    }
+}
+
+fn headers_value(headers: &HeaderMap) -> async_graphql::Value {
+    let map = headers
+        .keys()
+        .filter_map(|name| {
+            let value = header_value(headers, name.as_str())?;
+            Some((async_graphql::Name::new(name.as_str()), value))
+        })
+        .collect::<IndexMap<_, _>>();
+
+    async_graphql::Value::Object(map)
+}
+
+impl<'a, Ctx: ResolverContextLike<'a>> PathValue for EvaluationContext<'a, Ctx> {
+    fn raw_value<T: AsRef<str>>(&self, path: &[T]) -> Option<async_graphql::Value> {
+        let ctx = self;
+
+        path.split_first()
+            .and_then(|(head, tail)| match head.as_ref() {
+                "value" => Some(ctx.path_value(tail)?.into_owned()),
+                "args" => Some(ctx.path_arg(tail)?.into_owned()),
+                "headers" if tail.is_empty() => Some(headers_value(ctx.headers())),
+                "headers" if tail.len() == 1 => header_value(ctx.headers(), tail[0].as_ref()),
+                // Vars, context and env variables are always strings
+                "vars" | "context" | "env" if tail.len() == 1 => Some(
+                    async_graphql::Value::String(ctx.path_string(path)?.into_owned()),
+                ),
+                _ => None,
+            })
+    }
+}
+
 impl<'a, Ctx: ResolverContextLike<'a>> PathGraphql for EvaluationContext<'a, Ctx> {
     fn path_graphql<T: AsRef<str>>(&self, path: &[T]) -> Option<String> {
         let ctx = self;
@@ -92,6 +163,9 @@ impl<'a, Ctx: ResolverContextLike<'a>> PathGraphql for EvaluationContext<'a, Ctx
                 "args" => Some(ctx.path_arg(tail)?.to_string()),
                 "headers" => ctx.header(tail[0].as_ref()).map(|v| format!(r#""{v}""#)),
                 "vars" => ctx.var(tail[0].as_ref()).map(|v| format!(r#""{v}""#)),
+                "context" => ctx
+                    .context_var(tail[0].as_ref())
+                    .map(|v| format!(r#""{v}""#)),
                 "env" => ctx.env_var(tail[0].as_ref()).map(|v| format!(r#""{v}""#)),
                 _ => None,
             })
@@ -115,7 +189,7 @@ mod tests {
 
         use crate::core::http::RequestContext;
         use crate::core::ir::{EvaluationContext, ResolverContextLike};
-        use crate::core::path::{PathGraphql, PathString};
+        use crate::core::path::{PathGraphql, PathString, PathValue};
         use crate::core::EnvIO;
 
         struct Env {
@@ -169,6 +243,8 @@ mod tests {
             let mut map = HeaderMap::new();
 
             map.insert("x-existing", HeaderValue::from_static("header"));
+            map.append("x-repeated", HeaderValue::from_static("first"));
+            map.append("x-repeated", HeaderValue::from_static("second"));
 
             map
         });
@@ -212,6 +288,7 @@ mod tests {
             let mut req_ctx = RequestContext::default().allowed_headers(TEST_HEADERS.clone());
 
             req_ctx.server.vars = TEST_VARS.clone();
+            req_ctx.context = TEST_VARS.clone();
             req_ctx.runtime.env = Arc::new(Env::init(TEST_ENV_VARS.clone()));
 
             req_ctx
@@ -284,6 +361,16 @@ mod tests {
                 Some(Cow::Borrowed(r#"{"existing":"var"}"#))
             );
 
+            // context
+            assert_eq!(
+                EVAL_CTX.path_string(&["context", "existing"]),
+                Some(Cow::Borrowed("var"))
+            );
+            assert_eq!(EVAL_CTX.path_string(&["context", "missing"]), None);
+
+            // selection
+            assert_eq!(EVAL_CTX.path_string(&["selection"]), None);
+
             // envs
             assert_eq!(
                 EVAL_CTX.path_string(&["env", "existing"]),
@@ -357,5 +444,41 @@ mod tests {
             assert_eq!(EVAL_CTX.path_graphql(&["bar", "key"]), None);
             assert_eq!(EVAL_CTX.path_graphql(&["baz", "key"]), None);
         }
+
+        #[test]
+        fn path_to_raw_value() {
+            // headers
+            assert_eq!(
+                EVAL_CTX.raw_value(&["headers", "x-existing"]),
+                Some(Value::String("header".to_owned()))
+            );
+            assert_eq!(
+                EVAL_CTX.raw_value(&["headers", "x-repeated"]),
+                Some(Value::List(vec![
+                    Value::String("first".to_owned()),
+                    Value::String("second".to_owned())
+                ]))
+            );
+            assert_eq!(EVAL_CTX.raw_value(&["headers", "x-missing"]), None);
+            assert_eq!(
+                EVAL_CTX.raw_value(&["headers"]),
+                Some(Value::Object(IndexMap::from([
+                    (Name::new("x-existing"), Value::String("header".to_owned())),
+                    (
+                        Name::new("x-repeated"),
+                        Value::List(vec![
+                            Value::String("first".to_owned()),
+                            Value::String("second".to_owned())
+                        ])
+                    ),
+                ])))
+            );
+
+            // vars
+            assert_eq!(
+                EVAL_CTX.raw_value(&["vars", "existing"]),
+                Some(Value::String("var".to_owned()))
+            );
+        }
     }
 }
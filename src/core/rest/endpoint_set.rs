@@ -15,6 +15,8 @@ use crate::core::valid::Validator;
 #[derive(Default, Clone, Debug, MergeRight)]
 pub struct EndpointSet<Status> {
     endpoints: Vec<Endpoint>,
+    /// The operations the endpoints were parsed from
+    sources: Vec<String>,
     marker: std::marker::PhantomData<Status>,
 }
 
@@ -43,18 +45,24 @@ impl EndpointSet<Unchecked> {
         self.endpoints.push(endpoint);
     }
 
+    pub fn get_sources(&self) -> &Vec<String> {
+        &self.sources
+    }
+
     pub fn try_new(operations: &str) -> anyhow::Result<EndpointSet<Unchecked>> {
         let mut set = EndpointSet::default();
 
         for endpoint in Endpoint::try_new(operations)? {
             set.add_endpoint(endpoint);
         }
+        set.sources.push(operations.to_string());
 
         Ok(set)
     }
 
     pub fn extend(&mut self, other: EndpointSet<Unchecked>) {
         self.endpoints.extend(other.endpoints);
+        self.sources.extend(other.sources);
     }
 
     pub async fn into_checked(
@@ -78,6 +86,7 @@ impl EndpointSet<Unchecked> {
         Ok(EndpointSet {
             marker: std::marker::PhantomData::<Checked>,
             endpoints: self.endpoints,
+            sources: self.sources,
         })
     }
 }
@@ -5,7 +5,7 @@ use async_graphql_value::ConstValue;
 use super::ir::IoId;
 use crate::core::schema_extension::SchemaExtension;
 use crate::core::worker::{Command, Event};
-use crate::core::{Cache, EnvIO, FileIO, HttpIO, WorkerIO};
+use crate::core::{Cache, EnvIO, FileIO, HttpIO, WasmIO, WorkerIO};
 
 /// The TargetRuntime struct unifies the available runtime-specific
 /// IO implementations. This is used to reduce piping IO structs all
@@ -32,6 +32,8 @@ pub struct TargetRuntime {
     pub cmd_worker: Option<Arc<dyn WorkerIO<Event, Command>>>,
     /// Worker middleware for resolving data.
     pub worker: Option<Arc<dyn WorkerIO<ConstValue, ConstValue>>>,
+    /// Sandbox running the WASM modules of `@wasm`.
+    pub wasm: Option<Arc<dyn WasmIO>>,
 }
 
 impl TargetRuntime {
@@ -78,14 +80,14 @@ pub mod test {
     impl TestHttp {
         fn init(upstream: &Upstream) -> Arc<Self> {
             let mut builder = Client::builder()
-                .tcp_keepalive(Some(Duration::from_secs(upstream.tcp_keep_alive)))
+                .tcp_keepalive(upstream.get_tcp_keep_alive())
                 .timeout(Duration::from_secs(upstream.timeout))
                 .connect_timeout(Duration::from_secs(upstream.connect_timeout))
-                .http2_keep_alive_interval(Some(Duration::from_secs(upstream.keep_alive_interval)))
+                .http2_keep_alive_interval(upstream.get_http2_keep_alive_interval())
                 .http2_keep_alive_timeout(Duration::from_secs(upstream.keep_alive_timeout))
                 .http2_keep_alive_while_idle(upstream.keep_alive_while_idle)
                 .pool_idle_timeout(Some(Duration::from_secs(upstream.pool_idle_timeout)))
-                .pool_max_idle_per_host(upstream.pool_max_idle_per_host)
+                .pool_max_idle_per_host(upstream.get_pool_max_idle_per_host())
                 .user_agent(upstream.user_agent.clone());
 
             // Add Http2 Prior Knowledge
@@ -118,12 +120,7 @@ pub mod test {
     impl HttpIO for TestHttp {
         async fn execute(&self, request: reqwest::Request) -> Result<Response<Bytes>> {
             let response = self.client.execute(request).await;
-            Response::from_reqwest(
-                response?
-                    .error_for_status()
-                    .map_err(|err| err.without_url())?,
-            )
-            .await
+            Response::from_reqwest_for_status(response?).await
         }
     }
 
@@ -195,6 +192,7 @@ pub mod test {
                 Some(script) => Some(init_worker_io::<Value, Value>(script.to_owned())),
                 None => None,
             },
+            wasm: None,
         }
     }
 }
@@ -14,6 +14,7 @@ pub use uint16::*;
 pub use uint32::*;
 pub use uint64::*;
 pub use uint8::*;
+pub use upload::*;
 pub use url::*;
 
 mod bytes;
@@ -32,6 +33,7 @@ mod uint16;
 mod uint32;
 mod uint64;
 mod uint8;
+mod upload;
 mod url;
 
 use std::collections::{HashMap, HashSet};
@@ -61,6 +63,7 @@ lazy_static! {
             Arc::new(UInt64::default()),
             Arc::new(UInt128::default()),
             Arc::new(Bytes::default()),
+            Arc::new(Upload::default()),
         ];
         let mut hm = HashMap::new();
 
@@ -0,0 +1,62 @@
+use async_graphql_value::ConstValue;
+use schemars::schema::Schema;
+use schemars::{schema_for, JsonSchema};
+
+/// A file sent with the [GraphQL multipart request](https://github.com/jaydenseric/graphql-multipart-request-spec).
+/// The content of the file is base64 encoded.
+#[derive(JsonSchema, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Upload {
+    #[allow(dead_code)]
+    pub content: String,
+    #[allow(dead_code)]
+    pub content_type: Option<String>,
+    #[allow(dead_code)]
+    pub filename: Option<String>,
+}
+
+impl super::Scalar for Upload {
+    fn validate(&self) -> fn(&ConstValue) -> bool {
+        |value| match value {
+            ConstValue::Object(map) => map
+                .get("content")
+                .is_some_and(|content| matches!(content, ConstValue::String(_))),
+            _ => false,
+        }
+    }
+
+    fn schema(&self) -> Schema {
+        Schema::Object(schema_for!(Self).schema)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use async_graphql_value::ConstValue;
+    use serde_json::json;
+
+    use crate::core::scalar::{Scalar, Upload};
+
+    #[test]
+    fn test_upload_valid() {
+        let value = json!({"filename": "a.txt", "content": "aGVsbG8="});
+        assert!(Upload::default().validate()(
+            &ConstValue::from_json(value).unwrap()
+        ));
+    }
+
+    #[test]
+    fn test_upload_invalid_without_content() {
+        let value = json!({"filename": "a.txt"});
+        assert!(!Upload::default().validate()(
+            &ConstValue::from_json(value).unwrap()
+        ));
+    }
+
+    #[test]
+    fn test_upload_invalid_string() {
+        assert!(!Upload::default().validate()(&ConstValue::String(
+            "aGVsbG8=".to_string()
+        )));
+    }
+}
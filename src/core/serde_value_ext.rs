@@ -4,25 +4,17 @@ use async_graphql::{Name, Value as GraphQLValue};
 use indexmap::IndexMap;
 
 use crate::core::blueprint::DynamicValue;
-use crate::core::path::PathString;
+use crate::core::path::{PathString, PathValue};
 
 pub trait ValueExt {
-    fn render_value(&self, ctx: &impl PathString) -> GraphQLValue;
+    fn render_value(&self, ctx: &(impl PathString + PathValue)) -> GraphQLValue;
 }
 
 impl ValueExt for DynamicValue {
-    fn render_value<'a>(&self, ctx: &'a impl PathString) -> GraphQLValue {
+    fn render_value(&self, ctx: &(impl PathString + PathValue)) -> GraphQLValue {
         match self {
             DynamicValue::Value(value) => value.to_owned(),
-            DynamicValue::Mustache(m) => {
-                let rendered: Cow<'a, str> = Cow::Owned(m.render(ctx));
-
-                serde_json::from_str::<GraphQLValue>(rendered.as_ref())
-                    // parsing can fail when Mustache::render returns bare string and since
-                    // that string is not wrapped with quotes serde_json will fail to parse it
-                    // but, we can just use that string as is
-                    .unwrap_or_else(|_| GraphQLValue::String(rendered.into_owned()))
-            }
+            DynamicValue::Mustache(m) => m.render_typed(ctx),
             DynamicValue::Object(obj) => {
                 let out: IndexMap<_, _> = obj
                     .iter()
@@ -162,4 +154,14 @@ mod tests {
                 .unwrap();
         assert_eq!(result, expected);
     }
+
+    #[test]
+    fn test_render_value_keeps_type() {
+        let value = json!({"count": "{{args.count}}", "code": "{{args.code}}"});
+        let value = DynamicValue::try_from(&value).unwrap();
+        let ctx = json!({"args": {"count": 5, "code": "007"}});
+        let result = value.render_value(&ctx);
+        let expected = async_graphql::Value::from_json(json!({"count": 5, "code": "007"})).unwrap();
+        assert_eq!(result, expected);
+    }
 }
@@ -2,3 +2,16 @@ pub mod core;
 
 #[cfg(feature = "cli")]
 pub mod cli;
+
+pub use core::check::{CheckReport, Diagnostic, Severity};
+pub use core::compose::{compose, ComposeReport};
+
+/// Validates the configuration files without starting a server and returns
+/// every error and warning found in them.
+#[cfg(feature = "cli")]
+pub async fn check(sources: &[&str]) -> CheckReport {
+    match cli::runtime::init(&core::blueprint::Blueprint::default()) {
+        Ok(runtime) => core::check::check(runtime, sources).await,
+        Err(error) => CheckReport::from(error),
+    }
+}
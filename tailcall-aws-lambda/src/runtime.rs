@@ -62,5 +62,6 @@ pub fn init_runtime() -> TargetRuntime {
         extensions: Arc::new(vec![]),
         cmd_worker: None,
         worker: None,
+        wasm: None,
     }
 }
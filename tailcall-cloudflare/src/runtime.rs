@@ -43,5 +43,6 @@ pub fn init(env: Rc<worker::Env>) -> anyhow::Result<TargetRuntime> {
         extensions: Arc::new(vec![]),
         cmd_worker: None,
         worker: None,
+        wasm: None,
     })
 }
@@ -42,7 +42,7 @@ async fn test_simple() {
 
         let executor = Executor::new(&general_plan, &operation_plan);
 
-        let runtime = tailcall::cli::runtime::init(&Blueprint::default());
+        let runtime = tailcall::cli::runtime::init(&Blueprint::default()).unwrap();
         let req_ctx = RequestContext::new(runtime);
         let execution_result = executor.execute(&req_ctx, &execution_plan).await;
 
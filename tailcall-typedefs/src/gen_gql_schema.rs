@@ -17,14 +17,18 @@ lazy_static! {
     static ref DIRECTIVE_ALLOW_LIST: Vec<(&'static str, Vec<Entity>, bool)> = vec![
         ("server", vec![Entity::Schema], false),
         ("link", vec![Entity::Schema], true),
+        ("fragment", vec![Entity::Schema], true),
         ("upstream", vec![Entity::Schema], false),
         ("http", vec![Entity::FieldDefinition], false),
         ("call", vec![Entity::FieldDefinition], false),
+        ("default", vec![Entity::FieldDefinition], false),
         ("grpc", vec![Entity::FieldDefinition], false),
         ("addField", vec![Entity::Object], true),
         ("modify", vec![Entity::FieldDefinition], false),
         ("telemetry", vec![Entity::Schema], false),
         ("omit", vec![Entity::FieldDefinition], false),
+        ("oneOf", vec![Entity::InputObject], false),
+        ("patch", vec![Entity::FieldDefinition], false),
         ("groupBy", vec![Entity::FieldDefinition], false),
         ("expr", vec![Entity::FieldDefinition], false),
         (
@@ -39,7 +43,9 @@ lazy_static! {
             false,
         ),
         ("js", vec![Entity::FieldDefinition], false),
-        ("tag", vec![Entity::Object], false),
+        ("tag", vec![Entity::Object, Entity::FieldDefinition], false),
+        ("version", vec![Entity::Schema], false),
+        ("wasm", vec![Entity::FieldDefinition], false),
     ];
 }
 
@@ -60,7 +66,10 @@ static OBJECT_WHITELIST: &[&str] = &[
     "Encoding",
     "ExprBody",
     "JS",
+    "Default",
+    "Wasm",
     "Modify",
+    "Patch",
     "Telemetry",
     "TelemetryInner",
     "TelemetryExporter",
@@ -76,6 +85,7 @@ static OBJECT_WHITELIST: &[&str] = &[
 enum Entity {
     Schema,
     Object,
+    InputObject,
     FieldDefinition,
 }
 
@@ -92,6 +102,9 @@ impl ToGraphql for Entity {
             Entity::Object => {
                 write!(f, "OBJECT")
             }
+            Entity::InputObject => {
+                write!(f, "INPUT_OBJECT")
+            }
             Entity::FieldDefinition => {
                 write!(f, "FIELD_DEFINITION")
             }
@@ -50,7 +50,7 @@ async fn main() {
 
 async fn mode_check() -> Result<()> {
     let json_schema = get_file_path();
-    let rt = cli::runtime::init(&Default::default());
+    let rt = cli::runtime::init(&Default::default())?;
     let file_io = rt.file;
     let content = file_io
         .read(
@@ -76,7 +76,7 @@ async fn mode_fix() -> Result<()> {
 async fn update_json() -> Result<()> {
     let path = get_file_path();
     let schema = serde_json::to_string_pretty(&get_updated_json().await?)?;
-    let rt = cli::runtime::init(&Default::default());
+    let rt = cli::runtime::init(&Default::default())?;
     let file_io = rt.file;
     tracing::info!("Updating JSON Schema: {}", path.to_str().unwrap());
     file_io
@@ -93,7 +93,7 @@ fn get_file_path() -> PathBuf {
 }
 
 async fn get_updated_json() -> Result<Value> {
-    let mut schema: RootSchema = schemars::schema_for!(Config);
+    let mut schema: RootSchema = Config::json_schema();
     let scalar = CUSTOM_SCALARS
         .iter()
         .map(|(k, v)| (k.clone(), v.schema()))
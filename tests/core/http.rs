@@ -8,7 +8,7 @@ use std::sync::Arc;
 use anyhow::anyhow;
 use hyper::body::Bytes;
 use reqwest::header::{HeaderName, HeaderValue};
-use tailcall::core::http::Response;
+use tailcall::core::http::{Response, StatusError};
 use tailcall::core::HttpIO;
 
 use super::runtime::{ExecutionMock, ExecutionSpec};
@@ -109,10 +109,6 @@ impl HttpIO for Http {
         // Build the response with the status code from the mock.
         let status_code = reqwest::StatusCode::from_u16(mock_response.0.status)?;
 
-        if status_code.is_client_error() || status_code.is_server_error() {
-            return Err(anyhow::format_err!("Status code error"));
-        }
-
         let mut response = Response { status: status_code, ..Default::default() };
 
         // Insert headers from the mock into the response.
@@ -127,6 +123,10 @@ impl HttpIO for Http {
             response.body = Bytes::from(body.to_bytes());
         }
 
+        if status_code.is_client_error() || status_code.is_server_error() {
+            return Err(anyhow::Error::new(StatusError(response)).context("Status code error"));
+        }
+
         Ok(response)
     }
 }
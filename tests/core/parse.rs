@@ -300,6 +300,7 @@ impl ExecutionSpec {
             extensions: Arc::new(vec![]),
             cmd_worker: http_worker,
             worker,
+            wasm: None,
         };
 
         let endpoints = config
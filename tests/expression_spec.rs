@@ -9,7 +9,7 @@ mod tests {
     use tailcall::core::mustache::Mustache;
 
     async fn eval(expr: &IR) -> Result<Value, EvaluationError> {
-        let runtime = tailcall::cli::runtime::init(&Blueprint::default());
+        let runtime = tailcall::cli::runtime::init(&Blueprint::default()).unwrap();
         let req_ctx = RequestContext::new(runtime);
         let res_ctx = EmptyResolverContext {};
         let eval_ctx = EvaluationContext::new(&req_ctx, &res_ctx);
@@ -35,14 +35,14 @@ pub mod test {
     impl TestHttp {
         fn init(upstream: &Upstream) -> Arc<Self> {
             let mut builder = Client::builder()
-                .tcp_keepalive(Some(Duration::from_secs(upstream.tcp_keep_alive)))
+                .tcp_keepalive(upstream.get_tcp_keep_alive())
                 .timeout(Duration::from_secs(upstream.timeout))
                 .connect_timeout(Duration::from_secs(upstream.connect_timeout))
-                .http2_keep_alive_interval(Some(Duration::from_secs(upstream.keep_alive_interval)))
+                .http2_keep_alive_interval(upstream.get_http2_keep_alive_interval())
                 .http2_keep_alive_timeout(Duration::from_secs(upstream.keep_alive_timeout))
                 .http2_keep_alive_while_idle(upstream.keep_alive_while_idle)
                 .pool_idle_timeout(Some(Duration::from_secs(upstream.pool_idle_timeout)))
-                .pool_max_idle_per_host(upstream.pool_max_idle_per_host)
+                .pool_max_idle_per_host(upstream.get_pool_max_idle_per_host())
                 .user_agent(upstream.user_agent.clone());
 
             // Add Http2 Prior Knowledge
@@ -75,12 +75,7 @@ pub mod test {
     impl HttpIO for TestHttp {
         async fn execute(&self, request: reqwest::Request) -> Result<Response<Bytes>> {
             let response = self.client.execute(request).await;
-            Response::from_reqwest(
-                response?
-                    .error_for_status()
-                    .map_err(|err| err.without_url())?,
-            )
-            .await
+            Response::from_reqwest_for_status(response?).await
         }
     }
 
@@ -152,15 +147,18 @@ pub mod test {
                 Some(script) => Some(init_worker_io::<Value, Value>(script.to_owned())),
                 None => None,
             },
+            wasm: None,
         }
     }
 }
 
 #[cfg(test)]
 mod server_spec {
+    use std::time::Duration;
+
     use reqwest::Client;
     use serde_json::json;
-    use tailcall::cli::server::Server;
+    use tailcall::cli::server::{Server, Watcher};
     use tailcall::core::config::reader::ConfigReader;
 
     async fn test_server(configs: &[&str], url: &str) {
@@ -265,4 +263,73 @@ mod server_spec {
         )
         .await
     }
+
+    async fn greet(client: &Client) -> serde_json::Value {
+        client
+            .post("http://localhost:8805/graphql")
+            .json(&json!({ "query": "{ greet }" }))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn server_start_watch() {
+        let file = tempfile::Builder::new()
+            .suffix(".graphql")
+            .tempfile()
+            .unwrap();
+        let path = file.path().to_str().unwrap().to_string();
+        let config = |greeting: &str| {
+            format!(
+                r#"schema @server(port: 8805) {{ query: Query }}
+                type Query {{ greet: String @expr(body: "{greeting}") }}"#
+            )
+        };
+        std::fs::write(&path, config("Hello World!")).unwrap();
+
+        let runtime = crate::test::init(None);
+        let reader = ConfigReader::init(runtime.clone());
+        let config_module = reader.read_all(&[path.as_str()]).await.unwrap();
+        let watcher = Watcher::new(runtime, vec![path.clone()]).interval(Duration::from_millis(50));
+        let mut server = Server::new(config_module).watch(watcher);
+        let server_up_receiver = server.server_up_receiver();
+
+        tokio::spawn(async move {
+            server.start().await.unwrap();
+        });
+
+        server_up_receiver
+            .await
+            .expect("Server did not start up correctly");
+
+        let client = Client::new();
+        assert_eq!(
+            greet(&client).await,
+            json!({ "data": { "greet": "Hello World!" } })
+        );
+
+        // An invalid config keeps the last valid one
+        std::fs::write(&path, "type Query {").unwrap();
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert_eq!(
+            greet(&client).await,
+            json!({ "data": { "greet": "Hello World!" } })
+        );
+
+        std::fs::write(&path, config("Hello Watch!")).unwrap();
+        let expected = json!({ "data": { "greet": "Hello Watch!" } });
+        let mut response = greet(&client).await;
+        for _ in 0..50 {
+            if response == expected {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            response = greet(&client).await;
+        }
+        assert_eq!(response, expected);
+    }
 }